@@ -52,6 +52,10 @@ impl FloatTypeWrapper {
     pub fn new(value: f64) -> Self {
         Self(value.to_bits())
     }
+
+    pub fn into_f64(self) -> f64 {
+        f64::from_bits(self.0)
+    }
 }
 
 impl fmt::Display for FloatTypeWrapper {
@@ -392,6 +392,7 @@ pub mod known {
         bitxor_assign,
         bitxor,
         branch,
+        copy,
         deref_mut,
         deref,
         div_assign,
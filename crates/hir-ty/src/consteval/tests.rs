@@ -1,12 +1,17 @@
 use base_db::fixture::WithFixture;
 use hir_def::{db::DefDatabase, expr::Literal};
 
-use crate::{consteval::ComputedExpr, db::HirDatabase, test_db::TestDB};
+use crate::{
+    consteval::ComputedExpr,
+    db::HirDatabase,
+    mir::{MirEvalError, MirLowerError},
+    test_db::TestDB,
+};
 
 use super::ConstEvalError;
 
-fn check_fail(ra_fixture: &str, error: ConstEvalError) {
-    assert_eq!(eval_goal(ra_fixture), Err(error));
+fn check_fail(ra_fixture: &str, error: impl FnOnce(ConstEvalError) -> bool) {
+    assert!(error(eval_goal(ra_fixture).unwrap_err()));
 }
 
 fn check_number(ra_fixture: &str, answer: i128) {
@@ -18,6 +23,11 @@ fn check_number(ra_fixture: &str, answer: i128) {
     }
 }
 
+fn check_display(ra_fixture: &str, answer: &str) {
+    let r = eval_goal(ra_fixture).unwrap();
+    assert_eq!(r.to_string(), answer);
+}
+
 fn eval_goal(ra_fixture: &str) -> Result<ComputedExpr, ConstEvalError> {
     let (db, file_id) = TestDB::with_single_file(ra_fixture);
     let module_id = db.module_for_file(file_id);
@@ -49,15 +59,23 @@ fn add() {
 fn bit_op() {
     check_number(r#"const GOAL: u8 = !0 & !(!0 >> 1)"#, 128);
     check_number(r#"const GOAL: i8 = !0 & !(!0 >> 1)"#, 0);
-    // FIXME: rustc evaluate this to -128
-    check_fail(
-        r#"const GOAL: i8 = 1 << 7"#,
-        ConstEvalError::Panic("attempt to run invalid arithmetic operation".to_string()),
-    );
-    check_fail(
-        r#"const GOAL: i8 = 1 << 8"#,
-        ConstEvalError::Panic("attempt to run invalid arithmetic operation".to_string()),
-    );
+    check_number(r#"const GOAL: i8 = 1 << 7"#, -128);
+    check_number(r#"const GOAL: u128 = !0"#, u128::MAX as i128);
+    check_fail(r#"const GOAL: i8 = 1 << 8"#, |e| {
+        e == ConstEvalError::MirEvalError(MirEvalError::Panic(
+            "attempt to shift left with overflow".to_string(),
+        ))
+    });
+}
+
+#[test]
+fn overflow() {
+    check_fail(r#"const GOAL: u8 = 255 + 1;"#, |e| {
+        matches!(e, ConstEvalError::MirEvalError(MirEvalError::Panic(_)))
+    });
+    check_fail(r#"const GOAL: i32 = 1 / (2 - 2);"#, |e| {
+        matches!(e, ConstEvalError::MirEvalError(MirEvalError::Panic(_)))
+    });
 }
 
 #[test]
@@ -74,6 +92,204 @@ fn locals() {
     );
 }
 
+#[test]
+fn references() {
+    check_number(
+        r#"
+    const GOAL: usize = {
+        let mut x = 3;
+        let y = &mut x;
+        *y = 5;
+        x
+    };
+    "#,
+        5,
+    );
+    check_number(
+        r#"
+    const GOAL: u8 = {
+        let a = &&&2;
+        ***a + 1
+    };
+    "#,
+        3,
+    );
+}
+
+#[test]
+fn loops() {
+    check_number(
+        r#"
+    const GOAL: u8 = {
+        let mut x = 0;
+        loop {
+            x = x + 1;
+            while true {
+                break;
+            }
+            x = x + 1;
+            if x == 2 {
+                continue;
+            }
+            break;
+        };
+        x
+    };
+    "#,
+        4,
+    );
+    check_number(
+        r#"
+    const GOAL: usize = {
+        let mut i = 0;
+        let mut sum = 0;
+        'outer: while i < 10 {
+            i += 1;
+            let mut j = 0;
+            loop {
+                j += 1;
+                if j > i {
+                    continue 'outer;
+                }
+                if i + j > 12 {
+                    break 'outer;
+                }
+                sum += j;
+            }
+        }
+        sum
+    };
+    "#,
+        71,
+    );
+}
+
+#[test]
+fn arrays() {
+    check_number(
+        r#"
+    //- minicore: index, slice
+    const GOAL: u8 = {
+        let a = [10, 20, 3, 15];
+        let mut i = 0;
+        let mut max = 0;
+        while i < 4 {
+            if a[i] > max {
+                max = a[i];
+            }
+            i += 1;
+        }
+        max
+    };
+    "#,
+        20,
+    );
+    check_number(
+        r#"
+    //- minicore: index, slice
+    const GOAL: usize = {
+        let mut a = [0; 5];
+        a[2] = 7;
+        let [_, _, x, ..] = a;
+        x
+    };
+    "#,
+        7,
+    );
+    check_fail(
+        r#"
+    //- minicore: index, slice
+    const GOAL: u8 = {
+        let a = [1, 2, 3];
+        let i = 3;
+        a[i]
+    };
+    "#,
+        |e| matches!(e, ConstEvalError::MirEvalError(MirEvalError::Panic(_))),
+    );
+}
+
+#[test]
+fn nested_array_repeat_limit() {
+    check_number(
+        r#"
+    const GOAL: u8 = {
+        let a = [[3u8; 4]; 2];
+        let [_, [.., x]] = a;
+        x
+    };
+    "#,
+        3,
+    );
+    check_fail(
+        r#"
+    const GOAL: usize = {
+        let a = [[0u8; 1048576]; 1048576];
+        0
+    };
+    "#,
+        |e| matches!(e, ConstEvalError::MirEvalError(MirEvalError::NotSupported(_))),
+    );
+}
+
+#[test]
+fn structs() {
+    check_number(
+        r#"
+    struct Point { x: i32, y: i32 }
+    struct Wrapper(Point, u8);
+    const GOAL: i32 = {
+        let mut w = Wrapper(Point { x: 1, y: 2 }, 3);
+        w.0.y = 10;
+        let Wrapper(Point { x, y }, z) = w;
+        x + y * z as i32
+    };
+    "#,
+        31,
+    );
+}
+
+#[test]
+fn pattern_matching() {
+    check_number(
+        r#"
+    //- minicore: index, slice
+    enum E { A(u8), B { x: u8, y: u8 }, C }
+    const GOAL: u8 = {
+        let values = [E::A(3), E::B { x: 4, y: 5 }, E::C];
+        let mut i = 0;
+        let mut sum = 0;
+        while i < 3 {
+            sum += match &values[i] {
+                E::A(1 | 2) => 100,
+                E::A(x) if *x > 2 => *x,
+                E::A(_) => 200,
+                E::B { x, y: 5 } => *x * 10,
+                E::B { .. } => 50,
+                E::C => 1,
+            };
+            i += 1;
+        }
+        sum
+    };
+    "#,
+        44,
+    );
+    check_number(
+        r#"
+    const GOAL: u8 = {
+        let x = (2, 'a');
+        match x {
+            (0 | 1, _) => 1,
+            (_, 'b') => 2,
+            (y, _) => y * 3,
+        }
+    };
+    "#,
+        6,
+    );
+}
+
 #[test]
 fn consts() {
     check_number(
@@ -122,7 +338,7 @@ fn enums() {
     )
     .unwrap();
     match r {
-        ComputedExpr::Enum(name, _, Literal::Uint(val, _)) => {
+        ComputedExpr::Enum(name, _, Literal::Int(val, _)) => {
             assert_eq!(name, "E::A");
             assert_eq!(val, 1);
         }
@@ -130,6 +346,183 @@ fn enums() {
     }
 }
 
+#[test]
+fn function_call() {
+    check_number(
+        r#"
+    const fn f(x: usize) -> usize {
+        2 * x + 5
+    }
+    const GOAL: usize = f(3);
+    "#,
+        11,
+    );
+    check_number(
+        r#"
+    const fn add(x: u8, y: u8) -> u8 {
+        x + y
+    }
+    const GOAL: u8 = add(add(1, 2), 3);
+    "#,
+        6,
+    );
+}
+
+#[test]
+fn recursive_function_call() {
+    check_number(
+        r#"
+    const fn fib(n: i32) -> i32 {
+        if n <= 2 {
+            1
+        } else {
+            fib(n - 1) + fib(n - 2)
+        }
+    }
+    const GOAL: i32 = fib(10);
+    "#,
+        55,
+    );
+    check_fail(
+        r#"
+    const fn f(x: usize) -> usize {
+        f(x + 1)
+    }
+    const GOAL: usize = f(0);
+    "#,
+        |e| e == ConstEvalError::MirEvalError(MirEvalError::StackOverflow),
+    );
+}
+
+#[test]
+fn generic_function_call() {
+    check_number(
+        r#"
+    const fn first<T: Copy>(x: T, _y: T) -> T {
+        x
+    }
+    const fn len<const N: usize>(x: [u8; N]) -> usize {
+        N
+    }
+    const GOAL: u8 = first(5, 6) + len([1, 2, 3]) as u8 + first::<u8>(1, 2);
+    "#,
+        9,
+    );
+}
+
+#[test]
+fn method_call() {
+    check_number(
+        r#"
+    struct Point { x: i32, y: i32 }
+    impl Point {
+        const fn new(x: i32, y: i32) -> Self {
+            Point { x, y }
+        }
+        const fn manhattan(&self) -> i32 {
+            self.x + self.y
+        }
+    }
+    const GOAL: i32 = Point::new(3, 4).manhattan();
+    "#,
+        7,
+    );
+}
+
+#[test]
+fn non_const_function_call() {
+    check_fail(
+        r#"
+    fn f() -> usize {
+        5
+    }
+    const GOAL: usize = f();
+    "#,
+        |e| matches!(e, ConstEvalError::MirEvalError(MirEvalError::NotSupported(_))),
+    );
+}
+
+#[test]
+fn execution_limit() {
+    check_fail(
+        r#"
+    const GOAL: usize = {
+        let mut x = 0;
+        loop {
+            x = x + 1;
+            if x == 0 {
+                break;
+            }
+        }
+        x
+    };
+    "#,
+        |e| e == ConstEvalError::MirEvalError(MirEvalError::ExecutionLimitExceeded),
+    );
+}
+
+#[test]
+fn structured_values() {
+    check_display(
+        r#"
+    struct Config { verbose: bool, level: u8, name: &'static str }
+    const GOAL: Config = Config { verbose: true, level: 3 * 4, name: "main" };
+    "#,
+        r#"Config { verbose: true, level: 12, name: "main" }"#,
+    );
+    check_display(
+        r#"
+    struct Wrapper(i32, char);
+    struct Unit;
+    const GOAL: (Wrapper, Unit) = (Wrapper(-1, 'x'), Unit);
+    "#,
+        "(Wrapper(-1, 'x'), Unit, )",
+    );
+    check_display(
+        r#"
+    enum Shape { Circle { radius: u32 }, Square(u32), Empty }
+    const GOAL: [Shape; 3] = [Shape::Circle { radius: 2 }, Shape::Square(5), Shape::Empty];
+    "#,
+        "[Shape::Circle { radius: 2 }, Shape::Square(5), Shape::Empty]",
+    );
+}
+
+#[test]
+fn references_and_slices() {
+    check_display(r#"const GOAL: &[u8] = &[1, 2, 3];"#, "&[1, 2, 3]");
+    check_display(r#"const GOAL: &str = "hello";"#, r#""hello""#);
+    check_display(r#"const GOAL: &&i32 = &&5;"#, "&&5");
+    check_display(r#"const GOAL: &[u8; 2] = b"ab";"#, "&[97, 98]");
+}
+
+#[test]
+fn structured_const_references() {
+    check_number(
+        r#"
+    //- minicore: index, slice
+    struct Point { x: i32, y: i32 }
+    const ORIGIN: Point = Point { x: 2, y: 3 };
+    const TABLE: [u8; 4] = [1, 2, 4, 8];
+    const GOAL: i32 = ORIGIN.x * ORIGIN.y + TABLE[3] as i32;
+    "#,
+        14,
+    );
+    check_display(
+        r#"
+    const NAMES: &[&str] = &["a", "bc"];
+    const GOAL: &[&str] = NAMES;
+    "#,
+        r#"&["a", "bc"]"#,
+    );
+}
+
+#[test]
+fn type_mismatch() {
+    check_fail(r#"const GOAL: u8 = "a";"#, |e| {
+        matches!(e, ConstEvalError::MirLowerError(MirLowerError::TypeMismatch(_)))
+    });
+}
+
 #[test]
 fn const_loop() {
     check_fail(
@@ -139,7 +532,7 @@ fn const_loop() {
     const F2: i32 = 2 * F1;
     const GOAL: i32 = F3;
     "#,
-        ConstEvalError::Loop,
+        |e| e == ConstEvalError::MirLowerError(MirLowerError::Loop),
     );
 }
 
@@ -168,7 +561,11 @@ fn const_generic_subst() {
     }
     const GOAL: usize = Adder::<2, 3>::VAL;
     "#,
-        ConstEvalError::NotSupported("const generic without substitution"),
+        |e| {
+            e == ConstEvalError::MirEvalError(MirEvalError::NotSupported(
+                "const generic without substitution".to_string(),
+            ))
+        },
     );
 }
 
@@ -186,6 +583,6 @@ fn const_trait_assoc() {
     }
     const GOAL: usize = U0::VAL;
     "#,
-        ConstEvalError::IncompleteExpr,
+        |e| e == ConstEvalError::MirLowerError(MirLowerError::IncompleteExpr),
     );
 }
@@ -3,11 +3,15 @@ mod expr;
 mod match_check;
 mod unsafe_check;
 mod decl_check;
+mod binding_usage;
+mod move_check;
 
 pub use crate::diagnostics::{
+    binding_usage::{binding_usage, BindingUsage},
     decl_check::{incorrect_case, IncorrectCase},
     expr::{
         record_literal_missing_fields, record_pattern_missing_fields, BodyValidationDiagnostic,
     },
+    move_check::{moved_value_uses, MovedValueUse},
     unsafe_check::{missing_unsafe, unsafe_expressions, UnsafeExpr},
 };
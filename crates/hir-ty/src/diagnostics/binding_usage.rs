@@ -0,0 +1,330 @@
+//! Computes which local bindings of a body are never used, which `mut` annotations are
+//! unnecessary and which immutable bindings are mutated.
+//!
+//! This is an approximation of rustc's liveness and borrowck lints, working directly on the
+//! `Body` and its `InferenceResult`. It errs on the side of not reporting anything whenever a
+//! binding might be mutated in a way we can't see (e.g. through a call of a closure).
+
+use hir_def::{
+    body::Body,
+    expr::{Array, BinaryOp, BindingAnnotation, Expr, ExprId, Pat, PatId, Statement},
+    resolver::{resolver_for_expr, ValueNs},
+    DefWithBodyId,
+};
+use hir_expand::name::name;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    db::HirDatabase, infer::OverloadedDeref, Adjust, AutoBorrow, BindingMode, InferenceResult,
+    Interner, Mutability, TyExt, TyKind,
+};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BindingUsage {
+    /// Bindings which are never read.
+    pub unused: Vec<PatId>,
+    /// Bindings annotated with `mut` which are never mutated.
+    pub unused_mut: Vec<PatId>,
+    /// Bindings without a `mut` annotation together with the first expression mutating them.
+    pub need_mut: Vec<(PatId, ExprId)>,
+}
+
+pub fn binding_usage(db: &dyn HirDatabase, def: DefWithBodyId) -> BindingUsage {
+    let body = db.body(def);
+    let infer = db.infer(def);
+    let mut collector = UsageCollector {
+        db,
+        def,
+        body: &body,
+        infer: &infer,
+        used: FxHashSet::default(),
+        mutated: FxHashMap::default(),
+        maybe_mutated: FxHashSet::default(),
+        uninit: FxHashSet::default(),
+        whole_assignments: FxHashMap::default(),
+    };
+    collector.collect();
+    collector.finish()
+}
+
+struct UsageCollector<'a> {
+    db: &'a dyn HirDatabase,
+    def: DefWithBodyId,
+    body: &'a Body,
+    infer: &'a InferenceResult,
+    used: FxHashSet<PatId>,
+    /// Mutated bindings, mapped to the first expression mutating them.
+    mutated: FxHashMap<PatId, ExprId>,
+    /// Bindings that may be mutated in ways we do not track precisely.
+    maybe_mutated: FxHashSet<PatId>,
+    /// Bindings declared by a `let` without initializer.
+    uninit: FxHashSet<PatId>,
+    /// Number of assignments overwriting a whole uninitialized binding.
+    whole_assignments: FxHashMap<PatId, usize>,
+}
+
+impl UsageCollector<'_> {
+    fn collect(&mut self) {
+        let body = self.body;
+        for (_, expr) in body.exprs.iter() {
+            if let Expr::Block { statements, .. } = expr {
+                for statement in statements.iter() {
+                    if let Statement::Let { pat, initializer: None, .. } = statement {
+                        self.collect_bindings(*pat, &mut |this, pat| {
+                            this.uninit.insert(pat);
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut assignee_paths = FxHashSet::default();
+        for (expr_id, expr) in body.exprs.iter() {
+            match expr {
+                Expr::BinaryOp { lhs, op: Some(BinaryOp::Assignment { op }), .. } => {
+                    if op.is_some() {
+                        self.record_mutated_place(*lhs, expr_id);
+                    } else {
+                        self.assignee_places(*lhs, &mut |this, place| {
+                            if let Expr::Path(_) = &body.exprs[place] {
+                                assignee_paths.insert(place);
+                            }
+                            match this.resolve_local(place) {
+                                Some(pat) if this.uninit.contains(&pat) => {
+                                    *this.whole_assignments.entry(pat).or_default() += 1
+                                }
+                                _ => this.record_mutated_place(place, expr_id),
+                            }
+                        });
+                    }
+                }
+                Expr::Ref { expr, mutability: hir_def::type_ref::Mutability::Mut, .. } => {
+                    self.record_mutated_place(*expr, expr_id)
+                }
+                Expr::Call { callee, .. } => {
+                    if self.infer[*callee].as_fn_def(self.db).is_none() {
+                        self.record_maybe_mutated_place(*callee);
+                    }
+                }
+                Expr::MethodCall { receiver, .. } => {
+                    if self.infer.method_resolution(expr_id).is_none() {
+                        self.record_maybe_mutated_place(*receiver);
+                    }
+                }
+                Expr::Let { pat, expr } => self.record_ref_mut_bindings(*pat, *expr),
+                Expr::Match { expr, arms } => {
+                    for arm in arms.iter() {
+                        self.record_ref_mut_bindings(arm.pat, *expr);
+                    }
+                }
+                Expr::Block { statements, .. } => {
+                    for statement in statements.iter() {
+                        if let Statement::Let { pat, initializer: Some(init), .. } = statement {
+                            self.record_ref_mut_bindings(*pat, *init);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (expr_id, adjustments) in self.infer.expr_adjustments.iter() {
+            let mutates = adjustments.iter().any(|adj| {
+                matches!(
+                    adj.kind,
+                    Adjust::Borrow(AutoBorrow::Ref(Mutability::Mut))
+                        | Adjust::Borrow(AutoBorrow::RawPtr(Mutability::Mut))
+                        | Adjust::Deref(Some(OverloadedDeref(Mutability::Mut)))
+                )
+            });
+            if mutates && !self.derefs_through_pointer(*expr_id) {
+                self.record_mutated_place(*expr_id, *expr_id);
+            }
+        }
+
+        for (expr_id, expr) in body.exprs.iter() {
+            if let Expr::Path(_) = expr {
+                if !assignee_paths.contains(&expr_id) {
+                    if let Some(pat) = self.resolve_local(expr_id) {
+                        self.used.insert(pat);
+                    }
+                }
+            }
+        }
+
+        // A binding declared without initializer can be initialized by an assignment without
+        // being `mut`. As we don't know whether several assignments happen on different control
+        // flow paths, they only make the `mut` annotation potentially necessary.
+        for (pat, assignments) in std::mem::take(&mut self.whole_assignments) {
+            if assignments > 1 {
+                self.maybe_mutated.insert(pat);
+            }
+        }
+    }
+
+    fn finish(self) -> BindingUsage {
+        let mut res = BindingUsage::default();
+        let body = self.body;
+        let is_declaration = matches!(body.exprs[body.body_expr], Expr::Missing);
+        for (pat_id, pat) in body.pats.iter() {
+            let (mode, name) = match pat {
+                Pat::Bind { mode, name, .. } => (mode, name),
+                _ => continue,
+            };
+            if is_declaration && body.params.contains(&pat_id) {
+                continue;
+            }
+            let representative = body.pattern_representative(pat_id);
+            let unused_name =
+                *name == name![self] || name.as_text().map_or(true, |it| it.starts_with('_'));
+            let unused = !unused_name && !self.used.contains(&representative);
+            if unused && representative == pat_id {
+                res.unused.push(pat_id);
+            }
+            match (mode, self.mutated.get(&representative)) {
+                // An unused binding is reported as such, not also for its `mut`.
+                (BindingAnnotation::Mutable, None) => {
+                    if !unused && !self.maybe_mutated.contains(&representative) {
+                        res.unused_mut.push(pat_id);
+                    }
+                }
+                (BindingAnnotation::Unannotated | BindingAnnotation::Ref, Some(&expr)) => {
+                    if representative == pat_id {
+                        res.need_mut.push((pat_id, expr));
+                    }
+                }
+                _ => {}
+            }
+        }
+        res
+    }
+
+    fn resolve_local(&self, expr: ExprId) -> Option<PatId> {
+        let path = match &self.body.exprs[expr] {
+            Expr::Path(path) => path,
+            _ => return None,
+        };
+        let resolver = resolver_for_expr(self.db.upcast(), self.def, expr);
+        match resolver.resolve_path_in_value_ns_fully(self.db.upcast(), path.mod_path()) {
+            Some(ValueNs::LocalBinding(pat)) => Some(self.body.pattern_representative(pat)),
+            _ => None,
+        }
+    }
+
+    /// Finds the binding owning the place `expr`, if mutating that place mutates the binding
+    /// itself rather than something behind a reference or raw pointer.
+    fn place_root(&self, expr: ExprId) -> Option<PatId> {
+        match &self.body.exprs[expr] {
+            Expr::Path(_) => self.resolve_local(expr),
+            Expr::Field { expr: base, .. } | Expr::Index { base, .. } => {
+                // Fields and indices auto-deref their base. Inference doesn't record that for
+                // indexing without the `Index` lang items, so also check the type of the base.
+                if self.derefs_through_pointer(*base) || self.is_pointer_after_adjustments(*base) {
+                    return None;
+                }
+                self.place_root(*base)
+            }
+            Expr::UnaryOp { expr: base, op: hir_def::expr::UnaryOp::Deref } => {
+                if self.derefs_through_pointer(*base) || self.is_pointer_after_adjustments(*base) {
+                    return None;
+                }
+                self.place_root(*base)
+            }
+            _ => None,
+        }
+    }
+
+    fn record_mutated_place(&mut self, place: ExprId, by: ExprId) {
+        if let Some(pat) = self.place_root(place) {
+            self.mutated.entry(pat).or_insert(by);
+        }
+    }
+
+    fn record_maybe_mutated_place(&mut self, place: ExprId) {
+        if let Some(pat) = self.place_root(place) {
+            self.maybe_mutated.insert(pat);
+        }
+    }
+
+    /// Calls `cb` with every place assigned to by a (possibly destructuring) assignment.
+    fn assignee_places(&mut self, lhs: ExprId, cb: &mut dyn FnMut(&mut Self, ExprId)) {
+        match &self.body.exprs[lhs] {
+            Expr::Tuple { exprs, is_assignee_expr: true }
+            | Expr::Array(Array::ElementList { elements: exprs, is_assignee_expr: true }) => {
+                for &expr in exprs.iter() {
+                    self.assignee_places(expr, cb);
+                }
+            }
+            Expr::Call { args, is_assignee_expr: true, .. } => {
+                for &arg in args.iter() {
+                    self.assignee_places(arg, cb);
+                }
+            }
+            Expr::RecordLit { fields, is_assignee_expr: true, .. } => {
+                for field in fields.iter() {
+                    self.assignee_places(field.expr, cb);
+                }
+            }
+            Expr::Underscore | Expr::Range { .. } => {}
+            _ => cb(self, lhs),
+        }
+    }
+
+    /// Explicit `ref mut` bindings borrow the matched place mutably.
+    fn record_ref_mut_bindings(&mut self, pat: PatId, scrutinee: ExprId) {
+        if self.binds_by_ref_mut(pat) {
+            self.record_mutated_place(scrutinee, scrutinee);
+        }
+    }
+
+    fn binds_by_ref_mut(&self, pat: PatId) -> bool {
+        if self.infer.pat_adjustments.get(&pat).map_or(false, |it| !it.is_empty()) {
+            return false;
+        }
+        match &self.body.pats[pat] {
+            Pat::Bind { mode: BindingAnnotation::RefMut, .. } => true,
+            Pat::Ref { .. } => false,
+            p => {
+                let mut res = false;
+                p.walk_child_pats(|child| res |= self.binds_by_ref_mut(child));
+                res
+            }
+        }
+    }
+
+    fn collect_bindings(&mut self, pat: PatId, cb: &mut dyn FnMut(&mut Self, PatId)) {
+        let body = self.body;
+        if let Pat::Bind { .. } = &body.pats[pat] {
+            if let Some(BindingMode::Move) | None = self.infer.pat_binding_modes.get(&pat) {
+                cb(self, body.pattern_representative(pat));
+            }
+        }
+        body.pats[pat].walk_child_pats(|child| self.collect_bindings(child, cb));
+    }
+
+    /// Whether the adjustments of `expr` dereference a reference or raw pointer.
+    fn derefs_through_pointer(&self, expr: ExprId) -> bool {
+        let adjustments = match self.infer.expr_adjustments.get(&expr) {
+            Some(it) => it,
+            None => return false,
+        };
+        let mut ty = &self.infer[expr];
+        for adj in adjustments {
+            if let Adjust::Deref(_) = adj.kind {
+                if matches!(ty.kind(Interner), TyKind::Ref(..) | TyKind::Raw(..)) {
+                    return true;
+                }
+            }
+            ty = &adj.target;
+        }
+        false
+    }
+
+    fn is_pointer_after_adjustments(&self, expr: ExprId) -> bool {
+        let ty = match self.infer.expr_adjustments.get(&expr).and_then(|it| it.last()) {
+            Some(adj) => &adj.target,
+            None => &self.infer[expr],
+        };
+        matches!(ty.kind(Interner), TyKind::Ref(..) | TyKind::Raw(..))
+    }
+}
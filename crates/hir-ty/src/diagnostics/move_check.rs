@@ -0,0 +1,529 @@
+//! Finds uses of local bindings after their value has been moved out.
+//!
+//! This is a light-weight, flow-sensitive approximation of the borrow checker's
+//! "use of moved value" error working on the `Body`. Only moves of whole bindings
+//! are tracked; partial moves (out of fields or through destructuring patterns)
+//! and anything happening behind references are ignored, so that we never report
+//! code rustc accepts. Closures are treated alike whether they are `move` or not:
+//! a value consumed inside of a closure is moved into it when the closure is created,
+//! any other use is assumed to be a borrow.
+
+use std::sync::Arc;
+
+use chalk_ir::TypeFlags;
+use hir_def::{
+    body::Body,
+    expr::{
+        Array, BinaryOp, BindingAnnotation, Expr, ExprId, LabelId, Pat, PatId, Statement, UnaryOp,
+    },
+    lang_item::LangItemTarget,
+    resolver::{resolver_for_expr, ValueNs},
+    DefWithBodyId, HasModule, TraitId,
+};
+use hir_expand::name::{name, Name};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    db::HirDatabase, method_resolution::implements_trait, Adjust, BindingMode, Canonical,
+    CanonicalVarKinds, InferenceResult, Interner, TraitEnvironment, Ty, TyExt, TyKind,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MovedValueUse {
+    /// The binding whose value has been moved.
+    pub binding: PatId,
+    /// The expression moving the value.
+    pub moved_at: ExprId,
+    /// The expression using the binding after it has been moved.
+    pub used_at: ExprId,
+}
+
+pub fn moved_value_uses(db: &dyn HirDatabase, def: DefWithBodyId) -> Vec<MovedValueUse> {
+    let body = db.body(def);
+    let infer = db.infer(def);
+    let krate = def.module(db.upcast()).krate();
+    let env = def
+        .as_generic_def_id()
+        .map_or_else(|| Arc::new(TraitEnvironment::empty(krate)), |d| db.trait_environment(d));
+    let copy_trait = match db.lang_item(krate, name![copy].to_smol_str()) {
+        Some(LangItemTarget::TraitId(it)) => it,
+        // Without `Copy` we can't tell moves from copies.
+        _ => return Vec::new(),
+    };
+
+    let mut checker = MoveChecker {
+        db,
+        def,
+        body: &body,
+        infer: &infer,
+        env,
+        copy_trait,
+        state: Some(Moves::default()),
+        breakables: Vec::new(),
+        closure_moves: None,
+        res: Vec::new(),
+        reported: FxHashSet::default(),
+    };
+    checker.walk_expr(body.body_expr, Ctx::Move);
+    checker.res
+}
+
+/// Moved bindings, mapped to the expression that moved them.
+type Moves = FxHashMap<PatId, ExprId>;
+
+/// How the value of an expression is used by its parent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Ctx {
+    /// The value is consumed.
+    Move,
+    /// The expression is only used as a place, e.g. borrowed or matched on.
+    Place,
+}
+
+struct Breakable {
+    label: Option<Name>,
+    is_loop: bool,
+    /// State at the `break`s targeting this loop or block.
+    breaks: Option<Moves>,
+    /// State at the `continue`s targeting this loop.
+    continues: Option<Moves>,
+}
+
+struct MoveChecker<'a> {
+    db: &'a dyn HirDatabase,
+    def: DefWithBodyId,
+    body: &'a Body,
+    infer: &'a InferenceResult,
+    env: Arc<TraitEnvironment>,
+    copy_trait: TraitId,
+    /// The bindings moved at the current point, `None` if it is unreachable.
+    state: Option<Moves>,
+    breakables: Vec<Breakable>,
+    /// Bindings moved inside of the closure being walked, if any. They are moved into the
+    /// closure when it is created.
+    closure_moves: Option<Moves>,
+    res: Vec<MovedValueUse>,
+    reported: FxHashSet<ExprId>,
+}
+
+fn merge(lhs: Option<Moves>, rhs: Option<Moves>) -> Option<Moves> {
+    match (lhs, rhs) {
+        (Some(mut lhs), Some(rhs)) => {
+            for (pat, moved_at) in rhs {
+                lhs.entry(pat).or_insert(moved_at);
+            }
+            Some(lhs)
+        }
+        (lhs, None) => lhs,
+        (None, rhs) => rhs,
+    }
+}
+
+impl MoveChecker<'_> {
+    fn walk_expr(&mut self, expr: ExprId, ctx: Ctx) {
+        let body = self.body;
+        match &body.exprs[expr] {
+            Expr::Missing | Expr::Literal(_) | Expr::Underscore => {}
+            Expr::Path(_) => self.walk_path(expr, ctx),
+            Expr::If { condition, then_branch, else_branch } => {
+                self.walk_expr(*condition, Ctx::Move);
+                let before = self.state.clone();
+                self.walk_expr(*then_branch, Ctx::Move);
+                let after_then = std::mem::replace(&mut self.state, before);
+                if let Some(else_branch) = else_branch {
+                    self.walk_expr(*else_branch, Ctx::Move);
+                }
+                self.state = merge(after_then, self.state.take());
+            }
+            Expr::Let { pat, expr } => {
+                self.walk_expr(*expr, Ctx::Place);
+                self.bind_pat(*pat);
+            }
+            Expr::Block { statements, tail, label, .. } => {
+                let label = label.map(|it| self.label_name(it));
+                let has_label = label.is_some();
+                if has_label {
+                    self.push_breakable(label, false);
+                }
+                for statement in statements.iter() {
+                    match statement {
+                        Statement::Let { pat, initializer, else_branch, .. } => {
+                            if let Some(init) = initializer {
+                                let ctx = if self.is_by_value_binding(*pat) {
+                                    Ctx::Move
+                                } else {
+                                    Ctx::Place
+                                };
+                                self.walk_expr(*init, ctx);
+                            }
+                            if let Some(else_branch) = else_branch {
+                                let before = self.state.clone();
+                                self.walk_expr(*else_branch, Ctx::Move);
+                                self.state = before;
+                            }
+                            self.bind_pat(*pat);
+                        }
+                        Statement::Expr { expr, .. } => self.walk_expr(*expr, Ctx::Move),
+                    }
+                }
+                if let Some(tail) = tail {
+                    self.walk_expr(*tail, Ctx::Move);
+                }
+                if has_label {
+                    let breakable = self.breakables.pop().unwrap();
+                    self.state = merge(self.state.take(), breakable.breaks);
+                }
+            }
+            Expr::Loop { body: loop_body, label } => {
+                self.walk_loop(*label, None, None, *loop_body, false)
+            }
+            Expr::While { condition, body: loop_body, label } => {
+                self.walk_loop(*label, Some(*condition), None, *loop_body, true)
+            }
+            Expr::For { iterable, pat, body: loop_body, label } => {
+                self.walk_expr(*iterable, Ctx::Move);
+                self.walk_loop(*label, None, Some(*pat), *loop_body, true)
+            }
+            Expr::Call { callee, args, .. } => {
+                self.walk_expr(*callee, Ctx::Place);
+                for &arg in args.iter() {
+                    self.walk_expr(arg, Ctx::Move);
+                }
+                self.check_diverging(expr);
+            }
+            Expr::MethodCall { receiver, args, .. } => {
+                // We can only tell whether the receiver is taken by value if the method resolved.
+                let ctx = match self.infer.method_resolution(expr) {
+                    Some(_) => Ctx::Move,
+                    None => Ctx::Place,
+                };
+                self.walk_expr(*receiver, ctx);
+                for &arg in args.iter() {
+                    self.walk_expr(arg, Ctx::Move);
+                }
+                self.check_diverging(expr);
+            }
+            Expr::Match { expr: scrutinee, arms } => {
+                self.walk_expr(*scrutinee, Ctx::Place);
+                let before = self.state.take();
+                for arm in arms.iter() {
+                    let after_arms = std::mem::replace(&mut self.state, before.clone());
+                    self.bind_pat(arm.pat);
+                    if let Some(guard) = arm.guard {
+                        self.walk_expr(guard, Ctx::Move);
+                    }
+                    self.walk_expr(arm.expr, Ctx::Move);
+                    self.state = merge(after_arms, self.state.take());
+                }
+            }
+            Expr::Continue { label } => {
+                let state = self.state.take();
+                if let Some(breakable) = self.find_breakable(label, true) {
+                    breakable.continues = merge(breakable.continues.take(), state);
+                }
+            }
+            Expr::Break { expr: value, label } => {
+                if let Some(value) = value {
+                    self.walk_expr(*value, Ctx::Move);
+                }
+                let state = self.state.take();
+                if let Some(breakable) = self.find_breakable(label, false) {
+                    breakable.breaks = merge(breakable.breaks.take(), state);
+                }
+            }
+            Expr::Return { expr: value } => {
+                if let Some(value) = value {
+                    self.walk_expr(*value, Ctx::Move);
+                }
+                self.state = None;
+            }
+            Expr::Yield { expr: value } => {
+                if let Some(value) = value {
+                    self.walk_expr(*value, Ctx::Move);
+                }
+            }
+            Expr::RecordLit { fields, spread, .. } => {
+                for field in fields.iter() {
+                    self.walk_expr(field.expr, Ctx::Move);
+                }
+                if let Some(spread) = spread {
+                    // Only the fields not mentioned are moved out of the spread.
+                    self.walk_expr(*spread, Ctx::Place);
+                }
+            }
+            Expr::Field { expr: base, .. } => self.walk_expr(*base, Ctx::Place),
+            Expr::Await { expr: inner }
+            | Expr::Try { expr: inner }
+            | Expr::Cast { expr: inner, .. }
+            | Expr::Box { expr: inner } => self.walk_expr(*inner, Ctx::Move),
+            Expr::TryBlock { body: inner }
+            | Expr::Async { body: inner }
+            | Expr::Const { body: inner }
+            | Expr::Unsafe { body: inner } => self.walk_expr(*inner, Ctx::Move),
+            Expr::Ref { expr: inner, .. } => self.walk_expr(*inner, Ctx::Place),
+            Expr::UnaryOp { expr: inner, op } => {
+                let ctx = if *op == UnaryOp::Deref { Ctx::Place } else { Ctx::Move };
+                self.walk_expr(*inner, ctx);
+            }
+            Expr::BinaryOp { lhs, rhs, op } => match op {
+                Some(BinaryOp::Assignment { op: None }) => {
+                    self.walk_expr(*rhs, Ctx::Move);
+                    self.walk_assignee(*lhs);
+                }
+                Some(BinaryOp::Assignment { op: Some(_) }) => {
+                    self.walk_expr(*rhs, Ctx::Move);
+                    self.walk_expr(*lhs, Ctx::Place);
+                }
+                Some(BinaryOp::CmpOp(_)) => {
+                    self.walk_expr(*lhs, Ctx::Place);
+                    self.walk_expr(*rhs, Ctx::Place);
+                }
+                Some(BinaryOp::LogicOp(_)) => {
+                    self.walk_expr(*lhs, Ctx::Move);
+                    // The right hand side is evaluated conditionally.
+                    let before = self.state.clone();
+                    self.walk_expr(*rhs, Ctx::Move);
+                    self.state = merge(before, self.state.take());
+                }
+                Some(BinaryOp::ArithOp(_)) | None => {
+                    self.walk_expr(*lhs, Ctx::Move);
+                    self.walk_expr(*rhs, Ctx::Move);
+                }
+            },
+            Expr::Range { lhs, rhs, .. } => {
+                for &operand in lhs.iter().chain(rhs.iter()) {
+                    self.walk_expr(operand, Ctx::Move);
+                }
+            }
+            Expr::Index { base, index } => {
+                self.walk_expr(*base, Ctx::Place);
+                self.walk_expr(*index, Ctx::Move);
+            }
+            Expr::Closure { args, body: closure_body, .. } => {
+                self.walk_closure(expr, args, *closure_body)
+            }
+            Expr::Tuple { exprs, .. } => {
+                for &elem in exprs.iter() {
+                    self.walk_expr(elem, Ctx::Move);
+                }
+            }
+            Expr::Array(Array::ElementList { elements, .. }) => {
+                for &elem in elements.iter() {
+                    self.walk_expr(elem, Ctx::Move);
+                }
+            }
+            Expr::Array(Array::Repeat { initializer, .. }) => {
+                self.walk_expr(*initializer, Ctx::Move)
+            }
+        }
+    }
+
+    fn walk_path(&mut self, expr: ExprId, ctx: Ctx) {
+        let pat = match self.resolve_local(expr) {
+            Some(it) => it,
+            None => return,
+        };
+        let moved_at = match &self.state {
+            Some(state) => state.get(&pat).copied(),
+            None => return,
+        };
+        if let Some(moved_at) = moved_at {
+            if self.reported.insert(expr) {
+                self.res.push(MovedValueUse { binding: pat, moved_at, used_at: expr });
+            }
+            return;
+        }
+        if ctx == Ctx::Move && self.is_move(expr) {
+            if let Some(state) = &mut self.state {
+                state.insert(pat, expr);
+            }
+            if let Some(closure_moves) = &mut self.closure_moves {
+                closure_moves.insert(pat, expr);
+            }
+        }
+    }
+
+    /// Walks the left hand side of an assignment; assigning to a whole binding reinitializes it.
+    fn walk_assignee(&mut self, lhs: ExprId) {
+        let body = self.body;
+        match &body.exprs[lhs] {
+            Expr::Tuple { exprs, is_assignee_expr: true }
+            | Expr::Array(Array::ElementList { elements: exprs, is_assignee_expr: true }) => {
+                for &expr in exprs.iter() {
+                    self.walk_assignee(expr);
+                }
+            }
+            Expr::Call { args, is_assignee_expr: true, .. } => {
+                for &arg in args.iter() {
+                    self.walk_assignee(arg);
+                }
+            }
+            Expr::RecordLit { fields, is_assignee_expr: true, .. } => {
+                for field in fields.iter() {
+                    self.walk_assignee(field.expr);
+                }
+            }
+            Expr::Underscore | Expr::Range { .. } => {}
+            Expr::Path(_) => match self.resolve_local(lhs) {
+                Some(pat) => {
+                    if let Some(state) = &mut self.state {
+                        state.remove(&pat);
+                    }
+                }
+                None => self.walk_expr(lhs, Ctx::Place),
+            },
+            _ => self.walk_expr(lhs, Ctx::Place),
+        }
+    }
+
+    fn walk_loop(
+        &mut self,
+        label: Option<LabelId>,
+        condition: Option<ExprId>,
+        pat: Option<PatId>,
+        loop_body: ExprId,
+        exits_normally: bool,
+    ) {
+        let label = label.map(|it| self.label_name(it));
+        self.push_breakable(label, true);
+        let mut entry = self.state.clone();
+        let mut exit = None;
+        // The second iteration finds uses of values moved in the first one.
+        for _ in 0..2 {
+            self.state = entry.clone();
+            if let Some(condition) = condition {
+                self.walk_expr(condition, Ctx::Move);
+            }
+            if exits_normally {
+                exit = merge(exit, self.state.clone());
+            }
+            if let Some(pat) = pat {
+                self.bind_pat(pat);
+            }
+            self.walk_expr(loop_body, Ctx::Move);
+            let continues = self.breakables.last_mut().unwrap().continues.take();
+            entry = merge(merge(entry, self.state.take()), continues);
+        }
+        let breakable = self.breakables.pop().unwrap();
+        self.state = merge(exit, breakable.breaks);
+    }
+
+    fn walk_closure(&mut self, closure: ExprId, args: &[PatId], closure_body: ExprId) {
+        let outer_state = self.state.clone();
+        let outer_breakables = std::mem::take(&mut self.breakables);
+        let outer_closure_moves = self.closure_moves.replace(Moves::default());
+        for &arg in args {
+            self.bind_pat(arg);
+        }
+        self.walk_expr(closure_body, Ctx::Move);
+        self.breakables = outer_breakables;
+        let closure_moves =
+            std::mem::replace(&mut self.closure_moves, outer_closure_moves).unwrap_or_default();
+        self.state = outer_state;
+        // Values moved inside of the closure are moved into it when it is created.
+        if let Some(state) = &mut self.state {
+            for pat in closure_moves.keys() {
+                state.entry(*pat).or_insert(closure);
+            }
+        }
+        if let Some(outer) = &mut self.closure_moves {
+            for pat in closure_moves.keys() {
+                outer.entry(*pat).or_insert(closure);
+            }
+        }
+    }
+
+    fn push_breakable(&mut self, label: Option<Name>, is_loop: bool) {
+        self.breakables.push(Breakable { label, is_loop, breaks: None, continues: None });
+    }
+
+    fn find_breakable(
+        &mut self,
+        label: &Option<Name>,
+        is_continue: bool,
+    ) -> Option<&mut Breakable> {
+        self.breakables.iter_mut().rev().find(|it| {
+            let matches_label = match label {
+                Some(label) => it.label.as_ref() == Some(label),
+                // Unlabeled `break`s and `continue`s never target blocks.
+                None => it.is_loop,
+            };
+            matches_label && (it.is_loop || !is_continue)
+        })
+    }
+
+    fn label_name(&self, label: LabelId) -> Name {
+        self.body.labels[label].name.clone()
+    }
+
+    /// Introduces fresh bindings, which are not moved yet.
+    fn bind_pat(&mut self, pat: PatId) {
+        let body = self.body;
+        if let Some(state) = &mut self.state {
+            let mut stack = vec![pat];
+            while let Some(pat) = stack.pop() {
+                if let Pat::Bind { .. } = &body.pats[pat] {
+                    state.remove(&body.pattern_representative(pat));
+                }
+                body.pats[pat].walk_child_pats(|child| stack.push(child));
+            }
+        }
+    }
+
+    /// Whether `let pat = init` moves the whole `init`.
+    fn is_by_value_binding(&self, pat: PatId) -> bool {
+        match &self.body.pats[pat] {
+            Pat::Bind {
+                mode: BindingAnnotation::Unannotated | BindingAnnotation::Mutable, ..
+            } => {
+                matches!(self.infer.pat_binding_modes.get(&pat), None | Some(BindingMode::Move))
+            }
+            _ => false,
+        }
+    }
+
+    /// Stops the flow after calls of functions returning `!`.
+    fn check_diverging(&mut self, expr: ExprId) {
+        if self.infer[expr].is_never() {
+            self.state = None;
+        }
+    }
+
+    fn resolve_local(&self, expr: ExprId) -> Option<PatId> {
+        let path = match &self.body.exprs[expr] {
+            Expr::Path(path) => path,
+            _ => return None,
+        };
+        let resolver = resolver_for_expr(self.db.upcast(), self.def, expr);
+        match resolver.resolve_path_in_value_ns_fully(self.db.upcast(), path.mod_path()) {
+            Some(ValueNs::LocalBinding(pat)) => Some(self.body.pattern_representative(pat)),
+            _ => None,
+        }
+    }
+
+    /// Whether using the value of `expr` moves it rather than copying or borrowing it.
+    fn is_move(&self, expr: ExprId) -> bool {
+        if let Some(adjustments) = self.infer.expr_adjustments.get(&expr) {
+            // Auto-borrows and reborrows don't move, and we can't move out of a dereference.
+            if adjustments
+                .iter()
+                .any(|adj| matches!(adj.kind, Adjust::Borrow(_) | Adjust::Deref(_)))
+            {
+                return false;
+            }
+        }
+        !self.is_copy(&self.infer[expr])
+    }
+
+    fn is_copy(&self, ty: &Ty) -> bool {
+        // References may be reborrowed implicitly, don't bother with them.
+        if let TyKind::Ref(..) = ty.kind(Interner) {
+            return true;
+        }
+        if ty.data(Interner).flags.intersects(TypeFlags::HAS_ERROR) {
+            return true;
+        }
+        let ty = Canonical { value: ty.clone(), binders: CanonicalVarKinds::empty(Interner) };
+        implements_trait(&ty, self.db, self.env.clone(), self.copy_trait)
+    }
+}
@@ -18,6 +18,7 @@ mod inhabitedness;
 mod interner;
 mod lower;
 mod mapping;
+pub mod mir;
 mod tls;
 mod utils;
 mod walk;
@@ -51,7 +52,7 @@ pub use builder::{ParamKind, TyBuilder};
 pub use chalk_ext::*;
 pub use infer::{
     could_coerce, could_unify, Adjust, Adjustment, AutoBorrow, BindingMode, InferenceDiagnostic,
-    InferenceResult,
+    InferenceResult, PointerCast, TypeMismatch,
 };
 pub use interner::Interner;
 pub use lower::{
@@ -64,7 +65,7 @@ pub use mapping::{
     to_placeholder_idx,
 };
 pub use traits::TraitEnvironment;
-pub use utils::{all_super_traits, is_fn_unsafe_to_call};
+pub use utils::{all_super_traits, direct_super_traits, is_fn_unsafe_to_call};
 pub use walk::TypeWalk;
 
 pub use chalk_ir::{
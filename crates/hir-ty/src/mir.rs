@@ -0,0 +1,442 @@
+//! MIR definitions and implementation
+
+use crate::{
+    db::HirDatabase, mapping::ToChalk, CallableDefId, Const, Interner, PointerCast, Substitution,
+    Ty, TyKind,
+};
+use chalk_ir::Mutability;
+use hir_def::{
+    expr::{ExprId, Literal, Ordering, PatId},
+    type_ref::ConstScalar,
+    DefWithBodyId, FieldId, FunctionId, VariantId,
+};
+use la_arena::{Arena, ArenaMap, Idx, RawIdx};
+use smallvec::{smallvec, SmallVec};
+
+mod eval;
+mod lower;
+
+pub use eval::{interpret_mir, MirEvalError};
+pub use lower::{lower_to_mir, MirLowerError};
+pub(crate) use lower::{mir_body_query, mir_body_recover};
+
+/// A local variable of the MIR body. This is either the return slot, an argument, a user
+/// declared binding or a temporary introduced during lowering.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Local {
+    pub mutability: Mutability,
+    pub ty: Ty,
+}
+
+pub type LocalId = Idx<Local>;
+
+/// A value used by a statement or terminator.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Operand {
+    /// Creates a value by loading the given place. The type of the place must be `Copy`.
+    Copy(Place),
+    /// Creates a value by loading the given place, leaving it uninitialized afterwards.
+    Move(Place),
+    Constant(Constant),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Constant {
+    pub kind: ConstantKind,
+    pub ty: Ty,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConstantKind {
+    /// A literal written in the source code.
+    Literal(Literal),
+    /// A reference to a `const` item, evaluated lazily by the interpreter.
+    Const(hir_def::ConstId),
+    /// An already computed constant, e.g. a const generic argument.
+    Value(Const),
+    /// A zero sized value whose meaning is carried by its type, e.g. a function item.
+    Zst,
+}
+
+impl Operand {
+    fn from_literal(literal: Literal, ty: Ty) -> Self {
+        Operand::Constant(Constant { kind: ConstantKind::Literal(literal), ty })
+    }
+
+    fn from_const(konst: Const, ty: Ty) -> Self {
+        Operand::Constant(Constant { kind: ConstantKind::Value(konst), ty })
+    }
+
+    fn from_scalar(value: ConstScalar, ty: Ty) -> Self {
+        Operand::from_const(crate::consteval::intern_const_scalar(value, ty.clone()), ty)
+    }
+
+    fn from_fn(db: &dyn HirDatabase, func: FunctionId, generic_args: Substitution) -> Self {
+        let ty = TyKind::FnDef(CallableDefId::FunctionId(func).to_chalk(db), generic_args)
+            .intern(Interner);
+        Operand::Constant(Constant { kind: ConstantKind::Zst, ty })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ProjectionElem {
+    Deref,
+    Field(FieldId),
+    TupleField(usize),
+    Index(LocalId),
+    ConstantIndex { offset: u64, from_end: bool },
+}
+
+/// A place in memory, i.e. a local together with a sequence of projections applied to it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Place {
+    pub local: LocalId,
+    pub projection: Vec<ProjectionElem>,
+}
+
+impl Place {
+    fn project(&self, projection: ProjectionElem) -> Place {
+        Place {
+            local: self.local,
+            projection: self.projection.iter().cloned().chain([projection]).collect(),
+        }
+    }
+}
+
+impl From<Operand> for Rvalue {
+    fn from(x: Operand) -> Self {
+        Rvalue::Use(x)
+    }
+}
+
+impl From<LocalId> for Place {
+    fn from(local: LocalId) -> Self {
+        Self { local, projection: vec![] }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AggregateKind {
+    /// The type is of the element
+    Array(Ty),
+    /// The type is of the tuple
+    Tuple(Ty),
+    Adt(VariantId),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchTargets {
+    /// Possible values. The locations to branch to in each case
+    /// are found in the corresponding indices from the `targets` vector.
+    values: SmallVec<[u128; 1]>,
+
+    /// Possible branch sites. The last element of this vector is used
+    /// for the otherwise branch, so targets.len() == values.len() + 1
+    /// should hold.
+    targets: SmallVec<[BasicBlockId; 2]>,
+}
+
+impl SwitchTargets {
+    /// Creates switch targets from an iterator of values and target blocks.
+    ///
+    /// The iterator may be empty, in which case the `SwitchInt` instruction is equivalent to
+    /// `goto otherwise;`.
+    pub fn new(
+        targets: impl Iterator<Item = (u128, BasicBlockId)>,
+        otherwise: BasicBlockId,
+    ) -> Self {
+        let (values, mut targets): (SmallVec<_>, SmallVec<_>) = targets.unzip();
+        targets.push(otherwise);
+        Self { values, targets }
+    }
+
+    /// Builds a switch targets definition that jumps to `then` if the tested value equals `value`,
+    /// and to `else_` if not.
+    pub fn static_if(value: u128, then: BasicBlockId, else_: BasicBlockId) -> Self {
+        Self { values: smallvec![value], targets: smallvec![then, else_] }
+    }
+
+    /// Returns the fallback target that is jumped to when none of the values match the operand.
+    pub fn otherwise(&self) -> BasicBlockId {
+        *self.targets.last().unwrap()
+    }
+
+    /// Returns an iterator over the switch targets.
+    ///
+    /// The iterator will yield tuples containing the value and corresponding target to jump to, not
+    /// including the `otherwise` fallback target.
+    ///
+    /// Note that this may yield 0 elements. Only the `otherwise` branch is mandatory.
+    pub fn iter(&self) -> impl Iterator<Item = (u128, BasicBlockId)> + '_ {
+        self.values.iter().zip(&self.targets).map(|(x, y)| (*x, *y))
+    }
+
+    /// Returns a slice with all possible jump targets (including the fallback target).
+    pub fn all_targets(&self) -> &[BasicBlockId] {
+        &self.targets
+    }
+
+    /// Finds the `BasicBlock` to which this `SwitchInt` will branch given the
+    /// specific value. This cannot fail, as it'll return the `otherwise`
+    /// branch if there's not a specific match for the value.
+    pub fn target_for_value(&self, value: u128) -> BasicBlockId {
+        self.iter().find_map(|(v, t)| (v == value).then(|| t)).unwrap_or_else(|| self.otherwise())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Terminator {
+    Goto {
+        target: BasicBlockId,
+    },
+    SwitchInt {
+        discr: Operand,
+        targets: SwitchTargets,
+    },
+    /// Calls `func` with `args`, writing the result to `destination` and continuing at `target`.
+    /// `target` is `None` if the call never returns.
+    Call {
+        func: Operand,
+        args: Vec<Operand>,
+        destination: Place,
+        target: Option<BasicBlockId>,
+    },
+    Return,
+    Unreachable,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum BorrowKind {
+    /// Data must be immutable and is aliasable.
+    Shared,
+    /// Data is mutable and not aliasable.
+    Mut,
+}
+
+impl BorrowKind {
+    fn from_hir(m: hir_def::type_ref::Mutability) -> Self {
+        match m {
+            hir_def::type_ref::Mutability::Shared => BorrowKind::Shared,
+            hir_def::type_ref::Mutability::Mut => BorrowKind::Mut,
+        }
+    }
+
+    fn from_chalk(m: Mutability) -> Self {
+        match m {
+            Mutability::Not => BorrowKind::Shared,
+            Mutability::Mut => BorrowKind::Mut,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UnOp {
+    /// The `!` operator for logical inversion
+    Not,
+    /// The `-` operator for negation
+    Neg,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BinOp {
+    /// The `+` operator (addition)
+    Add,
+    /// The `-` operator (subtraction)
+    Sub,
+    /// The `*` operator (multiplication)
+    Mul,
+    /// The `/` operator (division)
+    ///
+    /// Division by zero is UB, because the compiler should have inserted checks
+    /// prior to this.
+    Div,
+    /// The `%` operator (modulus)
+    ///
+    /// Using zero as the denominator is UB, because the compiler should have
+    /// inserted checks prior to this.
+    Rem,
+    /// The `^` operator (bitwise xor)
+    BitXor,
+    /// The `&` operator (bitwise and)
+    BitAnd,
+    /// The `|` operator (bitwise or)
+    BitOr,
+    /// The `<<` operator (shift left)
+    ///
+    /// The offset is truncated to the size of the first operand before shifting.
+    Shl,
+    /// The `>>` operator (shift right)
+    ///
+    /// The offset is truncated to the size of the first operand before shifting.
+    Shr,
+    /// The `==` operator (equality)
+    Eq,
+    /// The `<` operator (less than)
+    Lt,
+    /// The `<=` operator (less than or equal to)
+    Le,
+    /// The `!=` operator (not equal to)
+    Ne,
+    /// The `>=` operator (greater than or equal to)
+    Ge,
+    /// The `>` operator (greater than)
+    Gt,
+}
+
+impl From<hir_def::expr::ArithOp> for BinOp {
+    fn from(op: hir_def::expr::ArithOp) -> Self {
+        match op {
+            hir_def::expr::ArithOp::Add => BinOp::Add,
+            hir_def::expr::ArithOp::Mul => BinOp::Mul,
+            hir_def::expr::ArithOp::Sub => BinOp::Sub,
+            hir_def::expr::ArithOp::Div => BinOp::Div,
+            hir_def::expr::ArithOp::Rem => BinOp::Rem,
+            hir_def::expr::ArithOp::Shl => BinOp::Shl,
+            hir_def::expr::ArithOp::Shr => BinOp::Shr,
+            hir_def::expr::ArithOp::BitXor => BinOp::BitXor,
+            hir_def::expr::ArithOp::BitOr => BinOp::BitOr,
+            hir_def::expr::ArithOp::BitAnd => BinOp::BitAnd,
+        }
+    }
+}
+
+impl From<hir_def::expr::CmpOp> for BinOp {
+    fn from(op: hir_def::expr::CmpOp) -> Self {
+        match op {
+            hir_def::expr::CmpOp::Eq { negated: false } => BinOp::Eq,
+            hir_def::expr::CmpOp::Eq { negated: true } => BinOp::Ne,
+            hir_def::expr::CmpOp::Ord { ordering: Ordering::Greater, strict: false } => BinOp::Ge,
+            hir_def::expr::CmpOp::Ord { ordering: Ordering::Greater, strict: true } => BinOp::Gt,
+            hir_def::expr::CmpOp::Ord { ordering: Ordering::Less, strict: false } => BinOp::Le,
+            hir_def::expr::CmpOp::Ord { ordering: Ordering::Less, strict: true } => BinOp::Lt,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CastKind {
+    /// All sorts of pointer-to-pointer casts, including unsizing coercions.
+    Pointer(PointerCast),
+    IntToInt,
+    FloatToInt,
+    FloatToFloat,
+    IntToFloat,
+    PtrToPtr,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Rvalue {
+    /// Yields the operand unchanged
+    Use(Operand),
+
+    /// Creates an array where each element is the value of the operand.
+    ///
+    /// The length of the array is given by the constant, which must be an evaluated `usize`.
+    Repeat(Operand, Const),
+
+    /// Creates a reference of the indicated kind to the place.
+    Ref(BorrowKind, Place),
+
+    /// Yields the length of the place, as a `usize`.
+    ///
+    /// If the type of the place is an array, this is the array length. For slices (`[T]`, not
+    /// `&[T]`) this accesses the place's metadata to determine the length.
+    Len(Place),
+
+    /// Performs essentially all of the casts that can be performed via `as`.
+    ///
+    /// This allows for casts from/to a variety of types.
+    Cast(CastKind, Operand, Ty),
+
+    /// Yields the result of the binary operation. Both operands must have a builtin scalar
+    /// type, the type of the result is determined by the operator.
+    BinaryOp(BinOp, Operand, Operand),
+
+    /// Exactly like `BinaryOp`, but for the unary operators.
+    UnaryOp(UnOp, Operand),
+
+    /// Computes the discriminant of the place, returning it as an integer of the enum's
+    /// representation type.
+    Discriminant(Place),
+
+    /// Creates an aggregate value, like a tuple, struct or an enum variant.
+    ///
+    /// The operands are given in field order.
+    Aggregate(AggregateKind, Vec<Operand>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StatementKind {
+    Assign(Place, Rvalue),
+    Nop,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: MirSpan,
+}
+
+impl StatementKind {
+    fn with_span(self, span: MirSpan) -> Statement {
+        Statement { kind: self, span }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct BasicBlock {
+    /// List of statements in this block.
+    pub statements: Vec<Statement>,
+
+    /// Terminator for this block.
+    ///
+    /// This is only `None` during construction.
+    pub terminator: Option<Terminator>,
+}
+
+pub type BasicBlockId = Idx<BasicBlock>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirBody {
+    pub basic_blocks: Arena<BasicBlock>,
+    pub locals: Arena<Local>,
+    pub start_block: BasicBlockId,
+    pub owner: DefWithBodyId,
+    /// The number of arguments of the body. Arguments are stored in the locals right after
+    /// the return slot.
+    pub arg_count: usize,
+    /// The locals that the user declared bindings are stored in.
+    pub binding_locals: ArenaMap<PatId, LocalId>,
+}
+
+impl MirBody {
+    /// The local that holds the return value of the body.
+    pub fn return_slot(&self) -> LocalId {
+        LocalId::from_raw(RawIdx::from(0))
+    }
+
+    /// The locals holding the arguments of the body, in order.
+    pub fn param_locals(&self) -> impl Iterator<Item = LocalId> {
+        (1..=self.arg_count as u32).map(|x| LocalId::from_raw(RawIdx::from(x)))
+    }
+}
+
+/// The source code construct a MIR statement originates from.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MirSpan {
+    ExprId(ExprId),
+    PatId(PatId),
+    Unknown,
+}
+
+impl From<ExprId> for MirSpan {
+    fn from(x: ExprId) -> Self {
+        MirSpan::ExprId(x)
+    }
+}
+
+impl From<PatId> for MirSpan {
+    fn from(x: PatId) -> Self {
+        MirSpan::PatId(x)
+    }
+}
@@ -0,0 +1,938 @@
+//! This module provides a MIR interpreter, which is used in const eval.
+
+use chalk_ir::{
+    fold::TypeFoldable, interner::HasInterner, DebruijnIndex, FloatTy, IntTy, PlaceholderIndex,
+    Scalar, UintTy,
+};
+use hir_def::{
+    builtin_type::{BuiltinFloat, BuiltinType},
+    expr::FloatTypeWrapper,
+    EnumVariantId,
+};
+use itertools::Either;
+
+use crate::{
+    consteval::{variant_discriminant, ComputedExpr, ConstEvalError},
+    db::HirDatabase,
+    fold_tys_and_consts, from_placeholder_idx, param_idx,
+    primitive::{builtin_int, builtin_uint, int_ty_from_builtin, uint_ty_from_builtin},
+    ConstValue, GenericArg, Interner, TyBuilder, TyExt, TyKind,
+};
+
+use super::*;
+
+/// The maximum number of values, counting the elements of nested arrays, that a single
+/// evaluation may create by repeating or copying arrays.
+const MAX_ARRAY_VALUES: usize = 1 << 20;
+/// The number of statements and terminators a single evaluation may execute, so that
+/// non-terminating consts can't hang analysis.
+const EXECUTION_LIMIT: usize = 100_000;
+/// The maximum depth of nested function calls.
+const STACK_DEPTH_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MirEvalError {
+    ConstEvalError(Box<ConstEvalError>),
+    /// Lowering the body of a called function failed.
+    MirLowerError(FunctionId, MirLowerError),
+    /// The evaluated code panicked, e.g. because of an arithmetic overflow.
+    Panic(String),
+    TypeError(&'static str),
+    NotSupported(String),
+    /// The evaluated code did something that is undefined behavior in Rust.
+    UndefinedBehavior(&'static str),
+    /// Reading a value that was never written.
+    UninitializedValue,
+    InvalidPointer,
+    ExecutionLimitExceeded,
+    StackOverflow,
+}
+
+impl From<ConstEvalError> for MirEvalError {
+    fn from(value: ConstEvalError) -> Self {
+        match value {
+            ConstEvalError::MirEvalError(e) => e,
+            _ => MirEvalError::ConstEvalError(Box::new(value)),
+        }
+    }
+}
+
+macro_rules! not_supported {
+    ($($x: tt)*) => {
+        return Err(MirEvalError::NotSupported(format!($($x)*)))
+    };
+}
+
+type Result<T> = std::result::Result<T, MirEvalError>;
+
+/// A value during interpretation. Composite values store their fields in declaration order.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Uninit,
+    Bool(bool),
+    Char(char),
+    Int(i128, IntTy),
+    Uint(u128, UintTy),
+    Float(f64, FloatTy),
+    /// The pointee of a `&str`.
+    Str(Box<str>),
+    Tuple(Vec<Value>),
+    /// Arrays and slices.
+    Array(Vec<Value>),
+    Adt(VariantId, Vec<Value>),
+    Ref(Pointer),
+}
+
+/// A pointer to an allocation on the interpreter heap, possibly into a nested field of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pointer {
+    alloc: usize,
+    path: Vec<usize>,
+}
+
+struct Evaluator<'a> {
+    db: &'a dyn HirDatabase,
+    /// Allocations are never freed, so pointers stay valid until evaluation finishes.
+    heap: Vec<Value>,
+    execution_limit: usize,
+    /// The number of values that may still be created by repeating or copying arrays.
+    array_values_limit: usize,
+    stack_depth: usize,
+}
+
+struct Frame<'a> {
+    body: &'a MirBody,
+    locals: ArenaMap<LocalId, usize>,
+    /// The generic arguments of the function being executed, used to replace the placeholders
+    /// in its body.
+    subst: Substitution,
+}
+
+/// Interprets the MIR body and converts its return value to a [`ComputedExpr`].
+pub fn interpret_mir(db: &dyn HirDatabase, body: &MirBody) -> Result<ComputedExpr> {
+    let mut evaluator = Evaluator {
+        db,
+        heap: vec![],
+        execution_limit: EXECUTION_LIMIT,
+        array_values_limit: MAX_ARRAY_VALUES,
+        stack_depth: 0,
+    };
+    let value = evaluator.interpret_body(body, vec![], Substitution::empty(Interner))?;
+    evaluator.to_computed_expr(&value)
+}
+
+impl Evaluator<'_> {
+    fn alloc(&mut self, value: Value) -> usize {
+        self.heap.push(value);
+        self.heap.len() - 1
+    }
+
+    fn interpret_body(
+        &mut self,
+        body: &MirBody,
+        args: Vec<Value>,
+        subst: Substitution,
+    ) -> Result<Value> {
+        let mut locals = ArenaMap::new();
+        for (id, local) in body.locals.iter() {
+            locals.insert(id, self.alloc(initial_value(&local.ty)));
+        }
+        let frame = Frame { body, locals, subst };
+        for (local, arg) in body.param_locals().zip(args) {
+            self.heap[frame.locals[local]] = arg;
+        }
+        let mut current = body.start_block;
+        loop {
+            let block = &body.basic_blocks[current];
+            self.execution_limit = self
+                .execution_limit
+                .checked_sub(block.statements.len() + 1)
+                .ok_or(MirEvalError::ExecutionLimitExceeded)?;
+            for statement in &block.statements {
+                match &statement.kind {
+                    StatementKind::Assign(place, rvalue) => {
+                        let value = self.eval_rvalue(&frame, rvalue)?;
+                        let addr = self.place_addr(&frame, place)?;
+                        self.write(&addr, value)?;
+                    }
+                    StatementKind::Nop => (),
+                }
+            }
+            let terminator = block
+                .terminator
+                .as_ref()
+                .ok_or(MirEvalError::TypeError("block without terminator"))?;
+            match terminator {
+                Terminator::Goto { target } => current = *target,
+                Terminator::SwitchInt { discr, targets } => {
+                    let value = self.eval_operand(&frame, discr)?;
+                    current = targets.target_for_value(switch_value(&value)?);
+                }
+                Terminator::Call { func, args, destination, target } => {
+                    let func_ty = match func {
+                        Operand::Constant(c) => self.subst(&frame, c.ty.clone()),
+                        _ => not_supported!("calling function pointers"),
+                    };
+                    let args = args
+                        .iter()
+                        .map(|op| self.eval_operand(&frame, op))
+                        .collect::<Result<Vec<_>>>()?;
+                    let result = self.exec_call(&func_ty, args)?;
+                    let addr = self.place_addr(&frame, destination)?;
+                    self.write(&addr, result)?;
+                    current = target.ok_or(MirEvalError::UndefinedBehavior(
+                        "returned from a diverging function",
+                    ))?;
+                }
+                Terminator::Return => {
+                    return Ok(self.heap[frame.locals[frame.body.return_slot()]].clone());
+                }
+                Terminator::Unreachable => {
+                    return Err(MirEvalError::UndefinedBehavior("unreachable code executed"))
+                }
+            }
+        }
+    }
+
+    fn exec_call(&mut self, func_ty: &Ty, args: Vec<Value>) -> Result<Value> {
+        let generic_args = match func_ty.kind(Interner) {
+            TyKind::FnDef(_, subst) => subst.clone(),
+            _ => return Err(MirEvalError::TypeError("call of a non function value")),
+        };
+        match func_ty.callable_def(self.db) {
+            Some(CallableDefId::FunctionId(f)) => self.exec_fn(f, args, generic_args),
+            Some(CallableDefId::StructId(s)) => Ok(Value::Adt(s.into(), args)),
+            Some(CallableDefId::EnumVariantId(v)) => Ok(Value::Adt(v.into(), args)),
+            None => Err(MirEvalError::TypeError("call of a non function value")),
+        }
+    }
+
+    fn exec_fn(
+        &mut self,
+        func: FunctionId,
+        args: Vec<Value>,
+        generic_args: Substitution,
+    ) -> Result<Value> {
+        let data = self.db.function_data(func);
+        if !data.has_const_kw() {
+            not_supported!("call to non-const function `{}`", data.name);
+        }
+        if self.stack_depth >= STACK_DEPTH_LIMIT {
+            return Err(MirEvalError::StackOverflow);
+        }
+        let body =
+            self.db.mir_body(func.into()).map_err(|e| MirEvalError::MirLowerError(func, e))?;
+        self.stack_depth += 1;
+        let result = self.interpret_body(&body, args, generic_args);
+        self.stack_depth -= 1;
+        result
+    }
+
+    /// Replaces the generic parameters of the executed function in `t` with its generic arguments.
+    fn subst<T: HasInterner<Interner = Interner> + TypeFoldable<Interner>>(
+        &self,
+        frame: &Frame<'_>,
+        t: T,
+    ) -> T {
+        if frame.subst.is_empty(Interner) {
+            return t;
+        }
+        let generic_arg = |idx: PlaceholderIndex| -> Option<GenericArg> {
+            let param = from_placeholder_idx(self.db, idx);
+            frame.subst.as_slice(Interner).get(param_idx(self.db, param)?).cloned()
+        };
+        fold_tys_and_consts(
+            t,
+            |x, _| match x {
+                Either::Left(ty) => Either::Left(match ty.kind(Interner) {
+                    TyKind::Placeholder(idx) => {
+                        generic_arg(*idx).and_then(|arg| arg.ty(Interner).cloned()).unwrap_or(ty)
+                    }
+                    _ => ty,
+                }),
+                Either::Right(c) => Either::Right(match &c.data(Interner).value {
+                    ConstValue::Placeholder(idx) => generic_arg(*idx)
+                        .and_then(|arg| arg.constant(Interner).cloned())
+                        .unwrap_or(c),
+                    _ => c,
+                }),
+            },
+            DebruijnIndex::INNERMOST,
+        )
+    }
+
+    fn place_addr(&self, frame: &Frame<'_>, place: &Place) -> Result<Pointer> {
+        let mut addr = Pointer { alloc: frame.locals[place.local], path: vec![] };
+        for proj in &place.projection {
+            match proj {
+                ProjectionElem::Deref => match self.read(&addr)? {
+                    Value::Ref(p) => addr = p.clone(),
+                    _ => return Err(MirEvalError::TypeError("deref of a non pointer value")),
+                },
+                ProjectionElem::Field(f) => {
+                    addr.path.push(u32::from(f.local_id.into_raw()) as usize)
+                }
+                ProjectionElem::TupleField(i) => addr.path.push(*i),
+                ProjectionElem::Index(local) => {
+                    let index = match &self.heap[frame.locals[*local]] {
+                        Value::Uint(x, _) => *x as usize,
+                        _ => return Err(MirEvalError::TypeError("index is not an usize")),
+                    };
+                    let len = self.len(&addr)?;
+                    if index >= len {
+                        return Err(MirEvalError::Panic(format!(
+                            "index out of bounds: the len is {len} but the index is {index}"
+                        )));
+                    }
+                    addr.path.push(index);
+                }
+                ProjectionElem::ConstantIndex { offset, from_end } => {
+                    let len = self.len(&addr)?;
+                    let offset = *offset as usize;
+                    let index = if *from_end { len.checked_sub(offset) } else { Some(offset) };
+                    match index {
+                        Some(index) if index < len => addr.path.push(index),
+                        _ => return Err(MirEvalError::TypeError("constant index out of bounds")),
+                    }
+                }
+            }
+        }
+        Ok(addr)
+    }
+
+    fn read(&self, p: &Pointer) -> Result<&Value> {
+        let mut value = self.heap.get(p.alloc).ok_or(MirEvalError::InvalidPointer)?;
+        for &i in &p.path {
+            value = match value {
+                Value::Tuple(fields) | Value::Array(fields) | Value::Adt(_, fields) => {
+                    fields.get(i).ok_or(MirEvalError::InvalidPointer)?
+                }
+                Value::Uninit => return Err(MirEvalError::UninitializedValue),
+                _ => return Err(MirEvalError::TypeError("projection of a scalar value")),
+            };
+        }
+        Ok(value)
+    }
+
+    fn write(&mut self, p: &Pointer, v: Value) -> Result<()> {
+        let mut value = self.heap.get_mut(p.alloc).ok_or(MirEvalError::InvalidPointer)?;
+        for &i in &p.path {
+            value = match value {
+                Value::Tuple(fields) | Value::Array(fields) | Value::Adt(_, fields) => {
+                    fields.get_mut(i).ok_or(MirEvalError::InvalidPointer)?
+                }
+                Value::Uninit => return Err(MirEvalError::UninitializedValue),
+                _ => return Err(MirEvalError::TypeError("projection of a scalar value")),
+            };
+        }
+        *value = v;
+        Ok(())
+    }
+
+    fn charge_array_values(&mut self, count: usize) -> Result<()> {
+        self.array_values_limit = match self.array_values_limit.checked_sub(count) {
+            Some(it) => it,
+            None => not_supported!("arrays with more than {} values in total", MAX_ARRAY_VALUES),
+        };
+        Ok(())
+    }
+
+    fn len(&self, p: &Pointer) -> Result<usize> {
+        match self.read(p)? {
+            Value::Array(elements) => Ok(elements.len()),
+            Value::Str(s) => Ok(s.len()),
+            _ => Err(MirEvalError::TypeError("length of a non array value")),
+        }
+    }
+
+    fn eval_rvalue(&mut self, frame: &Frame<'_>, rvalue: &Rvalue) -> Result<Value> {
+        Ok(match rvalue {
+            Rvalue::Use(op) => self.eval_operand(frame, op)?,
+            Rvalue::Ref(_, place) => Value::Ref(self.place_addr(frame, place)?),
+            Rvalue::Len(place) => {
+                let addr = self.place_addr(frame, place)?;
+                Value::Uint(self.len(&addr)? as u128, UintTy::Usize)
+            }
+            Rvalue::Repeat(op, len) => {
+                let len = self.subst(frame, len.clone());
+                let len = match self.const_to_value(&len, &TyBuilder::usize())? {
+                    Value::Uint(x, _) => x as usize,
+                    _ => return Err(MirEvalError::TypeError("array length is not an usize")),
+                };
+                let value = self.eval_operand(frame, op)?;
+                self.charge_array_values(value_count(&value).saturating_mul(len))?;
+                Value::Array(vec![value; len])
+            }
+            Rvalue::Cast(kind, op, target_ty) => {
+                let value = self.eval_operand(frame, op)?;
+                eval_cast(kind, value, &self.subst(frame, target_ty.clone()))?
+            }
+            Rvalue::BinaryOp(op, lhs, rhs) => {
+                let lhs = self.eval_operand(frame, lhs)?;
+                let rhs = self.eval_operand(frame, rhs)?;
+                self.eval_binop(op, lhs, rhs)?
+            }
+            Rvalue::UnaryOp(op, operand) => {
+                let value = self.eval_operand(frame, operand)?;
+                eval_unop(op, value)?
+            }
+            Rvalue::Discriminant(place) => {
+                let addr = self.place_addr(frame, place)?;
+                match self.read(&addr)? {
+                    Value::Adt(VariantId::EnumVariantId(v), _) => self.discriminant_value(*v)?,
+                    _ => return Err(MirEvalError::TypeError("discriminant of a non enum value")),
+                }
+            }
+            Rvalue::Aggregate(kind, values) => {
+                let values = values
+                    .iter()
+                    .map(|op| self.eval_operand(frame, op))
+                    .collect::<Result<Vec<_>>>()?;
+                match kind {
+                    AggregateKind::Array(_) => Value::Array(values),
+                    AggregateKind::Tuple(_) => Value::Tuple(values),
+                    AggregateKind::Adt(variant) => Value::Adt(*variant, values),
+                }
+            }
+        })
+    }
+
+    fn eval_operand(&mut self, frame: &Frame<'_>, op: &Operand) -> Result<Value> {
+        Ok(match op {
+            Operand::Copy(place) | Operand::Move(place) => {
+                let addr = self.place_addr(frame, place)?;
+                let value = match self.read(&addr)? {
+                    Value::Uninit => return Err(MirEvalError::UninitializedValue),
+                    value => value.clone(),
+                };
+                if let Value::Array(_) = value {
+                    self.charge_array_values(value_count(&value))?;
+                }
+                value
+            }
+            Operand::Constant(Constant { kind, ty }) => {
+                let ty = &self.subst(frame, ty.clone());
+                match kind {
+                    ConstantKind::Literal(l) => self.literal_to_value(l, ty)?,
+                    ConstantKind::Const(id) => {
+                        let computed = self.db.const_eval(*id)?;
+                        self.computed_to_value(&computed, ty)?
+                    }
+                    ConstantKind::Value(c) => {
+                        let c = self.subst(frame, c.clone());
+                        self.const_to_value(&c, ty)?
+                    }
+                    ConstantKind::Zst => Value::Tuple(vec![]),
+                }
+            }
+        })
+    }
+
+    fn literal_to_value(&mut self, l: &Literal, ty: &Ty) -> Result<Value> {
+        let ty = match ty.kind(Interner) {
+            TyKind::Scalar(_) => ty.clone(),
+            // Fall back to the type suffix if inference didn't give us a type.
+            _ => match l {
+                Literal::Int(_, Some(b)) => TyBuilder::builtin(BuiltinType::Int(*b)),
+                Literal::Uint(_, Some(b)) => TyBuilder::builtin(BuiltinType::Uint(*b)),
+                Literal::Float(_, Some(b)) => TyBuilder::builtin(BuiltinType::Float(*b)),
+                _ => ty.clone(),
+            },
+        };
+        Ok(match l {
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Char(c) => Value::Char(*c),
+            Literal::Int(x, _) => value_from_bits(*x as u128, &ty)?,
+            Literal::Uint(x, _) => value_from_bits(*x, &ty)?,
+            Literal::Float(f, _) => match ty.kind(Interner) {
+                TyKind::Scalar(Scalar::Float(t)) => Value::Float(f.clone().into_f64(), *t),
+                _ => return Err(MirEvalError::TypeError("float literal of non float type")),
+            },
+            Literal::String(s) => {
+                Value::Ref(Pointer { alloc: self.alloc(Value::Str(s.clone())), path: vec![] })
+            }
+            Literal::ByteString(bytes) => {
+                let bytes = bytes.iter().map(|&b| Value::Uint(b as u128, UintTy::U8)).collect();
+                Value::Ref(Pointer { alloc: self.alloc(Value::Array(bytes)), path: vec![] })
+            }
+        })
+    }
+
+    fn const_to_value(&mut self, c: &Const, ty: &Ty) -> Result<Value> {
+        match &c.data(Interner).value {
+            ConstValue::Concrete(c) => match &c.interned {
+                ConstScalar::Int(x) => value_from_bits(*x as u128, ty),
+                ConstScalar::UInt(x) => value_from_bits(*x, ty),
+                ConstScalar::Bool(b) => Ok(Value::Bool(*b)),
+                ConstScalar::Char(c) => Ok(Value::Char(*c)),
+                ConstScalar::Unknown => not_supported!("unknown constant"),
+            },
+            ConstValue::Placeholder(_) | ConstValue::BoundVar(_) => {
+                not_supported!("const generic without substitution")
+            }
+            ConstValue::InferenceVar(_) => not_supported!("unresolved constant"),
+        }
+    }
+
+    fn computed_to_value(&mut self, c: &ComputedExpr, ty: &Ty) -> Result<Value> {
+        Ok(match c {
+            ComputedExpr::Literal(l) => self.literal_to_value(l, ty)?,
+            ComputedExpr::Enum(_, v, _) => Value::Adt((*v).into(), vec![]),
+            ComputedExpr::Tuple(fields) => {
+                let tys =
+                    ty.as_tuple().ok_or(MirEvalError::TypeError("tuple of non tuple type"))?;
+                let values = fields
+                    .iter()
+                    .zip(tys.iter(Interner))
+                    .map(|(field, ty)| self.computed_to_value(field, ty.assert_ty_ref(Interner)))
+                    .collect::<Result<_>>()?;
+                Value::Tuple(values)
+            }
+            ComputedExpr::Adt { variant, fields, .. } => {
+                let subst = match ty.as_adt() {
+                    Some((_, subst)) => subst.clone(),
+                    None => return Err(MirEvalError::TypeError("adt value of non adt type")),
+                };
+                let field_types = self.db.field_types(*variant);
+                let values = field_types
+                    .iter()
+                    .zip(fields.iter())
+                    .map(|((_, field_ty), (_, field))| {
+                        self.computed_to_value(
+                            field,
+                            &field_ty.clone().substitute(Interner, &subst),
+                        )
+                    })
+                    .collect::<Result<_>>()?;
+                Value::Adt(*variant, values)
+            }
+            ComputedExpr::Array(elements) => {
+                let element_ty = match ty.kind(Interner) {
+                    TyKind::Array(ty, _) | TyKind::Slice(ty) => ty,
+                    _ => return Err(MirEvalError::TypeError("array value of non array type")),
+                };
+                let values = elements
+                    .iter()
+                    .map(|x| self.computed_to_value(x, element_ty))
+                    .collect::<Result<_>>()?;
+                Value::Array(values)
+            }
+            ComputedExpr::Reference(pointee) => {
+                let pointee_ty = ty
+                    .as_reference()
+                    .ok_or(MirEvalError::TypeError("reference value of non reference type"))?
+                    .0;
+                let value = self.computed_to_value(pointee, pointee_ty)?;
+                Value::Ref(Pointer { alloc: self.alloc(value), path: vec![] })
+            }
+        })
+    }
+
+    fn to_computed_expr(&self, value: &Value) -> Result<ComputedExpr> {
+        Ok(match value {
+            Value::Uninit => return Err(MirEvalError::UninitializedValue),
+            Value::Bool(_)
+            | Value::Char(_)
+            | Value::Int(..)
+            | Value::Uint(..)
+            | Value::Float(..) => ComputedExpr::Literal(scalar_to_literal(value)?),
+            Value::Tuple(fields) => ComputedExpr::Tuple(
+                fields.iter().map(|x| self.to_computed_expr(x)).collect::<Result<_>>()?,
+            ),
+            Value::Adt(VariantId::EnumVariantId(v), fields) if fields.is_empty() => {
+                let discriminant = scalar_to_literal(&self.discriminant_value(*v)?)?;
+                ComputedExpr::Enum(self.variant_name(*v), *v, discriminant)
+            }
+            Value::Adt(variant, fields) => {
+                let name = match variant {
+                    VariantId::StructId(s) => self.db.struct_data(*s).name.to_string(),
+                    VariantId::EnumVariantId(v) => self.variant_name(*v),
+                    VariantId::UnionId(_) => not_supported!("unions in const results"),
+                };
+                let variant_data = variant.variant_data(self.db.upcast());
+                let fields = variant_data
+                    .fields()
+                    .iter()
+                    .zip(fields)
+                    .map(|((_, data), value)| {
+                        Ok((data.name.to_string(), self.to_computed_expr(value)?))
+                    })
+                    .collect::<Result<_>>()?;
+                ComputedExpr::Adt { name, variant: *variant, kind: variant_data.kind(), fields }
+            }
+            Value::Array(elements) => ComputedExpr::Array(
+                elements.iter().map(|x| self.to_computed_expr(x)).collect::<Result<_>>()?,
+            ),
+            Value::Ref(p) => match self.read(p)? {
+                Value::Str(s) => ComputedExpr::Literal(Literal::String(s.clone())),
+                pointee => ComputedExpr::Reference(Box::new(self.to_computed_expr(pointee)?)),
+            },
+            Value::Str(s) => ComputedExpr::Literal(Literal::String(s.clone())),
+        })
+    }
+
+    fn variant_name(&self, v: EnumVariantId) -> String {
+        let enum_data = self.db.enum_data(v.parent);
+        format!("{}::{}", enum_data.name, enum_data.variants[v.local_id].name)
+    }
+
+    fn discriminant_value(&self, v: EnumVariantId) -> Result<Value> {
+        let value = variant_discriminant(self.db, v)?;
+        Ok(match self.db.enum_data(v.parent).variant_body_type() {
+            Either::Left(b) => Value::Int(value, int_ty_from_builtin(b)),
+            Either::Right(b) => Value::Uint(value as u128, uint_ty_from_builtin(b)),
+        })
+    }
+
+    fn values_eq(&self, lhs: &Value, rhs: &Value) -> Result<bool> {
+        Ok(match (lhs, rhs) {
+            (Value::Ref(l), Value::Ref(r)) => self.values_eq(self.read(l)?, self.read(r)?)?,
+            (Value::Tuple(l), Value::Tuple(r)) | (Value::Array(l), Value::Array(r)) => {
+                l.len() == r.len() && self.all_eq(l, r)?
+            }
+            (Value::Adt(lv, l), Value::Adt(rv, r)) => lv == rv && self.all_eq(l, r)?,
+            (Value::Uninit, _) | (_, Value::Uninit) => {
+                return Err(MirEvalError::UninitializedValue)
+            }
+            (l, r) => l == r,
+        })
+    }
+
+    fn all_eq(&self, lhs: &[Value], rhs: &[Value]) -> Result<bool> {
+        for (l, r) in lhs.iter().zip(rhs) {
+            if !self.values_eq(l, r)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn eval_binop(&self, op: &BinOp, lhs: Value, rhs: Value) -> Result<Value> {
+        match op {
+            BinOp::Eq => return Ok(Value::Bool(self.values_eq(&lhs, &rhs)?)),
+            BinOp::Ne => return Ok(Value::Bool(!self.values_eq(&lhs, &rhs)?)),
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                let ordering = match (&lhs, &rhs) {
+                    (Value::Int(l, _), Value::Int(r, _)) => l.partial_cmp(r),
+                    (Value::Uint(l, _), Value::Uint(r, _)) => l.partial_cmp(r),
+                    (Value::Float(l, _), Value::Float(r, _)) => l.partial_cmp(r),
+                    (Value::Bool(l), Value::Bool(r)) => l.partial_cmp(r),
+                    (Value::Char(l), Value::Char(r)) => l.partial_cmp(r),
+                    _ => return Err(MirEvalError::TypeError("comparison of incompatible values")),
+                };
+                let result = match ordering {
+                    Some(ordering) => match op {
+                        BinOp::Lt => ordering.is_lt(),
+                        BinOp::Le => ordering.is_le(),
+                        BinOp::Gt => ordering.is_gt(),
+                        _ => ordering.is_ge(),
+                    },
+                    // comparisons with NaN are always false
+                    None => false,
+                };
+                return Ok(Value::Bool(result));
+            }
+            BinOp::Shl | BinOp::Shr => {
+                let amount = match rhs {
+                    Value::Int(x, _) => u32::try_from(x).ok(),
+                    Value::Uint(x, _) => u32::try_from(x).ok(),
+                    _ => return Err(MirEvalError::TypeError("shift by a non integer amount")),
+                };
+                return eval_shift(op, lhs, amount);
+            }
+            _ => (),
+        }
+        match (lhs, rhs) {
+            (Value::Int(l, t), Value::Int(r, _)) => {
+                let result = match op {
+                    BinOp::Add => l.checked_add(r),
+                    BinOp::Sub => l.checked_sub(r),
+                    BinOp::Mul => l.checked_mul(r),
+                    BinOp::Div => {
+                        if r == 0 {
+                            return Err(MirEvalError::Panic("attempt to divide by zero".into()));
+                        }
+                        l.checked_div(r)
+                    }
+                    BinOp::Rem => {
+                        if r == 0 {
+                            return Err(MirEvalError::Panic(
+                                "attempt to calculate the remainder with a divisor of zero".into(),
+                            ));
+                        }
+                        l.checked_rem(r)
+                    }
+                    BinOp::BitAnd => Some(l & r),
+                    BinOp::BitOr => Some(l | r),
+                    BinOp::BitXor => Some(l ^ r),
+                    _ => return Err(MirEvalError::TypeError("invalid integer operation")),
+                };
+                match result {
+                    Some(x) if int_in_range(x, t) => Ok(Value::Int(x, t)),
+                    _ => Err(overflow_panic(op)),
+                }
+            }
+            (Value::Uint(l, t), Value::Uint(r, _)) => {
+                let result = match op {
+                    BinOp::Add => l.checked_add(r),
+                    BinOp::Sub => l.checked_sub(r),
+                    BinOp::Mul => l.checked_mul(r),
+                    BinOp::Div => {
+                        if r == 0 {
+                            return Err(MirEvalError::Panic("attempt to divide by zero".into()));
+                        }
+                        l.checked_div(r)
+                    }
+                    BinOp::Rem => {
+                        if r == 0 {
+                            return Err(MirEvalError::Panic(
+                                "attempt to calculate the remainder with a divisor of zero".into(),
+                            ));
+                        }
+                        l.checked_rem(r)
+                    }
+                    BinOp::BitAnd => Some(l & r),
+                    BinOp::BitOr => Some(l | r),
+                    BinOp::BitXor => Some(l ^ r),
+                    _ => return Err(MirEvalError::TypeError("invalid integer operation")),
+                };
+                match result {
+                    Some(x) if x <= uint_max(t) => Ok(Value::Uint(x, t)),
+                    _ => Err(overflow_panic(op)),
+                }
+            }
+            (Value::Float(l, t), Value::Float(r, _)) => {
+                let result = match op {
+                    BinOp::Add => l + r,
+                    BinOp::Sub => l - r,
+                    BinOp::Mul => l * r,
+                    BinOp::Div => l / r,
+                    BinOp::Rem => l % r,
+                    _ => return Err(MirEvalError::TypeError("invalid float operation")),
+                };
+                Ok(float_value(result, t))
+            }
+            (Value::Bool(l), Value::Bool(r)) => Ok(Value::Bool(match op {
+                BinOp::BitAnd => l & r,
+                BinOp::BitOr => l | r,
+                BinOp::BitXor => l ^ r,
+                _ => return Err(MirEvalError::TypeError("invalid bool operation")),
+            })),
+            _ => Err(MirEvalError::TypeError("binary operation on incompatible values")),
+        }
+    }
+}
+
+/// The number of values making up `value`, including the ones nested in it.
+fn value_count(value: &Value) -> usize {
+    match value {
+        Value::Tuple(values) | Value::Array(values) | Value::Adt(_, values) => {
+            values.iter().fold(1, |acc, it| acc.saturating_add(value_count(it)))
+        }
+        _ => 1,
+    }
+}
+
+/// The value a local holds before anything is written to it. Zero sized unit values are never
+/// written explicitly, so they start out initialized.
+fn initial_value(ty: &Ty) -> Value {
+    match ty.as_tuple() {
+        Some(subst) if subst.is_empty(Interner) => Value::Tuple(vec![]),
+        _ => Value::Uninit,
+    }
+}
+
+fn switch_value(value: &Value) -> Result<u128> {
+    Ok(match value {
+        Value::Bool(b) => *b as u128,
+        Value::Int(x, _) => *x as u128,
+        Value::Uint(x, _) => *x,
+        Value::Char(c) => *c as u128,
+        _ => return Err(MirEvalError::TypeError("switch on a non integer value")),
+    })
+}
+
+fn int_bits(t: IntTy) -> u32 {
+    match t {
+        IntTy::I8 => 8,
+        IntTy::I16 => 16,
+        IntTy::I32 => 32,
+        IntTy::I64 | IntTy::Isize => 64,
+        IntTy::I128 => 128,
+    }
+}
+
+fn uint_bits(t: UintTy) -> u32 {
+    match t {
+        UintTy::U8 => 8,
+        UintTy::U16 => 16,
+        UintTy::U32 => 32,
+        UintTy::U64 | UintTy::Usize => 64,
+        UintTy::U128 => 128,
+    }
+}
+
+fn uint_max(t: UintTy) -> u128 {
+    truncate(u128::MAX, uint_bits(t))
+}
+
+fn int_in_range(x: i128, t: IntTy) -> bool {
+    sign_extend(x as u128, int_bits(t)) == x
+}
+
+fn truncate(bits: u128, width: u32) -> u128 {
+    if width >= 128 {
+        bits
+    } else {
+        bits & ((1 << width) - 1)
+    }
+}
+
+fn sign_extend(bits: u128, width: u32) -> i128 {
+    if width >= 128 {
+        bits as i128
+    } else {
+        let shift = 128 - width;
+        ((bits << shift) as i128) >> shift
+    }
+}
+
+/// Creates an integer-like value of type `ty` from the given bits, wrapping them like `as`
+/// casts do.
+fn value_from_bits(bits: u128, ty: &Ty) -> Result<Value> {
+    Ok(match ty.kind(Interner) {
+        TyKind::Scalar(Scalar::Int(t)) => Value::Int(sign_extend(bits, int_bits(*t)), *t),
+        TyKind::Scalar(Scalar::Uint(t)) => Value::Uint(truncate(bits, uint_bits(*t)), *t),
+        TyKind::Scalar(Scalar::Bool) => Value::Bool(bits != 0),
+        TyKind::Scalar(Scalar::Char) => Value::Char(
+            char::from_u32(bits as u32).ok_or(MirEvalError::UndefinedBehavior("invalid char"))?,
+        ),
+        _ => return Err(MirEvalError::TypeError("integer value of non integer type")),
+    })
+}
+
+fn float_value(x: f64, t: FloatTy) -> Value {
+    match t {
+        FloatTy::F32 => Value::Float(x as f32 as f64, t),
+        FloatTy::F64 => Value::Float(x, t),
+    }
+}
+
+fn overflow_panic(op: &BinOp) -> MirEvalError {
+    let name = match op {
+        BinOp::Add => "add",
+        BinOp::Sub => "subtract",
+        BinOp::Mul => "multiply",
+        BinOp::Div => "divide",
+        BinOp::Rem => "calculate the remainder",
+        BinOp::Shl => "shift left",
+        BinOp::Shr => "shift right",
+        _ => "negate",
+    };
+    MirEvalError::Panic(format!("attempt to {name} with overflow"))
+}
+
+fn eval_shift(op: &BinOp, lhs: Value, amount: Option<u32>) -> Result<Value> {
+    let width = match &lhs {
+        Value::Int(_, t) => int_bits(*t),
+        Value::Uint(_, t) => uint_bits(*t),
+        _ => return Err(MirEvalError::TypeError("shift of a non integer value")),
+    };
+    let amount = match amount {
+        Some(x) if x < width => x,
+        _ => return Err(overflow_panic(op)),
+    };
+    Ok(match (lhs, op) {
+        (Value::Int(x, t), BinOp::Shl) => {
+            Value::Int(sign_extend((x as u128) << amount, int_bits(t)), t)
+        }
+        (Value::Int(x, t), _) => Value::Int(x >> amount, t),
+        (Value::Uint(x, t), BinOp::Shl) => Value::Uint(truncate(x << amount, uint_bits(t)), t),
+        (Value::Uint(x, t), _) => Value::Uint(x >> amount, t),
+        _ => return Err(MirEvalError::TypeError("shift of a non integer value")),
+    })
+}
+
+fn eval_unop(op: &UnOp, value: Value) -> Result<Value> {
+    Ok(match (op, value) {
+        (UnOp::Not, Value::Bool(b)) => Value::Bool(!b),
+        (UnOp::Not, Value::Int(x, t)) => Value::Int(!x, t),
+        (UnOp::Not, Value::Uint(x, t)) => Value::Uint(truncate(!x, uint_bits(t)), t),
+        (UnOp::Neg, Value::Int(x, t)) => match x.checked_neg() {
+            Some(x) if int_in_range(x, t) => Value::Int(x, t),
+            _ => return Err(MirEvalError::Panic("attempt to negate with overflow".into())),
+        },
+        (UnOp::Neg, Value::Float(x, t)) => Value::Float(-x, t),
+        _ => return Err(MirEvalError::TypeError("invalid unary operation")),
+    })
+}
+
+fn eval_cast(kind: &CastKind, value: Value, target_ty: &Ty) -> Result<Value> {
+    Ok(match kind {
+        CastKind::IntToInt => {
+            let bits = match value {
+                Value::Int(x, _) => x as u128,
+                Value::Uint(x, _) => x,
+                Value::Bool(b) => b as u128,
+                Value::Char(c) => c as u128,
+                _ => return Err(MirEvalError::TypeError("int cast of a non integer value")),
+            };
+            value_from_bits(bits, target_ty)?
+        }
+        CastKind::IntToFloat => {
+            let x = match value {
+                Value::Int(x, _) => x as f64,
+                Value::Uint(x, _) => x as f64,
+                _ => return Err(MirEvalError::TypeError("float cast of a non integer value")),
+            };
+            match target_ty.kind(Interner) {
+                TyKind::Scalar(Scalar::Float(t)) => float_value(x, *t),
+                _ => return Err(MirEvalError::TypeError("float cast to a non float type")),
+            }
+        }
+        CastKind::FloatToInt => {
+            let x = match value {
+                Value::Float(x, _) => x,
+                _ => return Err(MirEvalError::TypeError("int cast of a non float value")),
+            };
+            // `as` casts from floats saturate, and NaN becomes zero.
+            match target_ty.kind(Interner) {
+                TyKind::Scalar(Scalar::Int(t)) => {
+                    let width = int_bits(*t);
+                    let max = sign_extend(truncate(u128::MAX, width - 1), width);
+                    Value::Int((x as i128).clamp(-max - 1, max), *t)
+                }
+                TyKind::Scalar(Scalar::Uint(t)) => Value::Uint((x as u128).min(uint_max(*t)), *t),
+                _ => return Err(MirEvalError::TypeError("int cast to a non integer type")),
+            }
+        }
+        CastKind::FloatToFloat => match (value, target_ty.kind(Interner)) {
+            (Value::Float(x, _), TyKind::Scalar(Scalar::Float(t))) => float_value(x, *t),
+            _ => return Err(MirEvalError::TypeError("invalid float cast")),
+        },
+        CastKind::PtrToPtr
+        | CastKind::Pointer(PointerCast::Unsize | PointerCast::MutToConstPointer) => value,
+        CastKind::Pointer(_) => not_supported!("function pointer casts"),
+    })
+}
+
+fn scalar_to_literal(value: &Value) -> Result<Literal> {
+    Ok(match value {
+        Value::Bool(b) => Literal::Bool(*b),
+        Value::Char(c) => Literal::Char(*c),
+        Value::Int(x, t) => Literal::Int(*x, Some(builtin_int(*t))),
+        Value::Uint(x, t) => Literal::Uint(*x, Some(builtin_uint(*t))),
+        Value::Float(x, t) => {
+            let (x, b) = match t {
+                // Go through the shortest representation of the `f32`, so that `0.1f32` isn't
+                // shown as `0.10000000149011612`.
+                FloatTy::F32 => {
+                    ((*x as f32).to_string().parse::<f64>().unwrap_or(*x), BuiltinFloat::F32)
+                }
+                FloatTy::F64 => (*x, BuiltinFloat::F64),
+            };
+            Literal::Float(FloatTypeWrapper::new(x), Some(b))
+        }
+        _ => return Err(MirEvalError::TypeError("not a scalar value")),
+    })
+}
@@ -0,0 +1,1699 @@
+//! This module generates a polymorphic MIR from a hir body
+
+use std::sync::Arc;
+
+use chalk_ir::{Mutability, Scalar};
+use hir_def::{
+    body::Body,
+    expr::{
+        Array, BindingAnnotation, Expr, ExprId, LabelId, LogicOp, MatchArm, Pat, PatId,
+        RecordFieldPat, RecordLitField,
+    },
+    path::Path,
+    resolver::{resolver_for_expr, HasResolver, ResolveValueResult, ValueNs},
+    AdtId, AssocItemId, EnumId, LocalFieldId,
+};
+use hir_expand::name::Name;
+use itertools::Either;
+use la_arena::ArenaMap;
+
+use crate::{
+    consteval::{variant_discriminant, ConstEvalError},
+    db::HirDatabase,
+    infer::{Adjust, Adjustment, AutoBorrow, BindingMode, PointerCast},
+    to_placeholder_idx, CallableDefId, ConstData, ConstValue, InferenceResult, Interner, TyBuilder,
+    TyExt, TyKind, TypeMismatch,
+};
+
+use super::*;
+
+#[derive(Debug, Clone)]
+struct LoopBlocks {
+    begin: BasicBlockId,
+    /// `None` until a `break` targeting this loop is lowered, or the loop body falls through.
+    end: Option<BasicBlockId>,
+    /// The place that `break` with a value writes to.
+    place: Place,
+    label: Option<Name>,
+    /// Labeled blocks are breakable, but can't be `continue`d.
+    is_loop: bool,
+}
+
+struct MirLowerCtx<'a> {
+    result: MirBody,
+    owner: DefWithBodyId,
+    loop_blocks: Vec<LoopBlocks>,
+    db: &'a dyn HirDatabase,
+    body: &'a Body,
+    infer: &'a InferenceResult,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MirLowerError {
+    ConstEvalError(Box<ConstEvalError>),
+    IncompleteExpr,
+    UnresolvedName(String),
+    UnresolvedField,
+    UnresolvedMethod,
+    UnresolvedLabel,
+    MissingFunctionDefinition,
+    TypeError(&'static str),
+    /// The body contains a type error, so lowering it would produce nonsense.
+    TypeMismatch(TypeMismatch),
+    NotSupported(String),
+    ContinueWithoutLoop,
+    BreakWithoutLoop,
+    Loop,
+    /// Assigning to something that isn't a place expression.
+    MutatingRvalue,
+    /// A binding that isn't visible from the lowered expression.
+    UnaccessableLocal,
+    /// Something that should never happen and is definitely a bug, but we don't want to panic if it happened
+    ImplementationError(&'static str),
+}
+
+macro_rules! not_supported {
+    ($($x: tt)*) => {
+        return Err(MirLowerError::NotSupported(format!($($x)*)))
+    };
+}
+
+impl MirLowerError {
+    fn unresolved_path(p: &Path) -> Self {
+        Self::UnresolvedName(p.mod_path().to_string())
+    }
+}
+
+impl From<ConstEvalError> for MirLowerError {
+    fn from(value: ConstEvalError) -> Self {
+        match value {
+            ConstEvalError::MirLowerError(e) => e,
+            _ => MirLowerError::ConstEvalError(Box::new(value)),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, MirLowerError>;
+
+enum AdtPatternShape<'a> {
+    Tuple { args: &'a [PatId], ellipsis: Option<usize> },
+    Record { args: &'a [RecordFieldPat] },
+    Unit,
+}
+
+impl MirLowerCtx<'_> {
+    fn temp(&mut self, ty: Ty) -> Result<LocalId> {
+        if matches!(ty.kind(Interner), TyKind::Slice(_) | TyKind::Dyn(_) | TyKind::Str) {
+            not_supported!("unsized temporaries");
+        }
+        Ok(self.result.locals.alloc(Local { mutability: Mutability::Not, ty }))
+    }
+
+    fn lower_expr_to_some_operand(
+        &mut self,
+        expr_id: ExprId,
+        current: BasicBlockId,
+    ) -> Result<Option<(Operand, BasicBlockId)>> {
+        if !self.has_adjustments(expr_id) {
+            if let Expr::Literal(l) = &self.body.exprs[expr_id] {
+                let ty = self.expr_ty(expr_id);
+                return Ok(Some((Operand::from_literal(l.clone(), ty), current)));
+            }
+        }
+        let (p, current) = match self.lower_expr_as_place(current, expr_id, true)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        Ok(Some((Operand::Copy(p), current)))
+    }
+
+    fn lower_expr_to_some_place(
+        &mut self,
+        expr_id: ExprId,
+        current: BasicBlockId,
+    ) -> Result<Option<(Place, BasicBlockId)>> {
+        self.lower_expr_as_place(current, expr_id, true)
+    }
+
+    fn lower_expr_as_place(
+        &mut self,
+        current: BasicBlockId,
+        expr_id: ExprId,
+        upgrade_rvalue: bool,
+    ) -> Result<Option<(Place, BasicBlockId)>> {
+        match self.infer.expr_adjustments.get(&expr_id) {
+            Some(a) => self.lower_expr_as_place_with_adjust(current, expr_id, upgrade_rvalue, a),
+            None => self.lower_expr_as_place_without_adjust(current, expr_id, upgrade_rvalue),
+        }
+    }
+
+    fn lower_expr_as_place_with_adjust(
+        &mut self,
+        current: BasicBlockId,
+        expr_id: ExprId,
+        upgrade_rvalue: bool,
+        adjustments: &[Adjustment],
+    ) -> Result<Option<(Place, BasicBlockId)>> {
+        let try_rvalue = |this: &mut MirLowerCtx<'_>| {
+            if !upgrade_rvalue {
+                return Err(MirLowerError::MutatingRvalue);
+            }
+            let ty = adjustments
+                .last()
+                .map(|x| x.target.clone())
+                .unwrap_or_else(|| this.expr_ty(expr_id));
+            let place: Place = this.temp(ty)?.into();
+            let current = match this.lower_expr_to_place_with_adjust(
+                expr_id,
+                place.clone(),
+                current,
+                adjustments,
+            )? {
+                Some(x) => x,
+                None => return Ok(None),
+            };
+            Ok(Some((place, current)))
+        };
+        match adjustments.split_last() {
+            Some((last, rest)) => match &last.kind {
+                Adjust::Deref(None) => {
+                    let (p, current) =
+                        match self.lower_expr_as_place_with_adjust(current, expr_id, true, rest)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    Ok(Some((p.project(ProjectionElem::Deref), current)))
+                }
+                Adjust::Deref(Some(_)) => not_supported!("overloaded deref"),
+                // Unsizing an array in place, e.g. for indexing it, doesn't change its value.
+                Adjust::Pointer(PointerCast::Unsize)
+                    if matches!(last.target.kind(Interner), TyKind::Slice(_)) =>
+                {
+                    self.lower_expr_as_place_with_adjust(current, expr_id, upgrade_rvalue, rest)
+                }
+                _ => try_rvalue(self),
+            },
+            None => self.lower_expr_as_place_without_adjust(current, expr_id, upgrade_rvalue),
+        }
+    }
+
+    fn lower_expr_as_place_without_adjust(
+        &mut self,
+        current: BasicBlockId,
+        expr_id: ExprId,
+        upgrade_rvalue: bool,
+    ) -> Result<Option<(Place, BasicBlockId)>> {
+        let try_rvalue = |this: &mut MirLowerCtx<'_>| {
+            if !upgrade_rvalue {
+                return Err(MirLowerError::MutatingRvalue);
+            }
+            let ty = this.expr_ty(expr_id);
+            let place: Place = this.temp(ty)?.into();
+            let current =
+                match this.lower_expr_to_place_without_adjust(expr_id, place.clone(), current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+            Ok(Some((place, current)))
+        };
+        match &self.body.exprs[expr_id] {
+            Expr::Path(p) => {
+                let resolver = resolver_for_expr(self.db.upcast(), self.owner, expr_id);
+                match resolver.resolve_path_in_value_ns_fully(self.db.upcast(), p.mod_path()) {
+                    Some(ValueNs::LocalBinding(pat_id)) => {
+                        Ok(Some((self.binding_local(pat_id)?.into(), current)))
+                    }
+                    _ => try_rvalue(self),
+                }
+            }
+            Expr::UnaryOp { expr, op: hir_def::expr::UnaryOp::Deref } => {
+                let ty = self.expr_ty_after_adjustments(*expr);
+                if !matches!(ty.kind(Interner), TyKind::Ref(..) | TyKind::Raw(..)) {
+                    not_supported!("overloaded deref");
+                }
+                let (p, current) = match self.lower_expr_as_place(current, *expr, true)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                Ok(Some((p.project(ProjectionElem::Deref), current)))
+            }
+            Expr::Field { expr, name } => {
+                let (p, current) = match self.lower_expr_as_place(current, *expr, upgrade_rvalue)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let projection = match self.infer.field_resolution(expr_id) {
+                    Some(field) => ProjectionElem::Field(field),
+                    None => ProjectionElem::TupleField(
+                        name.as_tuple_index().ok_or(MirLowerError::UnresolvedField)?,
+                    ),
+                };
+                Ok(Some((p.project(projection), current)))
+            }
+            Expr::Index { base, index } => {
+                let base_ty = self.expr_ty_after_adjustments(*base);
+                let index_ty = self.expr_ty_after_adjustments(*index);
+                if !matches!(base_ty.kind(Interner), TyKind::Array(..) | TyKind::Slice(..))
+                    || index_ty != TyBuilder::usize()
+                {
+                    not_supported!("overloaded index");
+                }
+                let (p, current) = match self.lower_expr_as_place(current, *base, upgrade_rvalue)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let index_local = self.temp(index_ty)?;
+                let current = match self.lower_expr_to_place(*index, index_local.into(), current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                Ok(Some((p.project(ProjectionElem::Index(index_local)), current)))
+            }
+            _ => try_rvalue(self),
+        }
+    }
+
+    fn lower_expr_to_place_with_adjust(
+        &mut self,
+        expr_id: ExprId,
+        place: Place,
+        current: BasicBlockId,
+        adjustments: &[Adjustment],
+    ) -> Result<Option<BasicBlockId>> {
+        match adjustments.split_last() {
+            Some((last, rest)) => match &last.kind {
+                Adjust::NeverToAny => {
+                    let temp = self.temp(TyKind::Never.intern(Interner))?;
+                    self.lower_expr_to_place_with_adjust(expr_id, temp.into(), current, rest)
+                }
+                Adjust::Deref(_) => {
+                    let (p, current) = match self.lower_expr_as_place_with_adjust(
+                        current,
+                        expr_id,
+                        true,
+                        adjustments,
+                    )? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                    self.push_assignment(current, place, Operand::Copy(p).into(), expr_id.into());
+                    Ok(Some(current))
+                }
+                Adjust::Borrow(AutoBorrow::Ref(m) | AutoBorrow::RawPtr(m)) => {
+                    let (p, current) =
+                        match self.lower_expr_as_place_with_adjust(current, expr_id, true, rest)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    let bk = BorrowKind::from_chalk(*m);
+                    self.push_assignment(current, place, Rvalue::Ref(bk, p), expr_id.into());
+                    Ok(Some(current))
+                }
+                Adjust::Pointer(cast) => {
+                    let (p, current) =
+                        match self.lower_expr_as_place_with_adjust(current, expr_id, true, rest)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    self.push_assignment(
+                        current,
+                        place,
+                        Rvalue::Cast(
+                            CastKind::Pointer(*cast),
+                            Operand::Copy(p),
+                            last.target.clone(),
+                        ),
+                        expr_id.into(),
+                    );
+                    Ok(Some(current))
+                }
+            },
+            None => self.lower_expr_to_place_without_adjust(expr_id, place, current),
+        }
+    }
+
+    fn lower_expr_to_place(
+        &mut self,
+        expr_id: ExprId,
+        place: Place,
+        prev_block: BasicBlockId,
+    ) -> Result<Option<BasicBlockId>> {
+        if let Some(adjustments) = self.infer.expr_adjustments.get(&expr_id) {
+            return self.lower_expr_to_place_with_adjust(expr_id, place, prev_block, adjustments);
+        }
+        self.lower_expr_to_place_without_adjust(expr_id, place, prev_block)
+    }
+
+    fn lower_expr_to_place_without_adjust(
+        &mut self,
+        expr_id: ExprId,
+        place: Place,
+        mut current: BasicBlockId,
+    ) -> Result<Option<BasicBlockId>> {
+        match &self.body.exprs[expr_id] {
+            Expr::Missing => Err(MirLowerError::IncompleteExpr),
+            Expr::Path(p) => {
+                let unresolved_name = || MirLowerError::unresolved_path(p);
+                let resolver = resolver_for_expr(self.db.upcast(), self.owner, expr_id);
+                let pr = resolver
+                    .resolve_path_in_value_ns(self.db.upcast(), p.mod_path())
+                    .ok_or_else(unresolved_name)?;
+                let pr = match pr {
+                    ResolveValueResult::ValueNs(v) => v,
+                    ResolveValueResult::Partial(..) => {
+                        match self.infer.assoc_resolutions_for_expr(expr_id) {
+                            Some(AssocItemId::ConstId(c)) => ValueNs::ConstId(c),
+                            Some(AssocItemId::FunctionId(f)) => ValueNs::FunctionId(f),
+                            _ => return Err(unresolved_name()),
+                        }
+                    }
+                };
+                match pr {
+                    ValueNs::LocalBinding(pat_id) => {
+                        let local = self.binding_local(pat_id)?;
+                        self.push_assignment(
+                            current,
+                            place,
+                            Operand::Copy(local.into()).into(),
+                            expr_id.into(),
+                        );
+                        Ok(Some(current))
+                    }
+                    ValueNs::ConstId(const_id) => {
+                        let ty = self.expr_ty(expr_id);
+                        self.push_assignment(
+                            current,
+                            place,
+                            Operand::Constant(Constant { kind: ConstantKind::Const(const_id), ty })
+                                .into(),
+                            expr_id.into(),
+                        );
+                        Ok(Some(current))
+                    }
+                    ValueNs::EnumVariantId(variant_id) => {
+                        self.lower_unit_variant(variant_id.into(), place, current, expr_id)
+                    }
+                    ValueNs::StructId(struct_id) => {
+                        self.lower_unit_variant(struct_id.into(), place, current, expr_id)
+                    }
+                    ValueNs::GenericParam(p) => {
+                        let ty = self.db.const_param_ty(p);
+                        let value = ConstData {
+                            ty: ty.clone(),
+                            value: ConstValue::Placeholder(to_placeholder_idx(self.db, p.into())),
+                        }
+                        .intern(Interner);
+                        self.push_assignment(
+                            current,
+                            place,
+                            Operand::from_const(value, ty).into(),
+                            expr_id.into(),
+                        );
+                        Ok(Some(current))
+                    }
+                    ValueNs::FunctionId(_) => {
+                        // Function items are zero sized, their type tells which function is meant.
+                        let ty = self.expr_ty(expr_id);
+                        self.push_assignment(
+                            current,
+                            place,
+                            Operand::Constant(Constant { kind: ConstantKind::Zst, ty }).into(),
+                            expr_id.into(),
+                        );
+                        Ok(Some(current))
+                    }
+                    ValueNs::StaticId(_) => not_supported!("static items"),
+                    ValueNs::ImplSelf(_) => not_supported!("`Self` as a value"),
+                }
+            }
+            Expr::If { condition, then_branch, else_branch } => {
+                let (discr, current) = match self.lower_expr_to_some_operand(*condition, current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let start_of_then = self.new_basic_block();
+                let end_of_then =
+                    self.lower_expr_to_place(*then_branch, place.clone(), start_of_then)?;
+                let start_of_else = self.new_basic_block();
+                let end_of_else = if let Some(else_branch) = else_branch {
+                    self.lower_expr_to_place(*else_branch, place, start_of_else)?
+                } else {
+                    Some(start_of_else)
+                };
+                self.set_terminator(
+                    current,
+                    Terminator::SwitchInt {
+                        discr,
+                        targets: SwitchTargets::static_if(1, start_of_then, start_of_else),
+                    },
+                );
+                Ok(self.merge_blocks(end_of_then, end_of_else))
+            }
+            Expr::Let { pat, expr } => {
+                let (cond_place, current) = match self.lower_expr_to_some_place(*expr, current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let (then_target, else_target) =
+                    self.pattern_match(current, None, cond_place, *pat)?;
+                self.push_assignment(
+                    then_target,
+                    place.clone(),
+                    Operand::from_scalar(ConstScalar::Bool(true), bool_ty()).into(),
+                    expr_id.into(),
+                );
+                if let Some(else_target) = else_target {
+                    self.push_assignment(
+                        else_target,
+                        place,
+                        Operand::from_scalar(ConstScalar::Bool(false), bool_ty()).into(),
+                        expr_id.into(),
+                    );
+                }
+                Ok(self.merge_blocks(Some(then_target), else_target))
+            }
+            Expr::Unsafe { body } | Expr::Const { body } => {
+                self.lower_expr_to_place(*body, place, current)
+            }
+            Expr::Block { id: _, statements, tail, label } => match label {
+                Some(label) => {
+                    self.lower_loop(current, place.clone(), Some(*label), false, |this, begin| {
+                        if let Some(block) =
+                            this.lower_block_to_place(statements, begin, *tail, place)?
+                        {
+                            let end = this.current_loop_end()?;
+                            this.set_goto(block, end);
+                        }
+                        Ok(())
+                    })
+                }
+                None => self.lower_block_to_place(statements, current, *tail, place),
+            },
+            Expr::Loop { body, label } => {
+                self.lower_loop(current, place, *label, true, |this, begin| {
+                    let unit = this.temp(TyBuilder::unit())?;
+                    if let Some(block) = this.lower_expr_to_place(*body, unit.into(), begin)? {
+                        this.set_goto(block, begin);
+                    }
+                    Ok(())
+                })
+            }
+            Expr::While { condition, body, label } => {
+                self.lower_loop(current, place, *label, true, |this, begin| {
+                    let (discr, to_switch) =
+                        match this.lower_expr_to_some_operand(*condition, begin)? {
+                            Some(x) => x,
+                            None => return Ok(()),
+                        };
+                    let end = this.current_loop_end()?;
+                    let after_cond = this.new_basic_block();
+                    this.set_terminator(
+                        to_switch,
+                        Terminator::SwitchInt {
+                            discr,
+                            targets: SwitchTargets::static_if(1, after_cond, end),
+                        },
+                    );
+                    let unit = this.temp(TyBuilder::unit())?;
+                    if let Some(block) = this.lower_expr_to_place(*body, unit.into(), after_cond)? {
+                        this.set_goto(block, begin);
+                    }
+                    Ok(())
+                })
+            }
+            Expr::For { .. } => not_supported!("for loop"),
+            Expr::Call { callee, args, .. } => {
+                let callee_ty = self.expr_ty_after_adjustments(*callee);
+                match callee_ty.callable_def(self.db) {
+                    Some(CallableDefId::StructId(s)) => {
+                        self.lower_constructor_call(s.into(), args, place, current, expr_id)
+                    }
+                    Some(CallableDefId::EnumVariantId(v)) => {
+                        self.lower_constructor_call(v.into(), args, place, current, expr_id)
+                    }
+                    Some(CallableDefId::FunctionId(_)) => {
+                        let current = match self.lower_expr_to_some_place(*callee, current)? {
+                            Some((_, x)) => x,
+                            None => return Ok(None),
+                        };
+                        let func =
+                            Operand::Constant(Constant { kind: ConstantKind::Zst, ty: callee_ty });
+                        self.lower_call(func, args.iter().copied(), place, current, expr_id)
+                    }
+                    None => not_supported!("calling closures and function pointers"),
+                }
+            }
+            Expr::MethodCall { receiver, args, .. } => {
+                let (func_id, generic_args) =
+                    self.infer.method_resolution(expr_id).ok_or(MirLowerError::UnresolvedMethod)?;
+                let func = Operand::from_fn(self.db, func_id, generic_args);
+                let args = std::iter::once(*receiver).chain(args.iter().copied());
+                self.lower_call(func, args, place, current, expr_id)
+            }
+            Expr::Match { expr, arms } => {
+                let (cond_place, mut current) =
+                    match self.lower_expr_to_some_place(*expr, current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                let mut end = None;
+                let mut exhausted = false;
+                for MatchArm { pat, guard, expr } in arms.iter() {
+                    let (then, mut otherwise) =
+                        self.pattern_match(current, None, cond_place.clone(), *pat)?;
+                    let then = if let &Some(guard) = guard {
+                        let next = self.new_basic_block();
+                        let o = *otherwise.get_or_insert_with(|| self.new_basic_block());
+                        if let Some((discr, c)) = self.lower_expr_to_some_operand(guard, then)? {
+                            self.set_terminator(
+                                c,
+                                Terminator::SwitchInt {
+                                    discr,
+                                    targets: SwitchTargets::static_if(1, next, o),
+                                },
+                            );
+                        }
+                        next
+                    } else {
+                        then
+                    };
+                    if let Some(block) = self.lower_expr_to_place(*expr, place.clone(), then)? {
+                        let r = *end.get_or_insert_with(|| self.new_basic_block());
+                        self.set_goto(block, r);
+                    }
+                    match otherwise {
+                        Some(o) => current = o,
+                        None => {
+                            // The current pattern was irrefutable, so there is no need to generate code
+                            // for the rest of patterns
+                            exhausted = true;
+                            break;
+                        }
+                    }
+                }
+                if !exhausted {
+                    self.set_terminator(current, Terminator::Unreachable);
+                }
+                Ok(end)
+            }
+            Expr::Continue { label } => {
+                let loop_data = match label {
+                    Some(l) => self
+                        .loop_blocks
+                        .iter()
+                        .rev()
+                        .find(|x| x.label.as_ref() == Some(l))
+                        .ok_or(MirLowerError::UnresolvedLabel)?,
+                    None => self
+                        .loop_blocks
+                        .iter()
+                        .rev()
+                        .find(|x| x.is_loop)
+                        .ok_or(MirLowerError::ContinueWithoutLoop)?,
+                };
+                if !loop_data.is_loop {
+                    return Err(MirLowerError::ContinueWithoutLoop);
+                }
+                let begin = loop_data.begin;
+                self.set_goto(current, begin);
+                Ok(None)
+            }
+            Expr::Break { expr, label } => {
+                let idx = match label {
+                    Some(l) => self
+                        .loop_blocks
+                        .iter()
+                        .rposition(|x| x.label.as_ref() == Some(l))
+                        .ok_or(MirLowerError::UnresolvedLabel)?,
+                    None => self
+                        .loop_blocks
+                        .iter()
+                        .rposition(|x| x.is_loop)
+                        .ok_or(MirLowerError::BreakWithoutLoop)?,
+                };
+                if let Some(expr) = expr {
+                    let loop_place = self.loop_blocks[idx].place.clone();
+                    current = match self.lower_expr_to_place(*expr, loop_place, current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                }
+                let end = self.loop_end(idx);
+                self.set_goto(current, end);
+                Ok(None)
+            }
+            Expr::Return { expr } => {
+                if let Some(expr) = expr {
+                    let return_slot = self.result.return_slot().into();
+                    current = match self.lower_expr_to_place(*expr, return_slot, current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                }
+                self.set_terminator(current, Terminator::Return);
+                Ok(None)
+            }
+            Expr::Yield { .. } => not_supported!("yield"),
+            Expr::RecordLit { fields, path, spread, ellipsis: _, is_assignee_expr: _ } => {
+                let variant_id =
+                    self.infer.variant_resolution_for_expr(expr_id).ok_or_else(|| match path {
+                        Some(p) => MirLowerError::unresolved_path(p),
+                        None => MirLowerError::TypeError("record literal without path"),
+                    })?;
+                if let VariantId::UnionId(_) = variant_id {
+                    not_supported!("union literal");
+                }
+                let variant_data = variant_id.variant_data(self.db.upcast());
+                let mut operands = vec![None; variant_data.fields().len()];
+                for RecordLitField { name, expr } in fields.iter() {
+                    let field_id =
+                        variant_data.field(name).ok_or(MirLowerError::UnresolvedField)?;
+                    let (op, c) = match self.lower_expr_to_some_operand(*expr, current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                    current = c;
+                    operands[u32::from(field_id.into_raw()) as usize] = Some(op);
+                }
+                let spread_place = match spread {
+                    &Some(spread) => match self.lower_expr_to_some_place(spread, current)? {
+                        Some((p, c)) => {
+                            current = c;
+                            Some(p)
+                        }
+                        None => return Ok(None),
+                    },
+                    None => None,
+                };
+                let operands = operands
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, op)| match (op, &spread_place) {
+                        (Some(op), _) => Ok(op),
+                        (None, Some(sp)) => {
+                            let local_id = LocalFieldId::from_raw(RawIdx::from(i as u32));
+                            let field = FieldId { parent: variant_id, local_id };
+                            Ok(Operand::Copy(sp.project(ProjectionElem::Field(field))))
+                        }
+                        (None, None) => {
+                            Err(MirLowerError::TypeError("missing field in record literal"))
+                        }
+                    })
+                    .collect::<Result<_>>()?;
+                self.push_assignment(
+                    current,
+                    place,
+                    Rvalue::Aggregate(AggregateKind::Adt(variant_id), operands),
+                    expr_id.into(),
+                );
+                Ok(Some(current))
+            }
+            Expr::Field { .. } | Expr::Index { .. } => {
+                let (p, current) =
+                    match self.lower_expr_as_place_without_adjust(current, expr_id, true)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                self.push_assignment(current, place, Operand::Copy(p).into(), expr_id.into());
+                Ok(Some(current))
+            }
+            Expr::Await { .. } => not_supported!("await"),
+            Expr::Try { .. } => not_supported!("? operator"),
+            Expr::TryBlock { .. } => not_supported!("try block"),
+            Expr::Async { .. } => not_supported!("async block"),
+            Expr::Cast { expr, type_ref: _ } => {
+                let source_ty = self.expr_ty_after_adjustments(*expr);
+                let target_ty = self.expr_ty(expr_id);
+                if let Some((AdtId::EnumId(e), _)) = source_ty.as_adt() {
+                    // Casting a fieldless enum to an integer reads its discriminant.
+                    let (p, current) = match self.lower_expr_to_some_place(*expr, current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                    let discr: Place = self.temp(self.discr_ty(e))?.into();
+                    self.push_assignment(
+                        current,
+                        discr.clone(),
+                        Rvalue::Discriminant(p),
+                        expr_id.into(),
+                    );
+                    self.push_assignment(
+                        current,
+                        place,
+                        Rvalue::Cast(CastKind::IntToInt, Operand::Copy(discr), target_ty),
+                        expr_id.into(),
+                    );
+                    return Ok(Some(current));
+                }
+                let kind = cast_kind(&source_ty, &target_ty)?;
+                let (op, current) = match self.lower_expr_to_some_operand(*expr, current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                self.push_assignment(
+                    current,
+                    place,
+                    Rvalue::Cast(kind, op, target_ty),
+                    expr_id.into(),
+                );
+                Ok(Some(current))
+            }
+            Expr::Ref { expr, rawness: _, mutability } => {
+                let (p, current) = match self.lower_expr_to_some_place(*expr, current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let bk = BorrowKind::from_hir(*mutability);
+                self.push_assignment(current, place, Rvalue::Ref(bk, p), expr_id.into());
+                Ok(Some(current))
+            }
+            Expr::Box { .. } => not_supported!("box expression"),
+            Expr::UnaryOp { expr, op } => match op {
+                hir_def::expr::UnaryOp::Deref => {
+                    let (p, current) =
+                        match self.lower_expr_as_place_without_adjust(current, expr_id, true)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    self.push_assignment(current, place, Operand::Copy(p).into(), expr_id.into());
+                    Ok(Some(current))
+                }
+                hir_def::expr::UnaryOp::Not | hir_def::expr::UnaryOp::Neg => {
+                    if !is_builtin_scalar(&self.expr_ty_after_adjustments(*expr)) {
+                        not_supported!("overloaded unary operator");
+                    }
+                    let (operand, current) =
+                        match self.lower_expr_to_some_operand(*expr, current)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    let operation = match op {
+                        hir_def::expr::UnaryOp::Not => UnOp::Not,
+                        _ => UnOp::Neg,
+                    };
+                    self.push_assignment(
+                        current,
+                        place,
+                        Rvalue::UnaryOp(operation, operand),
+                        expr_id.into(),
+                    );
+                    Ok(Some(current))
+                }
+            },
+            Expr::BinaryOp { lhs, rhs, op } => {
+                let op = op.ok_or(MirLowerError::IncompleteExpr)?;
+                if let hir_def::expr::BinaryOp::Assignment { op } = op {
+                    if op.is_some()
+                        && !(is_builtin_scalar(&self.expr_ty_after_adjustments(*lhs))
+                            && is_builtin_scalar(&self.expr_ty_after_adjustments(*rhs)))
+                    {
+                        not_supported!("overloaded compound assignment");
+                    }
+                    let (rhs_op, current) = match self.lower_expr_to_some_operand(*rhs, current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                    let (lhs_place, current) =
+                        match self.lower_expr_as_place(current, *lhs, false)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    let rvalue = match op {
+                        Some(op) => {
+                            Rvalue::BinaryOp(op.into(), Operand::Copy(lhs_place.clone()), rhs_op)
+                        }
+                        None => rhs_op.into(),
+                    };
+                    self.push_assignment(current, lhs_place, rvalue, expr_id.into());
+                    return Ok(Some(current));
+                }
+                if let hir_def::expr::BinaryOp::LogicOp(op) = op {
+                    let (lhs_op, current) = match self.lower_expr_to_some_operand(*lhs, current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                    let rhs_block = self.new_basic_block();
+                    let short_circuit = self.new_basic_block();
+                    let is_or = op == LogicOp::Or;
+                    self.set_terminator(
+                        current,
+                        Terminator::SwitchInt {
+                            discr: lhs_op,
+                            targets: SwitchTargets::static_if(
+                                if is_or { 0 } else { 1 },
+                                rhs_block,
+                                short_circuit,
+                            ),
+                        },
+                    );
+                    self.push_assignment(
+                        short_circuit,
+                        place.clone(),
+                        Operand::from_scalar(ConstScalar::Bool(is_or), bool_ty()).into(),
+                        expr_id.into(),
+                    );
+                    let rhs_end = self.lower_expr_to_place(*rhs, place, rhs_block)?;
+                    return Ok(self.merge_blocks(Some(short_circuit), rhs_end));
+                }
+                if !(is_builtin_scalar(&self.expr_ty_after_adjustments(*lhs))
+                    && is_builtin_scalar(&self.expr_ty_after_adjustments(*rhs)))
+                {
+                    not_supported!("overloaded binary operator");
+                }
+                let (lhs_op, current) = match self.lower_expr_to_some_operand(*lhs, current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let (rhs_op, current) = match self.lower_expr_to_some_operand(*rhs, current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let op = match op {
+                    hir_def::expr::BinaryOp::ArithOp(op) => op.into(),
+                    hir_def::expr::BinaryOp::CmpOp(op) => op.into(),
+                    hir_def::expr::BinaryOp::LogicOp(_)
+                    | hir_def::expr::BinaryOp::Assignment { .. } => {
+                        unreachable!("handled above")
+                    }
+                };
+                self.push_assignment(
+                    current,
+                    place,
+                    Rvalue::BinaryOp(op, lhs_op, rhs_op),
+                    expr_id.into(),
+                );
+                Ok(Some(current))
+            }
+            Expr::Range { .. } => not_supported!("range"),
+            Expr::Closure { .. } => not_supported!("closure"),
+            Expr::Tuple { exprs, is_assignee_expr: _ } => {
+                let (values, current) = match self.lower_operands(exprs.iter().copied(), current)? {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                let ty = self.expr_ty(expr_id);
+                self.push_assignment(
+                    current,
+                    place,
+                    Rvalue::Aggregate(AggregateKind::Tuple(ty), values),
+                    expr_id.into(),
+                );
+                Ok(Some(current))
+            }
+            Expr::Array(l) => match l {
+                Array::ElementList { elements, .. } => {
+                    let elem_ty = match self.expr_ty(expr_id).kind(Interner) {
+                        TyKind::Array(ty, _) => ty.clone(),
+                        _ => {
+                            return Err(MirLowerError::TypeError(
+                                "Array expression with non array type",
+                            ))
+                        }
+                    };
+                    let (values, current) =
+                        match self.lower_operands(elements.iter().copied(), current)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    self.push_assignment(
+                        current,
+                        place,
+                        Rvalue::Aggregate(AggregateKind::Array(elem_ty), values),
+                        expr_id.into(),
+                    );
+                    Ok(Some(current))
+                }
+                Array::Repeat { initializer, .. } => {
+                    let len = match self.expr_ty(expr_id).kind(Interner) {
+                        TyKind::Array(_, len) => len.clone(),
+                        _ => {
+                            return Err(MirLowerError::TypeError(
+                                "Array repeat expression with non array type",
+                            ))
+                        }
+                    };
+                    let (init, current) =
+                        match self.lower_expr_to_some_operand(*initializer, current)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    self.push_assignment(current, place, Rvalue::Repeat(init, len), expr_id.into());
+                    Ok(Some(current))
+                }
+            },
+            Expr::Literal(l) => {
+                let ty = self.expr_ty(expr_id);
+                self.push_assignment(
+                    current,
+                    place,
+                    Operand::from_literal(l.clone(), ty).into(),
+                    expr_id.into(),
+                );
+                Ok(Some(current))
+            }
+            Expr::Underscore => not_supported!("underscore"),
+        }
+    }
+
+    fn lower_constructor_call(
+        &mut self,
+        variant: VariantId,
+        args: &[ExprId],
+        place: Place,
+        current: BasicBlockId,
+        expr_id: ExprId,
+    ) -> Result<Option<BasicBlockId>> {
+        let (values, current) = match self.lower_operands(args.iter().copied(), current)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        self.push_assignment(
+            current,
+            place,
+            Rvalue::Aggregate(AggregateKind::Adt(variant), values),
+            expr_id.into(),
+        );
+        Ok(Some(current))
+    }
+
+    fn lower_call(
+        &mut self,
+        func: Operand,
+        args: impl Iterator<Item = ExprId>,
+        destination: Place,
+        current: BasicBlockId,
+        expr_id: ExprId,
+    ) -> Result<Option<BasicBlockId>> {
+        let (args, current) = match self.lower_operands(args, current)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let target =
+            if self.expr_ty(expr_id).is_never() { None } else { Some(self.new_basic_block()) };
+        self.set_terminator(current, Terminator::Call { func, args, destination, target });
+        Ok(target)
+    }
+
+    fn lower_unit_variant(
+        &mut self,
+        variant: VariantId,
+        place: Place,
+        current: BasicBlockId,
+        expr_id: ExprId,
+    ) -> Result<Option<BasicBlockId>> {
+        if variant.variant_data(self.db.upcast()).kind() != hir_def::adt::StructKind::Unit {
+            not_supported!("constructors as function pointers");
+        }
+        self.push_assignment(
+            current,
+            place,
+            Rvalue::Aggregate(AggregateKind::Adt(variant), vec![]),
+            expr_id.into(),
+        );
+        Ok(Some(current))
+    }
+
+    fn lower_operands(
+        &mut self,
+        exprs: impl Iterator<Item = ExprId>,
+        mut current: BasicBlockId,
+    ) -> Result<Option<(Vec<Operand>, BasicBlockId)>> {
+        let mut values = vec![];
+        for expr in exprs {
+            match self.lower_expr_to_some_operand(expr, current)? {
+                Some((op, c)) => {
+                    values.push(op);
+                    current = c;
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((values, current)))
+    }
+
+    fn push_assignment(
+        &mut self,
+        block: BasicBlockId,
+        place: Place,
+        rvalue: Rvalue,
+        span: MirSpan,
+    ) {
+        self.result.basic_blocks[block]
+            .statements
+            .push(StatementKind::Assign(place, rvalue).with_span(span));
+    }
+
+    fn new_basic_block(&mut self) -> BasicBlockId {
+        self.result.basic_blocks.alloc(BasicBlock::default())
+    }
+
+    fn set_terminator(&mut self, block: BasicBlockId, terminator: Terminator) {
+        self.result.basic_blocks[block].terminator = Some(terminator);
+    }
+
+    fn set_goto(&mut self, from: BasicBlockId, target: BasicBlockId) {
+        self.set_terminator(from, Terminator::Goto { target });
+    }
+
+    fn expr_ty(&self, e: ExprId) -> Ty {
+        self.infer[e].clone()
+    }
+
+    fn expr_ty_after_adjustments(&self, e: ExprId) -> Ty {
+        match self.infer.expr_adjustments.get(&e).and_then(|x| x.last()) {
+            Some(adjustment) => adjustment.target.clone(),
+            None => self.expr_ty(e),
+        }
+    }
+
+    fn has_adjustments(&self, expr_id: ExprId) -> bool {
+        !self.infer.expr_adjustments.get(&expr_id).map(|x| x.is_empty()).unwrap_or(true)
+    }
+
+    fn discr_ty(&self, e: EnumId) -> Ty {
+        TyBuilder::builtin(match self.db.enum_data(e).variant_body_type() {
+            Either::Left(builtin) => hir_def::builtin_type::BuiltinType::Int(builtin),
+            Either::Right(builtin) => hir_def::builtin_type::BuiltinType::Uint(builtin),
+        })
+    }
+
+    fn binding_local(&self, b: PatId) -> Result<LocalId> {
+        match self.result.binding_locals.get(self.body.pattern_representative(b)) {
+            Some(x) => Ok(*x),
+            None => Err(MirLowerError::UnaccessableLocal),
+        }
+    }
+
+    fn merge_blocks(
+        &mut self,
+        b1: Option<BasicBlockId>,
+        b2: Option<BasicBlockId>,
+    ) -> Option<BasicBlockId> {
+        match (b1, b2) {
+            (None, None) => None,
+            (None, Some(b)) | (Some(b), None) => Some(b),
+            (Some(b1), Some(b2)) => {
+                let bm = self.new_basic_block();
+                self.set_goto(b1, bm);
+                self.set_goto(b2, bm);
+                Some(bm)
+            }
+        }
+    }
+
+    fn lower_loop(
+        &mut self,
+        prev_block: BasicBlockId,
+        place: Place,
+        label: Option<LabelId>,
+        is_loop: bool,
+        f: impl FnOnce(&mut MirLowerCtx<'_>, BasicBlockId) -> Result<()>,
+    ) -> Result<Option<BasicBlockId>> {
+        let begin = self.new_basic_block();
+        let label = label.map(|l| self.body.labels[l].name.clone());
+        self.loop_blocks.push(LoopBlocks { begin, end: None, place, label, is_loop });
+        self.set_goto(prev_block, begin);
+        let r = f(self, begin);
+        let my = self.loop_blocks.pop().expect("loop stack should be balanced");
+        r?;
+        Ok(my.end)
+    }
+
+    fn loop_end(&mut self, idx: usize) -> BasicBlockId {
+        match self.loop_blocks[idx].end {
+            Some(end) => end,
+            None => {
+                let end = self.new_basic_block();
+                self.loop_blocks[idx].end = Some(end);
+                end
+            }
+        }
+    }
+
+    fn current_loop_end(&mut self) -> Result<BasicBlockId> {
+        match self.loop_blocks.len().checked_sub(1) {
+            Some(idx) => Ok(self.loop_end(idx)),
+            None => Err(MirLowerError::ImplementationError("current_loop_end called with no loop")),
+        }
+    }
+
+    fn lower_block_to_place(
+        &mut self,
+        statements: &[hir_def::expr::Statement],
+        mut current: BasicBlockId,
+        tail: Option<ExprId>,
+        place: Place,
+    ) -> Result<Option<BasicBlockId>> {
+        for statement in statements.iter() {
+            match statement {
+                hir_def::expr::Statement::Let { pat, initializer, else_branch, type_ref: _ } => {
+                    let initializer = match initializer {
+                        Some(x) => *x,
+                        // A declaration without initializer, the binding will be assigned later.
+                        None => continue,
+                    };
+                    let (init_place, c) =
+                        match self.lower_expr_to_some_place(initializer, current)? {
+                            Some(x) => x,
+                            None => return Ok(None),
+                        };
+                    let (then, else_target) = self.pattern_match(c, None, init_place, *pat)?;
+                    current = then;
+                    if let Some(else_target) = else_target {
+                        let else_branch = else_branch.ok_or(MirLowerError::TypeError(
+                            "refutable pattern in let without else",
+                        ))?;
+                        let never = self.temp(TyKind::Never.intern(Interner))?;
+                        if let Some(b) =
+                            self.lower_expr_to_place(else_branch, never.into(), else_target)?
+                        {
+                            // The else branch of a let-else must diverge.
+                            self.set_terminator(b, Terminator::Unreachable);
+                        }
+                    }
+                }
+                hir_def::expr::Statement::Expr { expr, has_semi: _ } => {
+                    let ty = self.expr_ty_after_adjustments(*expr);
+                    let temp = self.temp(ty)?;
+                    current = match self.lower_expr_to_place(*expr, temp.into(), current)? {
+                        Some(x) => x,
+                        None => return Ok(None),
+                    };
+                }
+            }
+        }
+        match tail {
+            Some(tail) => self.lower_expr_to_place(tail, place, current),
+            None => Ok(Some(current)),
+        }
+    }
+
+    /// Appends statements and terminators to `current` that check if the value in `cond_place`
+    /// matches `pattern` and bind its bindings. Returns the block where execution continues if the
+    /// pattern matches, and the block it continues in otherwise, which is `None` if the pattern is
+    /// irrefutable and `current_else` was `None`.
+    fn pattern_match(
+        &mut self,
+        mut current: BasicBlockId,
+        mut current_else: Option<BasicBlockId>,
+        mut cond_place: Place,
+        pattern: PatId,
+    ) -> Result<(BasicBlockId, Option<BasicBlockId>)> {
+        // Match ergonomics: the pattern implicitly dereferences the matched value.
+        if let Some(adjustments) = self.infer.pat_adjustments.get(&pattern) {
+            for _ in adjustments {
+                cond_place = cond_place.project(ProjectionElem::Deref);
+            }
+        }
+        Ok(match &self.body.pats[pattern] {
+            Pat::Missing => return Err(MirLowerError::IncompleteExpr),
+            Pat::Wild => (current, current_else),
+            Pat::Tuple { args, ellipsis } => {
+                let size = match self.infer[pattern].as_tuple() {
+                    Some(subst) => subst.len(Interner),
+                    None => {
+                        return Err(MirLowerError::TypeError(
+                            "non tuple type matched with tuple pattern",
+                        ))
+                    }
+                };
+                self.pattern_match_tuple_like(
+                    current,
+                    current_else,
+                    args,
+                    *ellipsis,
+                    (0..size).map(ProjectionElem::TupleField),
+                    &cond_place,
+                )?
+            }
+            Pat::Or(pats) => {
+                let then_target = self.new_basic_block();
+                let mut finished = false;
+                for pat in &**pats {
+                    let (next, next_else) =
+                        self.pattern_match(current, None, cond_place.clone(), *pat)?;
+                    self.set_goto(next, then_target);
+                    match next_else {
+                        Some(t) => current = t,
+                        None => {
+                            finished = true;
+                            break;
+                        }
+                    }
+                }
+                if !finished {
+                    let ce = *current_else.get_or_insert_with(|| self.new_basic_block());
+                    self.set_goto(current, ce);
+                }
+                (then_target, current_else)
+            }
+            Pat::Record { args, .. } => {
+                let variant = self
+                    .infer
+                    .variant_resolution_for_pat(pattern)
+                    .ok_or(MirLowerError::TypeError("unresolved record pattern"))?;
+                self.pattern_matching_variant(
+                    cond_place,
+                    variant,
+                    current,
+                    pattern.into(),
+                    current_else,
+                    AdtPatternShape::Record { args },
+                )?
+            }
+            Pat::TupleStruct { args, ellipsis, .. } => {
+                let variant = self
+                    .infer
+                    .variant_resolution_for_pat(pattern)
+                    .ok_or(MirLowerError::TypeError("unresolved tuple struct pattern"))?;
+                self.pattern_matching_variant(
+                    cond_place,
+                    variant,
+                    current,
+                    pattern.into(),
+                    current_else,
+                    AdtPatternShape::Tuple { args, ellipsis: *ellipsis },
+                )?
+            }
+            Pat::Path(p) => {
+                let resolver = self.owner.resolver(self.db.upcast());
+                let pr = match self.infer.assoc_resolutions_for_pat(pattern) {
+                    Some(AssocItemId::ConstId(c)) => ValueNs::ConstId(c),
+                    _ => resolver
+                        .resolve_path_in_value_ns_fully(self.db.upcast(), p.mod_path())
+                        .ok_or_else(|| MirLowerError::unresolved_path(p))?,
+                };
+                match pr {
+                    ValueNs::EnumVariantId(v) => self.pattern_matching_variant(
+                        cond_place,
+                        v.into(),
+                        current,
+                        pattern.into(),
+                        current_else,
+                        AdtPatternShape::Unit,
+                    )?,
+                    ValueNs::StructId(_) => (current, current_else),
+                    ValueNs::ConstId(c) => {
+                        let ty = self.infer[pattern].clone();
+                        let c = Operand::Constant(Constant { kind: ConstantKind::Const(c), ty });
+                        self.pattern_match_const(current_else, current, c, cond_place, pattern)?
+                    }
+                    _ => not_supported!("this kind of path in patterns"),
+                }
+            }
+            Pat::Lit(l) => {
+                let (c, current) = match self.lower_expr_to_some_operand(*l, current)? {
+                    Some(x) => x,
+                    None => {
+                        return Err(MirLowerError::TypeError("diverging expression in pattern"))
+                    }
+                };
+                self.pattern_match_const(current_else, current, c, cond_place, pattern)?
+            }
+            Pat::Range { start, end } => {
+                let else_target = *current_else.get_or_insert_with(|| self.new_basic_block());
+                for (bound, op) in [(*start, BinOp::Le), (*end, BinOp::Ge)] {
+                    let (c, c_block) = match self.lower_expr_to_some_operand(bound, current)? {
+                        Some(x) => x,
+                        None => {
+                            return Err(MirLowerError::TypeError("diverging expression in pattern"))
+                        }
+                    };
+                    let discr: Place = self.temp(bool_ty())?.into();
+                    self.push_assignment(
+                        c_block,
+                        discr.clone(),
+                        Rvalue::BinaryOp(op, c, Operand::Copy(cond_place.clone())),
+                        pattern.into(),
+                    );
+                    let next = self.new_basic_block();
+                    self.set_terminator(
+                        c_block,
+                        Terminator::SwitchInt {
+                            discr: Operand::Copy(discr),
+                            targets: SwitchTargets::static_if(1, next, else_target),
+                        },
+                    );
+                    current = next;
+                }
+                (current, current_else)
+            }
+            Pat::Slice { prefix, slice, suffix } => {
+                let pattern_len = prefix.len() + suffix.len();
+                if let TyKind::Slice(_) = self.infer[pattern].kind(Interner) {
+                    let len: Place = self.temp(TyBuilder::usize())?.into();
+                    self.push_assignment(
+                        current,
+                        len.clone(),
+                        Rvalue::Len(cond_place.clone()),
+                        pattern.into(),
+                    );
+                    let op = if slice.is_some() { BinOp::Ge } else { BinOp::Eq };
+                    let discr: Place = self.temp(bool_ty())?.into();
+                    self.push_assignment(
+                        current,
+                        discr.clone(),
+                        Rvalue::BinaryOp(
+                            op,
+                            Operand::Copy(len),
+                            Operand::from_scalar(
+                                ConstScalar::UInt(pattern_len as u128),
+                                TyBuilder::usize(),
+                            ),
+                        ),
+                        pattern.into(),
+                    );
+                    let next = self.new_basic_block();
+                    let else_target = *current_else.get_or_insert_with(|| self.new_basic_block());
+                    self.set_terminator(
+                        current,
+                        Terminator::SwitchInt {
+                            discr: Operand::Copy(discr),
+                            targets: SwitchTargets::static_if(1, next, else_target),
+                        },
+                    );
+                    current = next;
+                }
+                if let Some(slice) = slice {
+                    if !matches!(self.body.pats[*slice], Pat::Missing | Pat::Wild) {
+                        not_supported!("subslice patterns");
+                    }
+                }
+                let fields = prefix
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &pat)| {
+                        (ProjectionElem::ConstantIndex { offset: i as u64, from_end: false }, pat)
+                    })
+                    .chain(suffix.iter().enumerate().map(|(i, &pat)| {
+                        let offset = (suffix.len() - i) as u64;
+                        (ProjectionElem::ConstantIndex { offset, from_end: true }, pat)
+                    }))
+                    .collect::<Vec<_>>();
+                self.pattern_match_fields(current, current_else, &cond_place, fields)?
+            }
+            Pat::Bind { subpat, .. } => {
+                let target_place = self.binding_local(pattern)?;
+                let (current, current_else) = match subpat {
+                    Some(subpat) => {
+                        self.pattern_match(current, current_else, cond_place.clone(), *subpat)?
+                    }
+                    None => (current, current_else),
+                };
+                let mode = self
+                    .infer
+                    .pat_binding_modes
+                    .get(&pattern)
+                    .copied()
+                    .unwrap_or(BindingMode::Move);
+                let rvalue = match mode {
+                    BindingMode::Move => Operand::Copy(cond_place).into(),
+                    BindingMode::Ref(m) => Rvalue::Ref(BorrowKind::from_chalk(m), cond_place),
+                };
+                self.push_assignment(current, target_place.into(), rvalue, pattern.into());
+                (current, current_else)
+            }
+            Pat::Ref { pat, mutability: _ } => self.pattern_match(
+                current,
+                current_else,
+                cond_place.project(ProjectionElem::Deref),
+                *pat,
+            )?,
+            Pat::Box { .. } => not_supported!("box pattern"),
+            Pat::ConstBlock(_) => not_supported!("const block pattern"),
+        })
+    }
+
+    fn pattern_match_const(
+        &mut self,
+        current_else: Option<BasicBlockId>,
+        current: BasicBlockId,
+        c: Operand,
+        cond_place: Place,
+        pattern: PatId,
+    ) -> Result<(BasicBlockId, Option<BasicBlockId>)> {
+        let then_target = self.new_basic_block();
+        let else_target = current_else.unwrap_or_else(|| self.new_basic_block());
+        let discr: Place = self.temp(bool_ty())?.into();
+        self.push_assignment(
+            current,
+            discr.clone(),
+            Rvalue::BinaryOp(BinOp::Eq, c, Operand::Copy(cond_place)),
+            pattern.into(),
+        );
+        self.set_terminator(
+            current,
+            Terminator::SwitchInt {
+                discr: Operand::Copy(discr),
+                targets: SwitchTargets::static_if(1, then_target, else_target),
+            },
+        );
+        Ok((then_target, Some(else_target)))
+    }
+
+    fn pattern_matching_variant(
+        &mut self,
+        cond_place: Place,
+        variant: VariantId,
+        current: BasicBlockId,
+        span: MirSpan,
+        current_else: Option<BasicBlockId>,
+        shape: AdtPatternShape<'_>,
+    ) -> Result<(BasicBlockId, Option<BasicBlockId>)> {
+        Ok(match variant {
+            VariantId::EnumVariantId(v) => {
+                let e = variant_discriminant(self.db, v)? as u128;
+                let tmp: Place = self.temp(self.discr_ty(v.parent))?.into();
+                self.push_assignment(
+                    current,
+                    tmp.clone(),
+                    Rvalue::Discriminant(cond_place.clone()),
+                    span,
+                );
+                let next = self.new_basic_block();
+                let else_target = current_else.unwrap_or_else(|| self.new_basic_block());
+                self.set_terminator(
+                    current,
+                    Terminator::SwitchInt {
+                        discr: Operand::Copy(tmp),
+                        targets: SwitchTargets::static_if(e, next, else_target),
+                    },
+                );
+                self.pattern_match_adt(next, Some(else_target), cond_place, variant, shape)?
+            }
+            VariantId::StructId(_) => {
+                self.pattern_match_adt(current, current_else, cond_place, variant, shape)?
+            }
+            VariantId::UnionId(_) => not_supported!("pattern matching on unions"),
+        })
+    }
+
+    fn pattern_match_adt(
+        &mut self,
+        current: BasicBlockId,
+        current_else: Option<BasicBlockId>,
+        cond_place: Place,
+        variant: VariantId,
+        shape: AdtPatternShape<'_>,
+    ) -> Result<(BasicBlockId, Option<BasicBlockId>)> {
+        let variant_data = variant.variant_data(self.db.upcast());
+        Ok(match shape {
+            AdtPatternShape::Record { args } => {
+                let fields = args
+                    .iter()
+                    .map(|x| {
+                        let local_id =
+                            variant_data.field(&x.name).ok_or(MirLowerError::UnresolvedField)?;
+                        Ok((ProjectionElem::Field(FieldId { parent: variant, local_id }), x.pat))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                self.pattern_match_fields(current, current_else, &cond_place, fields)?
+            }
+            AdtPatternShape::Tuple { args, ellipsis } => {
+                let fields = variant_data
+                    .fields()
+                    .iter()
+                    .map(|(local_id, _)| {
+                        ProjectionElem::Field(FieldId { parent: variant, local_id })
+                    })
+                    .collect::<Vec<_>>();
+                self.pattern_match_tuple_like(
+                    current,
+                    current_else,
+                    args,
+                    ellipsis,
+                    fields.into_iter(),
+                    &cond_place,
+                )?
+            }
+            AdtPatternShape::Unit => (current, current_else),
+        })
+    }
+
+    fn pattern_match_tuple_like(
+        &mut self,
+        current: BasicBlockId,
+        current_else: Option<BasicBlockId>,
+        args: &[PatId],
+        ellipsis: Option<usize>,
+        fields: impl DoubleEndedIterator<Item = ProjectionElem> + Clone,
+        cond_place: &Place,
+    ) -> Result<(BasicBlockId, Option<BasicBlockId>)> {
+        let (al, ar) = args.split_at(ellipsis.unwrap_or(args.len()));
+        let it = al
+            .iter()
+            .zip(fields.clone())
+            .chain(ar.iter().rev().zip(fields.rev()))
+            .map(|(x, y)| (y, *x))
+            .collect::<Vec<_>>();
+        self.pattern_match_fields(current, current_else, cond_place, it)
+    }
+
+    fn pattern_match_fields(
+        &mut self,
+        mut current: BasicBlockId,
+        mut current_else: Option<BasicBlockId>,
+        cond_place: &Place,
+        fields: Vec<(ProjectionElem, PatId)>,
+    ) -> Result<(BasicBlockId, Option<BasicBlockId>)> {
+        for (proj, arg) in fields {
+            let r = self.pattern_match(current, current_else, cond_place.project(proj), arg)?;
+            current = r.0;
+            current_else = r.1;
+        }
+        Ok((current, current_else))
+    }
+}
+
+fn bool_ty() -> Ty {
+    TyKind::Scalar(Scalar::Bool).intern(Interner)
+}
+
+fn is_builtin_scalar(ty: &Ty) -> bool {
+    matches!(ty.kind(Interner), TyKind::Scalar(_))
+}
+
+fn cast_kind(source_ty: &Ty, target_ty: &Ty) -> Result<CastKind> {
+    Ok(match (source_ty.kind(Interner), target_ty.kind(Interner)) {
+        (TyKind::Scalar(s), TyKind::Scalar(t)) => match (s, t) {
+            (Scalar::Float(_), Scalar::Float(_)) => CastKind::FloatToFloat,
+            (Scalar::Float(_), _) => CastKind::FloatToInt,
+            (_, Scalar::Float(_)) => CastKind::IntToFloat,
+            (_, _) => CastKind::IntToInt,
+        },
+        (TyKind::Ref(..) | TyKind::Raw(..), TyKind::Raw(..)) => CastKind::PtrToPtr,
+        _ => not_supported!("casting {:?} to {:?}", source_ty, target_ty),
+    })
+}
+
+pub fn mir_body_query(db: &dyn HirDatabase, def: DefWithBodyId) -> Result<Arc<MirBody>> {
+    let body = db.body(def);
+    let infer = db.infer(def);
+    if let Some(mismatch) = infer.type_mismatches().find(|it| !is_benign_mismatch(it)) {
+        return Err(MirLowerError::TypeMismatch(mismatch.clone()));
+    }
+    Ok(Arc::new(lower_to_mir(db, def, &body, &infer, body.body_expr)?))
+}
+
+/// Whether the interpreter can still make sense of a body with this type mismatch. Integers are
+/// represented the same whatever their type, and arrays the same as the slices they unsize to,
+/// which inference can't check without the `CoerceUnsized` lang items.
+fn is_benign_mismatch(mismatch: &TypeMismatch) -> bool {
+    fn is_int(ty: &Ty) -> bool {
+        matches!(ty.kind(Interner), TyKind::Scalar(Scalar::Int(_) | Scalar::Uint(_)))
+    }
+    match (mismatch.expected.kind(Interner), mismatch.actual.kind(Interner)) {
+        (TyKind::Ref(_, _, expected), TyKind::Ref(_, _, actual)) => {
+            match (expected.kind(Interner), actual.kind(Interner)) {
+                (TyKind::Slice(expected), TyKind::Array(actual, _)) => expected == actual,
+                _ => false,
+            }
+        }
+        _ => is_int(&mismatch.expected) && is_int(&mismatch.actual),
+    }
+}
+
+pub fn mir_body_recover(
+    _db: &dyn HirDatabase,
+    _cycle: &[String],
+    _def: &DefWithBodyId,
+) -> Result<Arc<MirBody>> {
+    Err(MirLowerError::Loop)
+}
+
+/// Lowers `root_expr` of the body of `owner` to MIR.
+///
+/// `root_expr` is usually the body expression itself, but it can also be an expression that
+/// doesn't have its own body, like the `N` in `[(); N]`.
+pub fn lower_to_mir(
+    db: &dyn HirDatabase,
+    owner: DefWithBodyId,
+    body: &Body,
+    infer: &InferenceResult,
+    root_expr: ExprId,
+) -> Result<MirBody> {
+    let is_root_body = root_expr == body.body_expr;
+    let mut basic_blocks = Arena::new();
+    let start_block = basic_blocks.alloc(BasicBlock::default());
+    let mut locals = Arena::new();
+    let (param_tys, return_ty) = match owner {
+        DefWithBodyId::FunctionId(f) if is_root_body => {
+            let sig = db
+                .callable_item_signature(f.into())
+                .substitute(Interner, &TyBuilder::placeholder_subst(db, f));
+            (sig.params().to_vec(), sig.ret().clone())
+        }
+        _ => {
+            let ty = match infer.expr_adjustments.get(&root_expr).and_then(|x| x.last()) {
+                Some(adjustment) => adjustment.target.clone(),
+                None => infer[root_expr].clone(),
+            };
+            (vec![], ty)
+        }
+    };
+    // 0 is the return slot
+    locals.alloc(Local { mutability: Mutability::Mut, ty: return_ty });
+    // 1 to param_len are the params
+    let param_locals = param_tys
+        .into_iter()
+        .map(|ty| locals.alloc(Local { mutability: Mutability::Not, ty }))
+        .collect::<Vec<_>>();
+    // and then the bindings declared in the body
+    let mut binding_locals = ArenaMap::new();
+    for (pat_id, pat) in body.pats.iter() {
+        if let Pat::Bind { mode, .. } = pat {
+            // Bindings of or-patterns share the local of their first occurrence.
+            if body.pattern_representative(pat_id) != pat_id {
+                continue;
+            }
+            let mutability = match mode {
+                BindingAnnotation::Mutable => Mutability::Mut,
+                _ => Mutability::Not,
+            };
+            let local_id = locals.alloc(Local { mutability, ty: infer[pat_id].clone() });
+            binding_locals.insert(pat_id, local_id);
+        }
+    }
+    let mir = MirBody {
+        basic_blocks,
+        locals,
+        start_block,
+        owner,
+        arg_count: param_locals.len(),
+        binding_locals,
+    };
+    let mut ctx = MirLowerCtx { result: mir, db, infer, body, owner, loop_blocks: vec![] };
+    let mut current = start_block;
+    for (&param, local) in body.params.iter().zip(param_locals) {
+        let (then, else_target) = ctx.pattern_match(current, None, local.into(), param)?;
+        if else_target.is_some() {
+            return Err(MirLowerError::TypeError("refutable pattern in function parameter"));
+        }
+        current = then;
+    }
+    let return_slot = ctx.result.return_slot();
+    if let Some(b) = ctx.lower_expr_to_place(root_expr, return_slot.into(), current)? {
+        ctx.set_terminator(b, Terminator::Return);
+    }
+    Ok(ctx.result)
+}
@@ -60,3 +60,25 @@ pub(super) fn float_ty_from_builtin(t: BuiltinFloat) -> FloatTy {
         BuiltinFloat::F64 => FloatTy::F64,
     }
 }
+
+pub(super) fn builtin_int(t: IntTy) -> BuiltinInt {
+    match t {
+        IntTy::Isize => BuiltinInt::Isize,
+        IntTy::I8 => BuiltinInt::I8,
+        IntTy::I16 => BuiltinInt::I16,
+        IntTy::I32 => BuiltinInt::I32,
+        IntTy::I64 => BuiltinInt::I64,
+        IntTy::I128 => BuiltinInt::I128,
+    }
+}
+
+pub(super) fn builtin_uint(t: UintTy) -> BuiltinUint {
+    match t {
+        UintTy::Usize => BuiltinUint::Usize,
+        UintTy::U8 => BuiltinUint::U8,
+        UintTy::U16 => BuiltinUint::U16,
+        UintTy::U32 => BuiltinUint::U32,
+        UintTy::U64 => BuiltinUint::U64,
+        UintTy::U128 => BuiltinUint::U128,
+    }
+}
@@ -1815,6 +1815,22 @@ fn main() {
     );
 }
 
+#[test]
+fn const_eval_const_fn_call_in_array_len() {
+    check_types(
+        r#"
+const fn compute_len() -> usize {
+    2 + 2
+}
+const N: usize = compute_len();
+fn main() {
+    let t: [u8; N] = loop {};
+    t;
+  //^ [u8; 4]
+}"#,
+    );
+}
+
 #[test]
 fn shadowing_primitive_with_inner_items() {
     check_types(
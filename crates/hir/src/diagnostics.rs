@@ -10,7 +10,7 @@ use hir_def::path::ModPath;
 use hir_expand::{name::Name, HirFileId, InFile};
 use syntax::{ast, AstPtr, SyntaxNodePtr, TextRange};
 
-use crate::{MacroKind, Type};
+use crate::{Local, MacroKind, Type};
 
 macro_rules! diagnostics {
     ($($diag:ident,)*) => {
@@ -40,6 +40,7 @@ diagnostics![
     MissingFields,
     MissingMatchArms,
     MissingUnsafe,
+    NeedMut,
     NoSuchField,
     ReplaceFilterMapNextWithFindMap,
     TypeMismatch,
@@ -49,6 +50,9 @@ diagnostics![
     UnresolvedMacroCall,
     UnresolvedModule,
     UnresolvedProcMacro,
+    UnusedMut,
+    UnusedVariable,
+    UseOfMovedValue,
 ];
 
 #[derive(Debug)]
@@ -169,4 +173,28 @@ pub struct TypeMismatch {
     pub actual: Type,
 }
 
+#[derive(Debug)]
+pub struct NeedMut {
+    pub local: Local,
+    /// The expression mutating the binding.
+    pub span: InFile<SyntaxNodePtr>,
+}
+
+#[derive(Debug)]
+pub struct UnusedMut {
+    pub local: Local,
+}
+
+#[derive(Debug)]
+pub struct UnusedVariable {
+    pub local: Local,
+}
+
+#[derive(Debug)]
+pub struct UseOfMovedValue {
+    pub local: Local,
+    pub moved_at: InFile<SyntaxNodePtr>,
+    pub used_at: InFile<SyntaxNodePtr>,
+}
+
 pub use hir_ty::diagnostics::IncorrectCase;
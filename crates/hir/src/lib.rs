@@ -84,9 +84,9 @@ pub use crate::{
     diagnostics::{
         AnyDiagnostic, BreakOutsideOfLoop, InactiveCode, IncorrectCase, InvalidDeriveTarget,
         MacroError, MalformedDerive, MismatchedArgCount, MissingFields, MissingMatchArms,
-        MissingUnsafe, NoSuchField, ReplaceFilterMapNextWithFindMap, TypeMismatch,
+        MissingUnsafe, NeedMut, NoSuchField, ReplaceFilterMapNextWithFindMap, TypeMismatch,
         UnimplementedBuiltinMacro, UnresolvedExternCrate, UnresolvedImport, UnresolvedMacroCall,
-        UnresolvedModule, UnresolvedProcMacro,
+        UnresolvedModule, UnresolvedProcMacro, UnusedMut, UnusedVariable, UseOfMovedValue,
     },
     has_source::HasSource,
     semantics::{PathResolution, Semantics, SemanticsScope, TypeInfo, VisibleTraits},
@@ -113,12 +113,18 @@ pub use {
         path::{ModPath, PathKind},
         type_ref::{Mutability, TypeRef},
         visibility::Visibility,
+        // FIXME: This is here since it is input of a method in `HirWrite`
+        // and things outside of hir need to implement that trait.
+        ModuleDefId,
     },
     hir_expand::{
         name::{known, Name},
         ExpandResult, HirFileId, InFile, MacroFile, Origin,
     },
-    hir_ty::display::HirDisplay,
+    hir_ty::{
+        display::{HirDisplay, HirWrite},
+        PointerCast, Safety,
+    },
 };
 
 // These are negative re-exports: pub using these names is forbidden, they
@@ -1316,6 +1322,50 @@ impl DefWithBody {
             }
         }
 
+        let usage = hir_ty::diagnostics::binding_usage(db, self.into());
+        // Bindings introduced by macro expansions or desugarings can't be fixed by the user.
+        let is_user_written = |pat_id| match source_map.pat_syntax(pat_id) {
+            Ok(source) => !source.file_id.is_macro(),
+            Err(SyntheticSyntax) => false,
+        };
+        let local = |pat_id| Local { parent: self.into(), pat_id };
+        for pat_id in usage.unused {
+            if is_user_written(pat_id) {
+                acc.push(UnusedVariable { local: local(pat_id) }.into());
+            }
+        }
+        for pat_id in usage.unused_mut {
+            if is_user_written(pat_id) {
+                acc.push(UnusedMut { local: local(pat_id) }.into());
+            }
+        }
+        for (pat_id, expr) in usage.need_mut {
+            if !is_user_written(pat_id) {
+                continue;
+            }
+            if let Ok(span) = source_map.expr_syntax(expr) {
+                acc.push(NeedMut { local: local(pat_id), span: span.map(|it| it.into()) }.into());
+            }
+        }
+
+        for moved in hir_ty::diagnostics::moved_value_uses(db, self.into()) {
+            let (moved_at, used_at) = match (
+                source_map.expr_syntax(moved.moved_at),
+                source_map.expr_syntax(moved.used_at),
+            ) {
+                (Ok(moved_at), Ok(used_at)) => (moved_at, used_at),
+                _ => continue,
+            };
+            acc.push(
+                UseOfMovedValue {
+                    local: Local { parent: self.into(), pat_id: moved.binding },
+                    moved_at: moved_at.map(|it| it.into()),
+                    used_at: used_at.map(|it| it.into()),
+                }
+                .into(),
+            );
+        }
+
         for diagnostic in BodyValidationDiagnostic::collect(db, self.into()) {
             match diagnostic {
                 BodyValidationDiagnostic::RecordMissingFields {
@@ -1732,6 +1782,10 @@ impl Static {
         let ty = ctx.lower_ty(&data.type_ref);
         Type::new_with_resolver_inner(db, &resolver, ty)
     }
+
+    pub fn eval(self, db: &dyn HirDatabase) -> Result<ComputedExpr, ConstEvalError> {
+        db.const_eval_static(self.id)
+    }
 }
 
 impl HasVisibility for Static {
@@ -1764,6 +1818,12 @@ impl Trait {
         db.trait_data(self.id).items.iter().map(|(_name, it)| (*it).into()).collect()
     }
 
+    /// Returns the traits this trait directly inherits from, i.e. the bounds on `Self` in its
+    /// declaration.
+    pub fn direct_supertraits(self, db: &dyn HirDatabase) -> Vec<Trait> {
+        hir_ty::direct_super_traits(db.upcast(), self.id).into_iter().map(Trait::from).collect()
+    }
+
     pub fn items_with_supertraits(self, db: &dyn HirDatabase) -> Vec<AssocItem> {
         let traits = all_super_traits(db.upcast(), self.into());
         traits.iter().flat_map(|tr| Trait::from(*tr).items(db)).collect()
@@ -2892,14 +2952,24 @@ impl Type {
         )
     }
 
+    /// Checks whether the type implements the trait with the given generic arguments. The
+    /// arguments which aren't given can be anything, so with none of them this checks whether
+    /// there is any implementation of the trait for the type.
     pub fn impls_trait(&self, db: &dyn HirDatabase, trait_: Trait, args: &[Type]) -> bool {
         let mut it = args.iter().map(|t| t.ty.clone());
+        let mut missing_args = false;
         let trait_ref = TyBuilder::trait_ref(db, trait_.id)
             .push(self.ty.clone())
             .fill(|x| {
-                let r = it.next().unwrap();
+                let r = it.next();
                 match x {
-                    ParamKind::Type => GenericArgData::Ty(r).intern(Interner),
+                    ParamKind::Type => {
+                        let r = r.unwrap_or_else(|| {
+                            missing_args = true;
+                            TyKind::Error.intern(Interner)
+                        });
+                        GenericArgData::Ty(r).intern(Interner)
+                    }
                     ParamKind::Const(ty) => {
                         // FIXME: this code is not covered in tests.
                         unknown_const_as_generic(ty.clone())
@@ -2908,9 +2978,11 @@ impl Type {
             })
             .build();
 
-        let goal = Canonical {
-            value: hir_ty::InEnvironment::new(&self.env.env, trait_ref.cast(Interner)),
-            binders: CanonicalVarKinds::empty(Interner),
+        let value = hir_ty::InEnvironment::new(&self.env.env, trait_ref.cast(Interner));
+        let goal = if missing_args {
+            hir_ty::replace_errors_with_variables(&value)
+        } else {
+            Canonical { value, binders: CanonicalVarKinds::empty(Interner) }
         };
 
         db.trait_solve(self.env.krate, goal).is_some()
@@ -3048,6 +3120,26 @@ impl Type {
             .collect()
     }
 
+    /// Returns the fields of `variant` with the generic arguments of this type substituted, if
+    /// this is the enum of `variant`.
+    pub fn variant_fields(&self, db: &dyn HirDatabase, variant: Variant) -> Vec<(Field, Type)> {
+        let substs = match self.ty.kind(Interner) {
+            TyKind::Adt(hir_ty::AdtId(AdtId::EnumId(e)), substs) if *e == variant.parent.id => {
+                substs
+            }
+            _ => return Vec::new(),
+        };
+        let variant_id = hir_def::VariantId::EnumVariantId(variant.into());
+        db.field_types(variant_id)
+            .iter()
+            .map(|(local_id, ty)| {
+                let def = Field { parent: variant.into(), id: local_id };
+                let ty = ty.clone().substitute(Interner, substs);
+                (def, self.derived(ty))
+            })
+            .collect()
+    }
+
     pub fn tuple_fields(&self, _db: &dyn HirDatabase) -> Vec<Type> {
         if let TyKind::Tuple(_, substs) = &self.ty.kind(Interner) {
             substs
@@ -3520,6 +3612,37 @@ pub enum BindingMode {
     Ref(Mutability),
 }
 
+/// A coercion or auto(de)ref step the compiler applies to an expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Adjustment {
+    pub source: Type,
+    pub target: Type,
+    pub kind: Adjust,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Adjust {
+    /// Go from ! to any type.
+    NeverToAny,
+    /// Dereference once, producing a place.
+    Deref(Option<OverloadedDeref>),
+    /// Take the address and produce either a `&` or `*` pointer.
+    Borrow(AutoBorrow),
+    Pointer(PointerCast),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AutoBorrow {
+    /// Converts from T to &T.
+    Ref(Mutability),
+    /// Converts from T to *T.
+    RawPtr(Mutability),
+}
+
+/// A `Deref(Mut)::deref(_mut)` call inserted by autoderef.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OverloadedDeref(pub Mutability);
+
 /// For IDE only
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ScopeDef {
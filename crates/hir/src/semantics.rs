@@ -29,9 +29,9 @@ use crate::{
     db::HirDatabase,
     semantics::source_to_def::{ChildContainer, SourceToDefCache, SourceToDefCtx},
     source_analyzer::{resolve_hir_path, SourceAnalyzer},
-    Access, BindingMode, BuiltinAttr, Callable, ConstParam, Crate, DeriveHelper, Field, Function,
-    HasSource, HirFileId, Impl, InFile, Label, LifetimeParam, Local, Macro, Module, ModuleDef,
-    Name, Path, ScopeDef, ToolModule, Trait, Type, TypeAlias, TypeParam, VariantDef,
+    Access, Adjustment, BindingMode, BuiltinAttr, Callable, ConstParam, Crate, DeriveHelper, Field,
+    Function, HasSource, HirFileId, Impl, InFile, Label, LifetimeParam, Local, Macro, Module,
+    ModuleDef, Name, Path, ScopeDef, ToolModule, Trait, Type, TypeAlias, TypeParam, VariantDef,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -333,6 +333,10 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         self.imp.is_implicit_reborrow(expr)
     }
 
+    pub fn expr_adjustments(&self, expr: &ast::Expr) -> Option<Vec<Adjustment>> {
+        self.imp.expr_adjustments(expr)
+    }
+
     pub fn type_of_expr(&self, expr: &ast::Expr) -> Option<TypeInfo> {
         self.imp.type_of_expr(expr)
     }
@@ -1056,6 +1060,10 @@ impl<'db> SemanticsImpl<'db> {
         self.analyze(expr.syntax())?.is_implicit_reborrow(self.db, expr)
     }
 
+    fn expr_adjustments(&self, expr: &ast::Expr) -> Option<Vec<Adjustment>> {
+        self.analyze(expr.syntax())?.expr_adjustments(self.db, expr)
+    }
+
     fn type_of_expr(&self, expr: &ast::Expr) -> Option<TypeInfo> {
         self.analyze(expr.syntax())?
             .type_of_expr(self.db, expr)
@@ -173,6 +173,49 @@ impl SourceAnalyzer {
         })
     }
 
+    pub(crate) fn expr_adjustments(
+        &self,
+        db: &dyn HirDatabase,
+        expr: &ast::Expr,
+    ) -> Option<Vec<crate::Adjustment>> {
+        let expr_id = self.expr_id(db, expr)?;
+        let infer = self.infer.as_ref()?;
+        let adjustments = infer.expr_adjustments.get(&expr_id)?;
+        let mut source = infer[expr_id].clone();
+        let mutability = |m: hir_ty::Mutability| match m {
+            hir_ty::Mutability::Mut => Mutability::Mut,
+            hir_ty::Mutability::Not => Mutability::Shared,
+        };
+        let adjustments = adjustments
+            .iter()
+            .map(|adjustment| {
+                let kind = match adjustment.kind {
+                    Adjust::NeverToAny => crate::Adjust::NeverToAny,
+                    Adjust::Deref(None) => crate::Adjust::Deref(None),
+                    Adjust::Deref(Some(deref)) => {
+                        crate::Adjust::Deref(Some(crate::OverloadedDeref(mutability(deref.0))))
+                    }
+                    Adjust::Borrow(AutoBorrow::Ref(m)) => {
+                        crate::Adjust::Borrow(crate::AutoBorrow::Ref(mutability(m)))
+                    }
+                    Adjust::Borrow(AutoBorrow::RawPtr(m)) => {
+                        crate::Adjust::Borrow(crate::AutoBorrow::RawPtr(mutability(m)))
+                    }
+                    Adjust::Pointer(cast) => crate::Adjust::Pointer(cast),
+                };
+                let target = adjustment.target.clone();
+                let res = crate::Adjustment {
+                    source: Type::new_with_resolver(db, &self.resolver, source.clone()),
+                    target: Type::new_with_resolver(db, &self.resolver, target.clone()),
+                    kind,
+                };
+                source = target;
+                res
+            })
+            .collect();
+        Some(adjustments)
+    }
+
     pub(crate) fn type_of_expr(
         &self,
         db: &dyn HirDatabase,
@@ -108,6 +108,7 @@ impl RootDatabase {
 
             // HirDatabase
             hir::db::InferQueryQuery
+            hir::db::MirBodyQuery
             hir::db::TyQuery
             hir::db::ValueTyQuery
             hir::db::ImplSelfTyQuery
@@ -478,6 +478,19 @@ use foo::bar::Baz;"#,
     );
 }
 
+#[test]
+fn inserts_after_frontmatter() {
+    check_none(
+        "foo::bar::Baz",
+        "---\n[dependencies]\n---",
+        r#"---
+[dependencies]
+---
+
+use foo::bar::Baz;"#,
+    );
+}
+
 #[test]
 fn inserts_after_multiple_single_line_comments() {
     check_none(
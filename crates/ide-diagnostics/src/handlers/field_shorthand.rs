@@ -101,7 +101,9 @@ fn check_pat_field_shorthand(
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::{check_diagnostics, check_fix};
+    use crate::tests::{
+        check_diagnostics, check_diagnostics_with_disabled, check_fix, check_fix_with_disabled,
+    };
 
     #[test]
     fn test_check_expr_field_shorthand() {
@@ -157,20 +159,22 @@ fn main() {
 
     #[test]
     fn test_check_pat_field_shorthand() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct A { a: &'static str }
 fn f(a: A) { let A { a: hello } = a; }
 "#,
+            &["unused_variables"],
         );
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct A(usize);
 fn f(a: A) { let A { 0: 0 } = a; }
 "#,
+            &["unused_variables"],
         );
 
-        check_fix(
+        check_fix_with_disabled(
             r#"
 struct A { a: &'static str }
 fn f(a: A) {
@@ -183,9 +187,10 @@ fn f(a: A) {
     let A { a } = a;
 }
 "#,
+            &["unused_variables"],
         );
 
-        check_fix(
+        check_fix_with_disabled(
             r#"
 struct A { a: &'static str, b: &'static str }
 fn f(a: A) {
@@ -198,6 +203,7 @@ fn f(a: A) {
     let A { a, b } = a;
 }
 "#,
+            &["unused_variables"],
         );
     }
 }
@@ -49,7 +49,10 @@ mod tests {
 
     #[test]
     fn cfg_diagnostics() {
-        check(
+        let mut config = DiagnosticsConfig::test_sample();
+        config.disabled.insert("unused_variables".to_string());
+        check_diagnostics_with_config(
+            config,
             r#"
 fn f() {
     // The three g̶e̶n̶d̶e̶r̶s̶ statements:
@@ -46,7 +46,7 @@ fn fixes(ctx: &DiagnosticsContext<'_>, d: &hir::IncorrectCase) -> Option<Vec<Ass
 
 #[cfg(test)]
 mod change_case {
-    use crate::tests::{check_diagnostics, check_fix};
+    use crate::tests::{check_diagnostics, check_diagnostics_with_disabled, check_fix};
 
     #[test]
     fn test_rename_incorrect_case() {
@@ -167,7 +167,7 @@ fn NonSnakeCaseName() {}
 
     #[test]
     fn incorrect_function_params() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 fn foo(SomeParam: u8) {}
     // ^^^^^^^^^ 💡 weak: Parameter `SomeParam` should have snake_case name, e.g. `some_param`
@@ -175,12 +175,13 @@ fn foo(SomeParam: u8) {}
 fn foo2(ok_param: &str, CAPS_PARAM: u8) {}
                      // ^^^^^^^^^^ 💡 weak: Parameter `CAPS_PARAM` should have snake_case name, e.g. `caps_param`
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn incorrect_variable_names() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 fn foo() {
     let SOME_VALUE = 10;
@@ -189,6 +190,7 @@ fn foo() {
      // ^^^^^^^^^^^^ 💡 weak: Variable `AnotherValue` should have snake_case name, e.g. `another_value`
 }
 "#,
+            &["unused_variables"],
         );
     }
 
@@ -278,7 +280,7 @@ static some_weird_const: u8 = 10;
 
     #[test]
     fn fn_inside_impl_struct() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct someStruct;
     // ^^^^^^^^^^ 💡 weak: Structure `someStruct` should have CamelCase name, e.g. `SomeStruct`
@@ -291,12 +293,13 @@ impl someStruct {
     }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn no_diagnostic_for_enum_varinats() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 enum Option { Some, None }
 
@@ -307,12 +310,13 @@ fn main() {
     }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn non_let_bind() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 enum Option { Some, None }
 
@@ -324,12 +328,13 @@ fn main() {
     }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn allow_attributes_crate_attr() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
@@ -346,6 +351,7 @@ mod F {
     fn CheckItWorksWithCrateAttr(BAD_NAME_HI: u8) {}
 }
     "#,
+            &["unused_variables"],
         );
     }
 
@@ -389,7 +395,7 @@ fn qualify() {
 
     #[test] // Issue #8809.
     fn parenthesized_parameter() {
-        check_diagnostics(r#"fn f((O): _) {}"#)
+        check_diagnostics_with_disabled(r#"fn f((O): _) {}"#, &["unused_variables"])
     }
 
     #[test]
@@ -440,7 +446,7 @@ trait BAD_TRAIT {
 
     #[test]
     fn allow_attributes() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 #[allow(non_snake_case)]
 fn NonSnakeCaseName(SOME_VAR: u8) -> u8{
@@ -481,6 +487,7 @@ pub const some_const: u8 = 10;
 #[allow(non_upper_case_globals)]
 pub static SomeStatic: u8 = 10;
     "#,
+            &["unused_variables"],
         );
     }
 }
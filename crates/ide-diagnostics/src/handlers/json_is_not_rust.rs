@@ -178,7 +178,7 @@ pub(crate) fn json_in_items(
 #[cfg(test)]
 mod tests {
     use crate::{
-        tests::{check_diagnostics_with_config, check_fix, check_no_fix},
+        tests::{check_diagnostics_with_config, check_fix, check_no_fix_with_disabled},
         DiagnosticsConfig,
     };
 
@@ -295,7 +295,7 @@ mod tests {
 
     #[test]
     fn no_emit_outside_of_item_position() {
-        check_no_fix(
+        check_no_fix_with_disabled(
             r#"
             fn foo() {
                 let json = {$0
@@ -307,6 +307,7 @@ mod tests {
                 };
             }
             "#,
+            &["unused_variables"],
         );
     }
 }
@@ -48,7 +48,7 @@ fn invalid_args_range(ctx: &DiagnosticsContext<'_>, d: &hir::MismatchedArgCount)
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::check_diagnostics;
+    use crate::tests::{check_diagnostics, check_diagnostics_with_disabled};
 
     #[test]
     fn simple_free_fn_zero() {
@@ -70,19 +70,21 @@ fn f() { zero(); }
 
     #[test]
     fn simple_free_fn_one() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 fn one(arg: u8) {}
 fn f() { one(); }
           //^^ error: expected 1 argument, found 0
 "#,
+            &["unused_variables"],
         );
 
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 fn one(arg: u8) {}
 fn f() { one(1); }
 "#,
+            &["unused_variables"],
         );
     }
 
@@ -114,7 +116,7 @@ fn f() {
 
     #[test]
     fn method_with_arg() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct S;
 impl S { fn method(&self, arg: u8) {} }
@@ -123,9 +125,10 @@ impl S { fn method(&self, arg: u8) {} }
                 S.method();
             }         //^^ error: expected 1 argument, found 0
             "#,
+            &["unused_variables"],
         );
 
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct S;
 impl S { fn method(&self, arg: u8) {} }
@@ -135,6 +138,7 @@ fn f() {
     S.method(1);
 }
 "#,
+            &["unused_variables"],
         );
     }
 
@@ -268,7 +272,7 @@ fn main() {
 
     #[test]
     fn cfgd_out_fn_params() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 fn foo(#[cfg(NEVER)] x: ()) {}
 
@@ -297,12 +301,13 @@ fn main() {
     }
 }
             "#,
+            &["unused_variables"],
         )
     }
 
     #[test]
     fn legacy_const_generics() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 #[rustc_legacy_const_generics(1, 3)]
 fn mixed<const N1: &'static str, const N2: bool>(
@@ -329,6 +334,7 @@ fn g() {
            //^ error: expected 4 arguments, found 3
 }
             "#,
+            &["unused_variables"],
         )
     }
 }
@@ -239,7 +239,7 @@ fn get_default_constructor(
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::{check_diagnostics, check_fix};
+    use crate::tests::{check_diagnostics, check_diagnostics_with_disabled, check_fix};
 
     #[test]
     fn missing_record_pat_field_diagnostic() {
@@ -271,31 +271,33 @@ fn baz(s: S) -> i32 {
 
     #[test]
     fn missing_record_pat_field_box() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r"
 struct S { s: Box<u32> }
 fn x(a: S) {
     let S { box s } = a;
 }
 ",
+            &["unused_variables"],
         )
     }
 
     #[test]
     fn missing_record_pat_field_ref() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r"
 struct S { s: u32 }
 fn x(a: S) {
     let S { ref s } = a;
 }
 ",
+            &["unused_variables"],
         )
     }
 
     #[test]
     fn missing_record_expr_in_assignee_expr() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r"
 struct S { s: usize, t: usize }
 struct S2 { s: S, t: () }
@@ -321,6 +323,7 @@ fn in_tuple_struct(a: T) {
     T(S { s, .. }) = a;
 }
             ",
+            &["unused_variables"],
         );
     }
 
@@ -618,7 +621,7 @@ fn test_fn() {
 
     #[test]
     fn test_fill_struct_fields_no_diagnostic() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct TestStruct { one: i32, two: i64 }
 
@@ -627,12 +630,13 @@ fn test_fn() {
     let s = TestStruct{ one, two: 2 };
 }
         "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_fill_struct_fields_no_diagnostic_on_spread() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct TestStruct { one: i32, two: i64 }
 
@@ -641,6 +645,7 @@ fn test_fn() {
     let s = TestStruct{ ..a };
 }
 "#,
+            &["unused_variables"],
         );
     }
 
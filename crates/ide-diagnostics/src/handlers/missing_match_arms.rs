@@ -18,7 +18,7 @@ pub(crate) fn missing_match_arms(
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::check_diagnostics;
+    use crate::tests::{check_diagnostics, check_diagnostics_with_disabled};
 
     fn check_diagnostics_no_bails(ra_fixture: &str) {
         cov_mark::check_count!(validate_match_bailed_out, 0);
@@ -551,7 +551,7 @@ fn bang(never: !) {
     fn unknown_type() {
         cov_mark::check_count!(validate_match_bailed_out, 1);
 
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 enum Option<T> { Some(T), None }
 
@@ -567,6 +567,7 @@ fn main() {
     }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
@@ -706,7 +707,7 @@ fn main() {
     fn no_panic_at_unimplemented_subpattern_type() {
         cov_mark::check_count!(validate_match_bailed_out, 1);
 
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct S { a: char}
 fn main(v: S) {
@@ -719,6 +720,7 @@ fn main(v: S) {
         //^ error: missing match arm: type `S` is non-empty
 }
 "#,
+            &["unused_variables"],
         );
     }
 
@@ -839,13 +841,14 @@ fn main() {
     fn record_struct_no_such_field() {
         cov_mark::check_count!(validate_match_bailed_out, 1);
 
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct Foo { }
 fn main(f: Foo) {
     match f { Foo { bar } => () }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
@@ -889,7 +892,8 @@ static __: () = {
 
     #[test]
     fn binding_mode_by_ref() {
-        check_diagnostics_no_bails(
+        cov_mark::check_count!(validate_match_bailed_out, 0);
+        crate::tests::check_diagnostics_with_disabled(
             r"
 enum E{ A, B }
 fn foo() {
@@ -898,6 +902,7 @@ fn foo() {
         x => {}
     }
 }",
+            &["unused_variables"],
         );
     }
 
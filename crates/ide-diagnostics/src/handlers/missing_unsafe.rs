@@ -13,11 +13,11 @@ pub(crate) fn missing_unsafe(ctx: &DiagnosticsContext<'_>, d: &hir::MissingUnsaf
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::check_diagnostics;
+    use crate::tests::{check_diagnostics, check_diagnostics_with_disabled};
 
     #[test]
     fn missing_unsafe_diagnostic_with_raw_ptr() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 fn main() {
     let x = &5 as *const usize;
@@ -25,12 +25,13 @@ fn main() {
     let z = *x;
 }         //^^ error: this operation is unsafe and requires an unsafe function or block
 "#,
+            &["unused_variables"],
         )
     }
 
     #[test]
     fn missing_unsafe_diagnostic_with_unsafe_call() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct HasUnsafe;
 
@@ -57,12 +58,13 @@ fn main() {
     }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn missing_unsafe_diagnostic_with_static_mut() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 struct Ty {
     a: u8,
@@ -78,6 +80,7 @@ fn main() {
     }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
@@ -0,0 +1,180 @@
+use either::Either;
+use ide_db::{assists::Assist, source_change::SourceChange};
+use syntax::{ast::HasName, AstNode, TextRange};
+use text_edit::TextEdit;
+
+use crate::{fix, Diagnostic, DiagnosticsContext};
+
+// Diagnostic: need-mut
+//
+// This diagnostic is triggered on mutation of an immutable binding.
+pub(crate) fn need_mut(ctx: &DiagnosticsContext<'_>, d: &hir::NeedMut) -> Diagnostic {
+    let range = ctx.sema.diagnostics_display_range(d.span.clone()).range;
+    Diagnostic::new("need-mut", "cannot mutate immutable binding", range)
+        .experimental()
+        .with_fixes(fixes(ctx, d, range))
+}
+
+fn fixes(ctx: &DiagnosticsContext<'_>, d: &hir::NeedMut, target: TextRange) -> Option<Vec<Assist>> {
+    let source = d.local.source(ctx.sema.db);
+    let file_id = source.file_id.original_file(ctx.sema.db);
+    let insert_at = match &source.value {
+        Either::Left(ident_pat) => {
+            // `ref x` makes `x` a shared reference, adding `mut` wouldn't help.
+            if ident_pat.ref_token().is_some() {
+                return None;
+            }
+            let name = ident_pat.name()?;
+            name.syntax().text_range().start()
+        }
+        Either::Right(self_param) => {
+            // `&self` would need a different receiver type instead.
+            if self_param.amp_token().is_some() {
+                return None;
+            }
+            let name = self_param.name()?;
+            name.syntax().text_range().start()
+        }
+    };
+
+    Some(vec![fix(
+        "add_mut",
+        "Change it to be mutable",
+        SourceChange::from_text_edit(file_id, TextEdit::insert(insert_at, "mut ".to_string())),
+        target,
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{
+        check_diagnostics, check_diagnostics_with_disabled, check_fix, check_no_fix,
+    };
+
+    #[test]
+    fn need_mut() {
+        check_diagnostics(
+            r#"
+struct S { f: i32 }
+impl S {
+    fn set(&mut self) {}
+}
+fn foo(a: i32) -> i32 {
+    a = 1;
+  //^^^^^ 💡 error: cannot mutate immutable binding
+    let s = S { f: 0 };
+    s.f = a;
+  //^^^^^^^ 💡 error: cannot mutate immutable binding
+    let t = S { f: 0 };
+    t.set();
+  //^ 💡 error: cannot mutate immutable binding
+    let u = 0;
+    let r = &mut u;
+          //^^^^^^ 💡 error: cannot mutate immutable binding
+    *r + s.f + t.f
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn no_need_mut() {
+        check_diagnostics(
+            r#"
+struct S { f: i32 }
+impl S {
+    fn set(&mut self) {}
+}
+fn foo(r: &mut S, v: &mut [i32], b: bool) -> i32 {
+    r.f = 1;
+    v[0] = 1;
+    r.set();
+    *r = S { f: 2 };
+    let x;
+    if b {
+        x = 1;
+    } else {
+        x = 2;
+    }
+    let mut y = 0;
+    let inc = &mut y;
+    *inc += x;
+    y
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn ref_mut_binding() {
+        check_diagnostics_with_disabled(
+            r#"
+fn foo() -> i32 {
+    let x = 0;
+    let ref mut y = x;
+                  //^ 💡 error: cannot mutate immutable binding
+    *y += 1;
+    match Some(x) {
+        Some(ref mut z) => *z,
+        None => 0,
+    }
+}
+"#,
+            &["unused_variables"],
+        );
+    }
+
+    #[test]
+    fn fix_add_mut() {
+        check_fix(
+            r#"
+fn foo() {
+    let x = 0;
+    x$0 = 1;
+}
+"#,
+            r#"
+fn foo() {
+    let mut x = 0;
+    x = 1;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fix_add_mut_to_self() {
+        check_fix(
+            r#"
+struct S { f: i32 }
+impl S {
+    fn f(self) -> i32 {
+        self$0.f = 1;
+        self.f
+    }
+}
+"#,
+            r#"
+struct S { f: i32 }
+impl S {
+    fn f(mut self) -> i32 {
+        self.f = 1;
+        self.f
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn no_fix_for_ref_binding() {
+        check_no_fix(
+            r#"
+fn foo() {
+    let ref x = 0;
+    x$0 = &1;
+}
+"#,
+        );
+    }
+}
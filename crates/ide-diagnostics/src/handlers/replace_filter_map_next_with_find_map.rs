@@ -55,23 +55,24 @@ fn fixes(
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::{check_diagnostics, check_fix};
+    use crate::tests::{check_diagnostics_with_disabled, check_fix};
 
     #[test]
     fn replace_filter_map_next_with_find_map2() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 //- minicore: iterators
 fn foo() {
     let m = core::iter::repeat(()).filter_map(|()| Some(92)).next();
 }         //^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: replace filter_map(..).next() with find_map(..)
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn replace_filter_map_next_with_find_map_no_diagnostic_without_next() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 //- minicore: iterators
 fn foo() {
@@ -80,12 +81,13 @@ fn foo() {
         .count();
 }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn replace_filter_map_next_with_find_map_no_diagnostic_with_intervening_methods() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 //- minicore: iterators
 fn foo() {
@@ -95,12 +97,13 @@ fn foo() {
         .next();
 }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn replace_filter_map_next_with_find_map_no_diagnostic_if_not_in_chain() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 //- minicore: iterators
 fn foo() {
@@ -109,6 +112,7 @@ fn foo() {
     let n = m.next();
 }
 "#,
+            &["unused_variables", "need-mut"],
         );
     }
 
@@ -170,11 +170,14 @@ fn str_ref_to_owned(
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::{check_diagnostics, check_fix, check_no_fix};
+    use crate::tests::{
+        check_diagnostics, check_diagnostics_with_disabled, check_fix, check_fix_with_disabled,
+        check_no_fix,
+    };
 
     #[test]
     fn missing_reference() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
 fn main() {
     test(123);
@@ -182,12 +185,13 @@ fn main() {
 }
 fn test(arg: &i32) {}
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_add_reference_to_int() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 fn main() {
     test(123$0);
@@ -200,12 +204,13 @@ fn main() {
 }
 fn test(arg: &i32) {}
             "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_add_mutable_reference_to_int() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 fn main() {
     test($0123);
@@ -218,12 +223,13 @@ fn main() {
 }
 fn test(arg: &mut i32) {}
             "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_add_reference_to_array() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 //- minicore: coerce_unsized
 fn main() {
@@ -237,12 +243,13 @@ fn main() {
 }
 fn test(arg: &[i32]) {}
             "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_add_reference_with_autoderef() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 //- minicore: coerce_unsized, deref
 struct Foo;
@@ -268,12 +275,13 @@ fn main() {
 }
 fn test(arg: &Bar) {}
             "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_add_reference_to_method_call() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 fn main() {
     Test.call_by_ref($0123);
@@ -292,12 +300,13 @@ impl Test {
     fn call_by_ref(&self, arg: &i32) {}
 }
             "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_add_reference_to_let_stmt() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 fn main() {
     let test: &i32 = $0123;
@@ -308,6 +317,7 @@ fn main() {
     let test: &i32 = &123;
 }
             "#,
+            &["unused_variables"],
         );
     }
 
@@ -341,7 +351,7 @@ fn main() {
 
     #[test]
     fn test_add_mutable_reference_to_let_stmt() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 fn main() {
     let test: &mut i32 = $0123;
@@ -352,6 +362,7 @@ fn main() {
     let test: &mut i32 = &mut 123;
 }
             "#,
+            &["unused_variables"],
         );
     }
 
@@ -380,7 +391,7 @@ fn div(x: i32, y: i32) -> Option<i32> {
 
     #[test]
     fn const_generic_type_mismatch() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
             pub struct Rate<const N: u32>;
             fn f<const N: u64>() -> Rate<N> { // FIXME: add some error
@@ -393,12 +404,13 @@ fn div(x: i32, y: i32) -> Option<i32> {
                   //^^^ error: expected Rate<5>, found Rate<_>
             }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn const_generic_unknown() {
-        check_diagnostics(
+        check_diagnostics_with_disabled(
             r#"
             pub struct Rate<T, const NOM: u32, const DENOM: u32>(T);
             fn run(t: Rate<u32, 1, 1>) {
@@ -407,12 +419,13 @@ fn div(x: i32, y: i32) -> Option<i32> {
                 run(Rate::<_, _, _>(5));
             }
 "#,
+            &["unused_variables"],
         );
     }
 
     #[test]
     fn test_wrap_return_type_option_tails() {
-        check_fix(
+        check_fix_with_disabled(
             r#"
 //- minicore: option, result
 fn div(x: i32, y: i32) -> Option<i32> {
@@ -436,6 +449,7 @@ fn div(x: i32, y: i32) -> Option<i32> {
     }
 }
 "#,
+            &["unused_variables"],
         );
     }
 
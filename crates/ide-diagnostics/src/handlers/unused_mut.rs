@@ -0,0 +1,187 @@
+use either::Either;
+use ide_db::{assists::Assist, source_change::SourceChange};
+use syntax::{AstNode, SyntaxKind, TextRange};
+use text_edit::TextEdit;
+
+use crate::{fix, Diagnostic, DiagnosticsContext, Severity};
+
+// Diagnostic: unused_mut
+//
+// This diagnostic is triggered when a binding is declared `mut` but never mutated.
+pub(crate) fn unused_mut(ctx: &DiagnosticsContext<'_>, d: &hir::UnusedMut) -> Diagnostic {
+    let source = d.local.source(ctx.sema.db);
+    let range = match &source.value {
+        Either::Left(ident_pat) => ident_pat.syntax().text_range(),
+        Either::Right(self_param) => self_param.syntax().text_range(),
+    };
+    Diagnostic::new("unused_mut", "variable does not need to be mutable", range)
+        .severity(Severity::WeakWarning)
+        .experimental()
+        .with_fixes(fixes(ctx, d))
+}
+
+fn fixes(ctx: &DiagnosticsContext<'_>, d: &hir::UnusedMut) -> Option<Vec<Assist>> {
+    let source = d.local.source(ctx.sema.db);
+    let file_id = source.file_id.original_file(ctx.sema.db);
+    let (mut_token, target) = match &source.value {
+        Either::Left(ident_pat) => (ident_pat.mut_token()?, ident_pat.syntax().text_range()),
+        Either::Right(self_param) => (self_param.mut_token()?, self_param.syntax().text_range()),
+    };
+    let mut delete = mut_token.text_range();
+    if let Some(ws) = mut_token.next_token().filter(|it| it.kind() == SyntaxKind::WHITESPACE) {
+        delete = TextRange::new(delete.start(), ws.text_range().end());
+    }
+
+    Some(vec![fix(
+        "remove_mut",
+        "Remove unnecessary `mut`",
+        SourceChange::from_text_edit(file_id, TextEdit::delete(delete)),
+        target,
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        tests::{check_diagnostics_with_config, check_fix},
+        DiagnosticsConfig,
+    };
+
+    fn check_diagnostics(ra_fixture: &str) {
+        let mut config = DiagnosticsConfig::test_sample();
+        config.disabled.insert("unused_variables".to_string());
+        check_diagnostics_with_config(config, ra_fixture)
+    }
+
+    #[test]
+    fn unused_mut() {
+        check_diagnostics(
+            r#"
+struct S { f: i32 }
+impl S {
+    fn by_value(mut self) -> i32 {
+              //^^^^^^^^ 💡 weak: variable does not need to be mutable
+        self.f
+    }
+}
+fn foo(mut a: i32) -> i32 {
+     //^^^^^ 💡 weak: variable does not need to be mutable
+    let mut b = 1;
+      //^^^^^ 💡 weak: variable does not need to be mutable
+    let (mut c, d) = (a, b);
+       //^^^^^ 💡 weak: variable does not need to be mutable
+    c + d
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn mutated_bindings() {
+        check_diagnostics(
+            r#"
+//- minicore: index, slice, fn
+struct S { f: i32 }
+impl S {
+    fn set(&mut self, f: i32) {
+        self.f = f;
+    }
+}
+fn foo(mut a: i32, mut arr: [i32; 2]) {
+    a += 1;
+    arr[0] = a;
+    let mut s = S { f: 0 };
+    s.set(1);
+    let mut t = S { f: 0 };
+    t.f = 2;
+    let mut u = S { f: 0 };
+    let r = &mut u;
+    r.f = 3;
+    let mut x = 0;
+    let mut inc = || x += 1;
+    inc();
+    let (mut y, mut z) = (0, 0);
+    (y, z) = (z, y);
+    let _ = (arr, s, t, y, z);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn deferred_initialization() {
+        check_diagnostics(
+            r#"
+fn foo(c: bool) -> i32 {
+    let x;
+    x = 1;
+    let mut y;
+      //^^^^^ 💡 weak: variable does not need to be mutable
+    y = 2;
+    let mut z;
+    if c {
+        z = 3;
+    } else {
+        z = 4;
+    }
+    x + y + z
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn mutation_through_reference_does_not_need_mut() {
+        check_diagnostics(
+            r#"
+struct S { f: i32 }
+fn foo(mut r: &mut S, p: *mut S) {
+     //^^^^^ 💡 weak: variable does not need to be mutable
+    r.f = 1;
+    *r = S { f: 2 };
+    unsafe { (*p).f = 3 };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fix_remove_mut() {
+        check_fix(
+            r#"
+fn foo() -> i32 {
+    let mut$0 x = 5;
+    x
+}
+"#,
+            r#"
+fn foo() -> i32 {
+    let x = 5;
+    x
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fix_remove_mut_self() {
+        check_fix(
+            r#"
+struct S;
+impl S {
+    fn f(mut$0 self) -> S {
+        self
+    }
+}
+"#,
+            r#"
+struct S;
+impl S {
+    fn f(self) -> S {
+        self
+    }
+}
+"#,
+        );
+    }
+}
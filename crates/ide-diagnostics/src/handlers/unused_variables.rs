@@ -0,0 +1,289 @@
+use either::Either;
+use ide_db::{
+    assists::Assist,
+    base_db::FileId,
+    source_change::SourceChange,
+    syntax_helpers::format_string::{is_format_string, lex_format_specifiers, FormatSpecifier},
+};
+use syntax::{
+    ast::{self, HasName},
+    AstNode, AstToken, SyntaxKind, TextRange,
+};
+use text_edit::TextEdit;
+
+use crate::{fix, Diagnostic, DiagnosticsContext, Severity};
+
+// Diagnostic: unused_variables
+//
+// This diagnostic is triggered when a local variable is never read.
+pub(crate) fn unused_variables(
+    ctx: &DiagnosticsContext<'_>,
+    d: &hir::UnusedVariable,
+) -> Option<Diagnostic> {
+    let source = d.local.source(ctx.sema.db);
+    let range = match &source.value {
+        Either::Left(ident_pat) => {
+            let file_id = source.file_id.original_file(ctx.sema.db);
+            if is_captured_by_format_string(ctx, file_id, ident_pat) {
+                return None;
+            }
+            binding_name_range(ident_pat)
+        }
+        Either::Right(self_param) => self_param.syntax().text_range(),
+    };
+    let d = Diagnostic::new("unused_variables", "unused variable", range)
+        .severity(Severity::WeakWarning)
+        .experimental()
+        .with_unused(true)
+        .with_fixes(fixes(ctx, d));
+    Some(d)
+}
+
+/// `format_args!` drops the format string when expanding, so bindings captured by it, as in
+/// `format!("{x}")`, look unused to hir. Look for such captures in the enclosing item instead.
+fn is_captured_by_format_string(
+    ctx: &DiagnosticsContext<'_>,
+    file_id: FileId,
+    ident_pat: &ast::IdentPat,
+) -> bool {
+    let name = match ident_pat.name() {
+        Some(it) => it.text().to_string(),
+        None => return false,
+    };
+    let item = ctx
+        .sema
+        .parse(file_id)
+        .syntax()
+        .covering_element(ident_pat.syntax().text_range())
+        .ancestors()
+        .find_map(ast::Item::cast);
+    let item = match item {
+        Some(it) => it,
+        None => return false,
+    };
+    item.syntax()
+        .descendants_with_tokens()
+        .filter_map(|it| it.into_token())
+        .filter(|token| {
+            token.kind() == SyntaxKind::STRING
+                && token.parent().map_or(false, |it| it.kind() == SyntaxKind::TOKEN_TREE)
+        })
+        .filter_map(ast::String::cast)
+        .any(|string| {
+            let is_format_string = ctx
+                .sema
+                .descend_into_macros(string.syntax().clone())
+                .into_iter()
+                .filter_map(ast::String::cast)
+                .any(|it| is_format_string(&it));
+            if !is_format_string {
+                return false;
+            }
+            let mut captured = false;
+            lex_format_specifiers(&string, &mut |range, kind| {
+                if let FormatSpecifier::Identifier = kind {
+                    captured |=
+                        string.text().get(std::ops::Range::<usize>::from(range)) == Some(&*name);
+                }
+            });
+            captured
+        })
+}
+
+fn fixes(ctx: &DiagnosticsContext<'_>, d: &hir::UnusedVariable) -> Option<Vec<Assist>> {
+    let source = d.local.source(ctx.sema.db);
+    let file_id = source.file_id.original_file(ctx.sema.db);
+    let ident_pat = source.value.left()?;
+    let name = ident_pat.name()?;
+    let name_range = name.syntax().text_range();
+
+    // `S { x }` has to become `S { x: _x }`, renaming the binding would otherwise rename the
+    // field it refers to.
+    let is_shorthand_field = ident_pat
+        .syntax()
+        .parent()
+        .and_then(ast::RecordPatField::cast)
+        .map_or(false, |field| field.name_ref().is_none());
+    let pat_start = ident_pat.syntax().text_range().start();
+    let mut builder = TextEdit::builder();
+    match is_shorthand_field {
+        true if pat_start == name_range.start() => {
+            builder.insert(pat_start, format!("{name}: _"));
+        }
+        true => {
+            builder.insert(pat_start, format!("{name}: "));
+            builder.insert(name_range.start(), "_".to_string());
+        }
+        false => builder.insert(name_range.start(), "_".to_string()),
+    }
+
+    Some(vec![fix(
+        "prefix_underscore",
+        &format!("Rename unused {name} to _{name}"),
+        SourceChange::from_text_edit(file_id, builder.finish()),
+        name_range,
+    )])
+}
+
+pub(crate) fn binding_name_range(ident_pat: &ast::IdentPat) -> TextRange {
+    match ident_pat.name() {
+        Some(name) => name.syntax().text_range(),
+        None => ident_pat.syntax().text_range(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        tests::{check_diagnostics_with_config, check_fix},
+        DiagnosticsConfig,
+    };
+
+    fn check_diagnostics(ra_fixture: &str) {
+        let mut config = DiagnosticsConfig::test_sample();
+        config.disabled.insert("unused_mut".to_string());
+        check_diagnostics_with_config(config, ra_fixture)
+    }
+
+    #[test]
+    fn unused_variables() {
+        check_diagnostics(
+            r#"
+fn foo(x: i32, _y: i32) {
+     //^ 💡 weak: unused variable
+    let a = 5;
+      //^ 💡 weak: unused variable
+    let _b = 6;
+    let (c, d) = (1, 2);
+       //^ 💡 weak: unused variable
+    let e;
+      //^ 💡 weak: unused variable
+    e = d;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn used_variables() {
+        check_diagnostics(
+            r#"
+//- minicore: option
+struct S { field: i32 }
+fn foo(s: S, v: &mut [i32]) -> i32 {
+    let S { field } = s;
+    let x = field;
+    let f = |y: i32| y + x;
+    v[0] = f(1);
+    match Some(2) {
+        Some(a) | Some(a) => a,
+        None => 0,
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn variables_captured_by_format_strings() {
+        check_diagnostics(
+            r#"
+#[rustc_builtin_macro]
+macro_rules! format_args {}
+macro_rules! format {
+    ($($arg:tt)*) => { format_args!($($arg)*) };
+}
+fn foo() {
+    let x = 1;
+    let width = 2;
+    let y = 3;
+      //^ 💡 weak: unused variable
+    format!("{x:width$}");
+    format!("{{y}}");
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn self_is_never_unused() {
+        check_diagnostics(
+            r#"
+struct S;
+impl S {
+    fn f(&self) {}
+}
+trait T {
+    fn g(&self, x: i32);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn variables_in_macro_expansions() {
+        check_diagnostics(
+            r#"
+macro_rules! m {
+    () => { let x = 1; };
+    ($e:expr) => { $e };
+}
+fn foo() {
+    m!();
+    let y = 2;
+    m!(y);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fix_prefix_with_underscore() {
+        check_fix(
+            r#"
+fn foo() {
+    let x$0 = 5;
+}
+"#,
+            r#"
+fn foo() {
+    let _x = 5;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fix_shorthand_field_pattern() {
+        check_fix(
+            r#"
+struct S { x: i32, y: i32 }
+fn foo(s: S) -> i32 {
+    let S { x$0, y } = s;
+    y
+}
+"#,
+            r#"
+struct S { x: i32, y: i32 }
+fn foo(s: S) -> i32 {
+    let S { x: _x, y } = s;
+    y
+}
+"#,
+        );
+        check_fix(
+            r#"
+struct S { x: i32 }
+fn foo(s: S) {
+    let S { ref x$0 } = s;
+}
+"#,
+            r#"
+struct S { x: i32 }
+fn foo(s: S) {
+    let S { x: ref _x } = s;
+}
+"#,
+        );
+    }
+}
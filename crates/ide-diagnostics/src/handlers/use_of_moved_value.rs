@@ -0,0 +1,204 @@
+use crate::{Diagnostic, DiagnosticsContext};
+
+// Diagnostic: use-of-moved-value
+//
+// This diagnostic is triggered if a local variable is used after its value has been moved.
+pub(crate) fn use_of_moved_value(
+    ctx: &DiagnosticsContext<'_>,
+    d: &hir::UseOfMovedValue,
+) -> Diagnostic {
+    let used_at = ctx.sema.diagnostics_display_range(d.used_at.clone());
+    let moved_at = ctx.sema.diagnostics_display_range(d.moved_at.clone());
+    Diagnostic::new(
+        "use-of-moved-value",
+        format!("use of moved value: `{}`", d.local.name(ctx.sema.db)),
+        used_at.range,
+    )
+    .with_related(vec![(moved_at, "value moved here".to_string())])
+    .experimental()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_diagnostics, check_diagnostics_with_disabled};
+
+    #[test]
+    fn use_after_move() {
+        check_diagnostics(
+            r#"
+//- minicore: copy
+struct S;
+fn consume(_: S) {}
+fn borrow(_: &S) {}
+fn foo() {
+    let s = S;
+    consume(s);
+          //^ related: value moved here
+    borrow(&s);
+          //^ error: use of moved value: `s`
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn copy_types_are_not_moved() {
+        check_diagnostics(
+            r#"
+//- minicore: copy
+struct C;
+impl Clone for C {
+    fn clone(&self) -> C {
+        C
+    }
+}
+impl Copy for C {}
+struct S;
+fn consume<T>(_: T) {}
+fn foo<T: Copy>(c: C, r: &S, t: T, n: i32) {
+    consume(c);
+    consume(c);
+    consume(r);
+    consume(r);
+    consume(t);
+    consume(t);
+    consume(n);
+    consume(n);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn borrows_do_not_move() {
+        check_diagnostics(
+            r#"
+//- minicore: copy
+struct S;
+impl S {
+    fn by_ref(&self) {}
+    fn by_mut(&mut self) {}
+    fn by_value(self) {}
+}
+fn foo() {
+    let mut s = S;
+    s.by_ref();
+    s.by_mut();
+    let r = &s;
+    let _ = s;
+    match s {
+        _ => {}
+    }
+    r.by_ref();
+    s.by_value();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn reinitialization() {
+        check_diagnostics(
+            r#"
+//- minicore: copy
+struct S;
+fn consume(_: S) {}
+fn foo() {
+    let mut s = S;
+    consume(s);
+    s = S;
+    consume(s);
+    let t = S;
+    let u = t;
+    let t = S;
+    consume(t);
+    consume(u);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn conditional_moves() {
+        check_diagnostics(
+            r#"
+//- minicore: copy
+struct S;
+fn consume(_: S) {}
+fn foo(b: bool) {
+    let s = S;
+    if b {
+        consume(s);
+              //^ related: value moved here
+    }
+    consume(s);
+          //^ error: use of moved value: `s`
+    let t = S;
+    if b {
+        consume(t);
+        return;
+    }
+    consume(t);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn moves_in_loops() {
+        check_diagnostics_with_disabled(
+            r#"
+//- minicore: copy
+struct S;
+fn consume(_: S) {}
+fn foo(b: bool) {
+    let s = S;
+    loop {
+        consume(s);
+              //^ error: use of moved value: `s`
+              //^ related: value moved here
+    }
+}
+fn bar(b: bool) {
+    let mut s = S;
+    while b {
+        consume(s);
+        s = S;
+    }
+    let t = S;
+    loop {
+        consume(t);
+        break;
+    }
+}
+"#,
+            &["unused_variables"],
+        );
+    }
+
+    #[test]
+    fn moves_into_closures() {
+        check_diagnostics(
+            r#"
+//- minicore: copy
+struct S;
+impl S {
+    fn by_ref(&self) {}
+}
+fn consume(_: S) {}
+fn foo() {
+    let s = S;
+    let f = || s.by_ref();
+    s.by_ref();
+    f();
+    let t = S;
+    let g = || consume(t);
+          //^^^^^^^^^^^^^ related: value moved here
+    t.by_ref();
+  //^ error: use of moved value: `t`
+    g();
+}
+"#,
+        );
+    }
+}
@@ -19,8 +19,19 @@ use crate::{DiagnosticsConfig, ExprFillDefaultMode, Severity};
 ///  * that the contents of the file containing the cursor match `after` after the diagnostic fix is applied
 #[track_caller]
 pub(crate) fn check_fix(ra_fixture_before: &str, ra_fixture_after: &str) {
-    check_nth_fix(0, ra_fixture_before, ra_fixture_after);
+    check_nth_fix(0, ra_fixture_before, ra_fixture_after, &[]);
 }
+
+/// Like `check_fix`, but disables the diagnostics with the given codes first.
+#[track_caller]
+pub(crate) fn check_fix_with_disabled(
+    ra_fixture_before: &str,
+    ra_fixture_after: &str,
+    disabled: &[&str],
+) {
+    check_nth_fix(0, ra_fixture_before, ra_fixture_after, disabled);
+}
+
 /// Takes a multi-file input fixture with annotated cursor positions,
 /// and checks that:
 ///  * a diagnostic is produced
@@ -28,17 +39,18 @@ pub(crate) fn check_fix(ra_fixture_before: &str, ra_fixture_after: &str) {
 ///  * that the contents of the file containing the cursor match `after` after each diagnostic fix is applied
 pub(crate) fn check_fixes(ra_fixture_before: &str, ra_fixtures_after: Vec<&str>) {
     for (i, ra_fixture_after) in ra_fixtures_after.iter().enumerate() {
-        check_nth_fix(i, ra_fixture_before, ra_fixture_after)
+        check_nth_fix(i, ra_fixture_before, ra_fixture_after, &[])
     }
 }
 
 #[track_caller]
-fn check_nth_fix(nth: usize, ra_fixture_before: &str, ra_fixture_after: &str) {
+fn check_nth_fix(nth: usize, ra_fixture_before: &str, ra_fixture_after: &str, disabled: &[&str]) {
     let after = trim_indent(ra_fixture_after);
 
     let (db, file_position) = RootDatabase::with_position(ra_fixture_before);
     let mut conf = DiagnosticsConfig::test_sample();
     conf.expr_fill_default = ExprFillDefaultMode::Default;
+    conf.disabled.extend(disabled.iter().map(|it| it.to_string()));
     let diagnostic =
         super::diagnostics(&db, &conf, &AssistResolveStrategy::All, file_position.file_id)
             .pop()
@@ -66,15 +78,18 @@ fn check_nth_fix(nth: usize, ra_fixture_before: &str, ra_fixture_after: &str) {
 
 /// Checks that there's a diagnostic *without* fix at `$0`.
 pub(crate) fn check_no_fix(ra_fixture: &str) {
+    check_no_fix_with_disabled(ra_fixture, &[]);
+}
+
+/// Like `check_no_fix`, but disables the diagnostics with the given codes first.
+pub(crate) fn check_no_fix_with_disabled(ra_fixture: &str, disabled: &[&str]) {
     let (db, file_position) = RootDatabase::with_position(ra_fixture);
-    let diagnostic = super::diagnostics(
-        &db,
-        &DiagnosticsConfig::test_sample(),
-        &AssistResolveStrategy::All,
-        file_position.file_id,
-    )
-    .pop()
-    .unwrap();
+    let mut config = DiagnosticsConfig::test_sample();
+    config.disabled.extend(disabled.iter().map(|it| it.to_string()));
+    let diagnostic =
+        super::diagnostics(&db, &config, &AssistResolveStrategy::All, file_position.file_id)
+            .pop()
+            .unwrap();
     assert!(diagnostic.fixes.is_none(), "got a fix when none was expected: {:?}", diagnostic);
 }
 
@@ -96,6 +111,15 @@ pub(crate) fn check_diagnostics(ra_fixture: &str) {
     check_diagnostics_with_config(config, ra_fixture)
 }
 
+/// Like `check_diagnostics`, but also disables the diagnostics with the given codes.
+#[track_caller]
+pub(crate) fn check_diagnostics_with_disabled(ra_fixture: &str, disabled: &[&str]) {
+    let mut config = DiagnosticsConfig::test_sample();
+    config.disabled.insert("inactive-code".to_string());
+    config.disabled.extend(disabled.iter().map(|it| it.to_string()));
+    check_diagnostics_with_config(config, ra_fixture)
+}
+
 #[track_caller]
 pub(crate) fn check_diagnostics_with_config(config: DiagnosticsConfig, ra_fixture: &str) {
     let (db, files) = RootDatabase::with_many_files(ra_fixture);
@@ -105,7 +129,7 @@ pub(crate) fn check_diagnostics_with_config(config: DiagnosticsConfig, ra_fixtur
         let expected = extract_annotations(&*db.file_text(file_id));
         let mut actual = diagnostics
             .into_iter()
-            .map(|d| {
+            .flat_map(|d| {
                 let mut annotation = String::new();
                 if let Some(fixes) = &d.fixes {
                     assert!(!fixes.is_empty());
@@ -117,7 +141,12 @@ pub(crate) fn check_diagnostics_with_config(config: DiagnosticsConfig, ra_fixtur
                 });
                 annotation.push_str(": ");
                 annotation.push_str(&d.message);
-                (d.range, annotation)
+                let related = d
+                    .related
+                    .into_iter()
+                    .filter(|(frange, _)| frange.file_id == file_id)
+                    .map(|(frange, message)| (frange.range, format!("related: {message}")));
+                std::iter::once((d.range, annotation)).chain(related).collect::<Vec<_>>()
             })
             .collect::<Vec<_>>();
         actual.sort_by_key(|(range, _)| range.start());
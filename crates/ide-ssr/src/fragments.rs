@@ -43,6 +43,23 @@ pub(crate) fn stmt(s: &str) -> Result<SyntaxNode, ()> {
     Ok(node.syntax().clone_subtree())
 }
 
+/// Parses a sequence of statements, optionally followed by an expression, as the statement list of
+/// a block. The braces of the list aren't part of `s`.
+pub(crate) fn stmts(s: &str) -> Result<SyntaxNode, ()> {
+    let s = s.trim();
+    let input = format!("const _: () = {{{}}};", s);
+    let parse = syntax::SourceFile::parse(&input);
+    if !parse.errors().is_empty() {
+        return Err(());
+    }
+    let node = parse.tree().syntax().descendants().find_map(ast::StmtList::cast).ok_or(())?;
+    let text = node.syntax().text().to_string();
+    if text.get(1..text.len() - 1) != Some(s) {
+        return Err(());
+    }
+    Ok(node.syntax().clone_subtree())
+}
+
 fn fragment<T: AstNode>(template: &str, s: &str) -> Result<SyntaxNode, ()> {
     let s = s.trim();
     let input = template.replace("{}", s);
@@ -11,6 +11,12 @@
 // The syntax for a structural search replace command is `<search_pattern> ==>> <replace_pattern>`.
 // A `$<name>` placeholder in the search pattern will match any AST node and `$<name>` will reference it in the replacement.
 // Within a macro call, a placeholder will match up until whatever token follows the placeholder.
+// A placeholder that appears more than once in the search pattern must match the same code each
+// time.
+//
+// A search pattern made of several statements (e.g. `let $a = $b.lock(); $a.push($c)`) matches
+// consecutive statements within a block. A placeholder that is the only thing within the braces of
+// an item matches all of its contents, e.g. `$body` in `impl Drop for $t { $body }`.
 //
 // All paths in both the search pattern and the replacement template must resolve in the context
 // in which this command is invoked. Paths in the search pattern will then match the code if they
@@ -40,6 +46,8 @@
 //
 // | kind(literal) | Is a literal (e.g. `42` or `"forty two"`)
 // | not(a)        | Negates the constraint `a`
+// | type(T)       | Has a type matching `T`, `_` matches any type (e.g. `type(Vec<_>)`)
+// | impls(Trait)  | Has a type that implements `Trait` for any generic arguments (e.g. `impls(From)`)
 // |===
 //
 // Available via the command `rust-analyzer.ssr`.
@@ -76,13 +84,19 @@ mod parsing;
 mod fragments;
 mod replacing;
 mod resolving;
+mod rule_file;
 mod search;
 #[macro_use]
 mod errors;
 #[cfg(test)]
 mod tests;
 
-pub use crate::{errors::SsrError, from_comment::ssr_from_comment, matching::Match};
+pub use crate::{
+    errors::SsrError,
+    from_comment::ssr_from_comment,
+    matching::Match,
+    rule_file::{NamedSsrRule, SsrRuleFile},
+};
 
 use crate::{errors::bail, matching::MatchFailureReason};
 use hir::Semantics;
@@ -257,6 +271,10 @@ impl<'db> MatchFinder<'db> {
             }
             if node_range.range == range.range {
                 for rule in &self.rules {
+                    // Sequences only match part of a statement list, never a single node.
+                    if rule.pattern.is_sequence() {
+                        continue;
+                    }
                     // For now we ignore rules that have a different kind than our node, otherwise
                     // we get lots of noise. If at some point we add support for restricting rules
                     // to a particular kind of thing (e.g. only match type references), then we can
@@ -342,7 +360,11 @@ impl SsrMatches {
 
 impl Match {
     pub fn matched_text(&self) -> String {
-        self.matched_node.text().to_string()
+        let text = self.matched_node.text();
+        match self.sequence_range {
+            Some(range) => text.slice(range - self.matched_node.text_range().start()).to_string(),
+            None => text.to_string(),
+        }
     }
 }
 
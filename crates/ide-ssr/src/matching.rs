@@ -3,15 +3,20 @@
 
 use crate::{
     parsing::{Constraint, NodeKind, Placeholder, Var},
-    resolving::{ResolvedPattern, ResolvedRule, UfcsCallInfo},
+    resolving::{ResolvedPattern, ResolvedRule, TypePattern, UfcsCallInfo},
     SsrMatches,
 };
 use hir::Semantics;
-use ide_db::{base_db::FileRange, FxHashMap};
+use ide_db::{
+    base_db::FileRange,
+    defs::{Definition, NameRefClass},
+    FxHashMap,
+};
 use std::{cell::Cell, iter::Peekable};
 use syntax::{
     ast::{self, AstNode, AstToken},
-    SmolStr, SyntaxElement, SyntaxElementChildren, SyntaxKind, SyntaxNode, SyntaxToken,
+    SmolStr, SyntaxElement, SyntaxElementChildren, SyntaxKind, SyntaxNode, SyntaxToken, TextRange,
+    T,
 };
 
 // Creates a match error. If we're currently attempting to match some code that we thought we were
@@ -47,6 +52,9 @@ macro_rules! fail_match {
 pub struct Match {
     pub(crate) range: FileRange,
     pub(crate) matched_node: SyntaxNode,
+    /// For a sequence of statements, the range of those statements within `matched_node`, which is
+    /// the statement list that contains them.
+    pub(crate) sequence_range: Option<TextRange>,
     pub(crate) placeholder_values: FxHashMap<Var, PlaceholderMatch>,
     pub(crate) ignored_comments: Vec<ast::Comment>,
     pub(crate) rule_index: usize,
@@ -96,6 +104,37 @@ pub(crate) fn get_match(
     })
 }
 
+/// Finds the runs of statements within `code`, a statement list, that match `rule`, whose pattern is
+/// a sequence of statements. The runs don't overlap.
+pub(crate) fn get_sequence_matches(
+    rule: &ResolvedRule,
+    code: &SyntaxNode,
+    restrict_range: &Option<FileRange>,
+    sema: &Semantics<'_, ide_db::RootDatabase>,
+) -> Vec<Match> {
+    if code.kind() != SyntaxKind::STMT_LIST {
+        return Vec::new();
+    }
+    let matcher = Matcher { sema, restrict_range: *restrict_range, rule };
+    let patterns = rule.pattern.node.children().collect::<Vec<_>>();
+    let stmts = code
+        .children()
+        .filter(|it| ast::Stmt::can_cast(it.kind()) || ast::Expr::can_cast(it.kind()))
+        .collect::<Vec<_>>();
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + patterns.len() <= stmts.len() {
+        match matcher.try_match_sequence(&patterns, &stmts[start..][..patterns.len()], code) {
+            Ok(m) => {
+                matches.push(m);
+                start += patterns.len();
+            }
+            Err(_) => start += 1,
+        }
+    }
+    matches
+}
+
 /// Checks if our search pattern matches a particular node of the AST.
 struct Matcher<'db, 'sema> {
     sema: &'sema Semantics<'db, ide_db::RootDatabase>,
@@ -129,6 +168,7 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         let mut the_match = Match {
             range: sema.original_range(code),
             matched_node: code.clone(),
+            sequence_range: None,
             placeholder_values: FxHashMap::default(),
             ignored_comments: Vec::new(),
             rule_index: rule.index,
@@ -149,6 +189,120 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         Ok(the_match)
     }
 
+    fn try_match_sequence(
+        &self,
+        patterns: &[SyntaxNode],
+        stmts: &[SyntaxNode],
+        list: &SyntaxNode,
+    ) -> Result<Match, MatchFailed> {
+        // A trailing expression in the pattern also matches an expression statement, in which case
+        // the semicolon isn't part of the match.
+        let pairs = patterns
+            .iter()
+            .zip(stmts)
+            .map(|(pattern, code)| match ast::ExprStmt::cast(code.clone()) {
+                Some(stmt) if ast::Expr::can_cast(pattern.kind()) => {
+                    (pattern, stmt.expr().map_or_else(|| code.clone(), |it| it.syntax().clone()))
+                }
+                _ => (pattern, code.clone()),
+            })
+            .collect::<Vec<_>>();
+        for (pattern, code) in &pairs {
+            self.attempt_match_node(&mut Phase::First, pattern, code)?;
+        }
+        let (first, last) = match (pairs.first(), pairs.last()) {
+            (Some((_, first)), Some((_, last))) => (first, last),
+            _ => fail_match!("Empty sequence"),
+        };
+        let (first_range, last_range) =
+            (self.sema.original_range(first), self.sema.original_range(last));
+        if first_range.file_id != last_range.file_id {
+            fail_match!("Sequence spans several files");
+        }
+        let range = FileRange {
+            file_id: first_range.file_id,
+            range: first_range.range.cover(last_range.range),
+        };
+        self.validate_range(&range)?;
+        let stmts_range = first.text_range().cover(last.text_range());
+        let mut the_match = Match {
+            range,
+            matched_node: list.clone(),
+            sequence_range: Some(stmts_range),
+            placeholder_values: FxHashMap::default(),
+            ignored_comments: Vec::new(),
+            rule_index: self.rule.index,
+            depth: 0,
+            rendered_template_paths: FxHashMap::default(),
+        };
+        // Comments between the statements would be lost when the sequence is replaced.
+        the_match.ignored_comments.extend(
+            list.children_with_tokens()
+                .filter_map(|it| it.into_token())
+                .filter(|it| stmts_range.contains_range(it.text_range()))
+                .filter_map(ast::Comment::cast),
+        );
+        for (pattern, code) in &pairs {
+            self.attempt_match_node(&mut Phase::Second(&mut the_match), pattern, code)?;
+        }
+        self.check_bindings_unused_after(&pairs, list, stmts_range)?;
+        the_match.depth = self.sema.ancestors_with_macros(list.clone()).count();
+        if let Some(template) = &self.rule.template {
+            the_match.render_template_paths(template, self.sema)?;
+        }
+        Ok(the_match)
+    }
+
+    /// Checks that the locals bound by `let` statements of a sequence aren't used after the
+    /// sequence, unless the template binds them again, since they'd be gone after the replacement.
+    /// Searches without a template don't remove anything, so they aren't restricted.
+    fn check_bindings_unused_after(
+        &self,
+        pairs: &[(&SyntaxNode, SyntaxNode)],
+        list: &SyntaxNode,
+        stmts_range: TextRange,
+    ) -> Result<(), MatchFailed> {
+        let template = match &self.rule.template {
+            Some(it) => it,
+            None => return Ok(()),
+        };
+        let template_lets = template
+            .node
+            .descendants()
+            .filter_map(ast::LetStmt::cast)
+            .filter_map(|it| Some(it.pat()?.syntax().text().to_string()))
+            .collect::<Vec<_>>();
+        let locals = pairs
+            .iter()
+            .filter(|(pattern, _)| {
+                let pattern_pat = ast::LetStmt::cast((*pattern).clone())
+                    .and_then(|it| it.pat())
+                    .map(|it| it.syntax().text().to_string());
+                !pattern_pat.map_or(false, |it| template_lets.contains(&it))
+            })
+            .filter_map(|(_, code)| ast::LetStmt::cast(code.clone())?.pat())
+            .flat_map(|pat| pat.syntax().descendants().filter_map(ast::IdentPat::cast))
+            .filter_map(|it| self.sema.to_def(&it))
+            .collect::<Vec<_>>();
+        if locals.is_empty() {
+            return Ok(());
+        }
+        let used_after = list
+            .descendants()
+            .filter(|it| it.text_range().start() >= stmts_range.end())
+            .filter_map(ast::NameRef::cast)
+            .filter_map(|it| NameRefClass::classify(self.sema, &it))
+            .any(|class| match class {
+                NameRefClass::Definition(Definition::Local(local)) => locals.contains(&local),
+                NameRefClass::FieldShorthand { local_ref, .. } => locals.contains(&local_ref),
+                _ => false,
+            });
+        if used_after {
+            fail_match!("A local bound by the sequence is used after it");
+        }
+        Ok(())
+    }
+
     /// Checks that `range` is within the permitted range if any. This is applicable when we're
     /// processing a macro expansion and we want to fail the match if we're working with a node that
     /// didn't originate from the token tree of the macro call.
@@ -179,6 +333,13 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
                 // We validated the range for the node when we started the match, so the placeholder
                 // probably can't fail range validation, but just to be safe...
                 self.validate_range(&original_range)?;
+                // A placeholder that appears more than once has to match the same code every time.
+                if let Some(previous) = matches_out.placeholder_values.get(&placeholder.ident) {
+                    if !self.is_same_code(&previous.range, &original_range) {
+                        fail_match!("Placeholder {} matched different code", placeholder.ident);
+                    }
+                    return Ok(());
+                }
                 matches_out.placeholder_values.insert(
                     placeholder.ident.clone(),
                     PlaceholderMatch::from_range(original_range),
@@ -204,6 +365,9 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
                 code.kind()
             );
         }
+        if let Some(placeholder) = self.get_body_placeholder(pattern) {
+            return self.attempt_match_body(phase, placeholder, code);
+        }
         // Some kinds of nodes have special handling. For everything else, we fall back to default
         // matching.
         match code.kind() {
@@ -216,6 +380,39 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         }
     }
 
+    /// Matches the whole contents of `code`, a list of statements or items, to a placeholder.
+    fn attempt_match_body(
+        &self,
+        phase: &mut Phase<'_>,
+        placeholder: &Placeholder,
+        code: &SyntaxNode,
+    ) -> Result<(), MatchFailed> {
+        if !placeholder.constraints.is_empty() {
+            fail_match!("Placeholders for bodies can't have constraints");
+        }
+        if self.sema.hir_file_for(code).is_macro() {
+            fail_match!("Bodies within macro expansions can't be matched");
+        }
+        // The whitespace around the contents is part of the body, so that the replacement keeps
+        // the layout of the original code.
+        let range = match (code.first_token(), code.last_token()) {
+            (Some(l_curly), Some(r_curly))
+                if l_curly.kind() == T!['{'] && r_curly.kind() == T!['}'] =>
+            {
+                TextRange::new(l_curly.text_range().end(), r_curly.text_range().start())
+            }
+            _ => fail_match!("Missing body: `{}`", code.text()),
+        };
+        if let Phase::Second(matches_out) = phase {
+            let file_id = self.sema.original_range(code).file_id;
+            matches_out.placeholder_values.insert(
+                placeholder.ident.clone(),
+                PlaceholderMatch::from_range(FileRange { file_id, range }),
+            );
+        }
+        Ok(())
+    }
+
     fn attempt_match_node_children(
         &self,
         phase: &mut Phase<'_>,
@@ -324,10 +521,47 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
                     fail_match!("Constraint {:?} failed for '{}'", constraint, code.text());
                 }
             }
+            Constraint::Type(text) => {
+                let ty = self.type_of(code)?;
+                let matches = match self.rule.constraint_types.get(text) {
+                    Some(pattern) => pattern.matches(self.sema.db, &ty),
+                    None => false,
+                };
+                if !matches {
+                    fail_match!("Type of '{}' doesn't match `{}`", code.text(), text);
+                }
+            }
+            Constraint::Impls(text) => {
+                let ty = self.type_of(code)?;
+                let implements = match self.rule.constraint_traits.get(text) {
+                    Some(&trait_) => ty.impls_trait(self.sema.db, trait_, &[]),
+                    None => false,
+                };
+                if !implements {
+                    fail_match!("Type of '{}' doesn't implement `{}`", code.text(), text);
+                }
+            }
         }
         Ok(())
     }
 
+    /// Returns the type of an expression, pattern or type, as written, before any coercions.
+    fn type_of(&self, code: &SyntaxNode) -> Result<hir::Type, MatchFailed> {
+        let ty = if let Some(expr) = ast::Expr::cast(code.clone()) {
+            self.sema.type_of_expr(&expr).map(|it| it.original)
+        } else if let Some(pat) = ast::Pat::cast(code.clone()) {
+            self.sema.type_of_pat(&pat).map(|it| it.original)
+        } else if let Some(ty) = ast::Type::cast(code.clone()) {
+            self.sema.resolve_type(&ty)
+        } else {
+            None
+        };
+        match ty {
+            Some(ty) if !ty.is_unknown() => Ok(ty),
+            _ => fail_match!("Failed to get the type of '{}'", code.text()),
+        }
+    }
+
     /// Paths are matched based on whether they refer to the same thing, even if they're written
     /// differently.
     fn attempt_match_path(
@@ -627,6 +861,36 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         res
     }
 
+    /// Returns the placeholder that makes up the whole contents of `list`, a list of statements or
+    /// items, if any.
+    fn get_body_placeholder(&self, list: &SyntaxNode) -> Option<&Placeholder> {
+        if !matches!(
+            list.kind(),
+            SyntaxKind::STMT_LIST | SyntaxKind::ASSOC_ITEM_LIST | SyntaxKind::ITEM_LIST
+        ) {
+            return None;
+        }
+        let mut children = list.children();
+        match (children.next(), children.next()) {
+            (Some(only_child), None) => self.rule.get_body_placeholder(&only_child),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the two ranges contain the same tokens, ignoring whitespace and comments.
+    fn is_same_code(&self, a: &FileRange, b: &FileRange) -> bool {
+        use ide_db::base_db::SourceDatabaseExt;
+        let tokens = |range: &FileRange| {
+            let text = self.sema.db.file_text(range.file_id);
+            let lexed = parser::LexedStr::new(&text[range.range]);
+            (0..lexed.len())
+                .filter(|&i| !lexed.kind(i).is_trivia())
+                .map(|i| (lexed.kind(i), lexed.text(i).to_string()))
+                .collect::<Vec<_>>()
+        };
+        tokens(a) == tokens(b)
+    }
+
     fn get_placeholder_for_node(&self, node: &SyntaxNode) -> Option<&Placeholder> {
         self.get_placeholder(&SyntaxElement::Node(node.clone()))
     }
@@ -636,6 +900,29 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
     }
 }
 
+impl TypePattern {
+    fn matches(&self, db: &dyn hir::db::HirDatabase, ty: &hir::Type) -> bool {
+        match self {
+            TypePattern::Any => true,
+            TypePattern::Adt(adt, args) => {
+                ty.as_adt() == Some(*adt)
+                    && args.iter().zip(ty.type_arguments()).all(|(arg, ty)| arg.matches(db, &ty))
+            }
+            TypePattern::Builtin(builtin) => ty.as_builtin() == Some(*builtin),
+            TypePattern::Ref(mutability, inner) => match ty.as_reference() {
+                Some((ty, m)) => m == *mutability && inner.matches(db, &ty),
+                None => false,
+            },
+            TypePattern::Tuple(fields) if fields.is_empty() => ty.is_unit(),
+            TypePattern::Tuple(fields) => {
+                let tys = ty.tuple_fields(db);
+                tys.len() == fields.len()
+                    && fields.iter().zip(&tys).all(|(field, ty)| field.matches(db, ty))
+            }
+        }
+    }
+}
+
 impl Match {
     fn render_template_paths(
         &mut self,
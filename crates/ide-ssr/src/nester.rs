@@ -6,6 +6,9 @@
 //! For example, if our search pattern is `foo(foo($a))` and the code had `foo(foo(foo(foo(42))))`,
 //! then we'll get 3 matches, however only the outermost and innermost matches can be accepted. The
 //! middle match would take the second `foo` from the outer match.
+//!
+//! Matches of statement sequences only cover part of the statement list they're found in, so
+//! several of them can share a node, and they only collide with the matches they overlap.
 
 use ide_db::FxHashMap;
 use syntax::SyntaxNode;
@@ -30,7 +33,7 @@ pub(crate) fn nest_and_remove_collisions(
 
 #[derive(Default)]
 struct MatchCollector {
-    matches_by_node: FxHashMap<SyntaxNode, Match>,
+    matches_by_node: FxHashMap<SyntaxNode, Vec<Match>>,
 }
 
 impl MatchCollector {
@@ -39,20 +42,30 @@ impl MatchCollector {
     /// match of the existing match.
     fn add_match(&mut self, m: Match, sema: &hir::Semantics<'_, ide_db::RootDatabase>) {
         let matched_node = m.matched_node.clone();
-        if let Some(existing) = self.matches_by_node.get_mut(&matched_node) {
-            try_add_sub_match(m, existing, sema);
-            return;
-        }
-        for ancestor in sema.ancestors_with_macros(m.matched_node.clone()) {
-            if let Some(existing) = self.matches_by_node.get_mut(&ancestor) {
-                try_add_sub_match(m, existing, sema);
+        for node in sema.ancestors_with_macros(matched_node.clone()) {
+            let existing = match self.matches_by_node.get_mut(&node) {
+                Some(it) => it,
+                None => continue,
+            };
+            if let Some(existing) = existing.iter_mut().find(|it| overlaps(it, &m)) {
+                if existing.range.range.contains_range(m.range.range) {
+                    try_add_sub_match(m, existing, sema);
+                }
                 return;
             }
         }
-        self.matches_by_node.insert(matched_node, m);
+        self.matches_by_node.entry(matched_node).or_default().push(m);
     }
 }
 
+/// Returns whether the code matched by `a` and `b` overlaps. Matches in different files can't be
+/// compared, so they're assumed to overlap.
+fn overlaps(a: &Match, b: &Match) -> bool {
+    a.range.file_id != b.range.file_id
+        || a.range.range.contains_range(b.range.range)
+        || a.range.range.intersect(b.range.range).map_or(false, |it| !it.is_empty())
+}
+
 /// Attempts to add `m` as a sub-match of `existing`.
 fn try_add_sub_match(
     m: Match,
@@ -71,7 +84,7 @@ fn try_add_sub_match(
             // exceptional.
             let mut collector = MatchCollector::default();
             for m in std::mem::take(&mut p.inner_matches.matches) {
-                collector.matches_by_node.insert(m.matched_node.clone(), m);
+                collector.matches_by_node.entry(m.matched_node.clone()).or_default().push(m);
             }
             collector.add_match(m, sema);
             p.inner_matches = collector.into();
@@ -84,7 +97,7 @@ impl From<MatchCollector> for SsrMatches {
     fn from(mut match_collector: MatchCollector) -> Self {
         let mut matches = SsrMatches::default();
         for (_, m) in match_collector.matches_by_node.drain() {
-            matches.matches.push(m);
+            matches.matches.extend(m);
         }
         matches.matches.sort_by(|a, b| {
             // Order matches by file_id then by start range. This should be sufficient since ranges
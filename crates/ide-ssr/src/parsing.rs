@@ -47,6 +47,10 @@ pub(crate) struct Var(pub(crate) String);
 pub(crate) enum Constraint {
     Kind(NodeKind),
     Not(Box<Constraint>),
+    /// The type of the matched node matches the given type, where `_` matches any type.
+    Type(String),
+    /// The type of the matched node implements the given trait.
+    Impls(String),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -73,6 +77,14 @@ impl ParsedRule {
             rules: Vec::new(),
         };
 
+        // A placeholder making up the whole body of an item only parses as a macro call.
+        let body_pattern = pattern.as_rust_code_with_bodies();
+        let body_template = template.and_then(|t| t.as_rust_code_with_bodies());
+        let item = |code: &str, with_bodies: Option<&str>| match with_bodies.map(fragments::item) {
+            Some(Ok(item)) => Ok(item),
+            _ => fragments::item(code),
+        };
+
         let raw_template_stmt = raw_template.map(fragments::stmt);
         if let raw_template_expr @ Some(Ok(_)) = raw_template.map(fragments::expr) {
             builder.try_add(fragments::expr(&raw_pattern), raw_template_expr);
@@ -80,9 +92,18 @@ impl ParsedRule {
             builder.try_add(fragments::expr(&raw_pattern), raw_template_stmt.clone());
         }
         builder.try_add(fragments::ty(&raw_pattern), raw_template.map(fragments::ty));
-        builder.try_add(fragments::item(&raw_pattern), raw_template.map(fragments::item));
+        builder.try_add(
+            item(&raw_pattern, body_pattern.as_deref()),
+            raw_template.map(|t| item(t, body_template.as_deref())),
+        );
         builder.try_add(fragments::pat(&raw_pattern), raw_template.map(fragments::pat));
         builder.try_add(fragments::stmt(&raw_pattern), raw_template_stmt);
+        // Single statements are covered above, only use statement lists for sequences.
+        if let Ok(stmts) = fragments::stmts(&raw_pattern) {
+            if stmts.children().count() > 1 {
+                builder.try_add(Ok(stmts), raw_template.map(fragments::stmts));
+            }
+        }
         builder.build()
     }
 }
@@ -186,11 +207,54 @@ impl RawPattern {
         res
     }
 
+    /// Like `as_rust_code`, but placeholders that are the only thing within a pair of braces are
+    /// written as macro calls, so that they can stand for the whole body of an item. Returns `None`
+    /// if there are no such placeholders.
+    fn as_rust_code_with_bodies(&self) -> Option<String> {
+        let non_whitespace = |t: &&PatternElement| match t {
+            PatternElement::Token(token) => token.kind != SyntaxKind::WHITESPACE,
+            PatternElement::Placeholder(_) => true,
+        };
+        let is_token = |t: Option<&PatternElement>, kind: SyntaxKind| match t {
+            Some(PatternElement::Token(token)) => token.kind == kind,
+            _ => false,
+        };
+        let mut res = String::new();
+        let mut has_bodies = false;
+        for (idx, t) in self.tokens.iter().enumerate() {
+            match t {
+                PatternElement::Token(token) => res.push_str(token.text.as_str()),
+                PatternElement::Placeholder(placeholder) => {
+                    let mut before = self.tokens[..idx].iter().rev().filter(non_whitespace);
+                    let mut after = self.tokens[idx + 1..].iter().filter(non_whitespace);
+                    // Braces after a `!` are the arguments of a macro call, not a body.
+                    let is_body = is_token(before.next(), T!['{'])
+                        && !is_token(before.next(), T![!])
+                        && is_token(after.next(), T!['}']);
+                    res.push_str(placeholder.stand_in_name.as_str());
+                    if is_body {
+                        res.push_str("!{}");
+                        has_bodies = true;
+                    }
+                }
+            }
+        }
+        if has_bodies {
+            Some(res)
+        } else {
+            None
+        }
+    }
+
     pub(crate) fn placeholders_by_stand_in(&self) -> FxHashMap<SmolStr, Placeholder> {
-        let mut res = FxHashMap::default();
+        let mut res: FxHashMap<SmolStr, Placeholder> = FxHashMap::default();
         for t in &self.tokens {
             if let PatternElement::Placeholder(placeholder) = t {
-                res.insert(SmolStr::new(placeholder.stand_in_name.clone()), placeholder.clone());
+                // Constraints only need to be given on one occurrence of a placeholder.
+                let stand_in = SmolStr::new(placeholder.stand_in_name.clone());
+                if res.get(&stand_in).map_or(true, |it| it.constraints.is_empty()) {
+                    res.insert(stand_in, placeholder.clone());
+                }
             }
         }
         res
@@ -212,13 +276,19 @@ impl FromStr for SsrPattern {
 /// replace pattern.
 fn parse_pattern(pattern_str: &str) -> Result<Vec<PatternElement>, SsrError> {
     let mut res = Vec::new();
-    let mut placeholder_names = FxHashSet::default();
+    let mut constraints_by_name: FxHashMap<Var, Vec<Constraint>> = FxHashMap::default();
     let mut tokens = tokenize(pattern_str)?.into_iter();
     while let Some(token) = tokens.next() {
         if token.kind == T![$] {
             let placeholder = parse_placeholder(&mut tokens)?;
-            if !placeholder_names.insert(placeholder.ident.clone()) {
-                bail!("Placeholder `{}` repeats more than once", placeholder.ident);
+            // A placeholder may appear more than once, in which case it has to match the same code
+            // each time. Conflicting constraints can't be satisfied though.
+            let constraints = constraints_by_name.entry(placeholder.ident.clone()).or_default();
+            if !placeholder.constraints.is_empty() {
+                if !constraints.is_empty() && *constraints != placeholder.constraints {
+                    bail!("Placeholder `{}` repeats with different constraints", placeholder.ident);
+                }
+                *constraints = placeholder.constraints.clone();
             }
             res.push(PatternElement::Placeholder(placeholder));
         } else {
@@ -326,10 +396,41 @@ fn parse_constraint(tokens: &mut std::vec::IntoIter<Token>) -> Result<Constraint
             expect_token(tokens, ")")?;
             Ok(Constraint::Not(Box::new(sub)))
         }
+        "type" => {
+            expect_token(tokens, "(")?;
+            Ok(Constraint::Type(parse_parenthesized_text(tokens)?))
+        }
+        "impls" => {
+            expect_token(tokens, "(")?;
+            Ok(Constraint::Impls(parse_parenthesized_text(tokens)?))
+        }
         x => bail!("Unsupported constraint type '{}'", x),
     }
 }
 
+/// Collects the text up to the `)` closing an already consumed `(`.
+fn parse_parenthesized_text(tokens: &mut std::vec::IntoIter<Token>) -> Result<String, SsrError> {
+    let mut depth = 0;
+    let mut text = String::new();
+    loop {
+        let t = tokens
+            .next()
+            .ok_or_else(|| SsrError::new("Unexpected end of constraint while looking for ')'"))?;
+        match t.kind {
+            T!['('] => depth += 1,
+            T![')'] if depth == 0 => break,
+            T![')'] => depth -= 1,
+            _ => {}
+        }
+        text.push_str(&t.text);
+    }
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("Constraint is missing a type");
+    }
+    Ok(text.to_string())
+}
+
 fn expect_token(tokens: &mut std::vec::IntoIter<Token>, expected: &str) -> Result<(), SsrError> {
     if let Some(t) = tokens.next() {
         if t.text == expected {
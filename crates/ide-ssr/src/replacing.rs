@@ -4,11 +4,15 @@ use ide_db::{FxHashMap, FxHashSet};
 use itertools::Itertools;
 use syntax::{
     ast::{self, AstNode, AstToken},
-    SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize,
+    SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize, T,
 };
 use text_edit::TextEdit;
 
-use crate::{fragments, resolving::ResolvedRule, Match, SsrMatches};
+use crate::{
+    fragments,
+    resolving::{body_placeholder_token, ResolvedRule},
+    Match, SsrMatches,
+};
 
 /// Returns a text edit that will replace each match in `matches` with its corresponding replacement
 /// template. Placeholders in the template will have been substituted with whatever they matched to
@@ -68,6 +72,10 @@ fn render_replace(match_info: &Match, file_src: &str, rules: &[ResolvedRule]) ->
         placeholder_tokens_requiring_parenthesis: FxHashSet::default(),
         placeholder_tokens_by_range: FxHashMap::default(),
     };
+    if template.node.kind() == SyntaxKind::STMT_LIST {
+        renderer.render_sequence(&template.node);
+        return renderer.out;
+    }
     renderer.render_node(&template.node);
     renderer.maybe_rerender_with_extra_parenthesis(&template.node);
     for comment in &match_info.ignored_comments {
@@ -83,6 +91,33 @@ impl ReplacementRenderer<'_> {
         }
     }
 
+    /// Renders a template made of several statements. The comments of the match go first, on lines
+    /// of their own, since a line comment would swallow the semicolon that often follows a match.
+    fn render_sequence(&mut self, template: &SyntaxNode) {
+        let start = usize::from(self.match_info.range.range.start());
+        let line_start = self.file_src[..start].rfind('\n').map_or(0, |it| it + 1);
+        let indent = &self.file_src[line_start..start];
+        let indent = &indent[..indent.len() - indent.trim_start().len()];
+        for comment in &self.match_info.ignored_comments {
+            self.out.push_str(&comment.syntax().to_string());
+            self.out.push('\n');
+            self.out.push_str(indent);
+        }
+        // The statements are parsed within braces that aren't part of the template. Each statement
+        // goes on a line of its own, at the indentation of the match.
+        let mut statements = template
+            .children_with_tokens()
+            .filter(|it| !matches!(it.kind(), T!['{'] | T!['}'] | SyntaxKind::WHITESPACE));
+        if let Some(first) = statements.next() {
+            self.render_node_or_token(&first);
+        }
+        for node_or_token in statements {
+            self.out.push('\n');
+            self.out.push_str(indent);
+            self.render_node_or_token(&node_or_token);
+        }
+    }
+
     fn render_node_or_token(&mut self, node_or_token: &SyntaxElement) {
         match node_or_token {
             SyntaxElement::Token(token) => {
@@ -95,6 +130,18 @@ impl ReplacementRenderer<'_> {
     }
 
     fn render_node(&mut self, node: &SyntaxNode) {
+        if self.render_body(node) {
+            return;
+        }
+        if let Some(token) = body_placeholder_token(node) {
+            if self.rule.get_placeholder(&token).is_some() {
+                // Only the placeholder was written in the template, not the macro call it's parsed
+                // as. Bodies never need parenthesis, even if they don't parse as a single node.
+                self.render_token(&token);
+                self.placeholder_tokens_by_range.retain(|_, it| *it != token);
+                return;
+            }
+        }
         if let Some(mod_path) = self.match_info.rendered_template_paths.get(node) {
             self.out.push_str(&mod_path.to_string());
             // Emit everything except for the segment's name-ref, since we already effectively
@@ -113,6 +160,32 @@ impl ReplacementRenderer<'_> {
         }
     }
 
+    /// Renders `list` if its only contents are a body placeholder. The matched body includes the
+    /// whitespace inside the braces, so the whitespace around the placeholder in the template is
+    /// dropped, keeping the layout of the original code.
+    fn render_body(&mut self, list: &SyntaxNode) -> bool {
+        if !matches!(
+            list.kind(),
+            SyntaxKind::STMT_LIST | SyntaxKind::ASSOC_ITEM_LIST | SyntaxKind::ITEM_LIST
+        ) {
+            return false;
+        }
+        let mut children = list.children();
+        let only_child = match (children.next(), children.next()) {
+            (Some(only_child), None) => only_child,
+            _ => return false,
+        };
+        if self.rule.get_body_placeholder(&only_child).is_none() {
+            return false;
+        }
+        for node_or_token in list.children_with_tokens() {
+            if node_or_token.kind() != SyntaxKind::WHITESPACE {
+                self.render_node_or_token(&node_or_token);
+            }
+        }
+        true
+    }
+
     fn render_token(&mut self, token: &SyntaxToken) {
         if let Some(placeholder) = self.rule.get_placeholder(token) {
             if let Some(placeholder_value) =
@@ -2,10 +2,13 @@
 
 use hir::AsAssocItem;
 use ide_db::{base_db::FilePosition, FxHashMap};
-use parsing::Placeholder;
+use parsing::{Constraint, Placeholder};
 use syntax::{ast, SmolStr, SyntaxKind, SyntaxNode, SyntaxToken};
 
-use crate::{errors::error, parsing, SsrError};
+use crate::{
+    errors::{bail, error},
+    fragments, parsing, SsrError,
+};
 
 pub(crate) struct ResolutionScope<'db> {
     scope: hir::SemanticsScope<'db>,
@@ -16,6 +19,10 @@ pub(crate) struct ResolvedRule {
     pub(crate) pattern: ResolvedPattern,
     pub(crate) template: Option<ResolvedPattern>,
     pub(crate) index: usize,
+    /// The types of `type(..)` constraints, keyed by the text of the constraint.
+    pub(crate) constraint_types: FxHashMap<String, TypePattern>,
+    /// The traits of `impls(..)` constraints, keyed by the text of the constraint.
+    pub(crate) constraint_traits: FxHashMap<String, hir::Trait>,
 }
 
 pub(crate) struct ResolvedPattern {
@@ -39,12 +46,34 @@ pub(crate) struct UfcsCallInfo {
     pub(crate) qualifier_type: Option<hir::Type>,
 }
 
+/// A type from a `type(..)` constraint, where `_` matches any type.
+#[derive(Debug)]
+pub(crate) enum TypePattern {
+    Any,
+    /// Generic arguments that aren't given match any type.
+    Adt(hir::Adt, Vec<TypePattern>),
+    Builtin(hir::BuiltinType),
+    Ref(hir::Mutability, Box<TypePattern>),
+    Tuple(Vec<TypePattern>),
+}
+
 impl ResolvedRule {
     pub(crate) fn new(
         rule: parsing::ParsedRule,
         resolution_scope: &ResolutionScope<'_>,
         index: usize,
     ) -> Result<ResolvedRule, SsrError> {
+        let mut constraint_types = FxHashMap::default();
+        let mut constraint_traits = FxHashMap::default();
+        for placeholder in rule.placeholders_by_stand_in.values() {
+            for constraint in &placeholder.constraints {
+                resolution_scope.resolve_constraint(
+                    constraint,
+                    &mut constraint_types,
+                    &mut constraint_traits,
+                )?;
+            }
+        }
         let resolver =
             Resolver { resolution_scope, placeholders_by_stand_in: rule.placeholders_by_stand_in };
         let resolved_template = match rule.template {
@@ -55,6 +84,8 @@ impl ResolvedRule {
             pattern: resolver.resolve_pattern_tree(rule.pattern)?,
             template: resolved_template,
             index,
+            constraint_types,
+            constraint_traits,
         })
     }
 
@@ -64,6 +95,33 @@ impl ResolvedRule {
         }
         self.pattern.placeholders_by_stand_in.get(token.text())
     }
+
+    /// Returns the placeholder that `node` stands in for, if it's the macro call that a placeholder
+    /// making up the whole body of an item is parsed as, e.g. `$body` in `impl Foo { $body }`.
+    pub(crate) fn get_body_placeholder(&self, node: &SyntaxNode) -> Option<&Placeholder> {
+        self.get_placeholder(&body_placeholder_token(node)?)
+    }
+}
+
+/// Returns the name of the macro if `node` is a call like `name!{}`, which is how placeholders
+/// making up a whole body are written when parsing.
+pub(crate) fn body_placeholder_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    use syntax::ast::AstNode;
+    let call = match ast::MacroExpr::cast(node.clone()) {
+        Some(it) => it.macro_call()?,
+        None => ast::MacroCall::cast(node.clone())?,
+    };
+    if call.semicolon_token().is_some() || call.token_tree()?.syntax().text() != "{}" {
+        return None;
+    }
+    call.path()?.as_single_name_ref()?.ident_token()
+}
+
+impl ResolvedPattern {
+    /// Whether the pattern is a sequence of statements, which matches a part of a block.
+    pub(crate) fn is_sequence(&self) -> bool {
+        self.node.kind() == SyntaxKind::STMT_LIST
+    }
 }
 
 struct Resolver<'a, 'db> {
@@ -247,6 +305,80 @@ impl<'db> ResolutionScope<'db> {
         }
     }
 
+    fn resolve_constraint(
+        &self,
+        constraint: &Constraint,
+        types: &mut FxHashMap<String, TypePattern>,
+        traits: &mut FxHashMap<String, hir::Trait>,
+    ) -> Result<(), SsrError> {
+        match constraint {
+            Constraint::Kind(_) => {}
+            Constraint::Not(sub) => self.resolve_constraint(sub, types, traits)?,
+            Constraint::Type(text) => {
+                let ty = self.resolve_type_pattern(&parse_constraint_type(text)?)?;
+                types.insert(text.clone(), ty);
+            }
+            Constraint::Impls(text) => {
+                let path = match parse_constraint_type(text)? {
+                    ast::Type::PathType(it) => it.path(),
+                    _ => None,
+                };
+                let path = path.ok_or_else(|| error!("Expected a trait, found `{}`", text))?;
+                if path.segments().any(|it| it.generic_arg_list().is_some()) {
+                    bail!("Trait constraints can't have generic arguments: `{}`", text);
+                }
+                match self.scope.speculative_resolve(&path) {
+                    Some(hir::PathResolution::Def(hir::ModuleDef::Trait(trait_))) => {
+                        traits.insert(text.clone(), trait_);
+                    }
+                    _ => bail!("Failed to resolve trait `{}`", text),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_type_pattern(&self, ty: &ast::Type) -> Result<TypePattern, SsrError> {
+        use syntax::ast::AstNode;
+        let missing = || error!("Incomplete type `{}` in constraint", ty.syntax().text());
+        Ok(match ty {
+            ast::Type::InferType(_) => TypePattern::Any,
+            ast::Type::ParenType(it) => self.resolve_type_pattern(&it.ty().ok_or_else(missing)?)?,
+            ast::Type::RefType(it) => {
+                let inner = self.resolve_type_pattern(&it.ty().ok_or_else(missing)?)?;
+                let mutability = hir::Mutability::from_mutable(it.mut_token().is_some());
+                TypePattern::Ref(mutability, Box::new(inner))
+            }
+            ast::Type::TupleType(it) => TypePattern::Tuple(
+                it.fields().map(|it| self.resolve_type_pattern(&it)).collect::<Result<_, _>>()?,
+            ),
+            ast::Type::PathType(it) => {
+                let path = it.path().ok_or_else(missing)?;
+                let args = path
+                    .segment()
+                    .and_then(|it| it.generic_arg_list())
+                    .into_iter()
+                    .flat_map(|it| it.generic_args())
+                    .filter_map(|arg| match arg {
+                        ast::GenericArg::TypeArg(it) => it.ty(),
+                        _ => None,
+                    })
+                    .map(|it| self.resolve_type_pattern(&it))
+                    .collect::<Result<_, _>>()?;
+                match self.scope.speculative_resolve(&path) {
+                    Some(hir::PathResolution::Def(hir::ModuleDef::Adt(adt))) => {
+                        TypePattern::Adt(adt, args)
+                    }
+                    Some(hir::PathResolution::Def(hir::ModuleDef::BuiltinType(builtin))) => {
+                        TypePattern::Builtin(builtin)
+                    }
+                    _ => bail!("Failed to resolve type `{}`", path),
+                }
+            }
+            _ => bail!("Unsupported type `{}` in constraint", ty.syntax().text()),
+        })
+    }
+
     fn qualifier_type(&self, path: &SyntaxNode) -> Option<hir::Type> {
         use syntax::ast::AstNode;
         if let Some(path) = ast::Path::cast(path.clone()) {
@@ -262,6 +394,14 @@ impl<'db> ResolutionScope<'db> {
     }
 }
 
+fn parse_constraint_type(text: &str) -> Result<ast::Type, SsrError> {
+    use syntax::ast::AstNode;
+    fragments::ty(text)
+        .ok()
+        .and_then(ast::Type::cast)
+        .ok_or_else(|| error!("Invalid type `{}` in constraint", text))
+}
+
 fn is_self(path: &ast::Path) -> bool {
     path.segment().map(|segment| segment.self_token().is_some()).unwrap_or(false)
 }
@@ -0,0 +1,106 @@
+//! Parsing of files containing many named SSR rules, so that codemods can be kept under version
+//! control and applied in one go.
+//!
+//! ```text
+//! // Comments run to the end of the line.
+//! [vec-is-empty]
+//! paths = crates/foo, crates/bar
+//! ${v:type(Vec<_>)}.len() == 0 ==>> ${v}.is_empty()
+//!
+//! [swap-args]
+//! foo($a, $b)
+//!     ==>> foo($b, $a)
+//! ```
+//!
+//! Every rule starts with a `[name]` header, optionally followed by a `paths = ...` line listing
+//! the paths the rule is restricted to. The rest of the section up to the next header is the rule
+//! itself, which may span several lines.
+
+use std::str::FromStr;
+
+use crate::{
+    errors::{bail, error},
+    SsrError, SsrRule,
+};
+
+#[derive(Debug)]
+pub struct SsrRuleFile {
+    pub rules: Vec<NamedSsrRule>,
+}
+
+#[derive(Debug)]
+pub struct NamedSsrRule {
+    pub name: String,
+    /// The paths the rule applies to, as written in the file. Empty if it applies everywhere.
+    pub paths: Vec<String>,
+    pub rule: SsrRule,
+}
+
+impl FromStr for SsrRuleFile {
+    type Err = SsrError;
+
+    fn from_str(text: &str) -> Result<SsrRuleFile, SsrError> {
+        let mut rules = Vec::new();
+        // The name, the line of the header, the paths and the lines of the rule being collected.
+        let mut current: Option<(String, usize, Vec<String>, Vec<&str>)> = None;
+        for (idx, line) in text.lines().enumerate() {
+            let line_number = idx + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with("//") {
+                continue;
+            }
+            if let Some(name) = parse_header(trimmed) {
+                if let Some(rule) = current.take() {
+                    rules.push(finish_rule(rule)?);
+                }
+                if rules.iter().any(|it: &NamedSsrRule| it.name == name) {
+                    bail!("Line {}: rule `{}` is defined more than once", line_number, name);
+                }
+                current = Some((name.to_string(), line_number, Vec::new(), Vec::new()));
+                continue;
+            }
+            let (_, _, paths, lines) = current.as_mut().ok_or_else(|| {
+                error!("Line {}: expected a `[name]` header before the rule", line_number)
+            })?;
+            match trimmed.strip_prefix("paths").and_then(|it| it.trim_start().strip_prefix('=')) {
+                Some(list) if lines.is_empty() => {
+                    paths.extend(
+                        list.split(',')
+                            .map(str::trim)
+                            .filter(|it| !it.is_empty())
+                            .map(String::from),
+                    );
+                }
+                _ => lines.push(line),
+            }
+        }
+        if let Some(rule) = current.take() {
+            rules.push(finish_rule(rule)?);
+        }
+        Ok(SsrRuleFile { rules })
+    }
+}
+
+/// Returns the name of a `[name]` header. Names are restricted so that headers can't be mistaken
+/// for the start of an array or slice pattern.
+fn parse_header(line: &str) -> Option<&str> {
+    let name = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return None;
+    }
+    Some(name)
+}
+
+fn finish_rule(
+    (name, line_number, paths, lines): (String, usize, Vec<String>, Vec<&str>),
+) -> Result<NamedSsrRule, SsrError> {
+    if lines.is_empty() {
+        bail!("Line {}: rule `{}` is empty", line_number, name);
+    }
+    let rule = lines
+        .join("\n")
+        .parse::<SsrRule>()
+        .map_err(|err| error!("Line {}: invalid rule `{}`: {}", line_number, name, err.0))?;
+    Ok(NamedSsrRule { name, paths, rule })
+}
@@ -41,7 +41,9 @@ impl<'db> MatchFinder<'db> {
             }
             return;
         }
-        if pick_path_for_usages(&rule.pattern).is_none() {
+        // The statements of a sequence can be at different depths than in the pattern, since a
+        // trailing expression may match an expression statement, so we can't start from a path.
+        if rule.pattern.is_sequence() || pick_path_for_usages(&rule.pattern).is_none() {
             self.slow_scan(rule, matches_out);
             return;
         }
@@ -211,6 +213,12 @@ impl<'db> MatchFinder<'db> {
         restrict_range: &Option<FileRange>,
         matches_out: &mut Vec<Match>,
     ) {
+        if rule.pattern.is_sequence() {
+            let matches = matching::get_sequence_matches(rule, code, restrict_range, &self.sema);
+            matches_out
+                .extend(matches.into_iter().filter(|m| self.range_within_restrictions(&m.range)));
+            return;
+        }
         if !self.within_range_restrictions(code) {
             cov_mark::hit!(replace_nonpath_within_selection);
             return;
@@ -227,7 +235,13 @@ impl<'db> MatchFinder<'db> {
             // There is no range restriction.
             return true;
         }
-        let node_range = self.sema.original_range(code);
+        self.range_within_restrictions(&self.sema.original_range(code))
+    }
+
+    fn range_within_restrictions(&self, node_range: &FileRange) -> bool {
+        if self.restrict_ranges.is_empty() {
+            return true;
+        }
         for range in &self.restrict_ranges {
             if range.file_id == node_range.file_id && range.range.contains_range(node_range.range) {
                 return true;
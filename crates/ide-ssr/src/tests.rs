@@ -6,7 +6,7 @@ use ide_db::{
 use std::sync::Arc;
 use test_utils::RangeOrOffset;
 
-use crate::{MatchFinder, SsrRule};
+use crate::{MatchFinder, SsrRule, SsrRuleFile};
 
 fn parse_error_text(query: &str) -> String {
     format!("{}", query.parse::<SsrRule>().unwrap_err())
@@ -33,8 +33,8 @@ fn parser_two_delimiters() {
 #[test]
 fn parser_repeated_name() {
     assert_eq!(
-        parse_error_text("foo($a, $a) ==>>"),
-        "Parse error: Placeholder `$a` repeats more than once"
+        parse_error_text("foo(${a:kind(literal)}, ${a:not(kind(literal))}) ==>>"),
+        "Parse error: Placeholder `$a` repeats with different constraints"
     );
 }
 
@@ -62,6 +62,46 @@ fn parser_undefined_placeholder_in_replacement() {
     );
 }
 
+#[test]
+fn parser_rule_file() {
+    let rule_file: SsrRuleFile = r#"
+// Comments and blank lines are ignored.
+[swap]
+paths = crates/foo, crates/bar
+foo($a, $b) ==>> foo($b, $a)
+
+[multi-line]
+bar($a)
+    ==>> baz($a)
+"#
+    .parse()
+    .unwrap();
+    let rules =
+        rule_file.rules.iter().map(|it| (it.name.as_str(), it.paths.join(" "))).collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        vec![("swap", "crates/foo crates/bar".to_string()), ("multi-line", String::new())]
+    );
+}
+
+#[test]
+fn parser_rule_file_errors() {
+    let error = |text: &str| text.parse::<SsrRuleFile>().unwrap_err().to_string();
+    assert_eq!(
+        error("foo() ==>> bar()"),
+        "Parse error: Line 1: expected a `[name]` header before the rule"
+    );
+    assert_eq!(error("[a]\n[b]\nfoo() ==>> bar()"), "Parse error: Line 1: rule `a` is empty");
+    assert_eq!(
+        error("[a]\nfoo() ==>> bar()\n[a]\nbar() ==>> foo()"),
+        "Parse error: Line 3: rule `a` is defined more than once"
+    );
+    assert_eq!(
+        error("\n[a]\nfoo()"),
+        "Parse error: Line 2: invalid rule `a`: Cannot find delimiter `==>>`"
+    );
+}
+
 /// `code` may optionally contain a cursor marker `$0`. If it doesn't, then the position will be
 /// the start of the file. If there's a second cursor marker, then we'll return a single range.
 pub(crate) fn single_file(code: &str) -> (ide_db::RootDatabase, FilePosition, Vec<FileRange>) {
@@ -536,6 +576,193 @@ fn literal_constraint() {
     assert_matches("Some(${a:not(kind(literal))})", code, &["Some(x1)", "Some(40 + 2)"]);
 }
 
+#[test]
+fn type_constraint() {
+    let code = r#"
+        struct Vec<T> { t: T }
+        impl<T> Vec<T> { fn len(&self) -> usize { 0 } }
+        struct Set;
+        impl Set { fn len(&self) -> usize { 0 } }
+        fn f1(v: Vec<i32>, w: Vec<bool>, r: &Vec<i32>, s: Set) {
+            v.len();
+            w.len();
+            r.len();
+            s.len();
+        }
+        "#;
+    assert_matches("${a:type(Vec<_>)}.len()", code, &["v.len()", "w.len()"]);
+    assert_matches("${a:type(Vec<i32>)}.len()", code, &["v.len()"]);
+    assert_matches("${a:type(&Vec<_>)}.len()", code, &["r.len()"]);
+    assert_matches("${a:not(type(Vec<_>))}.len()", code, &["r.len()", "s.len()"]);
+}
+
+#[test]
+fn impls_constraint() {
+    let code = r#"
+        trait Shape { fn area(&self) -> u32; }
+        struct Square;
+        impl Shape for Square { fn area(&self) -> u32 { 1 } }
+        struct Blob;
+        impl Blob { fn area(&self) -> u32 { 0 } }
+        fn f1(a: Square, b: Blob) {
+            a.area();
+            b.area();
+        }
+        "#;
+    assert_matches("${x:impls(Shape)}.area()", code, &["a.area()"]);
+    assert_matches("${x:not(impls(Shape))}.area()", code, &["b.area()"]);
+}
+
+#[test]
+fn impls_constraint_with_generic_trait() {
+    let code = r#"
+        trait Convert<T> { fn convert(&self) -> T; }
+        trait Combine<Rhs = Self> { fn combine(&self, rhs: Rhs) -> u32; }
+        struct Square;
+        impl Convert<u32> for Square { fn convert(&self) -> u32 { 1 } }
+        impl Combine for Square { fn combine(&self, rhs: Square) -> u32 { 2 } }
+        struct Blob;
+        impl Blob {
+            fn convert(&self) -> u32 { 0 }
+            fn combine(&self, rhs: Blob) -> u32 { 0 }
+        }
+        fn f1(a: Square, b: Blob) {
+            a.convert();
+            b.convert();
+            a.combine(Square);
+            b.combine(Blob);
+        }
+        "#;
+    assert_matches("${x:impls(Convert)}.convert()", code, &["a.convert()"]);
+    assert_matches("${x:not(impls(Convert))}.convert()", code, &["b.convert()"]);
+    assert_matches("${x:impls(Combine)}.combine($y)", code, &["a.combine(Square)"]);
+}
+
+#[test]
+fn parser_invalid_type_constraint() {
+    assert_eq!(
+        parse_error_text("${a:type()}.len() ==>> ()"),
+        "Parse error: Constraint is missing a type"
+    );
+
+    let (db, position, selections) = single_file("struct Foo;");
+    let mut match_finder = MatchFinder::in_context(&db, position, selections).unwrap();
+    let error = match_finder.add_search_pattern("${a:impls(Foo)}".parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "Parse error: Failed to resolve trait `Foo`");
+    let error = match_finder.add_search_pattern("${a:type(Bar)}".parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "Parse error: Failed to resolve type `Bar`");
+}
+
+#[test]
+fn match_repeated_placeholder() {
+    let code = r#"
+        fn add(a: i32, b: i32) -> i32 { a + b }
+        fn f1(x: i32, y: i32) {
+            add(x, x);
+            add(x, y);
+            add(y + 1, y+1);
+        }
+        "#;
+    assert_matches("add($a, $a)", code, &["add(x, x)", "add(y + 1, y+1)"]);
+}
+
+#[test]
+fn match_statement_sequence() {
+    let code = "fn f1() { let a = 1; let b = 2; let c = 3; a + b + c }";
+    assert_matches("let $a = 1; let $b = 2;", code, &["let a = 1; let b = 2;"]);
+    assert_matches("let $b = 2; let $c = 3; $d", code, &["let b = 2; let c = 3; a + b + c"]);
+    assert_no_match("let $a = 1; let $c = 3;", code);
+}
+
+#[test]
+fn replace_statement_sequence() {
+    assert_ssr_transform(
+        "let $a = $b.lock(); $a.push($c) ==>> $b.lock().push($c)",
+        r#"
+        struct V;
+        struct G;
+        impl V { fn lock(&self) -> G { G } }
+        impl G { fn push(&self, _: i32) {} }
+        fn f1(v: V) {
+            let g = v.lock();
+            g.push(1);
+            let h = v.lock();
+            // Pushes two.
+            h.push(2);
+            g.push(3);
+        }
+        "#,
+        expect![[r#"
+            struct V;
+            struct G;
+            impl V { fn lock(&self) -> G { G } }
+            impl G { fn push(&self, _: i32) {} }
+            fn f1(v: V) {
+                let g = v.lock();
+                g.push(1);
+                // Pushes two.
+                v.lock().push(2);
+                g.push(3);
+            }
+        "#]],
+    );
+    // Locals that the template binds again can still be used after the sequence.
+    assert_ssr_transform(
+        "let $a = $b.lock(); $a.push($c) ==>> let $a = $b.lock(); $a.push($c + 1)",
+        r#"
+        struct V;
+        struct G;
+        impl V { fn lock(&self) -> G { G } }
+        impl G { fn push(&self, _: i32) {} }
+        fn f1(v: V) {
+            let g = v.lock();
+            g.push(1);
+            g.push(3);
+        }
+        "#,
+        expect![[r#"
+            struct V;
+            struct G;
+            impl V { fn lock(&self) -> G { G } }
+            impl G { fn push(&self, _: i32) {} }
+            fn f1(v: V) {
+                let g = v.lock();
+                g.push(1 + 1);
+                g.push(3);
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn replace_item_body() {
+    assert_ssr_transform(
+        "impl Drop for $t { $body } ==>> impl Finalize for $t { $body }",
+        r#"
+        trait Drop { fn drop(&mut self); }
+        trait Finalize { fn drop(&mut self); }
+        struct A;
+        struct B;
+        impl Drop for A {
+            fn drop(&mut self) {}
+            fn other() {}
+        }
+        impl Drop for B {}
+        "#,
+        expect![[r#"
+            trait Drop { fn drop(&mut self); }
+            trait Finalize { fn drop(&mut self); }
+            struct A;
+            struct B;
+            impl Finalize for A {
+                fn drop(&mut self) {}
+                fn other() {}
+            }
+            impl Finalize for B {}
+        "#]],
+    );
+}
+
 #[test]
 fn match_reordered_struct_instantiation() {
     assert_matches(
@@ -167,6 +167,21 @@ pub(crate) fn extract_definitions_from_docs(
     .collect()
 }
 
+/// Resolves the intra-doc links in the documentation of `def`.
+pub(crate) fn resolve_doc_links(
+    db: &RootDatabase,
+    def: Definition,
+    docs: &hir::Documentation,
+) -> Vec<(String, Definition)> {
+    extract_definitions_from_docs(docs)
+        .into_iter()
+        .filter_map(|(_, link, ns)| {
+            let resolved = resolve_doc_path_for_def(db, def, &link, ns)?;
+            Some((link, resolved))
+        })
+        .collect()
+}
+
 pub(crate) fn resolve_doc_path_for_def(
     db: &dyn HirDatabase,
     def: Definition,
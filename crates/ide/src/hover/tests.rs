@@ -617,7 +617,7 @@ const foo$0: u32 = {
             ```
 
             ```rust
-            static foo: u32 = 456
+            static foo: u32 = 456 (0x1C8)
             ```
         "#]],
     );
@@ -3901,6 +3901,75 @@ const FOO$0: f64 = 1.0f64;
     );
 }
 
+#[test]
+fn hover_const_eval_structured() {
+    check(
+        r#"
+struct Config { verbose: bool, level: u8 }
+const CONFIG$0: Config = Config { verbose: false, level: 2 * 8 };
+"#,
+        expect![[r#"
+            *CONFIG*
+
+            ```rust
+            test
+            ```
+
+            ```rust
+            const CONFIG: Config = Config { verbose: false, level: 16 }
+            ```
+        "#]],
+    );
+    check(
+        r#"
+const TABLE$0: [u8; 4] = [1, 2, 4, 8];
+"#,
+        expect![[r#"
+            *TABLE*
+
+            ```rust
+            test
+            ```
+
+            ```rust
+            const TABLE: [u8; 4] = [1, 2, 4, 8]
+            ```
+        "#]],
+    );
+    check(
+        r#"
+const ZEROS$0: [u8; 40] = [0; 40];
+"#,
+        expect![[r#"
+            *ZEROS*
+
+            ```rust
+            test
+            ```
+
+            ```rust
+            const ZEROS: [u8; 40] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, ...]
+            ```
+        "#]],
+    );
+    check(
+        r#"
+static NAMES$0: &[&str] = &["a", "b"];
+"#,
+        expect![[r#"
+            *NAMES*
+
+            ```rust
+            test
+            ```
+
+            ```rust
+            static NAMES: &[&str] = &["a", "b"]
+            ```
+        "#]],
+    );
+}
+
 #[test]
 fn hover_const_pat() {
     check(
@@ -5127,7 +5196,7 @@ pub struct Type;
             ```
 
             ```rust
-            const KONST: dep::Type = $crate::Type
+            const KONST: dep::Type = Type
             ```
         "#]],
     );
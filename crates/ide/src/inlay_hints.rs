@@ -1,20 +1,26 @@
-use std::fmt;
+use std::{
+    fmt::{self, Write},
+    mem,
+};
 
 use either::Either;
-use hir::{known, Callable, HasVisibility, HirDisplay, Mutability, Semantics, TypeInfo};
+use hir::{
+    known, Callable, HasVisibility, HirDisplay, HirWrite, ModuleDefId, Mutability, Semantics,
+    TypeInfo,
+};
 use ide_db::{
-    base_db::FileRange, famous_defs::FamousDefs, syntax_helpers::node_ext::walk_ty, FxHashMap,
-    RootDatabase,
+    base_db::FileRange, defs::Definition, famous_defs::FamousDefs, search::SearchScope,
+    syntax_helpers::node_ext::walk_ty, FxHashMap, RootDatabase,
 };
 use itertools::Itertools;
-use stdx::to_lower_snake_case;
+use stdx::{never, to_lower_snake_case};
 use syntax::{
     ast::{self, AstNode, HasArgList, HasGenericParams, HasName, UnaryOp},
     match_ast, Direction, NodeOrToken, SmolStr, SyntaxKind, SyntaxNode, SyntaxToken, TextRange,
     TextSize, T,
 };
 
-use crate::FileId;
+use crate::{navigation_target::TryToNav, FileId};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InlayHintsConfig {
@@ -23,8 +29,11 @@ pub struct InlayHintsConfig {
     pub parameter_hints: bool,
     pub chaining_hints: bool,
     pub reborrow_hints: ReborrowHints,
+    pub adjustment_hints: bool,
     pub closure_return_type_hints: ClosureReturnTypeHints,
+    pub discriminant_hints: DiscriminantHints,
     pub binding_mode_hints: bool,
+    pub implicit_drop_hints: bool,
     pub lifetime_elision_hints: LifetimeElisionHints,
     pub param_names_for_lifetime_elision_hints: bool,
     pub hide_named_constructor_hints: bool,
@@ -40,6 +49,13 @@ pub enum ClosureReturnTypeHints {
     Never,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiscriminantHints {
+    Always,
+    Never,
+    Fieldless,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LifetimeElisionHints {
     Always,
@@ -56,13 +72,18 @@ pub enum ReborrowHints {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InlayKind {
+    AdjustmentHint,
     BindingModeHint,
     ChainingHint,
     ClosingBraceHint,
+    ClosingParenthesis,
     ClosureReturnTypeHint,
+    DiscriminantHint,
+    DropHint,
     GenericParamListHint,
     ImplicitReborrowHint,
     LifetimeHint,
+    OpeningParenthesis,
     ParameterHint,
     TypeHint,
 }
@@ -82,6 +103,7 @@ pub enum InlayTooltip {
     HoverOffset(FileId, TextSize),
 }
 
+#[derive(Default)]
 pub struct InlayHintLabel {
     pub parts: Vec<InlayHintLabelPart>,
 }
@@ -175,6 +197,9 @@ impl fmt::Debug for InlayHintLabelPart {
 // * return types of closure expressions
 // * elided lifetimes
 // * compiler inserted reborrows
+// * values of implicit enum discriminants
+// * compiler inserted adjustments of expressions, like autoref, autoderef and unsizing
+// * implicit drops of local variables at the end of their scope
 //
 // image::https://user-images.githubusercontent.com/48062697/113020660-b5f98b80-917a-11eb-8d70-3be3fd558cdd.png[]
 pub(crate) fn inlay_hints(
@@ -221,6 +246,7 @@ fn hints(
         match node {
             ast::Expr(expr) => {
                 chaining_hints(hints, sema, &famous_defs, config, file_id, &expr);
+                adjustment_hints(hints, sema, config, &expr);
                 match expr {
                     ast::Expr::CallExpr(it) => param_name_hints(hints, sema, config, ast::Expr::from(it)),
                     ast::Expr::MethodCallExpr(it) => {
@@ -230,6 +256,7 @@ fn hints(
                     // We could show reborrows for all expressions, but usually that is just noise to the user
                     // and the main point here is to show why "moving" a mutable reference doesn't necessarily move it
                     ast::Expr::PathExpr(_) => reborrow_hints(hints, sema, config, &expr),
+                    ast::Expr::BlockExpr(it) => implicit_drop_hints(hints, famous_defs, config, file_id, &it),
                     _ => None,
                 }
             },
@@ -249,6 +276,7 @@ fn hints(
                 ast::Item::Const(it) => implicit_static_hints(hints, config, Either::Right(it)),
                 _ => None,
             },
+            ast::Variant(it) => discriminant_hints(hints, sema, config, &it),
             // FIXME: fn-ptr type, dyn fn type, and trait object type elisions
             ast::Type(_) => None,
             _ => None,
@@ -609,9 +637,7 @@ fn closure_ret_hints(
     acc.push(InlayHint {
         range: param_list.syntax().text_range(),
         kind: InlayKind::ClosureReturnTypeHint,
-        label: hint_iterator(sema, &famous_defs, config, &ty)
-            .unwrap_or_else(|| ty.display_truncated(sema.db, config.max_length).to_string())
-            .into(),
+        label: label_of_ty(famous_defs, config, ty)?,
         tooltip: Some(InlayTooltip::HoverRanged(file_id, param_list.syntax().text_range())),
     });
     Some(())
@@ -623,7 +649,8 @@ fn reborrow_hints(
     config: &InlayHintsConfig,
     expr: &ast::Expr,
 ) -> Option<()> {
-    if config.reborrow_hints == ReborrowHints::Never {
+    // Reborrows are shown as part of the adjustment hints.
+    if config.reborrow_hints == ReborrowHints::Never || config.adjustment_hints {
         return None;
     }
 
@@ -644,6 +671,249 @@ fn reborrow_hints(
     Some(())
 }
 
+fn adjustment_hints(
+    acc: &mut Vec<InlayHint>,
+    sema: &Semantics<'_, RootDatabase>,
+    config: &InlayHintsConfig,
+    expr: &ast::Expr,
+) -> Option<()> {
+    if !config.adjustment_hints {
+        return None;
+    }
+    // Parentheses are transparent to type inference, the adjustments are shown on the outer
+    // expression only.
+    if expr.syntax().parent().map_or(false, |it| ast::ParenExpr::can_cast(it.kind())) {
+        return None;
+    }
+
+    let descended = sema.descend_node_into_attributes(expr.clone()).pop();
+    let desc_expr = descended.as_ref().unwrap_or(expr);
+    let adjustments = sema.expr_adjustments(desc_expr).filter(|it| !it.is_empty())?;
+    let range = expr.syntax().text_range();
+    // The hints are rendered as prefix operators, which bind tighter than these expressions but
+    // looser than the postfix ones the expression may be the operand of, like a method call.
+    let needs_parens =
+        matches!(expr, ast::Expr::BinExpr(_) | ast::Expr::CastExpr(_) | ast::Expr::RangeExpr(_))
+            || is_postfix_operand(expr);
+    if needs_parens {
+        acc.push(InlayHint {
+            range,
+            kind: InlayKind::OpeningParenthesis,
+            label: "(".to_string().into(),
+            tooltip: None,
+        });
+    }
+    for adjustment in adjustments.into_iter().rev() {
+        let text = match adjustment.kind {
+            hir::Adjust::NeverToAny => "<never-to-any>",
+            hir::Adjust::Deref(_) => "*",
+            hir::Adjust::Borrow(hir::AutoBorrow::Ref(Mutability::Shared)) => "&",
+            hir::Adjust::Borrow(hir::AutoBorrow::Ref(Mutability::Mut)) => "&mut ",
+            hir::Adjust::Borrow(hir::AutoBorrow::RawPtr(Mutability::Shared)) => "&raw const ",
+            hir::Adjust::Borrow(hir::AutoBorrow::RawPtr(Mutability::Mut)) => "&raw mut ",
+            hir::Adjust::Pointer(cast) => match cast {
+                hir::PointerCast::ReifyFnPointer => "<fn-item-to-fn-pointer>",
+                hir::PointerCast::UnsafeFnPointer => "<safe-fn-pointer-to-unsafe-fn-pointer>",
+                hir::PointerCast::ClosureFnPointer(hir::Safety::Unsafe) => {
+                    "<closure-to-unsafe-fn-pointer>"
+                }
+                hir::PointerCast::ClosureFnPointer(hir::Safety::Safe) => "<closure-to-fn-pointer>",
+                hir::PointerCast::MutToConstPointer => "<mut-ptr-to-const-ptr>",
+                hir::PointerCast::ArrayToPointer => "<array-ptr-to-element-ptr>",
+                hir::PointerCast::Unsize => "<unsize>",
+            },
+        };
+        acc.push(InlayHint {
+            range,
+            kind: InlayKind::AdjustmentHint,
+            label: text.to_string().into(),
+            tooltip: Some(InlayTooltip::String(format!(
+                "`{}` → `{}`",
+                adjustment.source.display(sema.db),
+                adjustment.target.display(sema.db)
+            ))),
+        });
+    }
+    if needs_parens {
+        acc.push(InlayHint {
+            range,
+            kind: InlayKind::ClosingParenthesis,
+            label: ")".to_string().into(),
+            tooltip: None,
+        });
+    }
+    Some(())
+}
+
+/// Whether `expr` is the operand of a postfix expression, e.g. the receiver of a method call.
+fn is_postfix_operand(expr: &ast::Expr) -> bool {
+    let parent = match expr.syntax().parent() {
+        Some(it) => it,
+        None => return false,
+    };
+    match_ast! {
+        match parent {
+            ast::MethodCallExpr(it) => it.receiver().as_ref() == Some(expr),
+            ast::FieldExpr(it) => it.expr().as_ref() == Some(expr),
+            ast::IndexExpr(it) => it.base().as_ref() == Some(expr),
+            ast::AwaitExpr(_) => true,
+            ast::TryExpr(_) => true,
+            _ => false,
+        }
+    }
+}
+
+fn discriminant_hints(
+    acc: &mut Vec<InlayHint>,
+    sema: &Semantics<'_, RootDatabase>,
+    config: &InlayHintsConfig,
+    variant: &ast::Variant,
+) -> Option<()> {
+    // Explicit discriminants are already visible.
+    if variant.expr().is_some() {
+        return None;
+    }
+    let name = variant.name()?;
+    let def = sema.to_def(variant)?;
+    let show = match config.discriminant_hints {
+        DiscriminantHints::Always => true,
+        DiscriminantHints::Fieldless => !def.parent_enum(sema.db).is_data_carrying(sema.db),
+        DiscriminantHints::Never => false,
+    };
+    if !show {
+        return None;
+    }
+    let value = match def.eval(sema.db) {
+        Ok(it) => it,
+        Err(_) => return None,
+    };
+    acc.push(InlayHint {
+        range: name.syntax().text_range(),
+        kind: InlayKind::DiscriminantHint,
+        label: format!("= {}", value).into(),
+        tooltip: Some(InlayTooltip::String("Implicit discriminant".into())),
+    });
+    Some(())
+}
+
+fn implicit_drop_hints(
+    acc: &mut Vec<InlayHint>,
+    famous_defs @ FamousDefs(sema, _): &FamousDefs<'_, '_>,
+    config: &InlayHintsConfig,
+    file_id: FileId,
+    block: &ast::BlockExpr,
+) -> Option<()> {
+    if !config.implicit_drop_hints {
+        return None;
+    }
+    let stmt_list = block.stmt_list()?;
+    let r_curly = stmt_list.r_curly_token()?;
+    let drop_trait = famous_defs.core_ops_Drop()?;
+    // Locals can only be used within the block that declares them.
+    let scope = FileRange { file_id, range: stmt_list.syntax().text_range() };
+
+    let mut dropped = Vec::new();
+    for stmt in stmt_list.statements() {
+        let pat = match stmt {
+            ast::Stmt::LetStmt(it) => match it.pat() {
+                Some(ast::Pat::IdentPat(it)) if it.ref_token().is_none() => it,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let local = match sema.to_def(&pat) {
+            Some(it) => it,
+            None => continue,
+        };
+        if !has_drop_glue(sema.db, drop_trait, &local.ty(sema.db), 0) {
+            continue;
+        }
+        let moved = Definition::Local(local)
+            .usages(sema)
+            .in_scope(SearchScope::file_range(scope))
+            .all()
+            .iter()
+            .flat_map(|(_, refs)| refs)
+            .filter_map(|it| it.name.as_name_ref().cloned())
+            .any(|name_ref| {
+                // NameRef -> PathSegment -> Path -> PathExpr
+                match name_ref.syntax().ancestors().nth(3).and_then(ast::PathExpr::cast) {
+                    Some(it) => is_moved(sema, ast::Expr::PathExpr(it)),
+                    // E.g. a shorthand record field
+                    None => true,
+                }
+            });
+        if !moved {
+            dropped.push(local.name(sema.db));
+        }
+    }
+
+    // Locals are dropped in the reverse order of their declaration.
+    for name in dropped.into_iter().rev() {
+        acc.push(InlayHint {
+            range: r_curly.text_range(),
+            kind: InlayKind::DropHint,
+            label: format!("drop({})", name).into(),
+            tooltip: Some(InlayTooltip::String("Implicit drop at the end of the scope".into())),
+        });
+    }
+    Some(())
+}
+
+/// Whether dropping a value of type `ty` runs any code, checked up to a few levels deep.
+fn has_drop_glue(db: &RootDatabase, drop_trait: hir::Trait, ty: &hir::Type, depth: usize) -> bool {
+    if depth > 3 || ty.is_reference() || ty.is_raw_ptr() || ty.is_copy(db) {
+        return false;
+    }
+    let variant_fields = match ty.as_adt() {
+        Some(hir::Adt::Enum(it)) => {
+            it.variants(db).into_iter().flat_map(|it| ty.variant_fields(db, it)).collect()
+        }
+        _ => Vec::new(),
+    };
+    ty.impls_trait(db, drop_trait, &[])
+        || ty.fields(db).iter().any(|(_, ty)| has_drop_glue(db, drop_trait, ty, depth + 1))
+        || variant_fields.iter().any(|(_, ty)| has_drop_glue(db, drop_trait, ty, depth + 1))
+        || ty.tuple_fields(db).iter().any(|ty| has_drop_glue(db, drop_trait, ty, depth + 1))
+}
+
+/// Whether the value of `expr` is moved out of, rather than only used as a place. Anything that
+/// isn't known to leave the value in place counts as a move.
+fn is_moved(sema: &Semantics<'_, RootDatabase>, expr: ast::Expr) -> bool {
+    let parent = match expr.syntax().parent() {
+        Some(it) => it,
+        None => return true,
+    };
+    match_ast! {
+        match parent {
+            ast::ParenExpr(it) => is_moved(sema, it.into()),
+            ast::RefExpr(_) => false,
+            ast::IndexExpr(it) => it.base().as_ref() != Some(&expr),
+            ast::FieldExpr(it) => {
+                let field = ast::Expr::from(it);
+                let is_copy = sema
+                    .type_of_expr(&field)
+                    .map_or(false, |ty| ty.original.is_copy(sema.db));
+                !is_copy && is_moved(sema, field)
+            },
+            ast::MethodCallExpr(it) => {
+                if it.receiver().as_ref() != Some(&expr) {
+                    return true;
+                }
+                sema.resolve_method_call(&it)
+                    .and_then(|func| func.self_param(sema.db))
+                    .map_or(true, |param| param.access(sema.db) == hir::Access::Owned)
+            },
+            ast::BinExpr(it) => match it.op_kind() {
+                Some(ast::BinaryOp::Assignment { .. }) => it.lhs().as_ref() != Some(&expr),
+                Some(ast::BinaryOp::CmpOp(_)) => false,
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
 fn chaining_hints(
     acc: &mut Vec<InlayHint>,
     sema: &Semantics<'_, RootDatabase>,
@@ -696,9 +966,7 @@ fn chaining_hints(
             acc.push(InlayHint {
                 range: expr.syntax().text_range(),
                 kind: InlayKind::ChainingHint,
-                label: hint_iterator(sema, &famous_defs, config, &ty)
-                    .unwrap_or_else(|| ty.display_truncated(sema.db, config.max_length).to_string())
-                    .into(),
+                label: label_of_ty(famous_defs, config, ty)?,
                 tooltip: Some(InlayTooltip::HoverRanged(file_id, expr.syntax().text_range())),
             });
         }
@@ -827,20 +1095,13 @@ fn bind_pat_hints(
 
     let krate = sema.scope(desc_pat.syntax())?.krate();
     let famous_defs = FamousDefs(sema, krate);
-    let label = hint_iterator(sema, &famous_defs, config, &ty);
-
-    let label = match label {
-        Some(label) => label,
-        None => {
-            let ty_name = ty.display_truncated(sema.db, config.max_length).to_string();
-            if config.hide_named_constructor_hints
-                && is_named_constructor(sema, pat, &ty_name).is_some()
-            {
-                return None;
-            }
-            ty_name
-        }
-    };
+    let label = label_of_ty(&famous_defs, config, ty)?;
+
+    if config.hide_named_constructor_hints
+        && is_named_constructor(sema, pat, &label.to_string()).is_some()
+    {
+        return None;
+    }
 
     acc.push(InlayHint {
         range: match pat.name() {
@@ -848,7 +1109,7 @@ fn bind_pat_hints(
             None => pat.syntax().text_range(),
         },
         kind: InlayKind::TypeHint,
-        label: label.into(),
+        label,
         tooltip: pat
             .name()
             .map(|it| it.syntax().text_range())
@@ -912,12 +1173,100 @@ fn is_named_constructor(
 }
 
 /// Checks if the type is an Iterator from std::iter and replaces its hint with an `impl Iterator<Item = Ty>`.
+/// Renders a type as a label, linking the names of the ADTs, traits and aliases in it to their
+/// definitions.
+fn label_of_ty(
+    famous_defs @ FamousDefs(sema, _): &FamousDefs<'_, '_>,
+    config: &InlayHintsConfig,
+    ty: hir::Type,
+) -> Option<InlayHintLabel> {
+    fn rec(
+        sema: &Semantics<'_, RootDatabase>,
+        famous_defs: &FamousDefs<'_, '_>,
+        mut max_length: Option<usize>,
+        ty: hir::Type,
+        label_builder: &mut InlayHintLabelBuilder<'_>,
+    ) -> Option<()> {
+        match hint_iterator(sema, famous_defs, &ty) {
+            Some(item_ty) => {
+                const LABEL_START: &str = "impl Iterator<Item = ";
+                const LABEL_END: &str = ">";
+
+                max_length =
+                    max_length.map(|len| len.saturating_sub(LABEL_START.len() + LABEL_END.len()));
+
+                label_builder.write_str(LABEL_START).ok()?;
+                rec(sema, famous_defs, max_length, item_ty, label_builder)?;
+                label_builder.write_str(LABEL_END).ok()
+            }
+            None => ty.display_truncated(sema.db, max_length).write_to(label_builder).ok(),
+        }
+    }
+
+    let mut label_builder = InlayHintLabelBuilder {
+        db: sema.db,
+        last_part: String::new(),
+        location: None,
+        result: InlayHintLabel::default(),
+    };
+    rec(sema, famous_defs, config.max_length, ty, &mut label_builder)?;
+    Some(label_builder.finish())
+}
+
+/// Collects the output of `HirDisplay` into label parts, one for each linked definition name.
+struct InlayHintLabelBuilder<'a> {
+    db: &'a RootDatabase,
+    result: InlayHintLabel,
+    last_part: String,
+    location: Option<FileRange>,
+}
+
+impl fmt::Write for InlayHintLabelBuilder<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.last_part.write_str(s)
+    }
+}
+
+impl HirWrite for InlayHintLabelBuilder<'_> {
+    fn start_location_link(&mut self, def: ModuleDefId) {
+        if self.location.is_some() {
+            never!("location link is already started");
+        }
+        self.make_new_part();
+        self.location = hir::ModuleDef::from(def)
+            .try_to_nav(self.db)
+            .map(|it| FileRange { file_id: it.file_id, range: it.focus_or_full_range() });
+    }
+
+    fn end_location_link(&mut self) {
+        self.make_new_part();
+    }
+}
+
+impl InlayHintLabelBuilder<'_> {
+    fn make_new_part(&mut self) {
+        let location = self.location.take();
+        if !self.last_part.is_empty() {
+            self.result.parts.push(InlayHintLabelPart {
+                text: mem::take(&mut self.last_part),
+                linked_location: location,
+            });
+        }
+    }
+
+    fn finish(mut self) -> InlayHintLabel {
+        self.make_new_part();
+        self.result
+    }
+}
+
+/// Returns the item type of `core::iter` adapters, so that they can be shown as
+/// `impl Iterator<Item = ...>` instead of the often long and uninteresting adapter type.
 fn hint_iterator(
     sema: &Semantics<'_, RootDatabase>,
     famous_defs: &FamousDefs<'_, '_>,
-    config: &InlayHintsConfig,
     ty: &hir::Type,
-) -> Option<String> {
+) -> Option<hir::Type> {
     let db = sema.db;
     let strukt = ty.strip_references().as_adt()?;
     let krate = strukt.module(db).krate();
@@ -940,21 +1289,7 @@ fn hint_iterator(
             _ => None,
         })?;
         if let Some(ty) = ty.normalize_trait_assoc_type(db, &[], assoc_type_item) {
-            const LABEL_START: &str = "impl Iterator<Item = ";
-            const LABEL_END: &str = ">";
-
-            let ty_display = hint_iterator(sema, famous_defs, config, &ty)
-                .map(|assoc_type_impl| assoc_type_impl.to_string())
-                .unwrap_or_else(|| {
-                    ty.display_truncated(
-                        db,
-                        config
-                            .max_length
-                            .map(|len| len.saturating_sub(LABEL_START.len() + LABEL_END.len())),
-                    )
-                    .to_string()
-                });
-            return Some(format!("{}{}{}", LABEL_START, ty_display, LABEL_END));
+            return Some(ty);
         }
     }
 
@@ -1218,7 +1553,7 @@ mod tests {
     use syntax::{TextRange, TextSize};
     use test_utils::extract_annotations;
 
-    use crate::inlay_hints::ReborrowHints;
+    use crate::inlay_hints::{DiscriminantHints, ReborrowHints};
     use crate::{fixture, inlay_hints::InlayHintsConfig, LifetimeElisionHints};
 
     use super::ClosureReturnTypeHints;
@@ -1231,7 +1566,10 @@ mod tests {
         lifetime_elision_hints: LifetimeElisionHints::Never,
         closure_return_type_hints: ClosureReturnTypeHints::Never,
         reborrow_hints: ReborrowHints::Always,
+        adjustment_hints: false,
+        discriminant_hints: DiscriminantHints::Never,
         binding_mode_hints: false,
+        implicit_drop_hints: false,
         hide_named_constructor_hints: false,
         hide_closure_initialization_hints: false,
         param_names_for_lifetime_elision_hints: false,
@@ -1733,7 +2071,30 @@ fn main(a: SliceIter<'_, Container>) {
                         range: 484..485,
                         kind: ChainingHint,
                         label: [
-                            "SliceIter<Container>",
+                            InlayHintLabelPart {
+                                text: "SliceIter",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 289..298,
+                                    },
+                                ),
+                            },
+                            "<",
+                            InlayHintLabelPart {
+                                text: "Container",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 238..247,
+                                    },
+                                ),
+                            },
+                            ">",
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2368,7 +2729,17 @@ fn main() {
                         range: 147..172,
                         kind: ChainingHint,
                         label: [
-                            "B",
+                            InlayHintLabelPart {
+                                text: "B",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 63..64,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2383,7 +2754,17 @@ fn main() {
                         range: 147..154,
                         kind: ChainingHint,
                         label: [
-                            "A",
+                            InlayHintLabelPart {
+                                text: "A",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 7..8,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2443,7 +2824,17 @@ fn main() {
                         range: 143..190,
                         kind: ChainingHint,
                         label: [
-                            "C",
+                            InlayHintLabelPart {
+                                text: "C",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 51..52,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2458,7 +2849,17 @@ fn main() {
                         range: 143..179,
                         kind: ChainingHint,
                         label: [
-                            "B",
+                            InlayHintLabelPart {
+                                text: "B",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 29..30,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2503,7 +2904,30 @@ fn main() {
                         range: 246..283,
                         kind: ChainingHint,
                         label: [
-                            "B<X<i32, bool>>",
+                            InlayHintLabelPart {
+                                text: "B",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 23..24,
+                                    },
+                                ),
+                            },
+                            "<",
+                            InlayHintLabelPart {
+                                text: "X",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 55..56,
+                                    },
+                                ),
+                            },
+                            "<i32, bool>>",
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2518,7 +2942,30 @@ fn main() {
                         range: 246..265,
                         kind: ChainingHint,
                         label: [
-                            "A<X<i32, bool>>",
+                            InlayHintLabelPart {
+                                text: "A",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 7..8,
+                                    },
+                                ),
+                            },
+                            "<",
+                            InlayHintLabelPart {
+                                text: "X",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 55..56,
+                                    },
+                                ),
+                            },
+                            "<i32, bool>>",
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2610,7 +3057,18 @@ fn main() {
                         range: 174..189,
                         kind: ChainingHint,
                         label: [
-                            "&mut MyIter",
+                            "&mut ",
+                            InlayHintLabelPart {
+                                text: "MyIter",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 24..30,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2654,7 +3112,17 @@ fn main() {
                         range: 124..130,
                         kind: TypeHint,
                         label: [
-                            "Struct",
+                            InlayHintLabelPart {
+                                text: "Struct",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 7..13,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2669,7 +3137,17 @@ fn main() {
                         range: 145..185,
                         kind: ChainingHint,
                         label: [
-                            "Struct",
+                            InlayHintLabelPart {
+                                text: "Struct",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 7..13,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2684,7 +3162,17 @@ fn main() {
                         range: 145..168,
                         kind: ChainingHint,
                         label: [
-                            "Struct",
+                            InlayHintLabelPart {
+                                text: "Struct",
+                                linked_location: Some(
+                                    FileRange {
+                                        file_id: FileId(
+                                            0,
+                                        ),
+                                        range: 7..13,
+                                    },
+                                ),
+                            },
                         ],
                         tooltip: Some(
                             HoverRanged(
@@ -2984,6 +3472,150 @@ fn f() {
     ];
   }
 //^ fn f
+"#,
+        );
+    }
+
+    #[test]
+    fn hints_discriminant() {
+        check_with_config(
+            InlayHintsConfig {
+                discriminant_hints: DiscriminantHints::Fieldless,
+                ..DISABLED_CONFIG
+            },
+            r#"
+enum Enum {
+    Variant,
+  //^^^^^^^= 0
+    Variant1,
+  //^^^^^^^^= 1
+    Variant2 = 5,
+    Variant3,
+  //^^^^^^^^= 6
+    Variant4 = 10,
+    Variant5,
+  //^^^^^^^^= 11 (0xB)
+}
+enum Data {
+    Variant(u32),
+    Variant1,
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn hints_adjustments() {
+        check_with_config(
+            InlayHintsConfig { adjustment_hints: true, ..DISABLED_CONFIG },
+            r#"
+//- minicore: coerce_unsized
+struct Struct;
+impl Struct {
+    fn by_ref(&self) {}
+    fn by_mut(&mut self) {}
+}
+fn f() {}
+
+fn main() {
+    let _: &u32 = &mut 0;
+                //^^^^^^&
+                //^^^^^^*
+    let _: *const u32 = &mut 0;
+                      //^^^^^^&raw const $
+                      //^^^^^^*
+    let _: &[u32] = &[0; 0];
+                  //^^^^^^^<unsize>
+                  //^^^^^^^&
+                  //^^^^^^^*
+    let _: fn() = f;
+                //^<fn-item-to-fn-pointer>
+    let _: unsafe fn() = f;
+                       //^<fn-item-to-fn-pointer>
+    let _: fn() = || {};
+                //^^^^^<closure-to-fn-pointer>
+    let _: *const u32 = &mut 0u32 as *mut u32;
+                      //^^^^^^^^^^^^^^^^^^^^^(
+                      //^^^^^^^^^^^^^^^^^^^^^<mut-ptr-to-const-ptr>
+                      //^^^^^^^^^^^^^^^^^^^^^)
+    Struct.by_ref();
+  //^^^^^^(
+  //^^^^^^&
+  //^^^^^^)
+    let mut s = Struct;
+    s.by_mut();
+  //^(
+  //^&mut $
+  //^)
+    let _: u32 = loop {};
+               //^^^^^^^<never-to-any>
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn adjustment_hints_replace_reborrow_hints() {
+        check_with_config(
+            InlayHintsConfig {
+                reborrow_hints: ReborrowHints::Always,
+                adjustment_hints: true,
+                ..DISABLED_CONFIG
+            },
+            r#"
+fn main() {
+    let unique = &mut ();
+    let _: &mut () = unique;
+                   //^^^^^^&mut $
+                   //^^^^^^*
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn hints_implicit_drops() {
+        check_with_config(
+            InlayHintsConfig { implicit_drop_hints: true, ..DISABLED_CONFIG },
+            r#"
+//- minicore: drop
+struct Guard;
+impl Drop for Guard {
+    fn drop(&mut self) {}
+}
+impl Guard {
+    fn by_ref(&self) {}
+    fn consume(self) {}
+}
+struct Wrapper(Guard);
+struct Plain;
+struct PhantomData<T>;
+enum Option<T> { None, Some(T) }
+fn take(_: Guard) {}
+
+fn main() {
+    let a = Guard;
+    let b = Wrapper(Guard);
+    let c = Plain;
+    let o = Option::Some(Guard);
+    let n: Option<Plain> = Option::None;
+    let p: PhantomData<Guard> = PhantomData;
+    let moved = Guard;
+    take(moved);
+    let consumed = Guard;
+    consumed.consume();
+    let borrowed = Guard;
+    borrowed.by_ref();
+    let _r = &borrowed;
+    {
+        let inner = Guard;
+      }
+    //^ drop(inner)
+  }
+//^ drop(borrowed)
+//^ drop(o)
+//^ drop(b)
+//^ drop(a)
 "#,
         );
     }
@@ -0,0 +1,315 @@
+//! Inline values for debugger sessions.
+
+use hir::{Local, ScopeDef, Semantics};
+use ide_db::{
+    base_db::{FileRange, SourceDatabaseExt},
+    defs::{Definition, NameClass, NameRefClass},
+    FxHashSet, RootDatabase,
+};
+use syntax::{ast, match_ast, AstNode, SyntaxNode, TextRange, TextSize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineValue {
+    pub range: TextRange,
+    pub kind: InlineValueKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InlineValueKind {
+    /// A local binding, the debugger can look its value up by name.
+    Variable(String),
+    /// A field access rooted at a local binding, the debugger has to evaluate it.
+    Expression(String),
+}
+
+// Feature: Inline Values
+//
+// While a debugger is paused, shows the values of the local bindings and the field accesses
+// on them that are in scope at the stopped location, for the lines leading up to it.
+pub(crate) fn inline_values(
+    db: &RootDatabase,
+    FileRange { file_id, range }: FileRange,
+    stopped_at: TextRange,
+) -> Vec<InlineValue> {
+    let sema = Semantics::new(db);
+    let file = sema.parse(file_id);
+    let text = db.file_text(file_id);
+    // The client may report a stopped location past the end of the file.
+    let len = TextSize::of(&*text);
+    let stopped_at = TextRange::new(stopped_at.start().min(len), stopped_at.end().min(len));
+    let stop = stopped_at.start();
+
+    let body_owner = match file
+        .syntax()
+        .token_at_offset(stop)
+        .right_biased()
+        .and_then(|token| token.parent_ancestors().find_map(ast::Fn::cast))
+    {
+        Some(it) => it.syntax().clone(),
+        None => return Vec::new(),
+    };
+    let in_scope = locals_in_scope(&sema, &body_owner, stop);
+    if in_scope.is_empty() {
+        return Vec::new();
+    }
+
+    // Values are only known for the code that already ran, which is everything before the
+    // stopped location, and the stopped line itself.
+    let stop_end = usize::from(stopped_at.end());
+    let line_end = text[stop_end..].find('\n').map_or(text.len(), |it| stop_end + it);
+    let visible = match range.intersect(TextRange::new(
+        body_owner.text_range().start(),
+        TextSize::try_from(line_end).unwrap().min(body_owner.text_range().end()),
+    )) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+
+    let mut res = Vec::new();
+    let mut expression_ranges: Vec<TextRange> = Vec::new();
+    for node in body_owner.descendants() {
+        let node_range = node.text_range();
+        if !visible.contains_range(node_range) {
+            continue;
+        }
+        match_ast! {
+            match node {
+                ast::FieldExpr(field_expr) => {
+                    // Only report the outermost access of a chain like `a.b.c`.
+                    if field_expr.syntax().parent().and_then(ast::FieldExpr::cast).is_some() {
+                        continue;
+                    }
+                    let root = field_access_root(&field_expr);
+                    let local = root.and_then(|path| {
+                        match NameRefClass::classify(&sema, &path.path()?.as_single_name_ref()?)? {
+                            NameRefClass::Definition(Definition::Local(local)) => Some(local),
+                            _ => None,
+                        }
+                    });
+                    if local.map_or(false, |local| in_scope.contains(&local)) {
+                        expression_ranges.push(node_range);
+                        res.push(InlineValue {
+                            range: node_range,
+                            kind: InlineValueKind::Expression(expression_text(field_expr.syntax())),
+                        });
+                    }
+                },
+                ast::NameRef(name_ref) => {
+                    if expression_ranges.iter().any(|it| it.contains_range(node_range)) {
+                        continue;
+                    }
+                    let local = match NameRefClass::classify(&sema, &name_ref) {
+                        Some(NameRefClass::Definition(Definition::Local(local))) => local,
+                        Some(NameRefClass::FieldShorthand { local_ref, .. }) => local_ref,
+                        _ => continue,
+                    };
+                    if in_scope.contains(&local) {
+                        res.push(variable(db, local, node_range));
+                    }
+                },
+                ast::Name(name) => {
+                    let local = match NameClass::classify(&sema, &name) {
+                        Some(NameClass::Definition(Definition::Local(local))) => local,
+                        Some(NameClass::PatFieldShorthand { local_def, .. }) => local_def,
+                        _ => continue,
+                    };
+                    if in_scope.contains(&local) {
+                        res.push(variable(db, local, node_range));
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+    res
+}
+
+/// Collects the locals visible at `offset`, as the debugger's frame sees them.
+fn locals_in_scope(
+    sema: &Semantics<'_, RootDatabase>,
+    body_owner: &SyntaxNode,
+    offset: TextSize,
+) -> FxHashSet<Local> {
+    let mut res = FxHashSet::default();
+    let token = match body_owner.token_at_offset(offset).right_biased() {
+        Some(it) => it,
+        None => return res,
+    };
+    // A `let` statement doesn't see its own bindings, its initializer sees the same scope as
+    // the statement does. Everything else is resolved from the innermost expression.
+    let node = token.parent_ancestors().take_while(|it| it != body_owner).find_map(|node| {
+        match ast::LetStmt::cast(node.clone()) {
+            Some(let_stmt) => let_stmt.initializer().map(|it| it.syntax().clone()),
+            None => ast::Expr::cast(node).map(|it| it.syntax().clone()),
+        }
+    });
+    let scope = match node {
+        Some(node) => sema.scope(&node),
+        None => sema.scope_at_offset(body_owner, offset),
+    };
+    if let Some(scope) = scope {
+        scope.process_all_names(&mut |_, def| {
+            if let ScopeDef::Local(local) = def {
+                res.insert(local);
+            }
+        });
+    }
+    res
+}
+
+fn variable(db: &RootDatabase, local: Local, range: TextRange) -> InlineValue {
+    InlineValue { range, kind: InlineValueKind::Variable(local.name(db).to_string()) }
+}
+
+fn field_access_root(field_expr: &ast::FieldExpr) -> Option<ast::PathExpr> {
+    let mut expr = field_expr.expr()?;
+    loop {
+        match expr {
+            ast::Expr::FieldExpr(inner) => expr = inner.expr()?,
+            ast::Expr::PathExpr(path) => return Some(path),
+            _ => return None,
+        }
+    }
+}
+
+fn expression_text(node: &SyntaxNode) -> String {
+    node.descendants_with_tokens()
+        .filter_map(|it| it.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .map(|token| token.text().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ide_db::base_db::FileRange;
+    use syntax::TextRange;
+
+    use crate::{fixture, InlineValueKind};
+
+    #[track_caller]
+    fn check(ra_fixture: &str) {
+        let (analysis, pos, annotations) = fixture::annotations(ra_fixture);
+        let file_text = analysis.file_text(pos.file_id).unwrap();
+        let frange = FileRange {
+            file_id: pos.file_id,
+            range: TextRange::up_to((file_text.len() as u32).into()),
+        };
+        let values = analysis.inline_values(frange, TextRange::empty(pos.offset)).unwrap();
+
+        let mut actual = values
+            .into_iter()
+            .map(|value| {
+                let text = match value.kind {
+                    InlineValueKind::Variable(name) => format!("variable {name}"),
+                    InlineValueKind::Expression(expr) => format!("expression {expr}"),
+                };
+                (value.range, text)
+            })
+            .collect::<Vec<_>>();
+        let mut expected =
+            annotations.into_iter().map(|(frange, text)| (frange.range, text)).collect::<Vec<_>>();
+        actual.sort_by_key(|(range, _)| range.start());
+        expected.sort_by_key(|(range, _)| range.start());
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn locals_before_stop() {
+        check(
+            r#"
+fn foo(a: i32) -> i32 {
+     //^ variable a
+    let b = a + 1;
+      //^ variable b
+          //^ variable a
+    let c = b * 2;
+      //^ variable c
+          //^ variable b
+    $0let d = c;
+          //^ variable c
+    d
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn locals_used_on_stopped_line() {
+        check(
+            r#"
+fn foo() -> i32 {
+    let x = 1;
+      //^ variable x
+    $0bar(x)
+      //^ variable x
+}
+fn bar(_: i32) -> i32 { 0 }
+"#,
+        );
+    }
+
+    #[test]
+    fn out_of_scope_and_shadowed_locals() {
+        check(
+            r#"
+//- minicore: option
+fn foo() {
+    {
+        let inner = 1;
+    }
+    let x = 1;
+    let x = x + 1;
+      //^ variable x
+    if let Some(y) = Some(x) {
+                        //^ variable x
+    }
+    $0x;
+  //^ variable x
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn field_accesses() {
+        check(
+            r#"
+struct Inner { value: i32 }
+struct S { inner: Inner, flag: bool }
+impl S {
+    fn get(&self) -> i32 {
+          //^^^^ variable self
+        let v = self.inner.value;
+          //^ variable v
+              //^^^^^^^^^^^^^^^^ expression self.inner.value
+        if self.flag {
+         //^^^^^^^^^ expression self.flag
+            $0return v;
+                 //^ variable v
+        }
+        0
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn stopped_past_end_of_file() {
+        let (analysis, file_id) = fixture::file("fn foo() { let x = 1; }");
+        let frange = FileRange { file_id, range: TextRange::up_to(23.into()) };
+        let stopped_at = TextRange::empty(100.into());
+        assert!(analysis.inline_values(frange, stopped_at).unwrap().is_empty());
+    }
+
+    #[test]
+    fn no_values_outside_of_bodies() {
+        check(
+            r#"
+struct S$0 { x: i32 }
+fn foo(x: i32) {}
+"#,
+        );
+    }
+}
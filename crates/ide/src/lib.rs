@@ -39,6 +39,7 @@ mod goto_implementation;
 mod goto_type_definition;
 mod hover;
 mod inlay_hints;
+mod inline_values;
 mod join_lines;
 mod markdown_remove;
 mod matching_brace;
@@ -53,6 +54,7 @@ mod static_index;
 mod status;
 mod syntax_highlighting;
 mod syntax_tree;
+mod type_hierarchy;
 mod typing;
 mod view_crate_graph;
 mod view_hir;
@@ -67,6 +69,7 @@ use ide_db::{
         salsa::{self, ParallelDatabase},
         CrateOrigin, Env, FileLoader, FileSet, SourceDatabase, VfsPath,
     },
+    defs::Definition,
     symbol_index, LineIndexDatabase,
 };
 use syntax::SourceFile;
@@ -82,9 +85,10 @@ pub use crate::{
     highlight_related::{HighlightRelatedConfig, HighlightedRange},
     hover::{HoverAction, HoverConfig, HoverDocFormat, HoverGotoTypeData, HoverResult},
     inlay_hints::{
-        ClosureReturnTypeHints, InlayHint, InlayHintLabel, InlayHintsConfig, InlayKind,
-        InlayTooltip, LifetimeElisionHints, ReborrowHints,
+        ClosureReturnTypeHints, DiscriminantHints, InlayHint, InlayHintLabel, InlayHintsConfig,
+        InlayKind, InlayTooltip, LifetimeElisionHints, ReborrowHints,
     },
+    inline_values::{InlineValue, InlineValueKind},
     join_lines::JoinLinesConfig,
     markup::Markup,
     moniker::{MonikerDescriptorKind, MonikerKind, MonikerResult, PackageInformation},
@@ -373,6 +377,16 @@ impl Analysis {
         self.with_db(|db| inlay_hints::inlay_hints(db, file_id, range, config))
     }
 
+    /// Returns the local bindings and field accesses whose values a paused debugger can show
+    /// inline in `frange`, given the location the debugger stopped at.
+    pub fn inline_values(
+        &self,
+        frange: FileRange,
+        stopped_at: TextRange,
+    ) -> Cancellable<Vec<InlineValue>> {
+        self.with_db(|db| inline_values::inline_values(db, frange, stopped_at))
+    }
+
     /// Returns the set of folding ranges.
     pub fn folding_ranges(&self, file_id: FileId) -> Cancellable<Vec<Fold>> {
         self.with_db(|db| folding_ranges::folding_ranges(&db.parse(file_id).tree()))
@@ -459,6 +473,16 @@ impl Analysis {
         self.with_db(|db| doc_links::external_docs(db, &position))
     }
 
+    /// Resolves the intra-doc links in `docs`, the documentation of `def`, returning each link
+    /// along with the definition it points to. Links that don't resolve are skipped.
+    pub fn resolve_doc_links(
+        &self,
+        def: Definition,
+        docs: &Documentation,
+    ) -> Cancellable<Vec<(String, Definition)>> {
+        self.with_db(|db| doc_links::resolve_doc_links(db, def, docs))
+    }
+
     /// Computes parameter information at the given position.
     pub fn signature_help(&self, position: FilePosition) -> Cancellable<Option<SignatureHelp>> {
         self.with_db(|db| signature_help::signature_help(db, position))
@@ -482,6 +506,30 @@ impl Analysis {
         self.with_db(|db| call_hierarchy::outgoing_calls(db, position))
     }
 
+    /// Computes type hierarchy candidates for the given file position.
+    pub fn type_hierarchy(
+        &self,
+        position: FilePosition,
+    ) -> Cancellable<Option<RangeInfo<Vec<NavigationTarget>>>> {
+        self.with_db(|db| type_hierarchy::type_hierarchy(db, position))
+    }
+
+    /// Computes the supertraits or implemented traits of the type at the given file position.
+    pub fn type_hierarchy_supertypes(
+        &self,
+        position: FilePosition,
+    ) -> Cancellable<Option<Vec<NavigationTarget>>> {
+        self.with_db(|db| type_hierarchy::supertypes(db, position))
+    }
+
+    /// Computes the implementing types of the trait at the given file position.
+    pub fn type_hierarchy_subtypes(
+        &self,
+        position: FilePosition,
+    ) -> Cancellable<Option<Vec<NavigationTarget>>> {
+        self.with_db(|db| type_hierarchy::subtypes(db, position))
+    }
+
     /// Returns a `mod name;` declaration which created the current module.
     pub fn parent_module(&self, position: FilePosition) -> Cancellable<Vec<NavigationTarget>> {
         self.with_db(|db| parent_module::parent_module(db, position))
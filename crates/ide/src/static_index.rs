@@ -112,10 +112,13 @@ impl StaticIndex<'_> {
                     closure_return_type_hints: crate::ClosureReturnTypeHints::WithBlock,
                     lifetime_elision_hints: crate::LifetimeElisionHints::Never,
                     reborrow_hints: crate::ReborrowHints::Never,
+                    adjustment_hints: false,
+                    discriminant_hints: crate::DiscriminantHints::Never,
                     hide_named_constructor_hints: false,
                     hide_closure_initialization_hints: false,
                     param_names_for_lifetime_elision_hints: false,
                     binding_mode_hints: false,
+                    implicit_drop_hints: false,
                     max_length: Some(25),
                     closing_brace_hints_min_lines: Some(25),
                 },
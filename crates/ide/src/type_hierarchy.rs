@@ -0,0 +1,242 @@
+//! Entry point for type-hierarchy
+
+use hir::{Adt, Impl, Semantics, Trait};
+use ide_db::{
+    defs::{Definition, NameClass, NameRefClass},
+    helpers::pick_best_token,
+    RootDatabase,
+};
+use itertools::Itertools;
+use syntax::{ast, AstNode, SyntaxKind::*};
+
+use crate::{FilePosition, NavigationTarget, RangeInfo, TryToNav};
+
+/// A node of the type hierarchy, either a trait or a nominal type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum HierarchyItem {
+    Trait(Trait),
+    Adt(Adt),
+}
+
+// Feature: Type Hierarchy
+//
+// Shows the supertraits and implementing types of a trait, and the traits implemented by a
+// struct, enum or union.
+//
+// |===
+// | Editor  | Action Name
+//
+// | VS Code | **Show Type Hierarchy**
+// |===
+pub(crate) fn type_hierarchy(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let sema = Semantics::new(db);
+    let RangeInfo { range, info: items } = items_at(&sema, position)?;
+    let navs = items.into_iter().filter_map(|item| to_nav(db, item)).collect();
+    Some(RangeInfo { range, info: navs })
+}
+
+/// Computes the supertypes of the item at the given position: the supertraits of a trait, or the
+/// traits implemented by a type.
+pub(crate) fn supertypes(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<Vec<NavigationTarget>> {
+    let sema = Semantics::new(db);
+    let RangeInfo { info: items, .. } = items_at(&sema, position)?;
+    let navs = items
+        .into_iter()
+        .flat_map(|item| match item {
+            HierarchyItem::Trait(trait_) => trait_.direct_supertraits(db),
+            HierarchyItem::Adt(adt) => Impl::all_for_type(db, adt.ty(db))
+                .into_iter()
+                .filter_map(|imp| imp.trait_(db))
+                .collect(),
+        })
+        .unique()
+        .filter_map(|trait_| to_nav(db, HierarchyItem::Trait(trait_)))
+        .collect();
+    Some(sorted(navs))
+}
+
+/// Computes the subtypes of the item at the given position: the types implementing a trait.
+///
+/// Implementations for types that aren't ADTs (`impl Trait for i32`, `impl<T> Trait for &T`)
+/// are represented by the impl block itself.
+pub(crate) fn subtypes(db: &RootDatabase, position: FilePosition) -> Option<Vec<NavigationTarget>> {
+    let sema = Semantics::new(db);
+    let RangeInfo { info: items, .. } = items_at(&sema, position)?;
+    let navs = items
+        .into_iter()
+        .filter_map(|item| match item {
+            HierarchyItem::Trait(trait_) => Some(Impl::all_for_trait(db, trait_)),
+            HierarchyItem::Adt(_) => None,
+        })
+        .flatten()
+        .filter_map(|imp| match imp.self_ty(db).as_adt() {
+            Some(adt) => to_nav(db, HierarchyItem::Adt(adt)),
+            None => imp.try_to_nav(db),
+        })
+        .unique()
+        .collect();
+    Some(sorted(navs))
+}
+
+fn items_at(
+    sema: &Semantics<'_, RootDatabase>,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<HierarchyItem>>> {
+    let file = sema.parse(position.file_id);
+    let token =
+        pick_best_token(file.syntax().token_at_offset(position.offset), |kind| match kind {
+            IDENT => 1,
+            _ => 0,
+        })?;
+    let range = token.text_range();
+    let items = sema
+        .descend_into_macros(token)
+        .into_iter()
+        .filter_map(|token| token.parent().and_then(ast::NameLike::cast))
+        .filter_map(|node| match &node {
+            ast::NameLike::Name(name) => match NameClass::classify(sema, name)? {
+                NameClass::Definition(def) => Some(def),
+                _ => None,
+            },
+            ast::NameLike::NameRef(name_ref) => match NameRefClass::classify(sema, name_ref)? {
+                NameRefClass::Definition(def) => Some(def),
+                _ => None,
+            },
+            ast::NameLike::Lifetime(_) => None,
+        })
+        .filter_map(|def| match def {
+            Definition::Trait(trait_) => Some(HierarchyItem::Trait(trait_)),
+            Definition::Adt(adt) => Some(HierarchyItem::Adt(adt)),
+            _ => None,
+        })
+        .unique()
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        return None;
+    }
+    Some(RangeInfo::new(range, items))
+}
+
+fn to_nav(db: &RootDatabase, item: HierarchyItem) -> Option<NavigationTarget> {
+    match item {
+        HierarchyItem::Trait(trait_) => Definition::Trait(trait_).try_to_nav(db),
+        HierarchyItem::Adt(adt) => adt.try_to_nav(db),
+    }
+}
+
+// Impls are stored in hash maps, order the results by location to keep them stable.
+fn sorted(mut navs: Vec<NavigationTarget>) -> Vec<NavigationTarget> {
+    navs.sort_by_key(|nav| (nav.file_id, nav.full_range.start()));
+    navs
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use ide_db::base_db::FilePosition;
+    use itertools::Itertools;
+
+    use crate::fixture;
+
+    fn check_hierarchy(
+        ra_fixture: &str,
+        expected: Expect,
+        expected_supertypes: Expect,
+        expected_subtypes: Expect,
+    ) {
+        let (analysis, pos) = fixture::position(ra_fixture);
+
+        let mut navs = analysis.type_hierarchy(pos).unwrap().unwrap().info;
+        assert_eq!(navs.len(), 1);
+        let nav = navs.pop().unwrap();
+        expected.assert_eq(&nav.debug_render());
+
+        let item_pos =
+            FilePosition { file_id: nav.file_id, offset: nav.focus_or_full_range().start() };
+        let supertypes = analysis.type_hierarchy_supertypes(item_pos).unwrap().unwrap();
+        expected_supertypes
+            .assert_eq(&supertypes.into_iter().map(|nav| nav.debug_render()).join("\n"));
+
+        let subtypes = analysis.type_hierarchy_subtypes(item_pos).unwrap().unwrap();
+        expected_subtypes.assert_eq(&subtypes.into_iter().map(|nav| nav.debug_render()).join("\n"));
+    }
+
+    #[test]
+    fn test_type_hierarchy_on_trait_def() {
+        check_hierarchy(
+            r#"
+trait Super {}
+trait Other {}
+trait Tr$0ait: Super + Other {}
+struct S;
+enum E {}
+impl Super for S {}
+impl Other for S {}
+impl Trait for S {}
+impl Trait for E {}
+"#,
+            expect![["Trait Trait FileId(0) 30..59 36..41"]],
+            expect![[r#"
+                Super Trait FileId(0) 0..14 6..11
+                Other Trait FileId(0) 15..29 21..26"#]],
+            expect![[r#"
+                S Struct FileId(0) 60..69 67..68
+                E Enum FileId(0) 70..79 75..76"#]],
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy_on_trait_ref() {
+        check_hierarchy(
+            r#"
+trait Trait {}
+struct S;
+impl Tra$0it for S {}
+impl Trait for i32 {}
+impl<T> Trait for &T {}
+"#,
+            expect![["Trait Trait FileId(0) 0..14 6..11"]],
+            expect![[]],
+            expect![[r#"
+                S Struct FileId(0) 15..24 22..23
+                impl Impl FileId(0) 45..66 60..63
+                impl Impl FileId(0) 67..90 85..87"#]],
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy_on_struct() {
+        check_hierarchy(
+            r#"
+trait A {}
+trait B {}
+struct S$0;
+impl S {}
+impl A for S {}
+impl B for S {}
+impl A for &S {}
+"#,
+            expect![["S Struct FileId(0) 22..31 29..30"]],
+            expect![[r#"
+                A Trait FileId(0) 0..10 6..7
+                B Trait FileId(0) 11..21 17..18"#]],
+            expect![[]],
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy_on_non_type() {
+        let (analysis, pos) = fixture::position(
+            r#"
+fn fo$0o() {}
+"#,
+        );
+        assert!(analysis.type_hierarchy(pos).unwrap().is_none());
+    }
+}
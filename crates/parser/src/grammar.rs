@@ -90,6 +90,7 @@ pub(crate) mod entry {
         pub(crate) fn source_file(p: &mut Parser<'_>) {
             let m = p.start();
             p.eat(SHEBANG);
+            p.eat(FRONTMATTER);
             items::mod_contents(p, false);
             m.complete(p, SOURCE_FILE);
         }
@@ -35,6 +35,22 @@ impl<'a> LexedStr<'a> {
             conv.offset = shebang_len;
         };
 
+        // The frontmatter of a cargo script can only be preceded by a shebang and whitespace.
+        let rest = &text[conv.offset..];
+        let whitespace_len = rest.len() - rest.trim_start().len();
+        let at_line_start =
+            conv.offset + whitespace_len == 0 || rest[..whitespace_len].ends_with('\n');
+        if let Some((len, terminated)) =
+            frontmatter_len(&rest[whitespace_len..]).filter(|_| at_line_start)
+        {
+            if whitespace_len > 0 {
+                conv.push(WHITESPACE, whitespace_len, None);
+            }
+            let err =
+                if terminated { None } else { Some("Missing closing `---` of the frontmatter") };
+            conv.push(FRONTMATTER, len, err);
+        }
+
         for token in rustc_lexer::tokenize(&text[conv.offset..]) {
             let token_text = &text[conv.offset..][..token.len];
 
@@ -122,6 +138,34 @@ impl<'a> LexedStr<'a> {
     }
 }
 
+/// Returns the length of the frontmatter at the start of `text` and whether it is terminated. The
+/// frontmatter embeds the manifest of a cargo script:
+///
+/// ```text
+/// ---cargo
+/// [dependencies]
+/// regex = "1"
+/// ---
+/// ```
+fn frontmatter_len(text: &str) -> Option<(usize, bool)> {
+    let dashes = text.len() - text.trim_start_matches('-').len();
+    if dashes < 3 {
+        return None;
+    }
+    let mut offset = text.find('\n').map_or(text.len(), |it| it + 1);
+    while offset < text.len() {
+        let line_end = text[offset..].find('\n').map_or(text.len(), |it| offset + it + 1);
+        let line = &text[offset..line_end];
+        // The closing fence has as many dashes as the opening one, the newline after it is left
+        // to the whitespace.
+        if line.starts_with(&text[..dashes]) && line[dashes..].trim().is_empty() {
+            return Some((offset + line.trim_end().len(), true));
+        }
+        offset = line_end;
+    }
+    Some((text.len(), false))
+}
+
 struct Converter<'a> {
     res: LexedStr<'a>,
     offset: usize,
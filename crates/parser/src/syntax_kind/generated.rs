@@ -121,6 +121,7 @@ pub enum SyntaxKind {
     LIFETIME_IDENT,
     COMMENT,
     SHEBANG,
+    FRONTMATTER,
     SOURCE_FILE,
     STRUCT,
     UNION,
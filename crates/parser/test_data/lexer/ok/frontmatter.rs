@@ -0,0 +1,7 @@
+#!/usr/bin/env cargo
+---cargo
+[dependencies]
+regex = "1"
+---
+
+fn main() {}
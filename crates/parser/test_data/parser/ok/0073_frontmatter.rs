@@ -0,0 +1,5 @@
+---
+[dependencies]
+---
+
+fn main() {}
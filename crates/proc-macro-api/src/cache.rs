@@ -0,0 +1,326 @@
+//! A content-addressed cache of proc-macro expansions.
+//!
+//! Expanding a macro requires a round trip to the proc-macro server, which is wasted work when
+//! the very same input was expanded before, e.g. after the expansion has been evicted from the
+//! LRU of the database, or after a restart when the cache is persisted to disk.
+//!
+//! Entries are keyed by a fingerprint of everything that is sent to the server, with the path of
+//! the dylib replaced by a hash of its contents, along with the server binary, the protocol
+//! version and the client version. This is only sound for deterministic macros, so the cache is
+//! opt-in and macros that read files or the environment can be excluded from it.
+
+use std::{
+    collections::HashMap,
+    fs, io, process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use paths::{AbsPath, AbsPathBuf};
+use serde::Deserialize;
+use tt::Subtree;
+
+use crate::msg::{ExpandMacro, FlatTree};
+
+/// How many expansions are kept in memory. The least recently used ones are evicted first, and
+/// can still be found on disk if the cache is persisted.
+const DEFAULT_CAPACITY: usize = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpansionCacheConfig {
+    /// Names of the macros whose expansions must never be cached.
+    pub exclude: Vec<String>,
+    /// Directory to persist the expansions to, so that they survive restarts.
+    pub directory: Option<AbsPathBuf>,
+    /// The version of the client, expansions persisted by other versions are ignored.
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Fingerprint(u128);
+
+#[derive(Debug)]
+pub(crate) struct ExpansionCache {
+    config: ExpansionCacheConfig,
+    /// Hash of what, besides the request, determines the expansion: the server binary, the
+    /// protocol version and the client version.
+    salt: u128,
+    capacity: usize,
+    entries: Mutex<Entries>,
+    /// Content hashes of the dylibs, along with the modification time and size they were computed
+    /// for, so that a dylib is only read again once it changed.
+    dylib_hashes: Mutex<HashMap<AbsPathBuf, (SystemTime, u64, u128)>>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// The expansions along with the tick they were last used at.
+    map: HashMap<Fingerprint, (Subtree, u64)>,
+    tick: u64,
+}
+
+impl Entries {
+    fn get(&mut self, key: Fingerprint) -> Option<Subtree> {
+        self.tick += 1;
+        let (subtree, last_used) = self.map.get_mut(&key)?;
+        *last_used = self.tick;
+        Some(subtree.clone())
+    }
+
+    fn insert(&mut self, key: Fingerprint, subtree: Subtree, capacity: usize) {
+        self.tick += 1;
+        self.map.insert(key, (subtree, self.tick));
+        while self.map.len() > capacity {
+            let lru = self.map.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(k, _)| *k);
+            match lru {
+                Some(it) => self.map.remove(&it),
+                None => break,
+            };
+        }
+    }
+}
+
+impl ExpansionCache {
+    /// Creates a cache for the expansions of the server at `server_path`, which speaks
+    /// `protocol_version`.
+    pub(crate) fn new(
+        config: ExpansionCacheConfig,
+        server_path: &AbsPath,
+        protocol_version: u32,
+    ) -> io::Result<ExpansionCache> {
+        let mut salt = hash_file(server_path)?.to_le_bytes().to_vec();
+        salt.extend(protocol_version.to_le_bytes());
+        salt.extend(config.version.as_bytes());
+        Ok(ExpansionCache::with_salt(config, stable_hash(&salt), DEFAULT_CAPACITY))
+    }
+
+    fn with_salt(config: ExpansionCacheConfig, salt: u128, capacity: usize) -> ExpansionCache {
+        ExpansionCache {
+            config,
+            salt,
+            capacity,
+            entries: Mutex::default(),
+            dylib_hashes: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn is_excluded(&self, macro_name: &str) -> bool {
+        self.config.exclude.iter().any(|it| it == macro_name)
+    }
+
+    pub(crate) fn get(&self, key: Fingerprint) -> Option<Subtree> {
+        if let Some(it) = self.entries.lock().unwrap_or_else(|e| e.into_inner()).get(key) {
+            return Some(it);
+        }
+        let path = self.entry_path(key)?;
+        let text = fs::read_to_string(&path).ok()?;
+        let mut deserializer = serde_json::Deserializer::from_str(&text);
+        deserializer.disable_recursion_limit();
+        let tree = match FlatTree::deserialize(&mut deserializer) {
+            Ok(it) => it,
+            Err(err) => {
+                tracing::warn!("corrupt proc-macro cache entry {}: {}", path.display(), err);
+                return None;
+            }
+        };
+        let subtree = tree.to_subtree();
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            key,
+            subtree.clone(),
+            self.capacity,
+        );
+        Some(subtree)
+    }
+
+    pub(crate) fn insert(&self, key: Fingerprint, subtree: &Subtree) {
+        if let Some(path) = self.entry_path(key) {
+            if let Err(err) = write_entry(&path, &FlatTree::new(subtree)) {
+                tracing::warn!(
+                    "failed to persist proc-macro expansion to {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            key,
+            subtree.clone(),
+            self.capacity,
+        );
+    }
+
+    /// Returns the content hash of the dylib at `path`, which is only recomputed when the
+    /// modification time or the size of the file changed.
+    pub(crate) fn dylib_hash(&self, path: &AbsPath) -> io::Result<u128> {
+        let metadata = fs::metadata(path)?;
+        let (modified, len) = (metadata.modified()?, metadata.len());
+        let mut hashes = self.dylib_hashes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&(m, l, hash)) = hashes.get(path) {
+            if (m, l) == (modified, len) {
+                return Ok(hash);
+            }
+        }
+        let hash = hash_file(path)?;
+        hashes.insert(path.to_path_buf(), (modified, len, hash));
+        Ok(hash)
+    }
+
+    fn entry_path(&self, key: Fingerprint) -> Option<AbsPathBuf> {
+        let dir = self.config.directory.as_ref()?;
+        Some(dir.join(format!("{:032x}.json", key.0)))
+    }
+
+    /// Computes the cache key of an expansion request for a dylib with the given content hash,
+    /// or `None` if the request can't be serialized, in which case it must not be cached.
+    pub(crate) fn fingerprint(&self, dylib_hash: u128, task: &ExpandMacro) -> Option<Fingerprint> {
+        let mut env = task.env.iter().collect::<Vec<_>>();
+        env.sort();
+        let key = (&task.macro_name, &task.macro_body, &task.attributes, env, &task.current_dir);
+        let mut bytes = self.salt.to_le_bytes().to_vec();
+        bytes.extend(dylib_hash.to_le_bytes());
+        if let Err(err) = serde_json::to_writer(&mut bytes, &key) {
+            tracing::warn!("failed to fingerprint proc-macro expansion: {}", err);
+            return None;
+        }
+        Some(Fingerprint(stable_hash(&bytes)))
+    }
+}
+
+/// FNV-1a, as the hashes end up in the names of the persisted entries, they must not change
+/// across platforms and Rust versions like the ones of `DefaultHasher` may.
+fn stable_hash(bytes: &[u8]) -> u128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| (hash ^ byte as u128).wrapping_mul(PRIME))
+}
+
+fn hash_file(path: &AbsPath) -> io::Result<u128> {
+    Ok(stable_hash(&fs::read(path)?))
+}
+
+fn write_entry(path: &AbsPath, tree: &FlatTree) -> io::Result<()> {
+    static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write to a temporary file first, so that readers never see a partial entry. It's unique to
+    // this write, as other threads and processes may be writing the same entry.
+    let tmp = path.as_ref().with_extension(format!(
+        "{}-{}.tmp",
+        process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp, serde_json::to_vec(tree)?)?;
+    fs::rename(&tmp, path).map_err(|err| {
+        let _ = fs::remove_file(&tmp);
+        err
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tt::{Ident, Leaf, Subtree, TokenId, TokenTree};
+
+    use super::*;
+
+    fn task(ident: &str, lib: &str, env: &[(&str, &str)]) -> ExpandMacro {
+        let mut subtree = Subtree::default();
+        subtree
+            .token_trees
+            .push(TokenTree::Leaf(Leaf::Ident(Ident { text: ident.into(), id: TokenId(0) })));
+        ExpandMacro {
+            macro_body: FlatTree::new(&subtree),
+            macro_name: "derive_foo".to_string(),
+            attributes: None,
+            lib: lib.into(),
+            env: env.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+            current_dir: None,
+        }
+    }
+
+    fn cache(salt: u128) -> ExpansionCache {
+        ExpansionCache::with_salt(ExpansionCacheConfig::default(), salt, DEFAULT_CAPACITY)
+    }
+
+    #[test]
+    fn fingerprint_ignores_dylib_path_and_env_order() {
+        let a = task("Foo", "/a/libfoo.so", &[("A", "1"), ("B", "2")]);
+        let b = task("Foo", "/b/libfoo.so", &[("B", "2"), ("A", "1")]);
+        assert_eq!(cache(0).fingerprint(0, &a), cache(0).fingerprint(0, &b));
+    }
+
+    #[test]
+    fn fingerprint_depends_on_input() {
+        let a = task("Foo", "/a/libfoo.so", &[]);
+        let cache = cache(0);
+        assert_ne!(cache.fingerprint(0, &a), cache.fingerprint(1, &a));
+        assert_ne!(
+            cache.fingerprint(0, &a),
+            cache.fingerprint(0, &task("Bar", "/a/libfoo.so", &[]))
+        );
+        assert_ne!(
+            cache.fingerprint(0, &a),
+            cache.fingerprint(0, &task("Foo", "/a/libfoo.so", &[("A", "1")]))
+        );
+    }
+
+    #[test]
+    fn fingerprint_depends_on_salt() {
+        let a = task("Foo", "/a/libfoo.so", &[]);
+        assert_ne!(cache(0).fingerprint(0, &a), cache(1).fingerprint(0, &a));
+    }
+
+    #[test]
+    fn stable_hash_is_stable() {
+        assert_eq!(stable_hash(b""), 0x6c62272e07bb014262b821756295c58d);
+        assert_eq!(stable_hash(b"a"), 0xd228cb696f1a8caf78912b704e4a8964);
+    }
+
+    fn subtree(ident: &str) -> Subtree {
+        task(ident, "/a/libfoo.so", &[]).macro_body.to_subtree()
+    }
+
+    fn key(ident: &str) -> Fingerprint {
+        cache(0).fingerprint(0, &task(ident, "/a/libfoo.so", &[])).unwrap()
+    }
+
+    #[test]
+    fn get_returns_inserted_expansions() {
+        let cache = cache(0);
+        assert_eq!(cache.get(key("Foo")), None);
+        cache.insert(key("Foo"), &subtree("foo"));
+        assert_eq!(cache.get(key("Foo")), Some(subtree("foo")));
+        assert_eq!(cache.get(key("Bar")), None);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_expansions() {
+        let cache = ExpansionCache::with_salt(ExpansionCacheConfig::default(), 0, 2);
+        cache.insert(key("A"), &subtree("a"));
+        cache.insert(key("B"), &subtree("b"));
+        assert!(cache.get(key("A")).is_some());
+        cache.insert(key("C"), &subtree("c"));
+        assert_eq!(cache.get(key("B")), None);
+        assert_eq!(cache.get(key("A")), Some(subtree("a")));
+        assert_eq!(cache.get(key("C")), Some(subtree("c")));
+    }
+
+    #[test]
+    fn expansions_persist_across_caches() {
+        let dir =
+            std::env::temp_dir().join(format!("ra-proc-macro-cache-test-{}", std::process::id()));
+        let config = ExpansionCacheConfig {
+            directory: Some(AbsPathBuf::assert(dir.clone())),
+            ..ExpansionCacheConfig::default()
+        };
+        let persisted = || ExpansionCache::with_salt(config.clone(), 0, DEFAULT_CAPACITY);
+        persisted().insert(key("Foo"), &subtree("foo"));
+        let reloaded = persisted().get(key("Foo"));
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(reloaded, Some(subtree("foo")));
+    }
+}
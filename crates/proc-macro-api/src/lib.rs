@@ -8,24 +8,28 @@
 #![warn(rust_2018_idioms, unused_lifetimes, semicolon_in_expressions_from_macros)]
 
 pub mod msg;
+mod cache;
 mod process;
 mod version;
 
 use paths::AbsPathBuf;
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fmt, io,
-    sync::{Arc, Mutex},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
 };
 
 use serde::{Deserialize, Serialize};
 use tt::Subtree;
 
 use crate::{
+    cache::ExpansionCache,
     msg::{ExpandMacro, FlatTree, PanicMessage},
     process::ProcMacroProcessSrv,
 };
 
+pub use cache::ExpansionCacheConfig;
 pub use version::{read_dylib_info, read_version, RustCInfo};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -35,17 +39,34 @@ pub enum ProcMacroKind {
     Attr,
 }
 
-/// A handle to an external process which load dylibs with macros (.so or .dll)
-/// and runs actual macro expansion functions.
+/// A handle to external processes which load dylibs with macros (.so or .dll)
+/// and run actual macro expansion functions.
 #[derive(Debug)]
 pub struct ProcMacroServer {
-    /// Currently, the proc macro process expands all procedural macros sequentially.
+    /// A proc macro process expands procedural macros sequentially, as expansions may change the
+    /// environment and working directory of the process.
     ///
-    /// That means that concurrent salsa requests may block each other when expanding proc macros,
-    /// which is unfortunate, but simple and good enough for the time being.
-    ///
-    /// Therefore, we just wrap the `ProcMacroProcessSrv` in a mutex here.
-    process: Arc<Mutex<ProcMacroProcessSrv>>,
+    /// So that concurrent salsa requests don't block each other when expanding proc macros, we
+    /// keep a pool of processes, and spawn more of them as long as they are all busy.
+    process: Arc<ProcessPool>,
+    cache: Option<Arc<ExpansionCache>>,
+}
+
+#[derive(Debug)]
+struct ProcessPool {
+    process_path: AbsPathBuf,
+    args: Vec<OsString>,
+    /// The protocol version of the first process, the others are spawned from the same binary.
+    version: u32,
+    max_processes: usize,
+    state: Mutex<PoolState>,
+    idle_changed: Condvar,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    idle: Vec<ProcMacroProcessSrv>,
+    spawned: usize,
 }
 
 pub struct MacroDylib {
@@ -70,14 +91,16 @@ impl MacroDylib {
 
 /// A handle to a specific macro (a `#[proc_macro]` annotated function).
 ///
-/// It exists withing a context of a specific [`ProcMacroProcess`] -- currently
-/// we share a single expander process for all macros.
+/// It exists withing a context of a specific [`ProcMacroServer`] -- all macros
+/// share its pool of expander processes.
 #[derive(Debug, Clone)]
 pub struct ProcMacro {
-    process: Arc<Mutex<ProcMacroProcessSrv>>,
+    process: Arc<ProcessPool>,
     dylib_path: AbsPathBuf,
     name: String,
     kind: ProcMacroKind,
+    /// The expansion cache along with the content hash of the dylib, if caching is enabled.
+    cache: Option<(Arc<ExpansionCache>, u128)>,
 }
 
 impl Eq for ProcMacro {}
@@ -116,14 +139,40 @@ impl ProcMacroServer {
         process_path: AbsPathBuf,
         args: impl IntoIterator<Item = impl AsRef<OsStr>>,
     ) -> io::Result<ProcMacroServer> {
-        let process = ProcMacroProcessSrv::run(process_path, args)?;
-        Ok(ProcMacroServer { process: Arc::new(Mutex::new(process)) })
+        let args: Vec<OsString> = args.into_iter().map(|s| s.as_ref().into()).collect();
+        // Spawn the first process eagerly, to report a broken server right away.
+        let process = ProcMacroProcessSrv::run(process_path.clone(), &args)?;
+        let max_processes = thread::available_parallelism().map_or(1, |it| it.get()).min(4);
+        let pool = ProcessPool {
+            process_path,
+            args,
+            version: process.version(),
+            max_processes,
+            state: Mutex::new(PoolState { idle: vec![process], spawned: 1 }),
+            idle_changed: Condvar::new(),
+        };
+        Ok(ProcMacroServer { process: Arc::new(pool), cache: None })
+    }
+
+    /// Caches the expansions of the macros loaded afterwards.
+    pub fn with_expansion_cache(mut self, config: ExpansionCacheConfig) -> ProcMacroServer {
+        match ExpansionCache::new(config, &self.process.process_path, self.process.version) {
+            Ok(cache) => self.cache = Some(Arc::new(cache)),
+            Err(err) => tracing::warn!("not caching proc-macro expansions: {}", err),
+        }
+        self
     }
 
     pub fn load_dylib(&self, dylib: MacroDylib) -> Result<Vec<ProcMacro>, ServerError> {
         let _p = profile::span("ProcMacroClient::by_dylib_path");
-        let macros =
-            self.process.lock().unwrap_or_else(|e| e.into_inner()).find_proc_macros(&dylib.path)?;
+        let macros = self.process.with_process(|it| it.find_proc_macros(&dylib.path))?;
+        let cache = self.cache.as_ref().and_then(|cache| match cache.dylib_hash(&dylib.path) {
+            Ok(hash) => Some((cache.clone(), hash)),
+            Err(err) => {
+                tracing::warn!("not caching expansions of {}: {}", dylib.path.display(), err);
+                None
+            }
+        });
 
         match macros {
             Ok(macros) => Ok(macros
@@ -133,6 +182,7 @@ impl ProcMacroServer {
                     name,
                     kind,
                     dylib_path: dylib.path.clone(),
+                    cache: cache.clone(),
                 })
                 .collect()),
             Err(message) => Err(ServerError { message, io: None }),
@@ -169,13 +219,88 @@ impl ProcMacro {
             current_dir,
         };
 
-        let request = msg::Request::ExpandMacro(task);
-        let response = self.process.lock().unwrap_or_else(|e| e.into_inner()).send_task(request)?;
-        match response {
-            msg::Response::ExpandMacro(it) => Ok(it.map(FlatTree::to_subtree)),
-            msg::Response::ListMacros { .. } => {
-                Err(ServerError { message: "unexpected response".to_string(), io: None })
+        let cache = match &self.cache {
+            Some((cache, dylib_hash)) if !cache.is_excluded(&self.name) => {
+                match cache.fingerprint(*dylib_hash, &task) {
+                    Some(key) => {
+                        if let Some(subtree) = cache.get(key) {
+                            return Ok(Ok(subtree));
+                        }
+                        Some((cache, key))
+                    }
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+
+        let response = self.process.with_process(|it| it.expand(task))?;
+        let expansion = match response {
+            msg::Response::ExpandMacro(it) => it.map(FlatTree::to_subtree),
+            _ => return Err(ServerError { message: "unexpected response".to_string(), io: None }),
+        };
+        // Panics may be caused by the environment, e.g. a missing file, so don't cache them.
+        if let (Some((cache, key)), Ok(subtree)) = (cache, &expansion) {
+            cache.insert(key, subtree);
+        }
+        Ok(expansion)
+    }
+}
+
+impl ProcessPool {
+    /// Runs `f` with an idle process, spawning a new one if all of them are busy and the pool
+    /// isn't full yet.
+    ///
+    /// Processes that failed to answer are likely dead or out of sync, they are dropped and
+    /// replaced on the next request. Panicking macros are not a failure of the process.
+    fn with_process<T>(
+        &self,
+        f: impl FnOnce(&mut ProcMacroProcessSrv) -> Result<T, ServerError>,
+    ) -> Result<T, ServerError> {
+        let mut process = self.checkout()?;
+        let res = f(&mut process);
+        let mut state = self.lock_state();
+        match res {
+            Ok(_) => state.idle.push(process),
+            Err(_) => state.spawned -= 1,
+        }
+        drop(state);
+        self.idle_changed.notify_one();
+        res
+    }
+
+    fn checkout(&self) -> Result<ProcMacroProcessSrv, ServerError> {
+        let mut state = self.lock_state();
+        loop {
+            if let Some(process) = state.idle.pop() {
+                return Ok(process);
             }
+            if state.spawned < self.max_processes {
+                state.spawned += 1;
+                drop(state);
+                return match ProcMacroProcessSrv::run(self.process_path.clone(), &self.args) {
+                    Ok(process) => {
+                        tracing::info!(
+                            "spawned another proc-macro server, protocol version {}",
+                            process.version()
+                        );
+                        Ok(process)
+                    }
+                    Err(err) => {
+                        self.lock_state().spawned -= 1;
+                        self.idle_changed.notify_one();
+                        Err(ServerError {
+                            message: "failed to spawn proc-macro server".into(),
+                            io: Some(err),
+                        })
+                    }
+                };
+            }
+            state = self.idle_changed.wait(state).unwrap_or_else(|e| e.into_inner());
         }
     }
+
+    fn lock_state(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
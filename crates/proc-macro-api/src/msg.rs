@@ -12,16 +12,30 @@ use crate::ProcMacroKind;
 
 pub use crate::msg::flat::FlatTree;
 
+/// Servers that predate the version check, they fail on any request they don't know about.
+pub const NO_VERSION_CHECK_VERSION: u32 = 0;
+/// The version of the protocol spoken by this client and server. Bump it whenever a request or
+/// response is added, and only send those to servers that report a recent enough version.
+pub const CURRENT_API_VERSION: u32 = 1;
+/// Since this version, servers load a dylib when they're first asked to expand one of its macros.
+/// Older ones expect its macros to be listed beforehand.
+pub const LAZY_DYLIB_LOADING_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Request {
-    ListMacros { dylib_path: PathBuf },
+    ListMacros {
+        dylib_path: PathBuf,
+    },
     ExpandMacro(ExpandMacro),
+    /// Asks the server for the protocol version it speaks, answered since version 1.
+    ApiVersionCheck {},
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Response {
     ListMacros(Result<Vec<(String, ProcMacroKind)>, String>),
     ExpandMacro(Result<FlatTree, PanicMessage>),
+    ApiVersionCheck(u32),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
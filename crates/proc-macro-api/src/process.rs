@@ -1,8 +1,10 @@
 //! Handle process life-time and message passing for proc-macro client
 
 use std::{
+    collections::HashSet,
     ffi::{OsStr, OsString},
     io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
     process::{Child, ChildStdin, ChildStdout, Command, Stdio},
 };
 
@@ -10,7 +12,10 @@ use paths::{AbsPath, AbsPathBuf};
 use stdx::JodChild;
 
 use crate::{
-    msg::{Message, Request, Response},
+    msg::{
+        ExpandMacro, Message, Request, Response, CURRENT_API_VERSION, LAZY_DYLIB_LOADING_VERSION,
+        NO_VERSION_CHECK_VERSION,
+    },
     ProcMacroKind, ServerError,
 };
 
@@ -19,6 +24,10 @@ pub(crate) struct ProcMacroProcessSrv {
     _process: Process,
     stdin: ChildStdin,
     stdout: BufReader<ChildStdout>,
+    /// The protocol version both sides understand.
+    version: u32,
+    /// The dylibs whose macros were listed by this process.
+    listed_dylibs: HashSet<PathBuf>,
 }
 
 impl ProcMacroProcessSrv {
@@ -26,28 +35,83 @@ impl ProcMacroProcessSrv {
         process_path: AbsPathBuf,
         args: impl IntoIterator<Item = impl AsRef<OsStr>>,
     ) -> io::Result<ProcMacroProcessSrv> {
-        let mut process = Process::run(process_path, args)?;
-        let (stdin, stdout) = process.stdio().expect("couldn't access child stdio");
+        let args: Vec<OsString> = args.into_iter().map(|s| s.as_ref().into()).collect();
+        let create_srv = || {
+            let mut process = Process::run(process_path.clone(), &args)?;
+            let (stdin, stdout) = process.stdio().expect("couldn't access child stdio");
+            io::Result::Ok(ProcMacroProcessSrv {
+                _process: process,
+                stdin,
+                stdout,
+                version: NO_VERSION_CHECK_VERSION,
+                listed_dylibs: HashSet::default(),
+            })
+        };
+
+        let mut srv = create_srv()?;
+        match srv.version_check() {
+            Ok(version) => {
+                tracing::info!("proc-macro server speaks protocol version {}", version);
+                // Newer servers still answer the requests of older versions.
+                srv.version = version.min(CURRENT_API_VERSION);
+                Ok(srv)
+            }
+            // Old servers exit on requests they can't parse, start a fresh one.
+            Err(err) if is_hang_up(&err) => {
+                tracing::info!(%err, "proc-macro server predates the version check");
+                create_srv()
+            }
+            Err(err) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("proc-macro server version check failed: {}", err),
+            )),
+        }
+    }
 
-        let srv = ProcMacroProcessSrv { _process: process, stdin, stdout };
+    pub(crate) fn version(&self) -> u32 {
+        self.version
+    }
 
-        Ok(srv)
+    fn version_check(&mut self) -> Result<u32, ServerError> {
+        let response = self.send_task(Request::ApiVersionCheck {})?;
+        match response {
+            Response::ApiVersionCheck(version) => Ok(version),
+            _ => Err(ServerError { message: "unexpected response".to_string(), io: None }),
+        }
     }
 
     pub(crate) fn find_proc_macros(
         &mut self,
         dylib_path: &AbsPath,
     ) -> Result<Result<Vec<(String, ProcMacroKind)>, String>, ServerError> {
-        let request = Request::ListMacros { dylib_path: dylib_path.to_path_buf().into() };
+        self.list_macros(dylib_path.to_path_buf().into())
+    }
+
+    fn list_macros(
+        &mut self,
+        dylib_path: PathBuf,
+    ) -> Result<Result<Vec<(String, ProcMacroKind)>, String>, ServerError> {
+        let request = Request::ListMacros { dylib_path: dylib_path.clone() };
 
         let response = self.send_task(request)?;
 
         match response {
-            Response::ListMacros(it) => Ok(it),
-            Response::ExpandMacro { .. } => {
-                Err(ServerError { message: "unexpected response".to_string(), io: None })
+            Response::ListMacros(it) => {
+                self.listed_dylibs.insert(dylib_path);
+                Ok(it)
+            }
+            _ => Err(ServerError { message: "unexpected response".to_string(), io: None }),
+        }
+    }
+
+    pub(crate) fn expand(&mut self, task: ExpandMacro) -> Result<Response, ServerError> {
+        // The pool may hand the expansion to a process which hasn't seen the dylib yet.
+        if self.version < LAZY_DYLIB_LOADING_VERSION && !self.listed_dylibs.contains(&task.lib) {
+            if let Err(message) = self.list_macros(task.lib.clone())? {
+                return Err(ServerError { message, io: None });
             }
         }
+        self.send_task(Request::ExpandMacro(task))
     }
 
     pub(crate) fn send_task(&mut self, req: Request) -> Result<Response, ServerError> {
@@ -103,5 +167,18 @@ fn send_request(
         .map_err(|err| ServerError { message: "failed to write request".into(), io: Some(err) })?;
     let res = Response::read(&mut reader, buf)
         .map_err(|err| ServerError { message: "failed to read response".into(), io: Some(err) })?;
-    res.ok_or_else(|| ServerError { message: "server exited".into(), io: None })
+    res.ok_or_else(|| ServerError { message: SERVER_EXITED.into(), io: None })
+}
+
+const SERVER_EXITED: &str = "server exited";
+
+/// Whether the server went away instead of answering, like servers do on requests they can't
+/// parse.
+fn is_hang_up(err: &ServerError) -> bool {
+    match &err.io {
+        Some(err) => {
+            matches!(err.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::UnexpectedEof)
+        }
+        None => err.message == SERVER_EXITED,
+    }
 }
@@ -46,10 +46,11 @@ const EXPANDER_STACK_SIZE: usize = 8 * 1024 * 1024;
 
 impl ProcMacroSrv {
     pub fn expand(&mut self, task: ExpandMacro) -> Result<FlatTree, PanicMessage> {
-        let expander = self.expander(task.lib.as_ref()).map_err(|err| {
-            debug_assert!(false, "should list macros before asking to expand");
-            PanicMessage(format!("failed to load macro: {}", err))
-        })?;
+        // The client spreads expansions over several servers, so this may be the first time this
+        // server sees the dylib.
+        let expander = self
+            .expander(task.lib.as_ref())
+            .map_err(|err| PanicMessage(format!("failed to load macro: {}", err)))?;
 
         let prev_env = EnvSnapshot::new();
         for (k, v) in &task.env {
@@ -39,7 +39,7 @@ pub(crate) struct BuildScriptOutput {
 }
 
 impl WorkspaceBuildScripts {
-    fn build_command(config: &CargoConfig) -> Command {
+    fn build_command(config: &CargoConfig, workspace: &CargoWorkspace) -> Command {
         if let Some([program, args @ ..]) = config.run_build_script_command.as_deref() {
             let mut cmd = Command::new(program);
             cmd.args(args);
@@ -56,6 +56,11 @@ impl WorkspaceBuildScripts {
         // flag below.
         cmd.arg("--all-targets");
 
+        if let Some(script) = workspace.cargo_script() {
+            cmd.arg("-Zscript").arg("--manifest-path").arg(script.as_os_str());
+            cmd.env("RUSTC_BOOTSTRAP", "1");
+        }
+
         if let Some(target) = &config.target {
             cmd.args(&["--target", target]);
         }
@@ -86,13 +91,13 @@ impl WorkspaceBuildScripts {
     ) -> io::Result<WorkspaceBuildScripts> {
         const RUST_1_62: Version = Version::new(1, 62, 0);
 
-        match Self::run_(Self::build_command(config), config, workspace, progress) {
+        match Self::run_(Self::build_command(config, workspace), config, workspace, progress) {
             Ok(WorkspaceBuildScripts { error: Some(error), .. })
                 if toolchain.as_ref().map_or(false, |it| *it >= RUST_1_62) =>
             {
                 // building build scripts failed, attempt to build with --keep-going so
                 // that we potentially get more build data
-                let mut cmd = Self::build_command(config);
+                let mut cmd = Self::build_command(config, workspace);
                 cmd.args(&["-Z", "unstable-options", "--keep-going"]).env("RUSTC_BOOTSTRAP", "1");
                 let mut res = Self::run_(cmd, config, workspace, progress)?;
                 res.error = Some(error);
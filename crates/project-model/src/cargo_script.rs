@@ -0,0 +1,48 @@
+//! Detection of cargo scripts, single-file packages with their manifest embedded in the source.
+//!
+//! The manifest is either in a frontmatter or, in the older syntax, in a `cargo` code block of the
+//! module docs:
+//!
+//! ```text
+//! #!/usr/bin/env cargo
+//! ---cargo
+//! [dependencies]
+//! regex = "1"
+//! ---
+//! ```
+//!
+//! ```text
+//! #!/usr/bin/env cargo
+//! //! ```cargo
+//! //! [dependencies]
+//! //! regex = "1"
+//! //! ```
+//! ```
+//!
+//! Cargo itself parses the manifest, we only need to know that there is one to treat the file as a
+//! package instead of a detached file.
+
+pub(crate) fn has_embedded_manifest(text: &str) -> bool {
+    let mut lines = text.lines().peekable();
+    if let Some(first) = lines.peek() {
+        // `#![attr]` is an inner attribute, not a shebang.
+        if first.starts_with("#!") && !first["#!".len()..].trim_start().starts_with('[') {
+            lines.next();
+        }
+    }
+    let mut lines = lines.skip_while(|line| line.trim().is_empty());
+    let first = match lines.next() {
+        Some(it) => it,
+        None => return false,
+    };
+    if let Some(rest) = first.strip_prefix("---") {
+        let info = rest.trim_start_matches('-').trim();
+        return info.is_empty() || info == "cargo";
+    }
+    std::iter::once(first).chain(lines).map_while(|line| line.trim_start().strip_prefix("//!")).any(
+        |doc| {
+            let doc = doc.trim();
+            doc.strip_prefix("```").map_or(false, |info| info.trim() == "cargo")
+        },
+    )
+}
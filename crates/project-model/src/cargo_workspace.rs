@@ -94,6 +94,9 @@ pub struct CargoConfig {
     pub features: CargoFeatures,
     /// rustc target
     pub target: Option<String>,
+    /// Targets to build specific packages for, by package name. Their dependencies are built for
+    /// the same target.
+    pub package_targets: FxHashMap<String, String>,
     /// Don't load sysroot crates (`std`, `core` & friends). Might be useful
     /// when debugging isolated issues.
     pub no_sysroot: bool,
@@ -163,6 +166,9 @@ pub struct PackageData {
     pub id: String,
     /// The contents of [package.metadata.rust-analyzer]
     pub metadata: RustAnalyzerPackageMetaData,
+    /// The target this package is built for, if it is not the one of the workspace. Its
+    /// dependencies are built for it as well.
+    pub target: Option<String>,
 }
 
 #[derive(Deserialize, Default, Debug, Clone, Eq, PartialEq)]
@@ -295,9 +301,24 @@ impl CargoWorkspace {
         }
         meta.current_dir(current_dir.as_os_str());
 
-        if let Some(target) = target {
-            meta.other_options(vec![String::from("--filter-platform"), target]);
+        // Keep the dependencies of every platform some crate is built for: packages with a target
+        // of their own, and build scripts and proc-macros, which run on the host.
+        let mut platforms = target.into_iter().collect::<Vec<_>>();
+        if !config.package_targets.is_empty() {
+            platforms.extend(config.package_targets.values().cloned());
+            platforms.extend(rustc_discover_host_triple(cargo_toml, &config.extra_env));
+        }
+        platforms.sort();
+        platforms.dedup();
+        let mut other_options = Vec::new();
+        for platform in platforms {
+            other_options.extend([String::from("--filter-platform"), platform]);
         }
+        let is_script = is_cargo_script(cargo_toml);
+        if is_script {
+            other_options.push(String::from("-Zscript"));
+        }
+        meta.other_options(other_options);
 
         // FIXME: Fetching metadata is a slow process, as it might require
         // calling crates.io. We should be reporting progress here, but it's
@@ -307,6 +328,10 @@ impl CargoWorkspace {
         (|| -> Result<cargo_metadata::Metadata, cargo_metadata::Error> {
             let mut command = meta.cargo_command();
             command.envs(&config.extra_env);
+            if is_script {
+                // Scripts are still unstable.
+                command.env("RUSTC_BOOTSTRAP", "1");
+            }
             let output = command.output()?;
             if !output.status.success() {
                 return Err(cargo_metadata::Error::CargoMetadata {
@@ -370,6 +395,7 @@ impl CargoWorkspace {
                 features: meta_pkg.features.clone().into_iter().collect(),
                 active_features: Vec::new(),
                 metadata: meta.rust_analyzer.unwrap_or_default(),
+                target: None,
             });
             let pkg_data = &mut packages[pkg];
             pkg_by_id.insert(id, pkg);
@@ -408,6 +434,18 @@ impl CargoWorkspace {
         CargoWorkspace { packages, targets, workspace_root }
     }
 
+    /// Sets the targets of the packages named in `package_targets`.
+    ///
+    /// FIXME: `package.forced-target` should be picked up as well, but `cargo metadata` doesn't
+    /// report it, so it has to be repeated in the config for now.
+    pub fn set_package_targets(&mut self, package_targets: &FxHashMap<String, String>) {
+        for (_, pkg) in self.packages.iter_mut() {
+            if let Some(target) = package_targets.get(&pkg.name) {
+                pkg.target = Some(target.clone());
+            }
+        }
+    }
+
     pub fn packages<'a>(&'a self) -> impl Iterator<Item = Package> + ExactSizeIterator + 'a {
         self.packages.iter().map(|(id, _pkg)| id)
     }
@@ -423,6 +461,14 @@ impl CargoWorkspace {
         &self.workspace_root
     }
 
+    /// The manifest of the script, if this is the workspace of a cargo script.
+    pub fn cargo_script(&self) -> Option<&ManifestPath> {
+        self.packages()
+            .filter(|&pkg| self[pkg].is_member)
+            .map(|pkg| &self[pkg].manifest)
+            .find(|manifest| is_cargo_script(manifest))
+    }
+
     pub fn package_flag(&self, package: &PackageData) -> String {
         if self.is_unique(&*package.name) {
             package.name.clone()
@@ -466,7 +512,7 @@ impl CargoWorkspace {
     }
 }
 
-fn rustc_discover_host_triple(
+pub(crate) fn rustc_discover_host_triple(
     cargo_toml: &ManifestPath,
     extra_env: &FxHashMap<String, String>,
 ) -> Option<String> {
@@ -493,7 +539,7 @@ fn rustc_discover_host_triple(
     }
 }
 
-fn cargo_config_build_target(
+pub(crate) fn cargo_config_build_target(
     cargo_toml: &ManifestPath,
     extra_env: &FxHashMap<String, String>,
 ) -> Option<String> {
@@ -513,3 +559,9 @@ fn cargo_config_build_target(
         Err(_) => None,
     }
 }
+
+/// Whether the manifest is a cargo script rather than a `Cargo.toml`, cargo needs `-Zscript` for
+/// those.
+pub(crate) fn is_cargo_script(manifest: &AbsPath) -> bool {
+    manifest.extension().map_or(false, |it| it == "rs")
+}
@@ -20,11 +20,12 @@
 mod manifest_path;
 mod cargo_workspace;
 mod cfg_flag;
-mod project_json;
+pub mod project_json;
 mod sysroot;
 mod workspace;
 mod rustc_cfg;
 mod build_scripts;
+mod cargo_script;
 
 #[cfg(test)]
 mod tests;
@@ -55,6 +56,8 @@ pub use crate::{
 pub enum ProjectManifest {
     ProjectJson(ManifestPath),
     CargoToml(ManifestPath),
+    /// A single-file package, a `.rs` file with an embedded manifest.
+    CargoScript(ManifestPath),
 }
 
 impl ProjectManifest {
@@ -67,7 +70,23 @@ impl ProjectManifest {
         if path.file_name().unwrap_or_default() == "Cargo.toml" {
             return Ok(ProjectManifest::CargoToml(path));
         }
-        bail!("project root must point to Cargo.toml or rust-project.json: {}", path.display())
+        if path.extension().unwrap_or_default() == "rs" && ProjectManifest::is_cargo_script(&path) {
+            return Ok(ProjectManifest::CargoScript(path));
+        }
+        bail!(
+            "project root must point to Cargo.toml, rust-project.json or a cargo script: {}",
+            path.display()
+        )
+    }
+
+    /// Checks whether the file at `path` is a cargo script, i.e. embeds its own manifest.
+    pub fn is_cargo_script(path: &AbsPath) -> bool {
+        fs::read_to_string(path).map_or(false, |text| ProjectManifest::has_embedded_manifest(&text))
+    }
+
+    /// Checks whether `text`, the contents of a Rust file, embeds a manifest.
+    pub fn has_embedded_manifest(text: &str) -> bool {
+        cargo_script::has_embedded_manifest(text)
     }
 
     pub fn discover_single(path: &AbsPath) -> Result<ProjectManifest> {
@@ -5,14 +5,15 @@
 //! system to generate `rust-project.json` which can be ingested by
 //! rust-analyzer.
 
-use std::path::PathBuf;
+use std::{path::PathBuf, process::Command};
 
+use anyhow::{bail, Context, Result};
 use base_db::{CrateDisplayName, CrateId, CrateName, Dependency, Edition};
 use paths::{AbsPath, AbsPathBuf};
 use rustc_hash::FxHashMap;
 use serde::{de, Deserialize};
 
-use crate::cfg_flag::CfgFlag;
+use crate::{cfg_flag::CfgFlag, utf8_stdout};
 
 /// Roots and crates that compose this Rust project.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -43,6 +44,47 @@ pub struct Crate {
     pub(crate) exclude: Vec<AbsPathBuf>,
     pub(crate) is_proc_macro: bool,
     pub(crate) repository: Option<String>,
+    /// The directory a build step generated sources into, exposed as `OUT_DIR`.
+    pub(crate) out_dir: Option<AbsPathBuf>,
+    pub(crate) runnables: Vec<Runnable>,
+}
+
+/// A command of the build system that generated the project, to build, test or run a crate.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Runnable {
+    pub kind: RunnableKind,
+    pub program: String,
+    /// The arguments of the command. For tests, `{test_id}` is replaced by the path of the test
+    /// or the module to run, relative to the crate root.
+    pub args: Vec<String>,
+    pub cwd: AbsPathBuf,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RunnableKind {
+    Build,
+    Test,
+    Run,
+}
+
+impl Crate {
+    fn is_same_crate(&self, other: &Crate) -> bool {
+        self.root_module == other.root_module
+            && self.display_name == other.display_name
+            && self.edition == other.edition
+            && self.target == other.target
+            && self.cfg == other.cfg
+            && self.env == other.env
+            && self.deps.iter().map(|dep| &dep.name).eq(other.deps.iter().map(|dep| &dep.name))
+    }
+
+    pub fn display_name(&self) -> Option<&CrateDisplayName> {
+        self.display_name.as_ref()
+    }
+
+    pub fn runnable(&self, kind: RunnableKind) -> Option<&Runnable> {
+        self.runnables.iter().find(|it| it.kind == kind)
+    }
 }
 
 impl ProjectJson {
@@ -105,12 +147,78 @@ impl ProjectJson {
                         exclude,
                         is_proc_macro: crate_data.is_proc_macro,
                         repository: crate_data.repository,
+                        out_dir: crate_data.out_dir.map(|it| base.join(it).normalize()),
+                        runnables: crate_data
+                            .runnables
+                            .into_iter()
+                            .map(|runnable| Runnable {
+                                kind: runnable.kind.into(),
+                                program: runnable.program,
+                                args: runnable.args,
+                                cwd: match runnable.cwd {
+                                    Some(cwd) => base.join(cwd).normalize(),
+                                    None => base.to_path_buf(),
+                                },
+                            })
+                            .collect(),
                     }
                 })
                 .collect::<Vec<_>>(),
         }
     }
 
+    /// Runs a discovery command, which prints the project that `file` belongs to in the
+    /// `rust-project.json` format. The path of the file is appended to the command, relative paths
+    /// in its output are resolved against `cwd`, the directory the command runs in.
+    pub fn discover(
+        command: &[String],
+        file: &AbsPath,
+        cwd: &AbsPath,
+        extra_env: &FxHashMap<String, String>,
+    ) -> Result<ProjectJson> {
+        let (program, args) = match command {
+            [program, args @ ..] => (program, args),
+            [] => bail!("the project discovery command is empty"),
+        };
+        let mut cmd = Command::new(program);
+        cmd.args(args).arg(file.as_os_str()).current_dir(cwd).envs(extra_env);
+        let stdout = utf8_stdout(cmd)?;
+        let data = serde_json::from_str(&stdout)
+            .with_context(|| format!("Failed to deserialize the project of {}", file.display()))?;
+        Ok(ProjectJson::new(cwd, data))
+    }
+
+    /// Adds the crates of `other` to this project, apart from those which are already part of it.
+    /// Crates are identified by their root module, name, edition, target, cfgs, env and the names
+    /// of their dependencies, as the same root module may be built in several configurations.
+    ///
+    /// This allows building up a project from the results of several discoveries, which usually
+    /// share dependencies.
+    pub fn merge(&mut self, other: ProjectJson) {
+        let n_crates = self.crates.len();
+        let mut ids = Vec::with_capacity(other.crates.len());
+        for krate in other.crates {
+            let existing = self.crates[..n_crates].iter().position(|it| it.is_same_crate(&krate));
+            match existing {
+                Some(idx) => ids.push(idx),
+                None => {
+                    ids.push(self.crates.len());
+                    self.crates.push(krate);
+                }
+            }
+        }
+        for krate in &mut self.crates[n_crates..] {
+            krate.deps.retain(|dep| (dep.crate_id.0 as usize) < ids.len());
+            for dep in &mut krate.deps {
+                dep.crate_id = CrateId(ids[dep.crate_id.0 as usize] as u32);
+            }
+        }
+        if self.sysroot.is_none() && self.sysroot_src.is_none() {
+            self.sysroot = other.sysroot;
+            self.sysroot_src = other.sysroot_src;
+        }
+    }
+
     /// Returns the number of crates in the project.
     pub fn n_crates(&self) -> usize {
         self.crates.len()
@@ -121,6 +229,11 @@ impl ProjectJson {
         self.crates.iter().enumerate().map(|(idx, krate)| (CrateId(idx as u32), krate))
     }
 
+    /// Returns the crate with the given root module.
+    pub fn crate_by_root(&self, root: &AbsPath) -> Option<&Crate> {
+        self.crates.iter().find(|krate| krate.root_module.as_path() == root)
+    }
+
     /// Returns the path to the project's root folder.
     pub fn path(&self) -> &AbsPath {
         &self.project_root
@@ -154,6 +267,10 @@ struct CrateData {
     is_proc_macro: bool,
     #[serde(default)]
     repository: Option<String>,
+    #[serde(default)]
+    out_dir: Option<PathBuf>,
+    #[serde(default)]
+    runnables: Vec<RunnableData>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -177,6 +294,33 @@ impl From<EditionData> for Edition {
     }
 }
 
+#[derive(Deserialize, Debug, Clone)]
+struct RunnableData {
+    kind: RunnableKindData,
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+enum RunnableKindData {
+    Build,
+    Test,
+    Run,
+}
+
+impl From<RunnableKindData> for RunnableKind {
+    fn from(data: RunnableKindData) -> Self {
+        match data {
+            RunnableKindData::Build => RunnableKind::Build,
+            RunnableKindData::Test => RunnableKind::Test,
+            RunnableKindData::Run => RunnableKind::Run,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 struct DepData {
     /// Identifies a crate by position in the crates array.
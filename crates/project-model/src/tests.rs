@@ -10,8 +10,8 @@ use paths::{AbsPath, AbsPathBuf};
 use serde::de::DeserializeOwned;
 
 use crate::{
-    CargoWorkspace, CfgOverrides, ProjectJson, ProjectJsonData, ProjectWorkspace, Sysroot,
-    WorkspaceBuildScripts,
+    cfg_flag::CfgFlag, project_json::RunnableKind, CargoWorkspace, CfgOverrides, ProjectJson,
+    ProjectJsonData, ProjectWorkspace, Sysroot, WorkspaceBuildScripts,
 };
 
 fn load_cargo(file: &str) -> CrateGraph {
@@ -27,6 +27,8 @@ fn load_cargo_with_overrides(file: &str, cfg_overrides: CfgOverrides) -> CrateGr
         sysroot: None,
         rustc: None,
         rustc_cfg: Vec::new(),
+        host: None,
+        target_cfgs: Default::default(),
         cfg_overrides,
         toolchain: None,
     };
@@ -1252,6 +1254,73 @@ fn cargo_hello_world_project_model() {
     )
 }
 
+#[test]
+fn cargo_hello_world_project_model_with_package_targets() {
+    let meta = get_test_json_file("hello-world-metadata.json");
+    let mut cargo_workspace = CargoWorkspace::new(meta);
+    let package_targets =
+        [("hello-world".to_string(), "thumbv7em-none-eabihf".to_string())].into_iter().collect();
+    cargo_workspace.set_package_targets(&package_targets);
+    let arch =
+        |arch: &str| vec![CfgFlag::KeyValue { key: "target_arch".into(), value: arch.into() }];
+    let project_workspace = ProjectWorkspace::Cargo {
+        cargo: cargo_workspace,
+        build_scripts: WorkspaceBuildScripts::default(),
+        sysroot: None,
+        rustc: None,
+        rustc_cfg: arch("x86_64"),
+        host: Some("x86_64-unknown-linux-gnu".to_string()),
+        target_cfgs: [("thumbv7em-none-eabihf".to_string(), arch("arm"))].into_iter().collect(),
+        cfg_overrides: CfgOverrides::default(),
+        toolchain: None,
+    };
+    let crate_graph = to_crate_graph(project_workspace);
+    let describe = |krate| {
+        let krate = &crate_graph[krate];
+        let name = krate.display_name.as_ref().unwrap().to_string();
+        let arch = krate.cfg_options.get_cfg_values("target_arch").cloned().collect::<Vec<_>>();
+        format!("{} ({})", name, arch.join(", "))
+    };
+    let mut crates = crate_graph
+        .iter()
+        .map(|krate| {
+            let deps = crate_graph[krate].dependencies.iter().map(|dep| describe(dep.crate_id));
+            format!("{} -> [{}]\n", describe(krate), deps.collect::<Vec<_>>().join(", "))
+        })
+        .collect::<Vec<_>>();
+    crates.sort();
+    expect![[r#"
+        an_example (arm) -> [hello_world (arm), libc (arm)]
+        hello_world (arm) -> [hello_world (arm), libc (arm)]
+        hello_world (arm) -> [libc (arm)]
+        it (arm) -> [hello_world (arm), libc (arm)]
+        libc (arm) -> []
+    "#]]
+    .assert_eq(&crates.concat());
+}
+
+#[test]
+fn cargo_script_roots_only_include_the_script() {
+    let mut meta: serde_json::Value = get_test_json_file("hello-world-metadata.json");
+    let manifest = meta["packages"][0]["manifest_path"].as_str().unwrap();
+    let script = manifest.replace("Cargo.toml", "script.rs");
+    meta["packages"][0]["manifest_path"] = script.clone().into();
+    let project_workspace = ProjectWorkspace::Cargo {
+        cargo: CargoWorkspace::new(serde_json::from_value(meta).unwrap()),
+        build_scripts: WorkspaceBuildScripts::default(),
+        sysroot: None,
+        rustc: None,
+        rustc_cfg: Vec::new(),
+        host: None,
+        target_cfgs: Default::default(),
+        cfg_overrides: CfgOverrides::default(),
+        toolchain: None,
+    };
+    let roots = project_workspace.to_roots();
+    assert_eq!(roots[0].include, vec![AbsPathBuf::assert(script.into())]);
+    assert!(roots[0].exclude.is_empty());
+}
+
 #[test]
 fn rust_project_hello_world_project_model() {
     let crate_graph = load_rust_project("hello-world-project.json");
@@ -1823,3 +1892,87 @@ fn rust_project_is_proc_macro_has_proc_macro_dep() {
     // on the proc_macro sysroot crate.
     crate_data.dependencies.iter().find(|&dep| dep.name.deref() == "proc_macro").unwrap();
 }
+
+#[test]
+fn cargo_script_embedded_manifest() {
+    let is_script = crate::cargo_script::has_embedded_manifest;
+    assert!(is_script("#!/usr/bin/env cargo\n---cargo\n[dependencies]\n---\nfn main() {}\n"));
+    assert!(is_script("\n----\n[package]\nedition = \"2021\"\n----\nfn main() {}\n"));
+    assert!(is_script(
+        "#!/usr/bin/env cargo\n//! Docs.\n//!\n//! ```cargo\n//! [dependencies]\n//! ```\n"
+    ));
+
+    assert!(!is_script("fn main() {}\n"));
+    assert!(!is_script("---toml\n---\nfn main() {}\n"));
+    assert!(!is_script("#![allow(unused)]\n//! ```cargo\n//! ```\n"));
+    assert!(!is_script("//! Docs.\n\n// ```cargo\nfn main() {}\n"));
+}
+
+#[test]
+fn rust_project_merge_shares_crates() {
+    let project = |crates: serde_json::Value| {
+        rooted_project_json(
+            serde_json::from_value(serde_json::json!({ "crates": crates })).unwrap(),
+        )
+    };
+    let mut project_a = project(serde_json::json!([
+        { "root_module": "dep/lib.rs", "edition": "2021", "deps": [] },
+        { "root_module": "a/main.rs", "edition": "2021", "deps": [{ "crate": 0, "name": "dep" }] },
+    ]));
+    let project_b = project(serde_json::json!([
+        { "root_module": "b/main.rs", "edition": "2021", "deps": [{ "crate": 1, "name": "dep" }] },
+        { "root_module": "dep/lib.rs", "edition": "2021", "deps": [] },
+        { "root_module": "dep/lib.rs", "edition": "2021", "deps": [], "cfg": ["test"] },
+    ]));
+    project_a.merge(project_b);
+
+    let crates = project_a
+        .crates()
+        .map(|(id, krate)| {
+            let root = krate.root_module.strip_prefix(project_a.path()).unwrap();
+            let root = AsRef::<Path>::as_ref(root).to_path_buf();
+            let deps = krate.deps.iter().map(|dep| dep.crate_id).collect::<Vec<_>>();
+            (id.0, root, deps)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        crates,
+        vec![
+            (0, PathBuf::from("dep/lib.rs"), vec![]),
+            (1, PathBuf::from("a/main.rs"), vec![base_db::CrateId(0)]),
+            (2, PathBuf::from("b/main.rs"), vec![base_db::CrateId(0)]),
+            (3, PathBuf::from("dep/lib.rs"), vec![]),
+        ]
+    );
+}
+
+#[test]
+fn rust_project_out_dir_and_runnables() {
+    let data = serde_json::json!({ "crates": [{
+        "root_module": "foo/lib.rs",
+        "edition": "2021",
+        "deps": [],
+        "out_dir": "out/foo",
+        "runnables": [
+            { "kind": "test", "program": "build-tool", "args": ["test", "//foo", "{test_id}"] },
+            { "kind": "build", "program": "build-tool", "args": ["build", "//foo"], "cwd": "foo" },
+        ],
+    }]});
+    let project = rooted_project_json(serde_json::from_value(data).unwrap());
+    let root = project.path().to_path_buf();
+
+    let krate = project.crate_by_root(&root.join("foo/lib.rs")).unwrap();
+    let test = krate.runnable(RunnableKind::Test).unwrap();
+    assert_eq!(test.args, ["test", "//foo", "{test_id}"]);
+    assert_eq!(test.cwd, root);
+    assert_eq!(krate.runnable(RunnableKind::Build).unwrap().cwd, root.join("foo"));
+    assert_eq!(krate.runnable(RunnableKind::Run), None);
+
+    let workspace = ProjectWorkspace::Json { project, sysroot: None, rustc_cfg: Vec::new() };
+    let roots = workspace.to_roots();
+    assert!(roots.iter().any(|it| it.include.contains(&root.join("out/foo"))));
+    let crate_graph = to_crate_graph(workspace);
+    let krate = crate_graph.iter().next().unwrap();
+    let out_dir = root.join("out/foo").display().to_string();
+    assert_eq!(crate_graph[krate].env.get("OUT_DIR"), Some(out_dir));
+}
@@ -2,7 +2,7 @@
 //! metadata` or `rust-project.json`) into representation stored in the salsa
 //! database -- `CrateGraph`.
 
-use std::{collections::VecDeque, fmt, fs, process::Command};
+use std::{collections::VecDeque, fmt, fs, iter, mem, process::Command};
 
 use anyhow::{format_err, Context, Result};
 use base_db::{
@@ -17,7 +17,10 @@ use stdx::{always, hash::NoHashHashMap};
 
 use crate::{
     build_scripts::BuildScriptOutput,
-    cargo_workspace::{DepKind, PackageData, RustcSource},
+    cargo_workspace::{
+        cargo_config_build_target, is_cargo_script, rustc_discover_host_triple, DepKind,
+        PackageData, RustcSource,
+    },
     cfg_flag::CfgFlag,
     rustc_cfg,
     sysroot::SysrootCrate,
@@ -73,23 +76,27 @@ pub enum ProjectWorkspace {
         rustc: Option<CargoWorkspace>,
         /// Holds cfg flags for the current target. We get those by running
         /// `rustc --print cfg`.
-        ///
-        /// FIXME: make this a per-crate map, as, eg, build.rs might have a
-        /// different target.
         rustc_cfg: Vec<CfgFlag>,
+        /// The host triple, build scripts and proc-macros are built for it.
+        host: Option<String>,
+        /// Holds cfg flags for the targets some crates are built for instead of
+        /// the current one, keyed by target triple. These are the host and the
+        /// configured targets of packages.
+        target_cfgs: FxHashMap<String, Vec<CfgFlag>>,
         cfg_overrides: CfgOverrides,
         toolchain: Option<Version>,
     },
     /// Project workspace was manually specified using a `rust-project.json` file.
     Json { project: ProjectJson, sysroot: Option<Sysroot>, rustc_cfg: Vec<CfgFlag> },
 
-    // FIXME: The primary limitation of this approach is that the set of detached files needs to be fixed at the beginning.
-    // That's not the end user experience we should strive for.
-    // Ideally, you should be able to just open a random detached file in existing cargo projects, and get the basic features working.
-    // That needs some changes on the salsa-level though.
-    // In particular, we should split the unified CrateGraph (which currently has maximal durability) into proper crate graph, and a set of ad hoc roots (with minimal durability).
+    // Besides the files from the config, rust-analyzer puts opened files which don't belong to any
+    // workspace in such a workspace, see `GlobalState::update_detached_files`.
+    //
+    // FIXME: Whenever a file is added, the whole crate graph is recreated, with maximal durability.
+    // That's the intended state until the crate graph is split, it keeps detached files working
+    // at the cost of invalidating everything when one is opened or closed.
+    // Ideally, we should split the unified CrateGraph (which currently has maximal durability) into proper crate graph, and a set of ad hoc roots (with minimal durability).
     // Then, we need to hide the graph behind the queries such that most queries look only at the proper crate graph, and fall back to ad hoc roots only if there's no results.
-    // After this, we should be able to tweak the logic in reload.rs to add newly opened files, which don't belong to any existing crates, to the set of the detached files.
     // //
     /// Project with a set of disjoint files, not belonging to any particular workspace.
     /// Backed by basic sysroot crates for basic completion and highlighting.
@@ -106,6 +113,8 @@ impl fmt::Debug for ProjectWorkspace {
                 sysroot,
                 rustc,
                 rustc_cfg,
+                host: _,
+                target_cfgs,
                 cfg_overrides,
                 toolchain,
             } => f
@@ -118,6 +127,7 @@ impl fmt::Debug for ProjectWorkspace {
                     &rustc.as_ref().map_or(0, |rc| rc.packages().len()),
                 )
                 .field("n_rustc_cfg", &rustc_cfg.len())
+                .field("n_other_targets", &target_cfgs.len())
                 .field("n_cfg_overrides", &cfg_overrides.len())
                 .field("toolchain", &toolchain)
                 .finish(),
@@ -162,7 +172,7 @@ impl ProjectWorkspace {
                     &config.extra_env,
                 )?
             }
-            ProjectManifest::CargoToml(cargo_toml) => {
+            ProjectManifest::CargoToml(cargo_toml) | ProjectManifest::CargoScript(cargo_toml) => {
                 let cargo_version = utf8_stdout({
                     let mut cmd = Command::new(toolchain::cargo());
                     cmd.envs(&config.extra_env);
@@ -186,7 +196,8 @@ impl ProjectWorkspace {
                         toolchain
                     )
                 })?;
-                let cargo = CargoWorkspace::new(meta);
+                let mut cargo = CargoWorkspace::new(meta);
+                cargo.set_package_targets(&config.package_targets);
 
                 let sysroot = if config.no_sysroot {
                     None
@@ -228,6 +239,22 @@ impl ProjectWorkspace {
                 let rustc_cfg =
                     rustc_cfg::get(Some(&cargo_toml), config.target.as_deref(), &config.extra_env);
 
+                let host = rustc_discover_host_triple(&cargo_toml, &config.extra_env);
+                let target = config
+                    .target
+                    .clone()
+                    .or_else(|| cargo_config_build_target(&cargo_toml, &config.extra_env))
+                    .or_else(|| host.clone());
+                let mut target_cfgs = FxHashMap::default();
+                let other_targets = cargo.packages().filter_map(|pkg| cargo[pkg].target.clone());
+                for other in host.clone().into_iter().chain(other_targets) {
+                    if Some(&other) == target.as_ref() || target_cfgs.contains_key(&other) {
+                        continue;
+                    }
+                    let cfgs = rustc_cfg::get(Some(&cargo_toml), Some(&other), &config.extra_env);
+                    target_cfgs.insert(other, cfgs);
+                }
+
                 let cfg_overrides = config.cfg_overrides();
                 ProjectWorkspace::Cargo {
                     cargo,
@@ -235,6 +262,8 @@ impl ProjectWorkspace {
                     sysroot,
                     rustc,
                     rustc_cfg,
+                    host,
+                    target_cfgs,
                     cfg_overrides,
                     toolchain,
                 }
@@ -285,6 +314,14 @@ impl ProjectWorkspace {
         Ok(ProjectWorkspace::DetachedFiles { files: detached_files, sysroot, rustc_cfg })
     }
 
+    /// Replaces the files of a detached files workspace. The sysroot doesn't depend on them, so
+    /// it doesn't have to be discovered again.
+    pub fn set_detached_files(&mut self, detached_files: Vec<AbsPathBuf>) {
+        if let ProjectWorkspace::DetachedFiles { files, .. } = self {
+            *files = detached_files;
+        }
+    }
+
     pub fn run_build_scripts(
         &self,
         config: &CargoConfig,
@@ -327,7 +364,7 @@ impl ProjectWorkspace {
                 .crates()
                 .map(|(_, krate)| PackageRoot {
                     is_local: krate.is_workspace_member,
-                    include: krate.include.clone(),
+                    include: krate.include.iter().cloned().chain(krate.out_dir.clone()).collect(),
                     exclude: krate.exclude.clone(),
                 })
                 .collect::<FxHashSet<_>>()
@@ -339,6 +376,8 @@ impl ProjectWorkspace {
                 sysroot,
                 rustc,
                 rustc_cfg: _,
+                host: _,
+                target_cfgs: _,
                 cfg_overrides: _,
                 build_scripts,
                 toolchain: _,
@@ -347,6 +386,11 @@ impl ProjectWorkspace {
                     .packages()
                     .map(|pkg| {
                         let is_local = cargo[pkg].is_local;
+                        // The directory of a cargo script isn't part of its package.
+                        if is_cargo_script(&cargo[pkg].manifest) {
+                            let include = vec![cargo[pkg].manifest.to_path_buf()];
+                            return PackageRoot { is_local, include, exclude: Vec::new() };
+                        }
                         let pkg_root = cargo[pkg].manifest.parent().to_path_buf();
 
                         let mut include = vec![pkg_root.clone()];
@@ -439,11 +483,15 @@ impl ProjectWorkspace {
                 sysroot,
                 rustc,
                 rustc_cfg,
+                host,
+                target_cfgs,
                 cfg_overrides,
                 build_scripts,
                 toolchain: _,
             } => cargo_to_crate_graph(
                 rustc_cfg.clone(),
+                host.as_deref(),
+                target_cfgs,
                 cfg_overrides,
                 load_proc_macro,
                 load,
@@ -487,7 +535,13 @@ fn project_json_to_crate_graph(
             Some((crate_id, krate, file_id))
         })
         .map(|(crate_id, krate, file_id)| {
-            let env = krate.env.clone().into_iter().collect();
+            let mut env: Env = krate.env.clone().into_iter().collect();
+            if let Some(out_dir) = &krate.out_dir {
+                // NOTE: cargo and rustc seem to hide non-UTF-8 strings from env! and option_env!()
+                if let Some(out_dir) = out_dir.as_os_str().to_str().map(|s| s.to_owned()) {
+                    env.set("OUT_DIR", out_dir);
+                }
+            }
             let proc_macro = match krate.proc_macro_dylib_path.clone() {
                 Some(it) => load_proc_macro(
                     krate.display_name.as_ref().map(|it| it.canonical_name()).unwrap_or(""),
@@ -553,137 +607,206 @@ fn project_json_to_crate_graph(
     crate_graph
 }
 
-fn cargo_to_crate_graph(
+fn cargo_to_crate_graph<'a>(
     rustc_cfg: Vec<CfgFlag>,
+    host: Option<&'a str>,
+    target_cfgs: &'a FxHashMap<String, Vec<CfgFlag>>,
     override_cfg: &CfgOverrides,
     load_proc_macro: &mut dyn FnMut(&str, &AbsPath) -> ProcMacroLoadResult,
     load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
-    cargo: &CargoWorkspace,
+    cargo: &'a CargoWorkspace,
     build_scripts: &WorkspaceBuildScripts,
     sysroot: Option<&Sysroot>,
     rustc: &Option<CargoWorkspace>,
 ) -> CrateGraph {
     let _p = profile::span("cargo_to_crate_graph");
     let mut crate_graph = CrateGraph::default();
-    let (public_deps, libproc_macro) = match sysroot {
-        Some(sysroot) => sysroot_to_crate_graph(&mut crate_graph, sysroot, rustc_cfg.clone(), load),
-        None => (SysrootPublicDeps::default(), None),
+
+    // The cfg options and sysroot crates for each platform, `None` being the current target.
+    // Every platform gets its own copy of the sysroot, as it is compiled with its cfgs.
+    let mut platforms = FxHashMap::default();
+    let all_platforms = iter::once((None, &rustc_cfg))
+        .chain(target_cfgs.iter().map(|(target, cfgs)| (Some(target.as_str()), cfgs)));
+    for (platform, cfgs) in all_platforms {
+        let (public_deps, libproc_macro) = match sysroot {
+            Some(sysroot) => sysroot_to_crate_graph(&mut crate_graph, sysroot, cfgs.clone(), load),
+            None => (SysrootPublicDeps::default(), None),
+        };
+
+        let mut cfg_options = CfgOptions::default();
+        cfg_options.extend(cfgs.iter().cloned());
+        cfg_options.insert_atom("debug_assertions".into());
+        platforms.insert(platform, (cfg_options, public_deps, libproc_macro));
+    }
+    // Targets we don't know the cfgs of are treated like the current one.
+    let platform_of = |target: Option<&'a str>| target.filter(|it| target_cfgs.contains_key(*it));
+    let host = platform_of(host);
+    let is_proc_macro = |pkg: Package| {
+        cargo[pkg]
+            .targets
+            .iter()
+            .any(|&tgt| cargo[tgt].kind == TargetKind::Lib && cargo[tgt].is_proc_macro)
     };
 
-    let mut cfg_options = CfgOptions::default();
-    cfg_options.extend(rustc_cfg);
+    // Find out which platforms each package is built for. Workspace members are built for their
+    // own target and their dependencies for the same one, except for proc-macros and build
+    // dependencies, which run on the host.
+    let mut pkg_platforms = FxHashMap::<Package, Vec<Option<&str>>>::default();
+    let mut queue = cargo
+        .packages()
+        .filter(|&pkg| cargo[pkg].is_member)
+        .map(|pkg| (pkg, platform_of(cargo[pkg].target.as_deref())))
+        .collect::<VecDeque<_>>();
+    let mut packages = cargo.packages();
+    loop {
+        let (pkg, platform) = match queue.pop_front() {
+            Some(it) => it,
+            // Packages no member depends on are built for the current target.
+            None => match packages.find(|pkg| !pkg_platforms.contains_key(pkg)) {
+                Some(pkg) => (pkg, None),
+                None => break,
+            },
+        };
+        let platform = if is_proc_macro(pkg) { host } else { platform };
+        let pkg_platforms = pkg_platforms.entry(pkg).or_default();
+        if pkg_platforms.contains(&platform) {
+            continue;
+        }
+        pkg_platforms.push(platform);
+        for dep in cargo[pkg].dependencies.iter() {
+            let platform = if dep.kind == DepKind::Build { host } else { platform };
+            queue.push_back((dep.pkg, platform));
+        }
+    }
 
     let mut pkg_to_lib_crate = FxHashMap::default();
 
-    cfg_options.insert_atom("debug_assertions".into());
-
     let mut pkg_crates = FxHashMap::default();
     // Does any crate signal to rust-analyzer that they need the rustc_private crates?
     let mut has_private = false;
-    // Next, create crates for each package, target pair
+    // Next, create crates for each package, platform, target triple
     for pkg in cargo.packages() {
-        let mut cfg_options = cfg_options.clone();
-
         let overrides = match override_cfg {
             CfgOverrides::Wildcard(cfg_diff) => Some(cfg_diff),
             CfgOverrides::Selective(cfg_overrides) => cfg_overrides.get(&cargo[pkg].name),
         };
 
-        // Add test cfg for local crates
-        if cargo[pkg].is_local {
-            cfg_options.insert_atom("test".into());
-        }
+        has_private |= cargo[pkg].metadata.rustc_private;
+        // The build script runs on the host whatever the package is built for, it is only added
+        // once.
+        let mut has_build_script = false;
+        for &platform in pkg_platforms.get(&pkg).into_iter().flatten() {
+            let mut lib_tgt = None;
+            for &tgt in cargo[pkg].targets.iter() {
+                if cargo[tgt].kind != TargetKind::Lib && !cargo[pkg].is_member {
+                    // For non-workspace-members, Cargo does not resolve dev-dependencies, so we
+                    // don't add any targets except the library target, since those will not work
+                    // correctly if they use dev-dependencies.
+                    // In fact, they can break quite badly if multiple client workspaces get merged:
+                    // https://github.com/rust-lang/rust-analyzer/issues/11300
+                    continue;
+                }
+                let is_build_script = cargo[tgt].kind == TargetKind::BuildScript;
+                if is_build_script && mem::replace(&mut has_build_script, true) {
+                    continue;
+                }
 
-        if let Some(overrides) = overrides {
-            // FIXME: this is sort of a hack to deal with #![cfg(not(test))] vanishing such as seen
-            // in ed25519_dalek (#7243), and libcore (#9203) (although you only hit that one while
-            // working on rust-lang/rust as that's the only time it appears outside sysroot).
-            //
-            // A more ideal solution might be to reanalyze crates based on where the cursor is and
-            // figure out the set of cfgs that would have to apply to make it active.
+                let tgt_platform = if is_build_script { host } else { platform };
+                let (cfg_options, public_deps, libproc_macro) = &platforms[&tgt_platform];
+                let mut cfg_options = cfg_options.clone();
 
-            cfg_options.apply_diff(overrides.clone());
-        };
+                // Add test cfg for local crates
+                if cargo[pkg].is_local {
+                    cfg_options.insert_atom("test".into());
+                }
 
-        has_private |= cargo[pkg].metadata.rustc_private;
-        let mut lib_tgt = None;
-        for &tgt in cargo[pkg].targets.iter() {
-            if cargo[tgt].kind != TargetKind::Lib && !cargo[pkg].is_member {
-                // For non-workspace-members, Cargo does not resolve dev-dependencies, so we don't
-                // add any targets except the library target, since those will not work correctly if
-                // they use dev-dependencies.
-                // In fact, they can break quite badly if multiple client workspaces get merged:
-                // https://github.com/rust-lang/rust-analyzer/issues/11300
-                continue;
-            }
+                if let Some(overrides) = overrides {
+                    // FIXME: this is sort of a hack to deal with #![cfg(not(test))] vanishing such
+                    // as seen in ed25519_dalek (#7243), and libcore (#9203) (although you only hit
+                    // that one while working on rust-lang/rust as that's the only time it appears
+                    // outside sysroot).
+                    //
+                    // A more ideal solution might be to reanalyze crates based on where the cursor
+                    // is and figure out the set of cfgs that would have to apply to make it active.
 
-            if let Some(file_id) = load(&cargo[tgt].root) {
-                let crate_id = add_target_crate_root(
-                    &mut crate_graph,
-                    &cargo[pkg],
-                    build_scripts.get_output(pkg),
-                    cfg_options.clone(),
-                    &mut |path| load_proc_macro(&cargo[tgt].name, path),
-                    file_id,
-                    &cargo[tgt].name,
-                    cargo[tgt].is_proc_macro,
-                );
-                if cargo[tgt].kind == TargetKind::Lib {
-                    lib_tgt = Some((crate_id, cargo[tgt].name.clone()));
-                    pkg_to_lib_crate.insert(pkg, crate_id);
-                }
-                // Even crates that don't set proc-macro = true are allowed to depend on proc_macro
-                // (just none of the APIs work when called outside of a proc macro).
-                if let Some(proc_macro) = libproc_macro {
-                    add_dep_with_prelude(
+                    cfg_options.apply_diff(overrides.clone());
+                };
+
+                if let Some(file_id) = load(&cargo[tgt].root) {
+                    let crate_id = add_target_crate_root(
                         &mut crate_graph,
-                        crate_id,
-                        CrateName::new("proc_macro").unwrap(),
-                        proc_macro,
+                        &cargo[pkg],
+                        build_scripts.get_output(pkg),
+                        cfg_options,
+                        &mut |path| load_proc_macro(&cargo[tgt].name, path),
+                        file_id,
+                        &cargo[tgt].name,
                         cargo[tgt].is_proc_macro,
                     );
+                    if cargo[tgt].kind == TargetKind::Lib {
+                        lib_tgt = Some((crate_id, cargo[tgt].name.clone()));
+                        pkg_to_lib_crate.insert((pkg, platform), crate_id);
+                    }
+                    // Even crates that don't set proc-macro = true are allowed to depend on
+                    // proc_macro (just none of the APIs work when called outside of a proc macro).
+                    if let Some(proc_macro) = *libproc_macro {
+                        add_dep_with_prelude(
+                            &mut crate_graph,
+                            crate_id,
+                            CrateName::new("proc_macro").unwrap(),
+                            proc_macro,
+                            cargo[tgt].is_proc_macro,
+                        );
+                    }
+                    // Add sysroot deps before the lib target of the package, so that a lib target
+                    // named `core` etc. can overwrite them.
+                    public_deps.add(crate_id, &mut crate_graph);
+
+                    pkg_crates
+                        .entry((pkg, platform))
+                        .or_insert_with(Vec::new)
+                        .push((crate_id, cargo[tgt].kind));
                 }
-
-                pkg_crates.entry(pkg).or_insert_with(Vec::new).push((crate_id, cargo[tgt].kind));
             }
-        }
-
-        // Set deps to the core, std and to the lib target of the current package
-        for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
-            // Add sysroot deps first so that a lib target named `core` etc. can overwrite them.
-            public_deps.add(from, &mut crate_graph);
-
-            if let Some((to, name)) = lib_tgt.clone() {
-                if to != from && kind != TargetKind::BuildScript {
-                    // (build script can not depend on its library target)
 
-                    // For root projects with dashes in their name,
-                    // cargo metadata does not do any normalization,
-                    // so we do it ourselves currently
-                    let name = CrateName::normalize_dashes(&name);
-                    add_dep(&mut crate_graph, from, name, to);
+            // Set deps to the lib target of the current package
+            for &(from, kind) in pkg_crates.get(&(pkg, platform)).into_iter().flatten() {
+                if let Some((to, name)) = lib_tgt.clone() {
+                    if to != from && kind != TargetKind::BuildScript {
+                        // (build script can not depend on its library target)
+
+                        // For root projects with dashes in their name,
+                        // cargo metadata does not do any normalization,
+                        // so we do it ourselves currently
+                        let name = CrateName::normalize_dashes(&name);
+                        add_dep(&mut crate_graph, from, name, to);
+                    }
                 }
             }
         }
     }
 
     // Now add a dep edge from all targets of upstream to the lib
-    // target of downstream.
+    // target of downstream built for the same platform.
     for pkg in cargo.packages() {
-        for dep in cargo[pkg].dependencies.iter() {
-            let name = CrateName::new(&dep.name).unwrap();
-            if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
-                for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
-                    if dep.kind == DepKind::Build && kind != TargetKind::BuildScript {
-                        // Only build scripts may depend on build dependencies.
-                        continue;
-                    }
-                    if dep.kind != DepKind::Build && kind == TargetKind::BuildScript {
-                        // Build scripts may only depend on build dependencies.
-                        continue;
-                    }
+        for &platform in pkg_platforms.get(&pkg).into_iter().flatten() {
+            for dep in cargo[pkg].dependencies.iter() {
+                let name = CrateName::new(&dep.name).unwrap();
+                let dep_platform = if dep.kind == DepKind::Build { host } else { platform };
+                let dep_platform = if is_proc_macro(dep.pkg) { host } else { dep_platform };
+                if let Some(&to) = pkg_to_lib_crate.get(&(dep.pkg, dep_platform)) {
+                    for &(from, kind) in pkg_crates.get(&(pkg, platform)).into_iter().flatten() {
+                        if dep.kind == DepKind::Build && kind != TargetKind::BuildScript {
+                            // Only build scripts may depend on build dependencies.
+                            continue;
+                        }
+                        if dep.kind != DepKind::Build && kind == TargetKind::BuildScript {
+                            // Build scripts may only depend on build dependencies.
+                            continue;
+                        }
 
-                    add_dep(&mut crate_graph, from, name.clone(), to)
+                        add_dep(&mut crate_graph, from, name.clone(), to)
+                    }
                 }
             }
         }
@@ -693,17 +816,29 @@ fn cargo_to_crate_graph(
         // If the user provided a path to rustc sources, we add all the rustc_private crates
         // and create dependencies on them for the crates which opt-in to that
         if let Some(rustc_workspace) = rustc {
+            // The rustc_private crates are built for the current target, but every platform's
+            // copy of the crates opting in depends on them.
+            let (cfg_options, public_deps, _) = &platforms[&None];
+            let mut pkg_to_lib_crate = pkg_to_lib_crate
+                .into_iter()
+                .filter(|((_, platform), _)| platform.is_none())
+                .map(|((pkg, _), crate_id)| (pkg, crate_id))
+                .collect();
+            let mut all_pkg_crates = FxHashMap::<_, Vec<_>>::default();
+            for ((pkg, _), crates) in pkg_crates {
+                all_pkg_crates.entry(pkg).or_default().extend(crates);
+            }
             handle_rustc_crates(
                 &mut crate_graph,
                 rustc_workspace,
                 load,
-                &cfg_options,
+                cfg_options,
                 override_cfg,
                 load_proc_macro,
                 &mut pkg_to_lib_crate,
-                &public_deps,
+                public_deps,
                 cargo,
-                &pkg_crates,
+                &all_pkg_crates,
                 build_scripts,
             );
         }
@@ -11,7 +11,13 @@ use std::{env, fs, path::Path, process};
 
 use lsp_server::Connection;
 use project_model::ProjectManifest;
-use rust_analyzer::{cli::flags, config::Config, from_json, lsp_ext::supports_utf8, Result};
+use rust_analyzer::{
+    cli::flags,
+    config::{ClientCapabilitiesExt, Config},
+    from_json,
+    lsp_ext::supports_utf8,
+    Result,
+};
 use vfs::AbsPathBuf;
 
 #[cfg(all(feature = "mimalloc"))]
@@ -89,7 +95,20 @@ fn try_main() -> Result<()> {
         flags::RustAnalyzerCmd::Symbols(cmd) => cmd.run()?,
         flags::RustAnalyzerCmd::Highlight(cmd) => cmd.run()?,
         flags::RustAnalyzerCmd::AnalysisStats(cmd) => cmd.run(verbosity)?,
-        flags::RustAnalyzerCmd::Diagnostics(cmd) => cmd.run()?,
+        flags::RustAnalyzerCmd::Diagnostics(cmd) => {
+            let code = cmd.run()?;
+            if code != 0 {
+                process::exit(code);
+            }
+        }
+        flags::RustAnalyzerCmd::Assist(cmd) => cmd.run()?,
+        flags::RustAnalyzerCmd::ApiDocs(cmd) => cmd.run()?,
+        flags::RustAnalyzerCmd::ApiDiff(cmd) => {
+            let code = cmd.run()?;
+            if code != 0 {
+                process::exit(code);
+            }
+        }
         flags::RustAnalyzerCmd::Ssr(cmd) => cmd.run()?,
         flags::RustAnalyzerCmd::Search(cmd) => cmd.run()?,
         flags::RustAnalyzerCmd::Lsif(cmd) => cmd.run()?,
@@ -158,6 +177,7 @@ fn run_server() -> Result<()> {
 
     let (initialize_id, initialize_params) = connection.initialize_start()?;
     tracing::info!("InitializeParams: {}", initialize_params);
+    let caps_ext = ClientCapabilitiesExt::from_json(&initialize_params["capabilities"]);
     let initialize_params =
         from_json::<lsp_types::InitializeParams>("InitializeParams", &initialize_params)?;
 
@@ -174,6 +194,7 @@ fn run_server() -> Result<()> {
     };
 
     let mut config = Config::new(root_path, initialize_params.capabilities);
+    config.set_caps_ext(caps_ext);
     if let Some(json) = initialize_params.initialization_options {
         if let Err(e) = config.update(json) {
             use lsp_types::{
@@ -203,7 +224,8 @@ fn run_server() -> Result<()> {
         },
     };
 
-    let initialize_result = serde_json::to_value(initialize_result).unwrap();
+    let mut initialize_result = serde_json::to_value(initialize_result).unwrap();
+    rust_analyzer::extend_server_capabilities(&config, &mut initialize_result["capabilities"]);
 
     connection.initialize_finish(initialize_id, initialize_result)?;
 
@@ -18,6 +18,10 @@ use crate::config::{Config, RustfmtConfig};
 use crate::semantic_tokens;
 
 pub fn server_capabilities(config: &Config) -> ServerCapabilities {
+    let runnable_kinds = match config.shell_runnables() {
+        true => json!(["cargo", "shell"]),
+        false => json!(["cargo"]),
+    };
     ServerCapabilities {
         text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
             open_close: Some(true),
@@ -134,7 +138,7 @@ pub fn server_capabilities(config: &Config) -> ServerCapabilities {
             "openCargoToml": true,
             "parentModule": true,
             "runnables": {
-                "kinds": [ "cargo" ],
+                "kinds": runnable_kinds,
             },
             "ssr": true,
             "workspaceSymbolScopeKindFiltering": true,
@@ -142,6 +146,19 @@ pub fn server_capabilities(config: &Config) -> ServerCapabilities {
     }
 }
 
+/// Adds the capabilities `lsp_types` can't express yet to the serialized `ServerCapabilities`.
+pub fn extend_server_capabilities(config: &Config, capabilities: &mut serde_json::Value) {
+    capabilities["typeHierarchyProvider"] = json!(true);
+    capabilities["inlineValueProvider"] = json!(true);
+    if config.pull_diagnostics() {
+        capabilities["diagnosticProvider"] = json!({
+            "identifier": "rust-analyzer",
+            "interFileDependencies": true,
+            "workspaceDiagnostics": true,
+        });
+    }
+}
+
 fn completions_resolve_provider(client_caps: &ClientCapabilities) -> Option<bool> {
     if completion_item_edit_resolve(client_caps) {
         Some(true)
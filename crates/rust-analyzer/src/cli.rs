@@ -7,6 +7,9 @@ mod symbols;
 mod highlight;
 mod analysis_stats;
 mod diagnostics;
+mod assist;
+mod api_docs;
+mod api_diff;
 mod ssr;
 mod lsif;
 mod scip;
@@ -0,0 +1,333 @@
+//! Compares the public API of the crates in two source trees, e.g. two checkouts of a repository,
+//! and classifies the differences by their semver impact.
+//!
+//! The API is collected like `api-docs` does, items are matched by their path. Removing an item or
+//! changing its signature is a major change, adding an item is a minor one, unless code using the
+//! parent of the item has to mention it, like a trait method without a default or a field of a
+//! struct without private fields.
+
+use std::{fmt, path::Path};
+
+use rustc_hash::FxHashMap;
+
+use ide::AnalysisHost;
+use project_model::CargoConfig;
+
+use crate::cli::{
+    api_docs::{workspace_crates, ApiCrate, ApiItem},
+    flags,
+    load_cargo::{load_workspace_at, LoadCargoConfig},
+};
+
+impl flags::ApiDiff {
+    /// Prints the changes and returns the exit code to use, `1` if there are major changes.
+    pub fn run(self) -> anyhow::Result<i32> {
+        let old = self.load_api(&self.old)?;
+        let new = self.load_api(&self.new)?;
+
+        let mut names = old.keys().chain(new.keys()).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        let mut changes = Vec::new();
+        for name in names {
+            let old = old.get(name).map_or(&[][..], |it| &it.items[..]);
+            let new = new.get(name).map_or(&[][..], |it| &it.items[..]);
+            changes.extend(diff(old, new));
+        }
+
+        for change in &changes {
+            print!("{}", change);
+        }
+        let bump = changes.iter().map(|it| it.impact).max();
+        let major = changes.iter().filter(|it| it.impact == Impact::Major).count();
+        let minor = changes.len() - major;
+        match bump {
+            Some(bump) => {
+                println!();
+                println!("{major} major and {minor} minor change(s), {bump} version bump required")
+            }
+            None => println!("no changes to the public API"),
+        }
+        Ok(if bump == Some(Impact::Major) { 1 } else { 0 })
+    }
+
+    fn load_api(&self, path: &Path) -> anyhow::Result<FxHashMap<String, ApiCrate>> {
+        let cargo_config = CargoConfig::default();
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: !self.disable_build_scripts,
+            with_proc_macro: !self.disable_proc_macros,
+            prefill_caches: false,
+        };
+        let (host, _vfs, _proc_macro) =
+            load_workspace_at(path, &cargo_config, &load_cargo_config, &|_| {})?;
+        collect_api(&host, self.crate_name.as_deref())
+    }
+}
+
+fn collect_api(
+    host: &AnalysisHost,
+    crate_name: Option<&str>,
+) -> anyhow::Result<FxHashMap<String, ApiCrate>> {
+    let db = host.raw_database();
+    let analysis = host.analysis();
+    let mut res = FxHashMap::default();
+    for krate in workspace_crates(db, crate_name) {
+        // Spans are not compared.
+        let api = ApiCrate::new(db, &analysis, krate, &|_| String::new())?;
+        res.insert(api.name.clone(), api);
+    }
+    Ok(res)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Impact {
+    Minor,
+    Major,
+}
+
+impl fmt::Display for Impact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Impact::Minor => "minor",
+            Impact::Major => "major",
+        })
+    }
+}
+
+struct Change<'a> {
+    impact: Impact,
+    old: Option<&'a ApiItem>,
+    new: Option<&'a ApiItem>,
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (what, item) = match (self.old, self.new) {
+            (Some(old), None) => ("removed", old),
+            (None, Some(new)) => ("added", new),
+            (_, Some(new)) => ("changed", new),
+            (None, None) => unreachable!(),
+        };
+        writeln!(f, "{} {} {} {}", self.impact, what, item.kind, item.path)?;
+        match (self.old, self.new) {
+            (Some(old), Some(new)) if old.signature == new.signature => {
+                let note = if new.required {
+                    "is now required"
+                } else if matches!(new.kind, "field" | "variant") {
+                    "is no longer required, its parent is not exhaustive anymore"
+                } else {
+                    "is no longer required, it has a default"
+                };
+                writeln!(f, "    {}", note)
+            }
+            (old, new) => {
+                if let Some(old) = old {
+                    writeln!(f, "    - {}", old.signature)?;
+                }
+                if let Some(new) = new {
+                    writeln!(f, "    + {}", new.signature)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Computes the changes between the items of two versions of a crate, in path order.
+///
+/// Paths are unique in valid code, but erroneous code, like two inherent impls defining the same
+/// method, can repeat them. Items sharing a path are paired up by their signature first, then in
+/// order.
+fn diff<'a>(old: &'a [ApiItem], new: &'a [ApiItem]) -> Vec<Change<'a>> {
+    let mut by_path = FxHashMap::<&str, (Vec<&ApiItem>, Vec<&ApiItem>)>::default();
+    for item in old {
+        by_path.entry(&item.path).or_default().0.push(item);
+    }
+    for item in new {
+        by_path.entry(&item.path).or_default().1.push(item);
+    }
+
+    let mut res = Vec::new();
+    for (_, (mut old, mut new)) in by_path {
+        old.retain(|old_item| {
+            let same = new.iter().position(|it| change_impact(old_item, it).is_none());
+            match same {
+                Some(idx) => {
+                    new.remove(idx);
+                    false
+                }
+                None => true,
+            }
+        });
+        let mut new = new.into_iter();
+        for item in old {
+            match new.next() {
+                Some(new) => {
+                    if let Some(impact) = change_impact(item, new) {
+                        res.push(Change { impact, old: Some(item), new: Some(new) });
+                    }
+                }
+                None => res.push(Change { impact: Impact::Major, old: Some(item), new: None }),
+            }
+        }
+        for item in new {
+            let impact = if item.required { Impact::Major } else { Impact::Minor };
+            res.push(Change { impact, old: None, new: Some(item) });
+        }
+    }
+    let path = |change: &Change<'_>| change.old.or(change.new).map(|it| it.path.clone());
+    res.sort_by_key(path);
+    res
+}
+
+fn change_impact(old: &ApiItem, new: &ApiItem) -> Option<Impact> {
+    if old.kind != new.kind || old.signature != new.signature {
+        return Some(Impact::Major);
+    }
+    match (old.required, new.required) {
+        (false, true) => {
+            // A trait item that loses its default breaks implementors, a type that becomes
+            // exhaustive only allows more code.
+            if matches!(new.kind, "field" | "variant") {
+                Some(Impact::Minor)
+            } else {
+                Some(Impact::Major)
+            }
+        }
+        (true, false) => {
+            if matches!(new.kind, "field" | "variant") {
+                Some(Impact::Major)
+            } else {
+                Some(Impact::Minor)
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use ide_db::base_db::fixture::ChangeFixture;
+
+    use super::*;
+
+    fn check(old: &str, new: &str, expect: Expect) {
+        let load = |fixture: &str| {
+            let mut host = AnalysisHost::default();
+            host.raw_database_mut().apply_change(ChangeFixture::parse(fixture).change);
+            collect_api(&host, None).unwrap()
+        };
+        let (old, new) = (load(old), load(new));
+        let actual = diff(&old["foo"].items, &new["foo"].items)
+            .iter()
+            .map(|it| it.to_string())
+            .collect::<String>();
+        expect.assert_eq(&actual)
+    }
+
+    #[test]
+    fn functions_and_types() {
+        check(
+            r#"
+//- /lib.rs crate:foo
+pub fn removed() {}
+pub fn changed(a: u32) {}
+pub struct S { pub a: u32 }
+pub struct Private { pub a: u32, b: u32 }
+"#,
+            r#"
+//- /lib.rs crate:foo
+pub fn changed(a: u64) {}
+pub fn added() {}
+pub struct S { pub a: u32, pub b: u32 }
+pub struct Private { pub a: u32, pub c: u32, b: u32 }
+"#,
+            expect![[r#"
+                minor added field foo::Private::c
+                    + pub c: u32
+                major added field foo::S::b
+                    + pub b: u32
+                minor added function foo::added
+                    + pub fn added()
+                major changed function foo::changed
+                    - pub fn changed(a: u32)
+                    + pub fn changed(a: u64)
+                major removed function foo::removed
+                    - pub fn removed()
+            "#]],
+        );
+    }
+
+    #[test]
+    fn traits_and_enums() {
+        check(
+            r#"
+//- /lib.rs crate:foo
+pub trait Tr {
+    fn required(&self);
+    fn provided(&self) {}
+}
+pub enum E { A }
+#[non_exhaustive]
+pub enum Open { A }
+"#,
+            r#"
+//- /lib.rs crate:foo
+pub trait Tr {
+    fn required(&self) {}
+    fn provided(&self);
+    fn new_provided(&self) {}
+    fn new_required(&self);
+}
+pub enum E { A, B }
+#[non_exhaustive]
+pub enum Open { A, B }
+"#,
+            expect![[r#"
+                major added variant foo::E::B
+                    + B
+                minor added variant foo::Open::B
+                    + B
+                minor added function foo::Tr::new_provided
+                    + pub fn new_provided(&self)
+                major added function foo::Tr::new_required
+                    + pub fn new_required(&self)
+                major changed function foo::Tr::provided
+                    is now required
+                minor changed function foo::Tr::required
+                    is no longer required, it has a default
+            "#]],
+        );
+    }
+
+    #[test]
+    fn methods_of_several_impls() {
+        check(
+            r#"
+//- /lib.rs crate:foo
+pub struct G<T>(T);
+impl G<u32> { pub fn get(&self) -> u32 { 0 } }
+impl G<i32> { pub fn get(&self) -> i32 { 0 } }
+pub struct S;
+impl S { pub fn f(&self) {} }
+impl S { pub fn f(&self, a: u32) {} }
+"#,
+            r#"
+//- /lib.rs crate:foo
+pub struct G<T>(T);
+impl G<u32> { pub fn get(&self) -> u64 { 0 } }
+impl G<i32> { pub fn get(&self) -> i32 { 0 } }
+pub struct S;
+impl S { pub fn f(&self, a: u32) {} }
+"#,
+            expect![[r#"
+                major changed function foo::G<u32>::get
+                    - pub fn get(&self) -> u32
+                    + pub fn get(&self) -> u64
+                major removed function foo::S::f
+                    - pub fn f(&self)
+            "#]],
+        );
+    }
+}
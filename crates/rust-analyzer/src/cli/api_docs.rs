@@ -0,0 +1,568 @@
+//! Prints the public API of the workspace crates as JSON, in a format modelled after the one of
+//! `rustdoc --output-format json`, but computed from the semantic model instead of the compiler:
+//!
+//! ```text
+//! {
+//!   "format_version": 1,
+//!   "crates": [{
+//!     "name": "foo",
+//!     "version": "0.1.0",
+//!     "items": [{
+//!       "path": "foo::Bar::new",
+//!       "kind": "function",
+//!       "signature": "pub fn new() -> Bar",
+//!       "docs": "Creates a [`Bar`].",
+//!       "links": { "Bar": "foo::Bar" },
+//!       "span": { "filename": "src/lib.rs", "begin": [3, 5], "end": [5, 6] }
+//!     }]
+//!   }]
+//! }
+//! ```
+//!
+//! The items of a crate are a flat list sorted by `path`, which is unique within the crate. Fields,
+//! variants and associated items extend the path of their parent, trait implementations for the
+//! public types of the crate are listed with the full path of the trait, like
+//! `impl core::fmt::Debug for foo::Bar`. The items of inherent impls and trait implementations
+//! for a generic type carry the generic arguments of the impl, like `foo::Bar<u32>::new` or
+//! `impl core::convert::From<u8> for foo::Bar<u32>`. `kind` is one of `module`, `struct`,
+//! `enum`, `union`, `field`, `variant`, `function`, `const`, `static`, `trait`, `type_alias`,
+//! `macro` or `impl`. `links` maps the intra-doc links of `docs` to the paths of the items they
+//! resolve to. Lines and columns of spans are one-based, columns count bytes.
+//!
+//! An item is public if it and all the modules leading to it are `pub` and it is not
+//! `#[doc(hidden)]`. Re-exports are not followed.
+
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use hir::{
+    Adt, AssocItem, Crate, Documentation, FieldSource, HasAttrs, HasSource, HasVisibility,
+    HirDisplay, Impl, InFile, Module, ModuleDef, ModuleSource, Variant, Visibility,
+};
+use ide::{Analysis, FileId};
+use ide_db::{base_db::SourceDatabaseExt, defs::Definition, LineIndexDatabase, RootDatabase};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+use syntax::{ast, AstNode, SyntaxNode};
+use vfs::AbsPathBuf;
+
+use crate::cli::{
+    flags,
+    load_cargo::{load_workspace_at, LoadCargoConfig},
+};
+
+/// Bumped whenever the output changes in an incompatible way.
+const FORMAT_VERSION: u32 = 1;
+
+impl flags::ApiDocs {
+    pub fn run(self) -> anyhow::Result<()> {
+        let cargo_config = Default::default();
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: !self.disable_build_scripts,
+            with_proc_macro: !self.disable_proc_macros,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &cargo_config, &load_cargo_config, &|_| {})?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+
+        let root = AbsPathBuf::assert(std::env::current_dir()?.join(&self.path)).normalize();
+        let file_name = |file_id| {
+            let path = vfs.file_path(file_id);
+            match path.as_path() {
+                Some(path) => match path.strip_prefix(&root) {
+                    Some(relative) => relative.as_ref().display().to_string(),
+                    None => path.display().to_string(),
+                },
+                None => path.to_string(),
+            }
+        };
+
+        let crates = workspace_crates(db, self.crate_name.as_deref());
+        if crates.is_empty() {
+            match self.crate_name {
+                Some(name) => bail!("crate `{}` is not part of the workspace", name),
+                None => bail!("the workspace has no crates"),
+            }
+        }
+        let crates = crates
+            .into_iter()
+            .map(|krate| ApiCrate::new(db, &analysis, krate, &file_name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let docs = ApiDocs { format_version: FORMAT_VERSION, crates };
+        println!("{}", serde_json::to_string_pretty(&docs)?);
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct ApiDocs {
+    format_version: u32,
+    crates: Vec<ApiCrate>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ApiCrate {
+    pub(crate) name: String,
+    pub(crate) version: Option<String>,
+    pub(crate) items: Vec<ApiItem>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ApiItem {
+    pub(crate) path: String,
+    pub(crate) kind: &'static str,
+    pub(crate) signature: String,
+    pub(crate) docs: Option<String>,
+    pub(crate) links: BTreeMap<String, String>,
+    pub(crate) span: Option<Span>,
+    /// Whether code outside of the crate has to mention the item when it implements, constructs
+    /// or matches on its parent: trait items without a default and the fields and variants of
+    /// exhaustive types.
+    #[serde(skip)]
+    pub(crate) required: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Span {
+    pub(crate) filename: String,
+    pub(crate) begin: (u32, u32),
+    pub(crate) end: (u32, u32),
+}
+
+/// Returns the crates of the workspace, skipping dependencies and the sysroot. Targets of a
+/// package that share its name are only listed once.
+pub(crate) fn workspace_crates(db: &RootDatabase, name: Option<&str>) -> Vec<Crate> {
+    let mut seen = FxHashSet::default();
+    Crate::all(db)
+        .into_iter()
+        .filter(|krate| !db.source_root(db.file_source_root(krate.root_file(db))).is_library)
+        .filter(|&krate| {
+            let krate_name = crate_name(db, krate);
+            name.map_or(true, |it| it == krate_name) && seen.insert(krate_name)
+        })
+        .collect()
+}
+
+fn crate_name(db: &RootDatabase, krate: Crate) -> String {
+    krate.display_name(db).map_or_else(|| "{unknown}".to_string(), |it| it.to_string())
+}
+
+impl ApiCrate {
+    pub(crate) fn new(
+        db: &RootDatabase,
+        analysis: &Analysis,
+        krate: Crate,
+        file_name: &dyn Fn(FileId) -> String,
+    ) -> anyhow::Result<ApiCrate> {
+        let name = crate_name(db, krate);
+        let mut collector = Collector {
+            db,
+            file_name,
+            items: Vec::new(),
+            paths: FxHashMap::default(),
+            docs: Vec::new(),
+        };
+        collector.collect_module(krate.root_module(db), &name);
+        for impl_ in Impl::all_in_crate(db, krate) {
+            collector.collect_impl(impl_);
+        }
+
+        // Links are resolved last, so that they can point to items that come after them.
+        let Collector { mut items, paths, docs, .. } = collector;
+        for (idx, def, docs) in docs {
+            for (link, target) in analysis.resolve_doc_links(def, &docs)? {
+                let path = paths.get(&target).cloned().or_else(|| fallback_path(db, target));
+                if let Some(path) = path {
+                    items[idx].links.insert(link, path);
+                }
+            }
+        }
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(ApiCrate { name, version: krate.version(db), items })
+    }
+}
+
+struct Collector<'a> {
+    db: &'a RootDatabase,
+    file_name: &'a dyn Fn(FileId) -> String,
+    items: Vec<ApiItem>,
+    /// The paths of the collected items, to render the targets of intra-doc links.
+    paths: FxHashMap<Definition, String>,
+    /// The docs of the collected items, by their index in `items`.
+    docs: Vec<(usize, Definition, Documentation)>,
+}
+
+impl Collector<'_> {
+    fn collect_module(&mut self, module: Module, path: &str) {
+        let db = self.db;
+        let src = match module.declaration_source(db) {
+            Some(it) => Some(syntax_node(it)),
+            None => {
+                let src = module.definition_source(db);
+                let node = match &src.value {
+                    ModuleSource::SourceFile(it) => it.syntax().clone(),
+                    ModuleSource::Module(it) => it.syntax().clone(),
+                    ModuleSource::BlockExpr(it) => it.syntax().clone(),
+                };
+                Some(src.with_value(node))
+            }
+        };
+        self.add(path.to_string(), "module", module, src);
+
+        for decl in module.declarations(db) {
+            let hidden = decl.attrs(db).map_or(false, |it| it.has_doc_hidden());
+            if decl.visibility(db) != Visibility::Public || hidden {
+                continue;
+            }
+            let name = match decl.name(db) {
+                Some(it) => format!("{}::{}", path, it),
+                None => continue,
+            };
+            match decl {
+                ModuleDef::Module(it) => self.collect_module(it, &name),
+                ModuleDef::Adt(adt) => {
+                    let kind = match adt {
+                        Adt::Struct(_) => "struct",
+                        Adt::Union(_) => "union",
+                        Adt::Enum(_) => "enum",
+                    };
+                    self.add(name.clone(), kind, adt, adt.source(db).map(syntax_node));
+                    match adt {
+                        Adt::Struct(it) => {
+                            let fields = it.fields(db);
+                            let exhaustive = !is_non_exhaustive(db, it)
+                                && fields.iter().all(|it| it.visibility(db) == Visibility::Public);
+                            self.collect_fields(&name, fields, exhaustive);
+                        }
+                        // A union is constructed from one of its fields, new ones don't matter.
+                        Adt::Union(it) => self.collect_fields(&name, it.fields(db), false),
+                        Adt::Enum(it) => {
+                            for variant in it.variants(db) {
+                                self.collect_variant(&name, variant);
+                            }
+                        }
+                    }
+                }
+                ModuleDef::Trait(it) => {
+                    self.add(name.clone(), "trait", it, it.source(db).map(syntax_node));
+                    for item in it.items(db) {
+                        self.collect_assoc_item(&name, item, true);
+                    }
+                }
+                ModuleDef::Function(it) => {
+                    self.add(name, "function", it, it.source(db).map(syntax_node));
+                }
+                ModuleDef::Const(it) => {
+                    self.add(name, "const", it, it.source(db).map(syntax_node));
+                }
+                ModuleDef::Static(it) => {
+                    self.add(name, "static", it, it.source(db).map(syntax_node));
+                }
+                ModuleDef::TypeAlias(it) => {
+                    self.add(name, "type_alias", it, it.source(db).map(syntax_node));
+                }
+                ModuleDef::Macro(it) => {
+                    let src = it.source(db).map(|src| {
+                        src.map(|it| it.either(|it| it.syntax().clone(), |it| it.syntax().clone()))
+                    });
+                    self.add(name, "macro", it, src);
+                }
+                ModuleDef::Variant(_) | ModuleDef::BuiltinType(_) => (),
+            }
+        }
+    }
+
+    fn collect_fields(&mut self, parent: &str, fields: Vec<hir::Field>, exhaustive: bool) {
+        let db = self.db;
+        for field in fields {
+            if field.visibility(db) != Visibility::Public || is_hidden(db, field) {
+                continue;
+            }
+            let src = field_source(db, field);
+            self.add(format!("{}::{}", parent, field.name(db)), "field", field, src).required =
+                exhaustive;
+        }
+    }
+
+    fn collect_variant(&mut self, parent: &str, variant: Variant) {
+        let db = self.db;
+        if is_hidden(db, variant) {
+            return;
+        }
+        let path = format!("{}::{}", parent, variant.name(db));
+        let parent_exhaustive = !is_non_exhaustive(db, variant.parent_enum(db));
+        self.add(path.clone(), "variant", variant, variant.source(db).map(syntax_node)).required =
+            parent_exhaustive;
+        let exhaustive = !is_non_exhaustive(db, variant);
+        // Variant fields have no visibility of their own, they are as public as the enum.
+        for field in variant.fields(db) {
+            if is_hidden(db, field) {
+                continue;
+            }
+            let src = field_source(db, field);
+            self.add(format!("{}::{}", path, field.name(db)), "field", field, src).required =
+                exhaustive;
+        }
+    }
+
+    fn collect_assoc_item(&mut self, parent: &str, item: AssocItem, in_trait: bool) {
+        let db = self.db;
+        let name = match item.name(db) {
+            Some(it) => format!("{}::{}", parent, it),
+            None => return,
+        };
+        if is_hidden(db, item) {
+            return;
+        }
+        let (added, has_default) = match item {
+            AssocItem::Function(it) => {
+                (self.add(name, "function", it, it.source(db).map(syntax_node)), it.has_body(db))
+            }
+            AssocItem::Const(it) => (
+                self.add(name, "const", it, it.source(db).map(syntax_node)),
+                it.value(db).is_some(),
+            ),
+            AssocItem::TypeAlias(it) => {
+                let src = it.source(db).map(syntax_node);
+                (self.add(name, "type_alias", it, src), it.type_ref(db).is_some())
+            }
+        };
+        added.required = in_trait && !has_default;
+    }
+
+    /// Collects the public items of inherent impls and the trait impls of the types that have
+    /// been collected already.
+    fn collect_impl(&mut self, impl_: Impl) {
+        let db = self.db;
+        let self_ty = impl_.self_ty(db);
+        let adt = match self_ty.as_adt() {
+            Some(it) => it,
+            None => return,
+        };
+        let adt_path = match self.paths.get(&Definition::Adt(adt)) {
+            Some(it) => it,
+            None => return,
+        };
+        if is_hidden(db, impl_) {
+            return;
+        }
+        // Impls for different instantiations of a type can define items of the same name, the
+        // generic arguments tell them apart.
+        let self_ty_text = self_ty.display(db).to_string();
+        let self_args = self_ty_text.strip_prefix(&*adt.name(db).to_string()).unwrap_or_default();
+        let self_path = format!("{}{}", adt_path, self_args);
+        match impl_.trait_(db) {
+            Some(trait_) => {
+                let src = impl_.source(db);
+                let trait_args = src
+                    .as_ref()
+                    .and_then(|it| match it.value.trait_()? {
+                        ast::Type::PathType(it) => it.path()?.segment()?.generic_arg_list(),
+                        _ => None,
+                    })
+                    .map_or_else(String::new, |it| it.syntax().text().to_string());
+                let signature =
+                    format!("impl {}{} for {}", trait_.name(db), trait_args, self_ty_text);
+                let path = format!(
+                    "impl {}{} for {}",
+                    trait_path(db, trait_, &self.paths),
+                    trait_args,
+                    self_path
+                );
+                let span = src.and_then(|it| self.span(syntax_node(it)));
+                self.items.push(ApiItem {
+                    path,
+                    kind: "impl",
+                    signature,
+                    docs: None,
+                    links: BTreeMap::new(),
+                    span,
+                    required: false,
+                });
+            }
+            None => {
+                for item in impl_.items(db) {
+                    if item.visibility(db) == Visibility::Public {
+                        self.collect_assoc_item(&self_path, item, false);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add<T>(
+        &mut self,
+        path: String,
+        kind: &'static str,
+        def: T,
+        src: Option<InFile<SyntaxNode>>,
+    ) -> &mut ApiItem
+    where
+        T: HirDisplay + HasAttrs + Into<Definition> + Copy,
+    {
+        let db = self.db;
+        let docs = def.docs(db);
+        if let Some(docs) = &docs {
+            self.docs.push((self.items.len(), def.into(), docs.clone()));
+        }
+        self.paths.insert(def.into(), path.clone());
+        self.items.push(ApiItem {
+            path,
+            kind,
+            signature: def.display(db).to_string(),
+            docs: docs.map(String::from),
+            links: BTreeMap::new(),
+            span: src.and_then(|it| self.span(it)),
+            required: false,
+        });
+        self.items.last_mut().unwrap()
+    }
+
+    fn span(&self, src: InFile<SyntaxNode>) -> Option<Span> {
+        let db = self.db;
+        let range = src.as_ref().original_file_range_opt(db)?;
+        let line_index = db.line_index(range.file_id);
+        let position = |offset| {
+            let line_col = line_index.line_col(offset);
+            (line_col.line + 1, line_col.col + 1)
+        };
+        Some(Span {
+            filename: (self.file_name)(range.file_id),
+            begin: position(range.range.start()),
+            end: position(range.range.end()),
+        })
+    }
+}
+
+fn syntax_node<N: AstNode>(src: InFile<N>) -> InFile<SyntaxNode> {
+    src.map(|it| it.syntax().clone())
+}
+
+fn field_source(db: &RootDatabase, field: hir::Field) -> Option<InFile<SyntaxNode>> {
+    let src = field.source(db)?;
+    Some(src.map(|it| match it {
+        FieldSource::Named(it) => it.syntax().clone(),
+        FieldSource::Pos(it) => it.syntax().clone(),
+    }))
+}
+
+fn is_hidden(db: &RootDatabase, def: impl HasAttrs) -> bool {
+    def.attrs(db).has_doc_hidden()
+}
+
+fn is_non_exhaustive(db: &RootDatabase, def: impl HasAttrs) -> bool {
+    def.attrs(db).by_key("non_exhaustive").exists()
+}
+
+fn trait_path(
+    db: &RootDatabase,
+    trait_: hir::Trait,
+    paths: &FxHashMap<Definition, String>,
+) -> String {
+    let def = Definition::Trait(trait_);
+    paths
+        .get(&def)
+        .cloned()
+        .or_else(|| fallback_path(db, def))
+        .unwrap_or_else(|| trait_.name(db).to_string())
+}
+
+/// Renders the path of an item that is not part of the listed API, e.g. because it lives in
+/// another crate.
+fn fallback_path(db: &RootDatabase, def: Definition) -> Option<String> {
+    let mut segments = vec![crate_name(db, def.krate(db)?)];
+    // Only the crate root has no parent module.
+    if let Some(module_path) = def.canonical_module_path(db) {
+        segments.extend(module_path.filter_map(|it| it.name(db)).map(|it| it.to_string()));
+        segments.push(def.name(db)?.to_string());
+    }
+    Some(segments.join("::"))
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use ide::AnalysisHost;
+    use ide_db::base_db::fixture::ChangeFixture;
+    use stdx::format_to;
+
+    use super::*;
+
+    fn check(ra_fixture: &str, expect: Expect) {
+        let mut host = AnalysisHost::default();
+        host.raw_database_mut().apply_change(ChangeFixture::parse(ra_fixture).change);
+        let db = host.raw_database();
+        let krate = workspace_crates(db, None)[0];
+        let api = ApiCrate::new(db, &host.analysis(), krate, &|_| String::new()).unwrap();
+
+        let mut actual = String::new();
+        for item in api.items {
+            format_to!(actual, "{} {}\n", item.kind, item.path);
+            for (link, target) in item.links {
+                format_to!(actual, "    {} -> {}\n", link, target);
+            }
+        }
+        expect.assert_eq(&actual)
+    }
+
+    #[test]
+    fn public_items() {
+        check(
+            r#"
+//- /lib.rs crate:foo
+pub mod api {
+    pub struct Foo { pub a: u32, b: u32 }
+    impl Foo {
+        pub fn new() -> Foo { Foo { a: 0, b: 0 } }
+        fn private() {}
+    }
+    /// Makes a [`Foo`] from an [`E`](crate::E).
+    pub fn make() -> Foo { Foo::new() }
+}
+mod private {
+    pub struct Unreachable;
+}
+pub enum E { A, B(u32) }
+#[doc(hidden)]
+pub fn hidden() {}
+pub trait Tr { fn f(&self); }
+impl Tr for api::Foo { fn f(&self) {} }
+pub struct G<T>(T);
+impl G<u32> { pub fn get() {} }
+impl G<i32> { pub fn get() {} }
+pub trait Conv<T> {}
+impl Conv<u8> for G<u32> {}
+impl Conv<u16> for G<u32> {}
+impl Tr for G<u32> { fn f(&self) {} }
+impl Tr for G<i32> { fn f(&self) {} }
+"#,
+            expect![[r#"
+                module foo
+                trait foo::Conv
+                enum foo::E
+                variant foo::E::A
+                variant foo::E::B
+                field foo::E::B::0
+                struct foo::G
+                function foo::G<i32>::get
+                function foo::G<u32>::get
+                trait foo::Tr
+                function foo::Tr::f
+                module foo::api
+                struct foo::api::Foo
+                field foo::api::Foo::a
+                function foo::api::Foo::new
+                function foo::api::make
+                    Foo -> foo::api::Foo
+                    crate::E -> foo::E
+                impl impl foo::Conv<u16> for foo::G<u32>
+                impl impl foo::Conv<u8> for foo::G<u32>
+                impl impl foo::Tr for foo::G<i32>
+                impl impl foo::Tr for foo::G<u32>
+                impl impl foo::Tr for foo::api::Foo
+            "#]],
+        );
+    }
+}
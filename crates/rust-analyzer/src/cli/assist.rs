@@ -0,0 +1,195 @@
+//! Lists the assists available at a location, and applies them to the files on disk.
+
+use std::{env, fs};
+
+use anyhow::{bail, format_err};
+use ide::{
+    Analysis, Assist, AssistConfig, AssistResolveStrategy, DiagnosticsConfig, FileRange,
+    FileSystemEdit, LineColUtf16, SourceChange, TextRange, TextSize,
+};
+use ide_db::base_db::AnchoredPathBuf;
+use project_model::CargoConfig;
+use vfs::{AbsPathBuf, Vfs, VfsPath};
+
+use crate::cli::{
+    flags,
+    load_cargo::{load_workspace_at, LoadCargoConfig},
+    Result,
+};
+
+impl flags::Assist {
+    pub fn run(self) -> Result<()> {
+        let cargo_config = CargoConfig::default();
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: !self.disable_build_scripts,
+            with_proc_macro: !self.disable_proc_macros,
+            prefill_caches: false,
+        };
+        let cwd = AbsPathBuf::assert(env::current_dir()?);
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(cwd.as_ref(), &cargo_config, &load_cargo_config, &|_| {})?;
+        let analysis = host.analysis();
+        let frange = parse_location(&analysis, &vfs, &cwd, &self.location)?;
+
+        let assist_config = AssistConfig {
+            // Snippets only make sense in an editor, the edits are written out as they are.
+            snippet_cap: None,
+            allowed: None,
+            insert_use: DiagnosticsConfig::test_sample().insert_use,
+            prefer_no_std: false,
+        };
+        let mut diagnostics_config = DiagnosticsConfig::test_sample();
+        diagnostics_config.proc_macros_enabled = !self.disable_proc_macros;
+        diagnostics_config.proc_attr_macros_enabled = !self.disable_proc_macros;
+
+        let id = match self.apply {
+            Some(it) => it,
+            None => {
+                let assists = analysis.assists_with_fixes(
+                    &assist_config,
+                    &diagnostics_config,
+                    AssistResolveStrategy::None,
+                    frange,
+                )?;
+                for (assist, nth) in with_nth(&assists) {
+                    println!("{}[{}]\t{}\t{}", assist.id.0, nth, assist.id.1.name(), assist.label);
+                }
+                return Ok(());
+            }
+        };
+
+        let assists = analysis.assists_with_fixes(
+            &assist_config,
+            &diagnostics_config,
+            AssistResolveStrategy::All,
+            frange,
+        )?;
+        let candidates = with_nth(&assists)
+            .filter(|(assist, _)| assist.id.0 == id)
+            .filter(|&(_, nth)| self.nth.map_or(true, |it| it == nth))
+            .map(|(assist, _)| assist)
+            .collect::<Vec<_>>();
+        let assist = match candidates.as_slice() {
+            [] => bail!("assist `{}` is not available at {}", id, self.location),
+            [it] => it,
+            _ => bail!("assist `{}` is ambiguous at {}, pick one with `--nth`", id, self.location),
+        };
+        let source_change = assist
+            .source_change
+            .as_ref()
+            .ok_or_else(|| format_err!("assist `{}` has no changes", id))?;
+        apply_source_change(&analysis, &vfs, source_change)?;
+        eprintln!("applied `{}`: {}", id, assist.label);
+        Ok(())
+    }
+}
+
+/// Numbers the assists sharing an id, so that they can be told apart.
+fn with_nth(assists: &[Assist]) -> impl Iterator<Item = (&Assist, usize)> {
+    assists.iter().enumerate().map(move |(idx, assist)| {
+        let nth = assists[..idx].iter().filter(|it| it.id.0 == assist.id.0).count();
+        (assist, nth)
+    })
+}
+
+/// Parses `file:line:col` or `file:line:col-line:col`.
+fn parse_location(
+    analysis: &Analysis,
+    vfs: &Vfs,
+    cwd: &AbsPathBuf,
+    location: &str,
+) -> Result<FileRange> {
+    let invalid = || format_err!("invalid location `{}`, expected `file:line:col`", location);
+    let (rest, end_col) = location.rsplit_once(':').ok_or_else(invalid)?;
+    let (rest, mid) = rest.rsplit_once(':').ok_or_else(invalid)?;
+    let (path, start, end) = match mid.split_once('-') {
+        Some((start_col, end_line)) => {
+            let (path, start_line) = rest.rsplit_once(':').ok_or_else(invalid)?;
+            (path, (start_line, start_col), Some((end_line, end_col)))
+        }
+        None => (rest, (mid, end_col), None),
+    };
+
+    let path = VfsPath::from(cwd.join(path).normalize());
+    let file_id =
+        vfs.file_id(&path).ok_or_else(|| format_err!("{} is not in the workspace", path))?;
+    let line_index = analysis.file_line_index(file_id)?;
+    let offset = |(line, col): (&str, &str)| -> Result<TextSize> {
+        let line =
+            line.parse::<u32>().map_err(|_| invalid())?.checked_sub(1).ok_or_else(invalid)?;
+        let col = col.parse::<u32>().map_err(|_| invalid())?.checked_sub(1).ok_or_else(invalid)?;
+        let line_col = line_index.to_utf8(LineColUtf16 { line, col });
+        line_index.offset(line_col).ok_or_else(|| format_err!("{} is out of bounds", location))
+    };
+    let start = offset(start)?;
+    let range = match end {
+        Some(end) => {
+            let end = offset(end)?;
+            if end < start {
+                bail!("invalid location `{}`, the range ends before it starts", location);
+            }
+            TextRange::new(start, end)
+        }
+        None => TextRange::empty(start),
+    };
+    Ok(FileRange { file_id, range })
+}
+
+/// Writes the changes to disk. Text edits are applied first, as they refer to the files by their
+/// current location.
+fn apply_source_change(analysis: &Analysis, vfs: &Vfs, source_change: &SourceChange) -> Result<()> {
+    let on_disk = |path: VfsPath| {
+        path.as_path()
+            .map(|it| it.to_path_buf())
+            .ok_or_else(|| format_err!("{} is not on disk", path))
+    };
+    let anchored = |path: &AnchoredPathBuf| {
+        let mut base = vfs.file_path(path.anchor);
+        base.pop();
+        base.join(&path.path).ok_or_else(|| format_err!("invalid path {}", path.path))
+    };
+
+    // Resolve all paths upfront, moves would invalidate the anchors.
+    let mut text_edits = Vec::new();
+    for (&file_id, edit) in &source_change.source_file_edits {
+        let mut text = analysis.file_text(file_id)?.to_string();
+        edit.apply(&mut text);
+        text_edits.push((on_disk(vfs.file_path(file_id))?, text));
+    }
+    let mut fs_edits = Vec::new();
+    for edit in &source_change.file_system_edits {
+        let edit = match edit {
+            FileSystemEdit::CreateFile { dst, initial_contents } => {
+                (None, on_disk(anchored(dst)?)?, Some(initial_contents.clone()))
+            }
+            FileSystemEdit::MoveFile { src, dst } => {
+                (Some(on_disk(vfs.file_path(*src))?), on_disk(anchored(dst)?)?, None)
+            }
+            FileSystemEdit::MoveDir { src, src_id: _, dst } => {
+                (Some(on_disk(anchored(src)?)?), on_disk(anchored(dst)?)?, None)
+            }
+        };
+        fs_edits.push(edit);
+    }
+
+    for (path, text) in text_edits {
+        fs::write(&path, text)?;
+        eprintln!("edited {}", path.display());
+    }
+    for (src, dst, contents) in fs_edits {
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match (src, contents) {
+            (Some(src), _) => {
+                fs::rename(&src, &dst)?;
+                eprintln!("moved {} to {}", src.display(), dst.display());
+            }
+            (None, contents) => {
+                fs::write(&dst, contents.unwrap_or_default())?;
+                eprintln!("created {}", dst.display());
+            }
+        }
+    }
+    Ok(())
+}
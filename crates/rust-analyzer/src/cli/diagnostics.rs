@@ -1,77 +1,118 @@
-//! Analyze all modules in a project for diagnostics. Exits with a non-zero
-//! status code if any errors are found.
+//! Analyze all modules in a project for diagnostics, optionally applying their fixes. Exits with
+//! a non-zero status code if any errors are left unfixed.
 
-use rustc_hash::FxHashSet;
+use std::{env, fs};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+use serde_json::json;
 
 use hir::{db::HirDatabase, Crate, Module};
-use ide::{AssistResolveStrategy, DiagnosticsConfig, Severity};
-use ide_db::base_db::SourceDatabaseExt;
+use ide::{
+    Analysis, AssistResolveStrategy, Diagnostic, DiagnosticsConfig, FileId, LineIndex, Severity,
+    SourceChange, TextEdit, TextRange,
+};
+use ide_db::{base_db::SourceDatabaseExt, LineIndexDatabase, RootDatabase};
+use vfs::{AbsPathBuf, Vfs};
 
 use crate::cli::{
-    flags,
+    flags::{self, DiagnosticsFormat},
     load_cargo::{load_workspace_at, LoadCargoConfig},
 };
 
 impl flags::Diagnostics {
-    pub fn run(self) -> anyhow::Result<()> {
+    /// Runs the diagnostics and returns the exit code to use.
+    pub fn run(self) -> anyhow::Result<i32> {
         let cargo_config = Default::default();
         let load_cargo_config = LoadCargoConfig {
             load_out_dirs_from_check: !self.disable_build_scripts,
             with_proc_macro: !self.disable_proc_macros,
             prefill_caches: false,
         };
-        let (host, _vfs, _proc_macro) =
+        let (host, vfs, _proc_macro) =
             load_workspace_at(&self.path, &cargo_config, &load_cargo_config, &|_| {})?;
         let db = host.raw_database();
         let analysis = host.analysis();
 
-        let mut found_error = false;
-        let mut visited_files = FxHashSet::default();
-
-        let work = all_modules(db).into_iter().filter(|module| {
-            let file_id = module.definition_source(db).file_id.original_file(db);
-            let source_root = db.file_source_root(file_id);
-            let source_root = db.source_root(source_root);
-            !source_root.is_library
-        });
-
-        for module in work {
-            let file_id = module.definition_source(db).file_id.original_file(db);
-            if !visited_files.contains(&file_id) {
-                let crate_name =
-                    module.krate().display_name(db).as_deref().unwrap_or("unknown").to_string();
-                println!("processing crate: {}, module: {}", crate_name, _vfs.file_path(file_id));
-                for diagnostic in analysis
-                    .diagnostics(
-                        &DiagnosticsConfig::test_sample(),
-                        AssistResolveStrategy::None,
-                        file_id,
-                    )
-                    .unwrap()
-                {
-                    if matches!(diagnostic.severity, Severity::Error) {
-                        found_error = true;
-                    }
+        let mut config = DiagnosticsConfig::test_sample();
+        config.proc_macros_enabled = !self.disable_proc_macros;
+        config.proc_attr_macros_enabled = !self.disable_proc_macros;
+        config.disable_experimental = !self.experimental;
 
-                    println!("{:?}", diagnostic);
-                }
+        let format = self.format.unwrap_or(DiagnosticsFormat::Text);
+        // Fixes are costly to compute, only do so when they are going to be used.
+        let resolve_fixes = self.fix || format != DiagnosticsFormat::Text;
 
-                visited_files.insert(file_id);
+        let mut diagnostics = Vec::new();
+        for file_id in workspace_files(db) {
+            let resolve = if resolve_fixes {
+                AssistResolveStrategy::All
+            } else {
+                AssistResolveStrategy::None
+            };
+            for diagnostic in analysis.diagnostics(&config, resolve, file_id)? {
+                diagnostics.push((file_id, diagnostic));
             }
         }
 
-        println!();
-        println!("diagnostic scan complete");
+        if self.fix {
+            let fixed = apply_fixes(&analysis, &vfs, &diagnostics)?;
+            eprintln!("fixed {} diagnostic(s)", fixed.len());
+            diagnostics = diagnostics
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, _)| !fixed.contains(idx))
+                .map(|(_, it)| it)
+                .collect();
+        }
 
-        if found_error {
-            println!();
-            anyhow::bail!("diagnostic error detected")
+        let paths = Paths::new(&vfs)?;
+        match format {
+            DiagnosticsFormat::Text => print_text(db, &paths, &diagnostics),
+            DiagnosticsFormat::Json => {
+                let diagnostics = diagnostics
+                    .iter()
+                    .map(|(file_id, d)| JsonDiagnostic::new(db, &paths, *file_id, d))
+                    .collect::<Vec<_>>();
+                println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+            }
+            DiagnosticsFormat::Sarif => {
+                println!("{}", serde_json::to_string_pretty(&sarif(db, &paths, &diagnostics))?);
+            }
         }
 
-        Ok(())
+        let has_errors = diagnostics.iter().any(|(_, d)| matches!(d.severity, Severity::Error));
+        if !self.detailed_exit_code {
+            if has_errors {
+                anyhow::bail!("diagnostic error detected")
+            }
+            return Ok(0);
+        }
+        let exit_code = if has_errors {
+            1
+        } else if !diagnostics.is_empty() {
+            2
+        } else {
+            0
+        };
+        Ok(exit_code)
     }
 }
 
+/// Returns the files of all modules of the workspace, skipping dependencies and the sysroot.
+fn workspace_files(db: &RootDatabase) -> Vec<FileId> {
+    let mut visited_files = FxHashSet::default();
+    let mut files = Vec::new();
+    for module in all_modules(db) {
+        let file_id = module.definition_source(db).file_id.original_file(db);
+        let source_root = db.source_root(db.file_source_root(file_id));
+        if !source_root.is_library && visited_files.insert(file_id) {
+            files.push(file_id);
+        }
+    }
+    files
+}
+
 fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
     let mut worklist: Vec<_> =
         Crate::all(db).into_iter().map(|krate| krate.root_module(db)).collect();
@@ -84,3 +125,397 @@ fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
 
     modules
 }
+
+/// Applies the fixes of the diagnostics that have exactly one of them, unless it conflicts with
+/// an earlier fix or can't be expressed as plain text edits. Returns the indices of the fixed
+/// diagnostics.
+fn apply_fixes(
+    analysis: &Analysis,
+    vfs: &Vfs,
+    diagnostics: &[(FileId, Diagnostic)],
+) -> anyhow::Result<FxHashSet<usize>> {
+    let (edits, fixed) = merge_fixes(diagnostics);
+    for (file_id, edit) in edits {
+        let mut text = analysis.file_text(file_id)?.to_string();
+        edit.apply(&mut text);
+        let path = vfs.file_path(file_id);
+        let path = path.as_path().ok_or_else(|| anyhow::format_err!("{} is not on disk", path))?;
+        fs::write(path, text)?;
+    }
+    Ok(fixed)
+}
+
+/// Merges the fixes [`apply_fixes`] applies into one edit per file.
+fn merge_fixes(
+    diagnostics: &[(FileId, Diagnostic)],
+) -> (FxHashMap<FileId, TextEdit>, FxHashSet<usize>) {
+    let mut edits: FxHashMap<FileId, TextEdit> = FxHashMap::default();
+    let mut fixed = FxHashSet::default();
+    for (idx, (_, diagnostic)) in diagnostics.iter().enumerate() {
+        let source_change = match diagnostic.fixes.as_deref() {
+            Some([fix]) => match &fix.source_change {
+                Some(it) => it,
+                None => continue,
+            },
+            _ => continue,
+        };
+        if let Some(merged) = merge_source_change(&edits, source_change) {
+            edits.extend(merged);
+            fixed.insert(idx);
+        }
+    }
+    (edits, fixed)
+}
+
+fn merge_source_change(
+    edits: &FxHashMap<FileId, TextEdit>,
+    source_change: &SourceChange,
+) -> Option<Vec<(FileId, TextEdit)>> {
+    if source_change.is_snippet || !source_change.file_system_edits.is_empty() {
+        return None;
+    }
+    source_change
+        .source_file_edits
+        .iter()
+        .map(|(&file_id, edit)| {
+            let mut merged = edits.get(&file_id).cloned().unwrap_or_default();
+            merged.union(edit.clone()).ok()?;
+            Some((file_id, merged))
+        })
+        .collect()
+}
+
+/// Renders file paths relative to the working directory when possible.
+struct Paths<'a> {
+    vfs: &'a Vfs,
+    cwd: AbsPathBuf,
+}
+
+impl<'a> Paths<'a> {
+    fn new(vfs: &'a Vfs) -> anyhow::Result<Paths<'a>> {
+        Ok(Paths { vfs, cwd: AbsPathBuf::assert(env::current_dir()?) })
+    }
+
+    fn display(&self, file_id: FileId) -> String {
+        let path = self.vfs.file_path(file_id);
+        match path.as_path() {
+            Some(path) => match path.strip_prefix(&self.cwd) {
+                Some(relative) => relative.as_ref().display().to_string(),
+                None => path.display().to_string(),
+            },
+            None => path.to_string(),
+        }
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::WeakWarning => "weak_warning",
+    }
+}
+
+fn print_text(db: &RootDatabase, paths: &Paths<'_>, diagnostics: &[(FileId, Diagnostic)]) {
+    let (mut errors, mut weak_warnings) = (0, 0);
+    for (file_id, d) in diagnostics {
+        match d.severity {
+            Severity::Error => errors += 1,
+            Severity::WeakWarning => weak_warnings += 1,
+        }
+        let start = Position::new(&db.line_index(*file_id), d.range.start());
+        println!(
+            "{}:{}:{}: {}[{}]: {}",
+            paths.display(*file_id),
+            start.line,
+            start.column,
+            severity_name(d.severity),
+            d.code.as_str(),
+            d.message
+        );
+    }
+    println!();
+    println!("diagnostic scan complete: {errors} error(s), {weak_warnings} weak warning(s)");
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic {
+    code: String,
+    severity: &'static str,
+    message: String,
+    file: String,
+    range: Range,
+    fixes: Vec<JsonFix>,
+}
+
+#[derive(Serialize)]
+struct JsonFix {
+    id: String,
+    label: String,
+    edits: Vec<JsonTextEdit>,
+}
+
+#[derive(Serialize)]
+struct JsonTextEdit {
+    file: String,
+    range: Range,
+    new_text: String,
+}
+
+impl JsonDiagnostic {
+    fn new(db: &RootDatabase, paths: &Paths<'_>, file_id: FileId, d: &Diagnostic) -> Self {
+        let fixes = d
+            .fixes
+            .iter()
+            .flatten()
+            .map(|fix| JsonFix {
+                id: fix.id.0.to_string(),
+                label: fix.label.to_string(),
+                edits: fix.source_change.iter().flat_map(|it| text_edits(db, paths, it)).collect(),
+            })
+            .collect();
+        JsonDiagnostic {
+            code: d.code.as_str().to_string(),
+            severity: severity_name(d.severity),
+            message: d.message.clone(),
+            file: paths.display(file_id),
+            range: Range::new(&db.line_index(file_id), d.range),
+            fixes,
+        }
+    }
+}
+
+fn text_edits(
+    db: &RootDatabase,
+    paths: &Paths<'_>,
+    source_change: &SourceChange,
+) -> Vec<JsonTextEdit> {
+    let mut res = Vec::new();
+    for (&file_id, edit) in &source_change.source_file_edits {
+        let line_index = db.line_index(file_id);
+        res.extend(edit.iter().map(|indel| JsonTextEdit {
+            file: paths.display(file_id),
+            range: Range::new(&line_index, indel.delete),
+            new_text: indel.insert.clone(),
+        }));
+    }
+    res
+}
+
+/// Line and column are one-based, columns count UTF-16 code units like LSP and SARIF do.
+#[derive(Serialize)]
+struct Position {
+    line: u32,
+    column: u32,
+}
+
+impl Position {
+    fn new(line_index: &LineIndex, offset: ide::TextSize) -> Position {
+        let line_col = line_index.to_utf16(line_index.line_col(offset));
+        Position { line: line_col.line + 1, column: line_col.col + 1 }
+    }
+}
+
+#[derive(Serialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+impl Range {
+    fn new(line_index: &LineIndex, range: TextRange) -> Range {
+        Range {
+            start: Position::new(line_index, range.start()),
+            end: Position::new(line_index, range.end()),
+        }
+    }
+
+    fn to_sarif_region(&self) -> serde_json::Value {
+        json!({
+            "startLine": self.start.line,
+            "startColumn": self.start.column,
+            "endLine": self.end.line,
+            "endColumn": self.end.column,
+        })
+    }
+}
+
+fn sarif(
+    db: &RootDatabase,
+    paths: &Paths<'_>,
+    diagnostics: &[(FileId, Diagnostic)],
+) -> serde_json::Value {
+    let mut codes = diagnostics.iter().map(|(_, d)| d.code.as_str()).collect::<Vec<_>>();
+    codes.sort_unstable();
+    codes.dedup();
+    let rules = codes
+        .iter()
+        .map(|code| {
+            json!({
+                "id": code,
+                "helpUri": format!("https://rust-analyzer.github.io/manual.html#{code}"),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let results = diagnostics
+        .iter()
+        .map(|(file_id, d)| {
+            let d = JsonDiagnostic::new(db, paths, *file_id, d);
+            let fixes = d
+                .fixes
+                .iter()
+                .map(|fix| {
+                    let mut changes: Vec<(&str, Vec<serde_json::Value>)> = Vec::new();
+                    for edit in &fix.edits {
+                        let replacement = json!({
+                            "deletedRegion": edit.range.to_sarif_region(),
+                            "insertedContent": { "text": edit.new_text },
+                        });
+                        match changes.iter_mut().find(|(file, _)| *file == edit.file) {
+                            Some((_, replacements)) => replacements.push(replacement),
+                            None => changes.push((&edit.file, vec![replacement])),
+                        }
+                    }
+                    let changes = changes
+                        .into_iter()
+                        .map(|(file, replacements)| {
+                            json!({
+                                "artifactLocation": { "uri": file },
+                                "replacements": replacements,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    json!({
+                        "description": { "text": fix.label },
+                        "artifactChanges": changes,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "ruleId": d.code,
+                "level": match d.severity {
+                    "error" => "error",
+                    _ => "note",
+                },
+                "message": { "text": d.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": d.file },
+                        "region": d.range.to_sarif_region(),
+                    },
+                }],
+                "fixes": fixes,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "rust-analyzer",
+                    "version": crate::version::version().to_string(),
+                    "informationUri": "https://rust-analyzer.github.io",
+                    "rules": rules,
+                },
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ide::AnalysisHost;
+    use ide_db::base_db::fixture::ChangeFixture;
+    use stdx::trim_indent;
+    use test_utils::assert_eq_text;
+
+    use super::*;
+
+    fn check_fixes(ra_fixture_before: &str, ra_fixture_after: &str) {
+        let mut host = AnalysisHost::default();
+        let fixture = ChangeFixture::parse(ra_fixture_before);
+        host.raw_database_mut().apply_change(fixture.change);
+        let analysis = host.analysis();
+        let file_id = fixture.files[0];
+
+        let mut config = DiagnosticsConfig::test_sample();
+        config.disabled.insert("inactive-code".to_string());
+        let diagnostics = analysis
+            .diagnostics(&config, AssistResolveStrategy::All, file_id)
+            .unwrap()
+            .into_iter()
+            .map(|d| (file_id, d))
+            .collect::<Vec<_>>();
+        let (edits, _) = merge_fixes(&diagnostics);
+
+        let mut actual = analysis.file_text(file_id).unwrap().to_string();
+        if let Some(edit) = edits.get(&file_id) {
+            edit.apply(&mut actual);
+        }
+        assert_eq_text!(&trim_indent(ra_fixture_after), &actual);
+    }
+
+    #[test]
+    fn applies_unambiguous_fixes() {
+        check_fixes(
+            r#"
+struct S { a: u32, b: u32 }
+fn f(a: u32, b: u32) -> S {
+    let mut x = 0;
+    let _ = x;
+    S { a: a, b: b }
+}
+"#,
+            r#"
+struct S { a: u32, b: u32 }
+fn f(a: u32, b: u32) -> S {
+    let x = 0;
+    let _ = x;
+    S { a, b }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn skips_fixes_creating_files() {
+        check_fixes(
+            r#"
+mod foo;
+fn f() {}
+"#,
+            r#"
+mod foo;
+fn f() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn merge_source_change_rejects_conflicts() {
+        let file_id = FileId(0);
+        let edit = |start: u32, end: u32, text: &str| {
+            TextEdit::replace(TextRange::new(start.into(), end.into()), text.to_string())
+        };
+        let mut edits = FxHashMap::default();
+        edits.insert(file_id, edit(0, 4, "a"));
+
+        let disjoint = SourceChange::from_text_edit(file_id, edit(6, 8, "b"));
+        let merged = merge_source_change(&edits, &disjoint).unwrap();
+        let mut text = "0123456789".to_string();
+        merged[0].1.apply(&mut text);
+        assert_eq!(text, "a45b89");
+
+        let overlapping = SourceChange::from_text_edit(file_id, edit(2, 6, "c"));
+        assert!(merge_source_change(&edits, &overlapping).is_none());
+
+        let mut snippet = SourceChange::from_text_edit(file_id, edit(6, 8, "$0"));
+        snippet.is_snippet = true;
+        assert!(merge_source_change(&edits, &snippet).is_none());
+    }
+}
@@ -92,12 +92,75 @@ xflags::xflags! {
             optional --disable-build-scripts
             /// Don't use expand proc macros.
             optional --disable-proc-macros
+            /// Also report experimental diagnostics.
+            optional --experimental
+
+            /// Output format, one of `text` (default), `json` or `sarif`.
+            optional --format format: DiagnosticsFormat
+            /// Apply the fixes of diagnostics that have a single, unambiguous quick fix.
+            optional --fix
+            /// Exit with `1` if errors are left, `2` if only weak warnings are left and `0`
+            /// otherwise, instead of failing only on errors.
+            optional --detailed-exit-code
+        }
+
+        /// List the assists available at a location, or apply one of them.
+        cmd assist
+            /// Location to look for assists at, `file:line:col` or `file:line:col-line:col`.
+            /// Lines and columns are 1-based, columns count UTF-16 code units.
+            required location: String
+        {
+            /// Apply the assist with this id instead of listing the available assists.
+            optional --apply id: String
+            /// Which of the assists sharing the id passed to `--apply` to pick, as listed.
+            optional --nth nth: usize
+
+            /// Don't run build scripts or load `OUT_DIR` values by running `cargo check` before analysis.
+            optional --disable-build-scripts
+            /// Don't use expand proc macros.
+            optional --disable-proc-macros
+        }
+
+        /// Print the public API of the workspace crates as JSON.
+        cmd api-docs
+            /// Directory with Cargo.toml.
+            required path: PathBuf
+        {
+            /// Only print the crate with this name.
+            optional --crate-name name: String
+
+            /// Don't run build scripts or load `OUT_DIR` values by running `cargo check` before analysis.
+            optional --disable-build-scripts
+            /// Don't use expand proc macros.
+            optional --disable-proc-macros
+        }
+
+        /// Compare the public API of the crates in two source trees and classify the changes by
+        /// their semver impact. Exits with `1` if there are breaking changes.
+        cmd api-diff
+            /// Directory with the Cargo.toml of the old version.
+            required old: PathBuf
+            /// Directory with the Cargo.toml of the new version.
+            required new: PathBuf
+        {
+            /// Only compare the crate with this name.
+            optional --crate-name name: String
+
+            /// Don't run build scripts or load `OUT_DIR` values by running `cargo check` before analysis.
+            optional --disable-build-scripts
+            /// Don't use expand proc macros.
+            optional --disable-proc-macros
         }
 
         cmd ssr
             /// A structured search replace rule (`$a.foo($b) ==> bar($a, $b)`)
             repeated rule: SsrRule
-        {}
+        {
+            /// Applies the rules from a rules file, after the ones given on the command line.
+            optional --rules path: PathBuf
+            /// Prints the changes as unified diffs instead of writing them.
+            optional --dry-run
+        }
 
         cmd search
             /// A structured search replace pattern (`$a.foo($b)`)
@@ -140,6 +203,9 @@ pub enum RustAnalyzerCmd {
     Highlight(Highlight),
     AnalysisStats(AnalysisStats),
     Diagnostics(Diagnostics),
+    Assist(Assist),
+    ApiDocs(ApiDocs),
+    ApiDiff(ApiDiff),
     Ssr(Ssr),
     Search(Search),
     ProcMacro(ProcMacro),
@@ -190,11 +256,47 @@ pub struct Diagnostics {
 
     pub disable_build_scripts: bool,
     pub disable_proc_macros: bool,
+    pub experimental: bool,
+    pub format: Option<DiagnosticsFormat>,
+    pub fix: bool,
+    pub detailed_exit_code: bool,
+}
+
+#[derive(Debug)]
+pub struct Assist {
+    pub location: String,
+
+    pub apply: Option<String>,
+    pub nth: Option<usize>,
+    pub disable_build_scripts: bool,
+    pub disable_proc_macros: bool,
+}
+
+#[derive(Debug)]
+pub struct ApiDocs {
+    pub path: PathBuf,
+
+    pub crate_name: Option<String>,
+    pub disable_build_scripts: bool,
+    pub disable_proc_macros: bool,
+}
+
+#[derive(Debug)]
+pub struct ApiDiff {
+    pub old: PathBuf,
+    pub new: PathBuf,
+
+    pub crate_name: Option<String>,
+    pub disable_build_scripts: bool,
+    pub disable_proc_macros: bool,
 }
 
 #[derive(Debug)]
 pub struct Ssr {
     pub rule: Vec<SsrRule>,
+
+    pub rules: Option<PathBuf>,
+    pub dry_run: bool,
 }
 
 #[derive(Debug)]
@@ -237,6 +339,13 @@ pub enum OutputFormat {
     Csv,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticsFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
 impl RustAnalyzer {
     pub fn verbosity(&self) -> Verbosity {
         if self.quiet {
@@ -260,3 +369,16 @@ impl FromStr for OutputFormat {
         }
     }
 }
+
+impl FromStr for DiagnosticsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            _ => Err(format!("unknown diagnostics format `{}`", s)),
+        }
+    }
+}
@@ -1,40 +1,111 @@
 //! Applies structured search replace rules from the command line.
 
-use ide_ssr::MatchFinder;
+use std::{env, fs, path::Path, sync::Arc};
+
+use ide::{Change, FileId};
+use ide_db::base_db::SourceDatabaseExt;
+use ide_ssr::{MatchFinder, SsrRule, SsrRuleFile};
 use project_model::CargoConfig;
+use rustc_hash::FxHashMap;
+use vfs::AbsPathBuf;
 
-use crate::cli::{
-    flags,
-    load_cargo::{load_workspace_at, LoadCargoConfig},
-    Result,
+use crate::{
+    cli::{
+        flags,
+        load_cargo::{load_workspace_at, LoadCargoConfig},
+        Result,
+    },
+    diff::unified_diff,
 };
 
+/// A set of rules that is applied at once, on top of the changes made by the previous steps.
+struct Step {
+    name: Option<String>,
+    /// The files the rules may change, all files if empty.
+    paths: Vec<AbsPathBuf>,
+    rules: Vec<SsrRule>,
+}
+
 impl flags::Ssr {
     pub fn run(self) -> Result<()> {
-        use ide_db::base_db::SourceDatabaseExt;
         let cargo_config = CargoConfig::default();
         let load_cargo_config = LoadCargoConfig {
             load_out_dirs_from_check: true,
             with_proc_macro: true,
             prefill_caches: false,
         };
-        let (host, vfs, _proc_macro) = load_workspace_at(
-            &std::env::current_dir()?,
-            &cargo_config,
-            &load_cargo_config,
-            &|_| {},
-        )?;
-        let db = host.raw_database();
-        let mut match_finder = MatchFinder::at_first_file(db)?;
-        for rule in self.rule {
-            match_finder.add_rule(rule)?;
+        let cwd = AbsPathBuf::assert(env::current_dir()?);
+        let (mut host, vfs, _proc_macro) =
+            load_workspace_at(cwd.as_ref(), &cargo_config, &load_cargo_config, &|_| {})?;
+
+        let mut steps = Vec::new();
+        if !self.rule.is_empty() {
+            steps.push(Step { name: None, paths: Vec::new(), rules: self.rule });
+        }
+        if let Some(rules_path) = &self.rules {
+            let rules_path = cwd.join(rules_path);
+            let rule_file: SsrRuleFile = fs::read_to_string(&rules_path)?.parse()?;
+            // Paths in a rules file are relative to it, so that it can be run from anywhere.
+            let base = rules_path.parent().unwrap_or(&cwd);
+            steps.extend(rule_file.rules.into_iter().map(|rule| Step {
+                name: Some(rule.name),
+                paths: rule.paths.iter().map(|it| base.join(it).normalize()).collect(),
+                rules: vec![rule.rule],
+            }));
+        }
+
+        let mut original_texts: FxHashMap<FileId, Arc<String>> = FxHashMap::default();
+        for step in steps {
+            let edits = {
+                let mut match_finder = MatchFinder::at_first_file(host.raw_database())?;
+                for rule in step.rules {
+                    match_finder.add_rule(rule)?;
+                }
+                match_finder.edits()
+            };
+            let mut change = Change::new();
+            let mut changed_files = 0;
+            for (file_id, edit) in edits {
+                let in_paths = match vfs.file_path(file_id).as_path() {
+                    Some(path) => {
+                        step.paths.is_empty() || step.paths.iter().any(|it| path.starts_with(it))
+                    }
+                    None => false,
+                };
+                if !in_paths {
+                    continue;
+                }
+                let text = host.raw_database().file_text(file_id);
+                original_texts.entry(file_id).or_insert_with(|| text.clone());
+                let mut text = String::clone(&text);
+                edit.apply(&mut text);
+                change.change_file(file_id, Some(Arc::new(text)));
+                changed_files += 1;
+            }
+            if let Some(name) = &step.name {
+                eprintln!("{}: changed {} files", name, changed_files);
+            }
+            host.apply_change(change);
         }
-        let edits = match_finder.edits();
-        for (file_id, edit) in edits {
-            if let Some(path) = vfs.file_path(file_id).as_path() {
-                let mut contents = db.file_text(file_id).to_string();
-                edit.apply(&mut contents);
-                std::fs::write(path, contents)?;
+
+        let mut changed = original_texts
+            .into_iter()
+            .filter_map(|(file_id, original)| {
+                let path = vfs.file_path(file_id).as_path()?.to_path_buf();
+                Some((path, file_id, original))
+            })
+            .collect::<Vec<_>>();
+        changed.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+        for (path, file_id, original) in changed {
+            let text = host.raw_database().file_text(file_id);
+            if self.dry_run {
+                let display_path = match path.strip_prefix(&cwd) {
+                    Some(it) => Path::display(it.as_ref()).to_string(),
+                    None => path.display().to_string(),
+                };
+                print!("{}", unified_diff(&display_path, &original, &text));
+            } else {
+                fs::write(&path, text.as_bytes())?;
             }
         }
         Ok(())
@@ -46,7 +117,6 @@ impl flags::Search {
     /// `debug_snippet`. This is intended for debugging and probably isn't in it's current form useful
     /// for much else.
     pub fn run(self) -> Result<()> {
-        use ide_db::base_db::SourceDatabaseExt;
         use ide_db::symbol_index::SymbolsDatabase;
         let cargo_config = CargoConfig::default();
         let load_cargo_config = LoadCargoConfig {
@@ -21,6 +21,7 @@ use ide_db::{
 };
 use itertools::Itertools;
 use lsp_types::{ClientCapabilities, MarkupKind};
+use proc_macro_api::ExpansionCacheConfig;
 use project_model::{
     CargoConfig, CargoFeatures, ProjectJson, ProjectJsonData, ProjectManifest, RustcSource,
     UnsetTestCrates,
@@ -96,6 +97,9 @@ config_data! {
         cargo_noDefaultFeatures: bool    = "false",
         /// Internal config for debugging, disables loading of sysroot crates.
         cargo_noSysroot: bool            = "false",
+        /// Compilation target overrides for specific packages, mapping package names to target
+        /// triples. The dependencies of these packages are built for the same target.
+        cargo_packageTargets: FxHashMap<String, String> = "{}",
         /// Compilation target override (target triple).
         cargo_target: Option<String>     = "null",
         /// Unsets `#[cfg(test)]` for the specified crates.
@@ -220,6 +224,15 @@ config_data! {
         /// and a blue icon in the `Problems Panel`.
         diagnostics_warningsAsInfo: Vec<String> = "[]",
 
+        /// Command to discover the project of an opened file that doesn't belong
+        /// to any loaded project, for build systems other than Cargo.
+        ///
+        /// The path of the file is appended to the command, which must print
+        /// the project in the `rust-project.json` format to stdout. The command
+        /// runs in the workspace root, relative paths are resolved against it.
+        /// The projects of all opened files are merged together.
+        discoverProjectCommand: Option<Vec<String>> = "null",
+
         /// These directories will be ignored by rust-analyzer. They are
         /// relative to the workspace root, and globs are not supported. You may
         /// also need to add the folders to Code's `files.watcherExclude`.
@@ -285,6 +298,15 @@ config_data! {
         inlayHints_closingBraceHints_minLines: usize               = "25",
         /// Whether to show inlay type hints for return types of closures.
         inlayHints_closureReturnTypeHints_enable: ClosureReturnTypeHintsDef  = "\"never\"",
+        /// Whether to show enum variant discriminant hints.
+        inlayHints_discriminantHints_enable: DiscriminantHintsDef  = "\"never\"",
+        /// Whether to show inlay hints for type adjustments, like autoref, autoderef and unsizing.
+        /// Implicit reborrows are shown as part of these, regardless of
+        /// `#rust-analyzer.inlayHints.reborrowHints.enable#`.
+        inlayHints_expressionAdjustmentHints_enable: bool          = "false",
+        /// Whether to show inlay hints for the implicit drops of local variables at the end of
+        /// their scope.
+        inlayHints_implicitDrops_enable: bool                      = "false",
         /// Whether to show inlay type hints for elided lifetimes in function signatures.
         inlayHints_lifetimeElisionHints_enable: LifetimeElisionDef = "\"never\"",
         /// Whether to prefer using parameter names as the name for elided lifetime hints if possible.
@@ -349,7 +371,8 @@ config_data! {
         /// of projects.
         ///
         /// Elements must be paths pointing to `Cargo.toml`,
-        /// `rust-project.json`, or JSON objects in `rust-project.json` format.
+        /// `rust-project.json`, cargo scripts, or JSON objects in
+        /// `rust-project.json` format.
         linkedProjects: Vec<ManifestOrProjectJson> = "[]",
 
         /// Number of syntax trees rust-analyzer keeps in memory. Defaults to 128.
@@ -360,6 +383,15 @@ config_data! {
 
         /// Expand attribute macros. Requires `#rust-analyzer.procMacro.enable#` to be set.
         procMacro_attributes_enable: bool = "true",
+        /// Directory to persist cached proc-macro expansions to, relative to the workspace root.
+        /// Requires `#rust-analyzer.procMacro.cache.enable#` to be set.
+        procMacro_cache_directory: Option<PathBuf> = "null",
+        /// Cache the expansions of procedural macros, so that the same input is only sent to the
+        /// proc-macro server once. Only enable this if the proc-macros in use are deterministic.
+        procMacro_cache_enable: bool = "false",
+        /// Names of proc-macros whose expansions must never be cached, e.g. because they read
+        /// files or environment variables.
+        procMacro_cache_exclude: Vec<String> = "[]",
         /// Enable support for procedural macros, implies `#rust-analyzer.cargo.buildScripts.enable#`.
         procMacro_enable: bool                     = "true",
         /// These proc-macros will be ignored when trying to expand them.
@@ -463,12 +495,37 @@ impl Default for ConfigData {
 pub struct Config {
     pub discovered_projects: Option<Vec<ProjectManifest>>,
     caps: lsp_types::ClientCapabilities,
+    caps_ext: ClientCapabilitiesExt,
     root_path: AbsPathBuf,
     data: ConfigData,
     detached_files: Vec<AbsPathBuf>,
     snippets: Vec<Snippet>,
 }
 
+/// Client capabilities which `lsp_types::ClientCapabilities` can't represent yet.
+#[derive(Debug, Clone, Default)]
+pub struct ClientCapabilitiesExt {
+    /// `textDocument.diagnostic`
+    pub pull_diagnostics: bool,
+    /// `workspace.diagnostics.refreshSupport`
+    pub diagnostics_refresh: bool,
+}
+
+impl ClientCapabilitiesExt {
+    /// Extracts the capabilities from the raw `capabilities` of the `InitializeParams`.
+    pub fn from_json(caps: &serde_json::Value) -> ClientCapabilitiesExt {
+        ClientCapabilitiesExt {
+            pull_diagnostics: caps
+                .pointer("/textDocument/diagnostic")
+                .map_or(false, |it| it.is_object()),
+            diagnostics_refresh: caps
+                .pointer("/workspace/diagnostics/refreshSupport")
+                .and_then(|it| it.as_bool())
+                .unwrap_or(false),
+        }
+    }
+}
+
 type ParallelCachePrimingNumThreads = u8;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -661,6 +718,7 @@ impl Config {
     pub fn new(root_path: AbsPathBuf, caps: ClientCapabilities) -> Self {
         Config {
             caps,
+            caps_ext: ClientCapabilitiesExt::default(),
             data: ConfigData::default(),
             detached_files: Vec::new(),
             discovered_projects: None,
@@ -742,6 +800,10 @@ impl Config {
         &self.caps
     }
 
+    pub fn set_caps_ext(&mut self, caps_ext: ClientCapabilitiesExt) {
+        self.caps_ext = caps_ext;
+    }
+
     pub fn detached_files(&self) -> &[AbsPathBuf] {
         &self.detached_files
     }
@@ -779,7 +841,8 @@ impl Config {
                         .iter()
                         .filter(|p| {
                             let (ProjectManifest::ProjectJson(path)
-                            | ProjectManifest::CargoToml(path)) = p;
+                            | ProjectManifest::CargoToml(path)
+                            | ProjectManifest::CargoScript(path)) = p;
                             !exclude_dirs.iter().any(|p| path.starts_with(p))
                         })
                         .cloned()
@@ -817,6 +880,10 @@ impl Config {
         )
     }
 
+    pub fn discover_project_command(&self) -> Option<&[String]> {
+        self.data.discoverProjectCommand.as_deref()
+    }
+
     pub fn prefill_caches(&self) -> bool {
         self.data.cachePriming_enable
     }
@@ -936,8 +1003,23 @@ impl Config {
         self.experimental("serverStatusNotification")
     }
 
+    /// Whether the client can run `"shell"` runnables, as used by `rust-project.json` crates.
+    pub fn shell_runnables(&self) -> bool {
+        self.experimental("shellRunnables")
+    }
+
     pub fn publish_diagnostics(&self) -> bool {
-        self.data.diagnostics_enable
+        self.data.diagnostics_enable && !self.caps_ext.pull_diagnostics
+    }
+
+    /// Whether native diagnostics are requested by the client through `textDocument/diagnostic`
+    /// and `workspace/diagnostic` instead of being published by the server.
+    pub fn pull_diagnostics(&self) -> bool {
+        self.data.diagnostics_enable && self.caps_ext.pull_diagnostics
+    }
+
+    pub fn diagnostics_refresh(&self) -> bool {
+        self.pull_diagnostics() && self.caps_ext.diagnostics_refresh
     }
 
     pub fn diagnostics(&self) -> DiagnosticsConfig {
@@ -988,6 +1070,18 @@ impl Config {
         Some((path, vec!["proc-macro".into()]))
     }
 
+    pub fn proc_macro_cache(&self) -> Option<ExpansionCacheConfig> {
+        if !self.data.procMacro_cache_enable {
+            return None;
+        }
+        let directory = self.data.procMacro_cache_directory.as_ref();
+        Some(ExpansionCacheConfig {
+            exclude: self.data.procMacro_cache_exclude.clone(),
+            directory: directory.map(|it| self.root_path.join(it)),
+            version: crate::version::version().to_string(),
+        })
+    }
+
     pub fn dummy_replacements(&self) -> &FxHashMap<Box<str>, Box<[Box<str>]>> {
         &self.data.procMacro_ignored
     }
@@ -1038,6 +1132,7 @@ impl Config {
                 },
             },
             target: self.data.cargo_target.clone(),
+            package_targets: self.data.cargo_packageTargets.clone(),
             no_sysroot: self.data.cargo_noSysroot,
             rustc_source,
             unset_test_crates: UnsetTestCrates::Only(self.data.cargo_unsetTest.clone()),
@@ -1125,6 +1220,11 @@ impl Config {
                 ClosureReturnTypeHintsDef::Never => ide::ClosureReturnTypeHints::Never,
                 ClosureReturnTypeHintsDef::WithBlock => ide::ClosureReturnTypeHints::WithBlock,
             },
+            discriminant_hints: match self.data.inlayHints_discriminantHints_enable {
+                DiscriminantHintsDef::Always => ide::DiscriminantHints::Always,
+                DiscriminantHintsDef::Never => ide::DiscriminantHints::Never,
+                DiscriminantHintsDef::Fieldless => ide::DiscriminantHints::Fieldless,
+            },
             lifetime_elision_hints: match self.data.inlayHints_lifetimeElisionHints_enable {
                 LifetimeElisionDef::Always => ide::LifetimeElisionHints::Always,
                 LifetimeElisionDef::Never => ide::LifetimeElisionHints::Never,
@@ -1139,7 +1239,9 @@ impl Config {
                 ReborrowHintsDef::Never => ide::ReborrowHints::Never,
                 ReborrowHintsDef::Mutable => ide::ReborrowHints::MutableOnly,
             },
+            adjustment_hints: self.data.inlayHints_expressionAdjustmentHints_enable,
             binding_mode_hints: self.data.inlayHints_bindingModeHints_enable,
+            implicit_drop_hints: self.data.inlayHints_implicitDrops_enable,
             param_names_for_lifetime_elision_hints: self
                 .data
                 .inlayHints_lifetimeElisionHints_useParameterNames,
@@ -1472,6 +1574,7 @@ mod de_unit_v {
     named_unit_variant!(skip_trivial);
     named_unit_variant!(mutable);
     named_unit_variant!(with_block);
+    named_unit_variant!(fieldless);
 }
 
 #[derive(Deserialize, Debug, Clone, Copy)]
@@ -1595,6 +1698,17 @@ enum ClosureReturnTypeHintsDef {
     WithBlock,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum DiscriminantHintsDef {
+    #[serde(deserialize_with = "true_or_always")]
+    Always,
+    #[serde(deserialize_with = "false_or_never")]
+    Never,
+    #[serde(deserialize_with = "de_unit_v::fieldless")]
+    Fieldless,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(untagged)]
 enum ReborrowHintsDef {
@@ -1902,6 +2016,19 @@ fn field_props(field: &str, ty: &str, doc: &[&str], default: &str) -> serde_json
                 "Only show type hints for return types of closures with blocks."
             ]
         },
+        "DiscriminantHintsDef" => set! {
+            "type": "string",
+            "enum": [
+                "always",
+                "never",
+                "fieldless"
+            ],
+            "enumDescriptions": [
+                "Always show all discriminant hints.",
+                "Never show discriminant hints.",
+                "Only show discriminant hints on fieldless enum variants."
+            ]
+        },
         "ReborrowHintsDef" => set! {
             "type": "string",
             "enum": [
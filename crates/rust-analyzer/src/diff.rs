@@ -1,6 +1,9 @@
 //! Generate minimal `TextEdit`s from different text versions
+use std::fmt::Write;
+
 use dissimilar::Chunk;
 use ide::{TextEdit, TextRange, TextSize};
+use rustc_hash::FxHashMap;
 
 pub(crate) fn diff(left: &str, right: &str) -> TextEdit {
     let chunks = dissimilar::diff(left, right);
@@ -38,6 +41,113 @@ fn textedit_from_chunks(chunks: Vec<dissimilar::Chunk<'_>>) -> TextEdit {
     builder.finish()
 }
 
+/// Renders the changes between two versions of the file at `path` as a unified diff.
+pub(crate) fn unified_diff(path: &str, left: &str, right: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let left_lines = left.split_inclusive('\n').collect::<Vec<_>>();
+    let right_lines = right.split_inclusive('\n').collect::<Vec<_>>();
+
+    // Diff the lines rather than the characters, by mapping every distinct line to a char.
+    let mut interned = FxHashMap::default();
+    let left_chars = encode_lines(&mut interned, &left_lines);
+    let right_chars = encode_lines(&mut interned, &right_lines);
+
+    // Every line, tagged with ' ', '-' or '+'.
+    let mut lines = Vec::new();
+    match (left_chars, right_chars) {
+        (Some(left_chars), Some(right_chars)) => {
+            let (mut left_idx, mut right_idx) = (0, 0);
+            for chunk in dissimilar::diff(&left_chars, &right_chars) {
+                match chunk {
+                    Chunk::Equal(text) => {
+                        let n = text.chars().count();
+                        lines.extend(left_lines[left_idx..][..n].iter().map(|&it| (' ', it)));
+                        left_idx += n;
+                        right_idx += n;
+                    }
+                    Chunk::Delete(text) => {
+                        let n = text.chars().count();
+                        lines.extend(left_lines[left_idx..][..n].iter().map(|&it| ('-', it)));
+                        left_idx += n;
+                    }
+                    Chunk::Insert(text) => {
+                        let n = text.chars().count();
+                        lines.extend(right_lines[right_idx..][..n].iter().map(|&it| ('+', it)));
+                        right_idx += n;
+                    }
+                }
+            }
+        }
+        // Too many distinct lines to map them to chars, replace the whole file instead.
+        _ => {
+            lines.extend(left_lines.iter().map(|&it| ('-', it)));
+            lines.extend(right_lines.iter().map(|&it| ('+', it)));
+        }
+    }
+
+    let changed = lines.iter().enumerate().filter(|(_, (tag, _))| *tag != ' ').map(|(idx, _)| idx);
+    // Group the changes into hunks, merging the ones whose context would overlap.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for idx in changed {
+        let start = idx.saturating_sub(CONTEXT);
+        let end = (idx + 1 + CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut buf = format!("--- a/{path}\n+++ b/{path}\n");
+    for (start, end) in hunks {
+        let before = &lines[..start];
+        let left_start = before.iter().filter(|(tag, _)| *tag != '+').count();
+        let right_start = before.iter().filter(|(tag, _)| *tag != '-').count();
+        let hunk = &lines[start..end];
+        let left_len = hunk.iter().filter(|(tag, _)| *tag != '+').count();
+        let right_len = hunk.iter().filter(|(tag, _)| *tag != '-').count();
+        // Empty ranges are numbered by the line before them.
+        let line_number = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+        let _ = writeln!(
+            buf,
+            "@@ -{},{} +{},{} @@",
+            line_number(left_start, left_len),
+            left_len,
+            line_number(right_start, right_len),
+            right_len
+        );
+        for (tag, line) in hunk {
+            buf.push(*tag);
+            buf.push_str(line);
+            if !line.ends_with('\n') {
+                buf.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    buf
+}
+
+/// Maps every line to a char from the supplementary planes, returns `None` once they run out.
+fn encode_lines<'a>(interned: &mut FxHashMap<&'a str, char>, lines: &[&'a str]) -> Option<String> {
+    let mut res = String::with_capacity(lines.len());
+    for &line in lines {
+        let next = 0x10000 + interned.len() as u32;
+        let c = match interned.get(line) {
+            Some(&it) => it,
+            None => {
+                let c = char::from_u32(next)?;
+                interned.insert(line, c);
+                c
+            }
+        };
+        res.push(c);
+    }
+    Some(res)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,4 +160,29 @@ mod tests {
         edit.apply(&mut original);
         assert_eq!(original, result);
     }
+
+    #[test]
+    fn unified_diff_hunks() {
+        let left = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let right = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk";
+        expect_test::expect![[r#"
+            --- a/lib.rs
+            +++ b/lib.rs
+            @@ -1,5 +1,5 @@
+             a
+            -b
+            +B
+             c
+             d
+             e
+            @@ -8,3 +8,4 @@
+             h
+             i
+             j
+            +k
+            \ No newline at end of file
+        "#]]
+        .assert_eq(&unified_diff("lib.rs", left, right));
+        assert_eq!(unified_diff("lib.rs", left, left), "");
+    }
 }
@@ -1,6 +1,7 @@
 //! See [RequestDispatcher].
 use std::{fmt, panic, thread};
 
+use crossbeam_channel::Sender;
 use ide::Cancelled;
 use lsp_server::ExtractError;
 use serde::{de::DeserializeOwned, Serialize};
@@ -12,6 +13,14 @@ use crate::{
     LspError, Result,
 };
 
+/// A request handler which can send tasks before its response, see
+/// [`RequestDispatcher::on_with_sender`].
+type HandlerWithSender<R> = fn(
+    GlobalStateSnapshot,
+    <R as lsp_types::request::Request>::Params,
+    Sender<Task>,
+) -> Result<<R as lsp_types::request::Request>::Result>;
+
 /// A visitor for routing a raw JSON request to an appropriate handler function.
 ///
 /// Most requests are read-only and async and are handled on the threadpool
@@ -119,6 +128,40 @@ impl<'a> RequestDispatcher<'a> {
         self
     }
 
+    /// Dispatches the request onto thread pool, letting the handler send
+    /// tasks, like partial results, before the response.
+    pub(crate) fn on_with_sender<R>(&mut self, f: HandlerWithSender<R>) -> &mut Self
+    where
+        R: lsp_types::request::Request + 'static,
+        R::Params: DeserializeOwned + panic::UnwindSafe + Send + fmt::Debug,
+        R::Result: Serialize,
+    {
+        let (req, params, panic_context) = match self.parse::<R>() {
+            Some(it) => it,
+            None => return self,
+        };
+
+        self.global_state.task_pool.handle.spawn_with_sender({
+            let world = self.global_state.snapshot();
+            move |sender| {
+                let result = panic::catch_unwind({
+                    let sender = panic::AssertUnwindSafe(sender.clone());
+                    move || {
+                        let _pctx = stdx::panic_context::enter(panic_context);
+                        f(world, params, sender.0)
+                    }
+                });
+                let task = match thread_result_to_response::<R>(req.id.clone(), result) {
+                    Ok(response) => Task::Response(response),
+                    Err(_) => Task::Retry(req),
+                };
+                sender.send(task).unwrap();
+            }
+        });
+
+        self
+    }
+
     pub(crate) fn finish(&mut self) {
         if let Some(req) = self.req.take() {
             tracing::error!("unknown request: {:?}", req);
@@ -12,10 +12,12 @@ use ide_db::base_db::{CrateId, FileLoader, SourceDatabase};
 use lsp_types::{SemanticTokens, Url};
 use parking_lot::{Mutex, RwLock};
 use proc_macro_api::ProcMacroServer;
-use project_model::{CargoWorkspace, ProjectWorkspace, Target, WorkspaceBuildScripts};
+use project_model::{
+    project_json, CargoWorkspace, ProjectJson, ProjectWorkspace, Target, WorkspaceBuildScripts,
+};
 use rustc_hash::FxHashMap;
 use stdx::hash::NoHashHashMap;
-use vfs::AnchoredPathBuf;
+use vfs::{AbsPathBuf, AnchoredPathBuf};
 
 use crate::{
     config::Config,
@@ -60,6 +62,10 @@ pub(crate) struct GlobalState {
     pub(crate) semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
     pub(crate) shutdown_requested: bool,
     pub(crate) proc_macro_changed: bool,
+    /// Whether the diagnostics clients pulled may have changed in a way they
+    /// can't know about, like on a change on disk or to the crate graph. Edits
+    /// to open documents don't count, clients re-pull those themselves.
+    pub(crate) pull_diagnostics_invalidated: bool,
     pub(crate) last_reported_status: Option<lsp_ext::ServerStatusParams>,
     pub(crate) source_root_config: SourceRootConfig,
     pub(crate) proc_macro_clients: Vec<Result<ProcMacroServer, String>>,
@@ -104,6 +110,28 @@ pub(crate) struct GlobalState {
     pub(crate) fetch_build_data_queue:
         OpQueue<(Arc<Vec<ProjectWorkspace>>, Vec<anyhow::Result<WorkspaceBuildScripts>>)>,
 
+    /// Opened Rust files which don't belong to any of the `workspaces`. They
+    /// are analyzed on their own, backed by the sysroot.
+    pub(crate) detached_files: Vec<AbsPathBuf>,
+    /// The workspace of the `detached_files`. It is kept apart from the
+    /// `workspaces`, as it changes whenever such a file is opened, which
+    /// shouldn't invalidate build data or require a `cargo metadata` run. Once
+    /// loaded, it is kept even without detached files, to reuse its sysroot.
+    pub(crate) detached_workspace: Option<ProjectWorkspace>,
+    /// Opened detached files which turned out to be cargo scripts. Unlike other
+    /// detached files, they are loaded as cargo workspaces with the `workspaces`.
+    pub(crate) cargo_scripts: Vec<AbsPathBuf>,
+    /// Opened detached files which the `discoverProjectCommand` was run for.
+    pub(crate) discovered_files: Vec<AbsPathBuf>,
+    /// The projects found by the `discoverProjectCommand`, merged together.
+    pub(crate) discovered_project: Option<ProjectJson>,
+    /// The workspace of the `discovered_project`. Like the `detached_workspace`,
+    /// it is kept apart from the `workspaces`, so that discovering another
+    /// project only requires recreating the crate graph.
+    pub(crate) discovered_workspace: Option<Arc<ProjectWorkspace>>,
+    /// The proc-macro server of the `discovered_workspace`.
+    pub(crate) discovered_proc_macro_client: Option<Result<ProcMacroServer, String>>,
+
     pub(crate) prime_caches_queue: OpQueue<()>,
 }
 
@@ -116,6 +144,7 @@ pub(crate) struct GlobalStateSnapshot {
     pub(crate) semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
     vfs: Arc<RwLock<(vfs::Vfs, NoHashHashMap<FileId, LineEndings>)>>,
     pub(crate) workspaces: Arc<Vec<ProjectWorkspace>>,
+    pub(crate) discovered_workspace: Option<Arc<ProjectWorkspace>>,
     pub(crate) proc_macros_loaded: bool,
 }
 
@@ -151,6 +180,7 @@ impl GlobalState {
             semantic_tokens_cache: Arc::new(Default::default()),
             shutdown_requested: false,
             proc_macro_changed: false,
+            pull_diagnostics_invalidated: false,
             last_reported_status: None,
             source_root_config: SourceRootConfig::default(),
             proc_macro_clients: vec![],
@@ -170,6 +200,14 @@ impl GlobalState {
             prime_caches_queue: OpQueue::default(),
 
             fetch_build_data_queue: OpQueue::default(),
+
+            detached_files: Vec::new(),
+            detached_workspace: None,
+            cargo_scripts: Vec::new(),
+            discovered_files: Vec::new(),
+            discovered_project: None,
+            discovered_workspace: None,
+            discovered_proc_macro_client: None,
         };
         // Apply any required database inputs from the config.
         this.update_configuration(config);
@@ -181,6 +219,7 @@ impl GlobalState {
         // A file was added or deleted
         let mut has_structure_changes = false;
         let mut workspace_structure_change = None;
+        let mut build_file_change = None;
 
         let (change, changed_files) = {
             let mut change = Change::new();
@@ -195,11 +234,16 @@ impl GlobalState {
                     let path = path.to_path_buf();
                     if reload::should_refresh_for_change(&path, file.change_kind) {
                         workspace_structure_change = Some(path);
+                    } else if reload::should_rediscover_for_change(&path) {
+                        build_file_change = Some(path);
                     }
                     if file.is_created_or_deleted() {
                         has_structure_changes = true;
                     }
                 }
+                if !self.mem_docs.contains(&vfs.file_path(file.file_id)) {
+                    self.pull_diagnostics_invalidated = true;
+                }
 
                 // Clear native diagnostics when their file gets deleted
                 if !file.exists() {
@@ -245,6 +289,11 @@ impl GlobalState {
                 });
         }
 
+        if let Some(path) = build_file_change.filter(|_| self.discovered_project.is_some()) {
+            tracing::info!("build file changed: {}", path.display());
+            self.rediscover_projects();
+        }
+
         true
     }
 
@@ -252,6 +301,7 @@ impl GlobalState {
         GlobalStateSnapshot {
             config: Arc::clone(&self.config),
             workspaces: Arc::clone(&self.workspaces),
+            discovered_workspace: self.discovered_workspace.clone(),
             analysis: self.analysis_host.analysis(),
             vfs: Arc::clone(&self.vfs),
             check_fixes: Arc::clone(&self.diagnostics.check_fixes),
@@ -317,7 +367,7 @@ impl GlobalState {
         }
     }
 
-    fn send(&mut self, message: lsp_server::Message) {
+    pub(crate) fn send(&mut self, message: lsp_server::Message) {
         self.sender.send(message).unwrap()
     }
 }
@@ -349,6 +399,27 @@ impl GlobalStateSnapshot {
         Some(self.mem_docs.get(&path)?.version)
     }
 
+    /// Returns the Rust files of the workspace, skipping dependencies and the sysroot.
+    pub(crate) fn workspace_rust_files(&self) -> Cancellable<Vec<FileId>> {
+        let files = self
+            .vfs
+            .read()
+            .0
+            .iter()
+            .filter(|(_, path)| {
+                path.name_and_extension().map_or(false, |(_, ext)| ext == Some("rs"))
+            })
+            .map(|(file_id, _)| file_id)
+            .collect::<Vec<_>>();
+        let mut res = Vec::with_capacity(files.len());
+        for file_id in files {
+            if !self.analysis.is_library_file(file_id)? {
+                res.push(file_id);
+            }
+        }
+        Ok(res)
+    }
+
     pub(crate) fn anchored_path(&self, path: &AnchoredPathBuf) -> Url {
         let mut base = self.vfs.read().0.file_path(path.anchor);
         base.pop();
@@ -372,6 +443,28 @@ impl GlobalStateSnapshot {
             ProjectWorkspace::DetachedFiles { .. } => None,
         })
     }
+
+    /// Returns the `rust-project.json` crate of the file, if it belongs to one.
+    pub(crate) fn project_json_crate_for_file(
+        &self,
+        file_id: FileId,
+    ) -> Cancellable<Option<&project_json::Crate>> {
+        let crate_id = match &*self.analysis.crate_for(file_id)? {
+            &[crate_id, ..] => crate_id,
+            _ => return Ok(None),
+        };
+        let root = self.analysis.crate_root(crate_id)?;
+        let path = self.vfs.read().0.file_path(root);
+        let path = match path.as_path() {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+        let mut workspaces = self.workspaces.iter().chain(self.discovered_workspace.as_deref());
+        Ok(workspaces.find_map(|ws| match ws {
+            ProjectWorkspace::Json { project, .. } => project.crate_by_root(path),
+            ProjectWorkspace::Cargo { .. } | ProjectWorkspace::DetachedFiles { .. } => None,
+        }))
+    }
 }
 
 pub(crate) fn file_id_to_url(vfs: &vfs::Vfs, id: FileId) -> Url {
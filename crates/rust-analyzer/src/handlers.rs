@@ -3,11 +3,15 @@
 //! `ide` crate.
 
 use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
     io::Write as _,
+    mem,
     process::{self, Stdio},
 };
 
 use anyhow::Context;
+use crossbeam_channel::Sender;
 use ide::{
     AnnotationConfig, AssistKind, AssistResolveStrategy, FileId, FilePosition, FileRange,
     HoverAction, HoverGotoTypeData, Query, RangeInfo, ReferenceCategory, Runnable, RunnableKind,
@@ -25,7 +29,7 @@ use lsp_types::{
     SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensResult, SymbolInformation,
     SymbolTag, TextDocumentIdentifier, Url, WorkspaceEdit,
 };
-use project_model::{ManifestPath, ProjectWorkspace, TargetKind};
+use project_model::{project_json, ManifestPath, ProjectWorkspace, TargetKind};
 use serde_json::json;
 use stdx::{format_to, never};
 use syntax::{algo, ast, AstNode, TextRange, TextSize, T};
@@ -39,12 +43,15 @@ use crate::{
     global_state::{GlobalState, GlobalStateSnapshot},
     line_index::LineEndings,
     lsp_ext::{self, PositionOrRange, ViewCrateGraphParams, WorkspaceSymbolParams},
+    lsp_types_ext,
     lsp_utils::{all_edits_are_disjoint, invalid_params_error},
+    main_loop::Task,
     to_proto, LspError, Result,
 };
 
 pub(crate) fn handle_workspace_reload(state: &mut GlobalState, _: ()) -> Result<()> {
     state.proc_macro_clients.clear();
+    state.discovered_proc_macro_client = None;
     state.proc_macro_changed = false;
     state.fetch_workspaces_queue.request_op("reload workspace request".to_string());
     state.fetch_build_data_queue.request_op("reload workspace request".to_string());
@@ -696,6 +703,10 @@ pub(crate) fn handle_runnables(
     let line_index = snap.file_line_index(file_id)?;
     let offset = params.position.and_then(|it| from_proto::offset(&line_index, it).ok());
     let cargo_spec = CargoTargetSpec::for_file(&snap, file_id)?;
+    let project_json_crate = match snap.config.shell_runnables() {
+        true => snap.project_json_crate_for_file(file_id)?,
+        false => None,
+    };
 
     let expect_test = match offset {
         Some(offset) => {
@@ -712,13 +723,15 @@ pub(crate) fn handle_runnables(
         if should_skip_for_offset(&runnable, offset) {
             continue;
         }
-        if should_skip_target(&runnable, cargo_spec.as_ref()) {
+        if should_skip_target(&runnable, cargo_spec.as_ref(), project_json_crate) {
             continue;
         }
         let mut runnable = to_proto::runnable(&snap, runnable)?;
         if expect_test {
-            runnable.label = format!("{} + expect", runnable.label);
-            runnable.args.expect_test = Some(true);
+            if let lsp_ext::RunnableArgs::Cargo(args) = &mut runnable.args {
+                runnable.label = format!("{} + expect", runnable.label);
+                args.expect_test = Some(true);
+            }
         }
         res.push(runnable);
     }
@@ -732,7 +745,7 @@ pub(crate) fn handle_runnables(
                     label: format!("cargo {} -p {} --all-targets", cmd, spec.package),
                     location: None,
                     kind: lsp_ext::RunnableKind::Cargo,
-                    args: lsp_ext::CargoRunnable {
+                    args: lsp_ext::RunnableArgs::Cargo(lsp_ext::CargoRunnable {
                         workspace_root: Some(spec.workspace_root.clone().into()),
                         override_cargo: config.override_cargo.clone(),
                         cargo_args: vec![
@@ -744,12 +757,31 @@ pub(crate) fn handle_runnables(
                         cargo_extra_args: config.cargo_extra_args.clone(),
                         executable_args: Vec::new(),
                         expect_test: None,
-                    },
+                    }),
                 })
             }
         }
         None => {
-            if !snap.config.linked_projects().is_empty()
+            let build = project_json_crate.and_then(|krate| {
+                Some((krate, krate.runnable(project_json::RunnableKind::Build)?))
+            });
+            if let Some((krate, command)) = build {
+                // Add the build command of the `rust-project.json` crate instead
+                let label = match krate.display_name() {
+                    Some(name) => format!("build {}", name),
+                    None => "build".to_string(),
+                };
+                res.push(lsp_ext::Runnable {
+                    label,
+                    location: None,
+                    kind: lsp_ext::RunnableKind::Shell,
+                    args: lsp_ext::RunnableArgs::Shell(lsp_ext::ShellRunnable {
+                        program: command.program.clone(),
+                        args: command.args.clone(),
+                        cwd: command.cwd.clone().into(),
+                    }),
+                });
+            } else if !snap.config.linked_projects().is_empty()
                 || !snap
                     .config
                     .discovered_projects
@@ -761,14 +793,14 @@ pub(crate) fn handle_runnables(
                     label: "cargo check --workspace".to_string(),
                     location: None,
                     kind: lsp_ext::RunnableKind::Cargo,
-                    args: lsp_ext::CargoRunnable {
+                    args: lsp_ext::RunnableArgs::Cargo(lsp_ext::CargoRunnable {
                         workspace_root: None,
                         override_cargo: config.override_cargo,
                         cargo_args: vec!["check".to_string(), "--workspace".to_string()],
                         cargo_extra_args: config.cargo_extra_args,
                         executable_args: Vec::new(),
                         expect_test: None,
-                    },
+                    }),
                 });
             }
         }
@@ -1340,7 +1372,19 @@ pub(crate) fn publish_diagnostics(
             }),
             source: Some("rust-analyzer".to_string()),
             message: d.message,
-            related_information: None,
+            related_information: if d.related.is_empty() {
+                None
+            } else {
+                Some(
+                    d.related
+                        .into_iter()
+                        .filter_map(|(frange, message)| {
+                            let location = to_proto::location(snap, frange).ok()?;
+                            Some(lsp_types::DiagnosticRelatedInformation { location, message })
+                        })
+                        .collect(),
+                )
+            },
             tags: if d.unused { Some(vec![DiagnosticTag::UNNECESSARY]) } else { None },
             data: None,
         })
@@ -1348,6 +1392,91 @@ pub(crate) fn publish_diagnostics(
     Ok(diagnostics)
 }
 
+pub(crate) fn handle_document_diagnostic(
+    snap: GlobalStateSnapshot,
+    params: lsp_types_ext::DocumentDiagnosticParams,
+) -> Result<lsp_types_ext::DocumentDiagnosticReport> {
+    let _p = profile::span("handle_document_diagnostic");
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    // Like with published diagnostics, don't report anything for dependencies and the sysroot.
+    let diagnostics = if snap.analysis.is_library_file(file_id)? {
+        Vec::new()
+    } else {
+        publish_diagnostics(&snap, file_id)?
+    };
+    Ok(diagnostic_report(diagnostics, params.previous_result_id.as_deref()))
+}
+
+pub(crate) fn handle_workspace_diagnostic(
+    snap: GlobalStateSnapshot,
+    params: lsp_types_ext::WorkspaceDiagnosticParams,
+    sender: Sender<Task>,
+) -> Result<lsp_types_ext::WorkspaceDiagnosticReport> {
+    let _p = profile::span("handle_workspace_diagnostic");
+    // How many reports are batched into a partial result.
+    const PARTIAL_RESULT_LEN: usize = 32;
+
+    let previous_result_ids: HashMap<Url, String> =
+        params.previous_result_ids.into_iter().map(|it| (it.uri, it.value)).collect();
+    let partial_result_token = params.partial_result_params.partial_result_token;
+    let send_partial_result = |items| {
+        let token = partial_result_token.as_ref().unwrap();
+        let value = lsp_types_ext::WorkspaceDiagnosticReport { items };
+        let notification = lsp_server::Notification::new(
+            "$/progress".to_string(),
+            json!({ "token": token, "value": value }),
+        );
+        sender.send(Task::Notification(notification)).unwrap();
+    };
+
+    let mut items = Vec::new();
+    for file_id in snap.workspace_rust_files()? {
+        let uri = snap.file_id_to_url(file_id);
+        let previous_result_id = previous_result_ids.get(&uri).map(String::as_str);
+        let diagnostics = publish_diagnostics(&snap, file_id)?;
+        // Don't bloat the response with every clean file the client doesn't know about yet.
+        if diagnostics.is_empty() && previous_result_id.is_none() {
+            continue;
+        }
+        let report = diagnostic_report(diagnostics, previous_result_id);
+        let version = snap.url_file_version(&uri);
+        items.push(lsp_types_ext::WorkspaceDocumentDiagnosticReport { uri, version, report });
+        // Let the client show the reports while the rest of the workspace is being checked.
+        if partial_result_token.is_some() && items.len() == PARTIAL_RESULT_LEN {
+            send_partial_result(mem::take(&mut items));
+        }
+    }
+    // Once partial results were used, they have to carry the whole result.
+    if partial_result_token.is_some() {
+        send_partial_result(mem::take(&mut items));
+    }
+    Ok(lsp_types_ext::WorkspaceDiagnosticReport { items })
+}
+
+/// Builds a pull diagnostics report, which is `unchanged` if the diagnostics are the same as the
+/// ones the client got with `previous_result_id`.
+fn diagnostic_report(
+    diagnostics: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> lsp_types_ext::DocumentDiagnosticReport {
+    // The result id is derived from the diagnostics themselves, so that it stays valid across
+    // changes which don't affect the file's diagnostics.
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(&diagnostics).unwrap_or_default().hash(&mut hasher);
+    let result_id = format!("{:016x}", hasher.finish());
+
+    if previous_result_id == Some(result_id.as_str()) {
+        lsp_types_ext::DocumentDiagnosticReport::Unchanged(
+            lsp_types_ext::UnchangedDocumentDiagnosticReport { result_id },
+        )
+    } else {
+        lsp_types_ext::DocumentDiagnosticReport::Full(lsp_types_ext::FullDocumentDiagnosticReport {
+            result_id: Some(result_id),
+            items: diagnostics,
+        })
+    }
+}
+
 pub(crate) fn handle_inlay_hints(
     snap: GlobalStateSnapshot,
     params: InlayHintParams,
@@ -1378,6 +1507,33 @@ pub(crate) fn handle_inlay_hints_resolve(
     mut hint: InlayHint,
 ) -> Result<InlayHint> {
     let _p = profile::span("handle_inlay_hints_resolve");
+    let hover_config = snap.config.hover();
+    let markup_kind = hover_config.documentation.clone().unwrap_or(ide::HoverDocFormat::Markdown);
+    // FIXME: hover actions?
+    let hover_markup = |file_range| -> Result<Option<lsp_types::MarkupContent>> {
+        Ok(snap
+            .analysis
+            .hover(&hover_config, file_range)?
+            .map(|info| to_proto::markup_content(info.info.markup, markup_kind.clone())))
+    };
+
+    // Label parts linked to a definition show the hover of that definition.
+    if let lsp_types::InlayHintLabel::LabelParts(parts) = &mut hint.label {
+        for part in parts.iter_mut().filter(|it| it.tooltip.is_none()) {
+            let location = match &part.location {
+                Some(it) => it,
+                None => continue,
+            };
+            let file_range = from_proto::file_range(
+                &snap,
+                TextDocumentIdentifier::new(location.uri.clone()),
+                Range::new(location.range.start, location.range.start),
+            )?;
+            part.tooltip =
+                hover_markup(file_range)?.map(lsp_types::InlayHintLabelPartTooltip::MarkupContent);
+        }
+    }
+
     let data = match hint.data.take() {
         Some(it) => it,
         None => return Ok(hint),
@@ -1393,20 +1549,24 @@ pub(crate) fn handle_inlay_hints_resolve(
             PositionOrRange::Range(range) => range,
         },
     )?;
-    let info = match snap.analysis.hover(&snap.config.hover(), file_range)? {
-        None => return Ok(hint),
-        Some(info) => info,
-    };
+    if let Some(markup) = hover_markup(file_range)? {
+        hint.tooltip = Some(lsp_types::InlayHintTooltip::MarkupContent(markup));
+    }
+    Ok(hint)
+}
 
-    let markup_kind =
-        snap.config.hover().documentation.map_or(ide::HoverDocFormat::Markdown, |kind| kind);
+pub(crate) fn handle_inline_value(
+    snap: GlobalStateSnapshot,
+    params: lsp_types_ext::InlineValueParams,
+) -> Result<Option<Vec<lsp_types_ext::InlineValue>>> {
+    let _p = profile::span("handle_inline_value");
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    let line_index = snap.file_line_index(file_id)?;
+    let range = from_proto::text_range(&line_index, params.range)?;
+    let stopped_at = from_proto::text_range(&line_index, params.context.stopped_location)?;
 
-    // FIXME: hover actions?
-    hint.tooltip = Some(lsp_types::InlayHintTooltip::MarkupContent(to_proto::markup_content(
-        info.info.markup,
-        markup_kind,
-    )));
-    Ok(hint)
+    let values = snap.analysis.inline_values(FileRange { file_id, range }, stopped_at)?;
+    Ok(Some(values.into_iter().map(|it| to_proto::inline_value(&line_index, it)).collect()))
 }
 
 pub(crate) fn handle_call_hierarchy_prepare(
@@ -1501,6 +1661,73 @@ pub(crate) fn handle_call_hierarchy_outgoing(
     Ok(Some(res))
 }
 
+pub(crate) fn handle_prepare_type_hierarchy(
+    snap: GlobalStateSnapshot,
+    params: lsp_types_ext::TypeHierarchyPrepareParams,
+) -> Result<Option<Vec<lsp_types_ext::TypeHierarchyItem>>> {
+    let _p = profile::span("handle_prepare_type_hierarchy");
+    let position = from_proto::file_position(&snap, params.text_document_position_params)?;
+
+    let nav_info = match snap.analysis.type_hierarchy(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+
+    let RangeInfo { range: _, info: navs } = nav_info;
+    let res = navs
+        .into_iter()
+        .map(|it| to_proto::type_hierarchy_item(&snap, it))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Some(res))
+}
+
+pub(crate) fn handle_type_hierarchy_supertypes(
+    snap: GlobalStateSnapshot,
+    params: lsp_types_ext::TypeHierarchySupertypesParams,
+) -> Result<Option<Vec<lsp_types_ext::TypeHierarchyItem>>> {
+    let _p = profile::span("handle_type_hierarchy_supertypes");
+    let item = params.item;
+
+    let doc = TextDocumentIdentifier::new(item.uri);
+    let frange = from_proto::file_range(&snap, doc, item.selection_range)?;
+    let fpos = FilePosition { file_id: frange.file_id, offset: frange.range.start() };
+
+    let navs = match snap.analysis.type_hierarchy_supertypes(fpos)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let res = navs
+        .into_iter()
+        .map(|it| to_proto::type_hierarchy_item(&snap, it))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Some(res))
+}
+
+pub(crate) fn handle_type_hierarchy_subtypes(
+    snap: GlobalStateSnapshot,
+    params: lsp_types_ext::TypeHierarchySubtypesParams,
+) -> Result<Option<Vec<lsp_types_ext::TypeHierarchyItem>>> {
+    let _p = profile::span("handle_type_hierarchy_subtypes");
+    let item = params.item;
+
+    let doc = TextDocumentIdentifier::new(item.uri);
+    let frange = from_proto::file_range(&snap, doc, item.selection_range)?;
+    let fpos = FilePosition { file_id: frange.file_id, offset: frange.range.start() };
+
+    let navs = match snap.analysis.type_hierarchy_subtypes(fpos)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let res = navs
+        .into_iter()
+        .map(|it| to_proto::type_hierarchy_item(&snap, it))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Some(res))
+}
+
 pub(crate) fn handle_semantic_tokens_full(
     snap: GlobalStateSnapshot,
     params: SemanticTokensParams,
@@ -1694,7 +1921,11 @@ fn runnable_action_links(
     }
 
     let cargo_spec = CargoTargetSpec::for_file(snap, runnable.nav.file_id).ok()?;
-    if should_skip_target(&runnable, cargo_spec.as_ref()) {
+    let project_json_crate = match snap.config.shell_runnables() {
+        true => snap.project_json_crate_for_file(runnable.nav.file_id).ok()?,
+        false => None,
+    };
+    if should_skip_target(&runnable, cargo_spec.as_ref(), project_json_crate) {
         return None;
     }
 
@@ -1713,7 +1944,9 @@ fn runnable_action_links(
         group.commands.push(to_command_link(run_command, r.label.clone()));
     }
 
-    if hover_actions_config.debug && client_commands_config.debug_single {
+    // Only cargo runnables can be built for debugging.
+    let can_debug = matches!(r.kind, lsp_ext::RunnableKind::Cargo);
+    if hover_actions_config.debug && can_debug && client_commands_config.debug_single {
         let dbg_command = to_proto::command::debug_single(&r);
         group.commands.push(to_command_link(dbg_command, r.label));
     }
@@ -1759,7 +1992,11 @@ fn prepare_hover_actions(
         .collect()
 }
 
-fn should_skip_target(runnable: &Runnable, cargo_spec: Option<&CargoTargetSpec>) -> bool {
+fn should_skip_target(
+    runnable: &Runnable,
+    cargo_spec: Option<&CargoTargetSpec>,
+    project_json_crate: Option<&project_json::Crate>,
+) -> bool {
     match runnable.kind {
         RunnableKind::Bin => {
             // Do not suggest binary run on other target than binary
@@ -1768,7 +2005,8 @@ fn should_skip_target(runnable: &Runnable, cargo_spec: Option<&CargoTargetSpec>)
                     spec.target_kind,
                     TargetKind::Bin | TargetKind::Example | TargetKind::Test
                 ),
-                None => true,
+                None => project_json_crate
+                    .map_or(true, |it| it.runnable(project_json::RunnableKind::Run).is_none()),
             }
         }
         _ => false,
@@ -40,6 +40,7 @@ mod version;
 
 pub mod config;
 pub mod lsp_ext;
+pub mod lsp_types_ext;
 
 #[cfg(test)]
 mod integrated_benchmarks;
@@ -48,7 +49,11 @@ use std::fmt;
 
 use serde::de::DeserializeOwned;
 
-pub use crate::{caps::server_capabilities, main_loop::main_loop, version::version};
+pub use crate::{
+    caps::{extend_server_capabilities, server_capabilities},
+    main_loop::main_loop,
+    version::version,
+};
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Result<T, E = Error> = std::result::Result<T, E>;
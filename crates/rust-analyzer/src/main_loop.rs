@@ -14,14 +14,14 @@ use ide_db::base_db::{SourceDatabase, SourceDatabaseExt, VfsPath};
 use itertools::Itertools;
 use lsp_server::{Connection, Notification, Request};
 use lsp_types::notification::Notification as _;
-use vfs::{ChangeKind, FileId};
+use vfs::{AbsPathBuf, ChangeKind, FileId};
 
 use crate::{
     config::Config,
     dispatch::{NotificationDispatcher, RequestDispatcher},
     from_proto,
     global_state::{file_id_to_url, url_to_file_id, GlobalState},
-    handlers, lsp_ext,
+    handlers, lsp_ext, lsp_types_ext,
     lsp_utils::{apply_document_changes, notification_is, Progress},
     mem_docs::DocumentData,
     reload::{self, BuildDataProgress, ProjectWorkspaceProgress},
@@ -63,11 +63,18 @@ enum Event {
 #[derive(Debug)]
 pub(crate) enum Task {
     Response(lsp_server::Response),
+    Notification(lsp_server::Notification),
     Retry(lsp_server::Request),
     Diagnostics(Vec<(FileId, Vec<lsp_types::Diagnostic>)>),
     PrimeCaches(PrimeCachesProgress),
     FetchWorkspace(ProjectWorkspaceProgress),
     FetchBuildData(BuildDataProgress),
+    FetchDetachedFiles(anyhow::Result<project_model::ProjectWorkspace>),
+    DiscoverProject(AbsPathBuf, anyhow::Result<project_model::ProjectJson>),
+    FetchDiscoveredProject(
+        project_model::ProjectJson,
+        anyhow::Result<project_model::ProjectWorkspace>,
+    ),
 }
 
 #[derive(Debug)]
@@ -113,6 +120,7 @@ impl GlobalState {
     fn run(mut self, inbox: Receiver<lsp_server::Message>) -> Result<()> {
         if self.config.linked_projects().is_empty()
             && self.config.detached_files().is_empty()
+            && self.config.discover_project_command().is_none()
             && self.config.notifications().cargo_toml_not_found
         {
             self.show_and_log_error("rust-analyzer failed to discover workspace".to_string(), None);
@@ -307,6 +315,13 @@ impl GlobalState {
                 }
             }
 
+            // Refresh pulled diagnostics if the client supports it and can't have noticed the
+            // change itself.
+            if self.pull_diagnostics_invalidated && self.config.diagnostics_refresh() {
+                self.pull_diagnostics_invalidated = false;
+                self.send_request::<lsp_types_ext::WorkspaceDiagnosticRefresh>((), |_, _| ());
+            }
+
             if !was_quiescent || state_changed || memdocs_added_or_removed {
                 if self.config.publish_diagnostics() {
                     self.update_diagnostics()
@@ -425,6 +440,7 @@ impl GlobalState {
     fn handle_task(&mut self, prime_caches_progress: &mut Vec<PrimeCachesProgress>, task: Task) {
         match task {
             Task::Response(response) => self.respond(response),
+            Task::Notification(notification) => self.send(notification.into()),
             Task::Retry(req) => self.on_request(req),
             Task::Diagnostics(diagnostics_per_file) => {
                 for (file_id, diagnostics) in diagnostics_per_file {
@@ -482,6 +498,61 @@ impl GlobalState {
                     self.report_progress("Loading", state, msg, None);
                 }
             }
+            Task::FetchDetachedFiles(workspace) => match workspace {
+                Ok(mut workspace) => {
+                    // The files might have changed in the meantime, the sysroot is still valid.
+                    workspace.set_detached_files(self.detached_files.clone());
+                    self.detached_workspace = Some(workspace);
+                    self.recreate_crate_graph("fetched detached files".to_string());
+                }
+                Err(err) => tracing::error!("failed to load detached files: {:?}", err),
+            },
+            Task::DiscoverProject(file, project) => match project {
+                Ok(project) => {
+                    tracing::info!("discovered the project of {}", file.display());
+                    let merged = match self.discovered_project.take() {
+                        Some(mut it) => {
+                            it.merge(project);
+                            it
+                        }
+                        None => project,
+                    };
+                    self.discovered_project = Some(merged.clone());
+                    let cargo_config = self.config.cargo();
+                    self.task_pool.handle.spawn_with_sender(move |sender| {
+                        let workspace = project_model::ProjectWorkspace::load_inline(
+                            merged.clone(),
+                            cargo_config.target.as_deref(),
+                            &cargo_config.extra_env,
+                        );
+                        sender.send(Task::FetchDiscoveredProject(merged, workspace)).unwrap();
+                    });
+                }
+                // Discovery fails for the files which don't belong to any project, which is
+                // common enough to not warrant a dialog.
+                Err(err) => {
+                    tracing::error!(
+                        "failed to discover the project of {}: {:#}",
+                        file.display(),
+                        err
+                    )
+                }
+            },
+            Task::FetchDiscoveredProject(project, workspace) => match workspace {
+                // Skip the result if another project was discovered in the meantime.
+                Ok(workspace) if self.discovered_project.as_ref() == Some(&project) => {
+                    self.discovered_workspace = Some(Arc::new(workspace));
+                    self.spawn_discovered_proc_macro_server();
+                    self.recreate_crate_graph("loaded discovered project".to_string());
+                    // The opened files of the project aren't detached anymore.
+                    self.update_detached_files();
+                }
+                Ok(_) => (),
+                Err(err) => self.show_and_log_error(
+                    "rust-analyzer failed to load the discovered project".to_string(),
+                    Some(format!("{:#}", err)),
+                ),
+            },
         }
     }
 
@@ -671,6 +742,16 @@ impl GlobalState {
             .on::<lsp_types::request::CallHierarchyOutgoingCalls>(
                 handlers::handle_call_hierarchy_outgoing,
             )
+            .on::<lsp_types_ext::DocumentDiagnosticRequest>(handlers::handle_document_diagnostic)
+            .on_with_sender::<lsp_types_ext::WorkspaceDiagnosticRequest>(
+                handlers::handle_workspace_diagnostic,
+            )
+            .on::<lsp_types_ext::InlineValueRequest>(handlers::handle_inline_value)
+            .on::<lsp_types_ext::PrepareTypeHierarchy>(handlers::handle_prepare_type_hierarchy)
+            .on::<lsp_types_ext::TypeHierarchySupertypes>(
+                handlers::handle_type_hierarchy_supertypes,
+            )
+            .on::<lsp_types_ext::TypeHierarchySubtypes>(handlers::handle_type_hierarchy_subtypes)
             .on::<lsp_types::request::SemanticTokensFullRequest>(
                 handlers::handle_semantic_tokens_full,
             )
@@ -715,6 +796,7 @@ impl GlobalState {
                         .write()
                         .0
                         .set_file_contents(path, Some(params.text_document.text.into_bytes()));
+                    this.update_detached_files();
                 }
                 Ok(())
             })?
@@ -752,6 +834,7 @@ impl GlobalState {
                     if let Some(path) = path.as_path() {
                         this.loader.handle.invalidate(path.to_path_buf());
                     }
+                    this.update_detached_files();
                 }
                 Ok(())
             })?
@@ -12,7 +12,7 @@
 //! correct. Instead, we try to provide a best-effort service. Even if the
 //! project is currently loading and we don't have a full project model, we
 //! still want to respond to various  requests.
-use std::{mem, sync::Arc};
+use std::{iter, mem, sync::Arc};
 
 use flycheck::{FlycheckConfig, FlycheckHandle};
 use hir::db::DefDatabase;
@@ -22,7 +22,7 @@ use ide_db::base_db::{
     ProcMacroLoadResult, SourceRoot, VfsPath,
 };
 use proc_macro_api::{MacroDylib, ProcMacroServer};
-use project_model::{ProjectWorkspace, WorkspaceBuildScripts};
+use project_model::{ProjectJson, ProjectManifest, ProjectWorkspace, WorkspaceBuildScripts};
 use syntax::SmolStr;
 use vfs::{file_set::FileSetConfig, AbsPath, AbsPathBuf, ChangeKind};
 
@@ -115,6 +115,7 @@ impl GlobalState {
         self.task_pool.handle.spawn_with_sender({
             let linked_projects = self.config.linked_projects();
             let detached_files = self.config.detached_files().to_vec();
+            let cargo_scripts = self.cargo_scripts.clone();
             let cargo_config = self.config.cargo();
 
             move |sender| {
@@ -149,6 +150,13 @@ impl GlobalState {
                     })
                     .collect::<Vec<_>>();
 
+                let (scripts, detached_files): (Vec<_>, Vec<_>) =
+                    detached_files.into_iter().partition(|it| ProjectManifest::is_cargo_script(it));
+                workspaces.extend(scripts.into_iter().chain(cargo_scripts).map(|script| {
+                    let manifest = ProjectManifest::from_manifest_file(script)?;
+                    project_model::ProjectWorkspace::load(manifest, &cargo_config, &progress)
+                }));
+
                 if !detached_files.is_empty() {
                     workspaces
                         .push(project_model::ProjectWorkspace::load_detached_files(detached_files));
@@ -217,11 +225,13 @@ impl GlobalState {
                     sysroot,
                     rustc,
                     rustc_cfg,
+                    host,
+                    target_cfgs,
                     cfg_overrides,
 
                     build_scripts: _,
                     toolchain: _,
-                } => Some((cargo, sysroot, rustc, rustc_cfg, cfg_overrides)),
+                } => Some((cargo, sysroot, rustc, rustc_cfg, host, target_cfgs, cfg_overrides)),
                 _ => None,
             };
             match (key(left), key(right)) {
@@ -283,6 +293,11 @@ impl GlobalState {
                             ]
                         })
                     })
+                    .chain(self.config.discover_project_command().into_iter().flat_map(|_| {
+                        BUILD_FILES
+                            .iter()
+                            .map(|it| format!("{}/**/{}", self.config.root_path().display(), it))
+                    }))
                     .map(|glob_pattern| lsp_types::FileSystemWatcher { glob_pattern, kind: None })
                     .collect(),
             };
@@ -297,62 +312,189 @@ impl GlobalState {
             );
         }
 
-        let mut change = Change::new();
+        if self.proc_macro_clients.is_empty() && self.config.proc_macro_srv().is_some() {
+            tracing::info!("Spawning proc-macro servers");
+            self.proc_macro_clients =
+                self.workspaces.iter().filter_map(|ws| self.spawn_proc_macro_server(ws)).collect();
+        }
+        self.spawn_discovered_proc_macro_server();
 
-        let files_config = self.config.files();
-        let project_folders = ProjectFolders::new(&self.workspaces, &files_config.exclude);
+        self.recreate_crate_graph(cause);
+        self.reload_flycheck();
+        // Files might have been added to or removed from the workspaces.
+        self.update_detached_files();
+        tracing::info!("did switch workspaces");
+    }
 
+    /// Spawns the proc-macro server for `ws`, preferring the one shipped with its sysroot. Returns
+    /// `None` if proc macros are disabled.
+    fn spawn_proc_macro_server(
+        &self,
+        ws: &ProjectWorkspace,
+    ) -> Option<Result<ProcMacroServer, String>> {
+        let (mut path, mut args) = self.config.proc_macro_srv()?;
         let standalone_server_name =
             format!("rust-analyzer-proc-macro-srv{}", std::env::consts::EXE_SUFFIX);
 
-        if self.proc_macro_clients.is_empty() {
-            if let Some((path, args)) = self.config.proc_macro_srv() {
-                tracing::info!("Spawning proc-macro servers");
-                self.proc_macro_clients = self
-                    .workspaces
-                    .iter()
-                    .map(|ws| {
-                        let mut args = args.clone();
-                        let mut path = path.clone();
-
-                        if let ProjectWorkspace::Cargo { sysroot, .. }
-                        | ProjectWorkspace::Json { sysroot, .. } = ws
-                        {
-                            tracing::debug!("Found a cargo workspace...");
-                            if let Some(sysroot) = sysroot.as_ref() {
-                                tracing::debug!("Found a cargo workspace with a sysroot...");
-                                let server_path =
-                                    sysroot.root().join("libexec").join(&standalone_server_name);
-                                if std::fs::metadata(&server_path).is_ok() {
-                                    tracing::debug!(
-                                        "And the server exists at {}",
-                                        server_path.display()
-                                    );
-                                    path = server_path;
-                                    args = vec![];
-                                } else {
-                                    tracing::debug!(
-                                        "And the server does not exist at {}",
-                                        server_path.display()
-                                    );
-                                }
-                            }
-                        }
+        if let ProjectWorkspace::Cargo { sysroot, .. } | ProjectWorkspace::Json { sysroot, .. } = ws
+        {
+            tracing::debug!("Found a cargo workspace...");
+            if let Some(sysroot) = sysroot.as_ref() {
+                tracing::debug!("Found a cargo workspace with a sysroot...");
+                let server_path = sysroot.root().join("libexec").join(standalone_server_name);
+                if std::fs::metadata(&server_path).is_ok() {
+                    tracing::debug!("And the server exists at {}", server_path.display());
+                    path = server_path;
+                    args = vec![];
+                } else {
+                    tracing::debug!("And the server does not exist at {}", server_path.display());
+                }
+            }
+        }
 
-                        tracing::info!(?args, "Using proc-macro server at {}", path.display(),);
-                        ProcMacroServer::spawn(path.clone(), args.clone()).map_err(|err| {
-                            let error = format!(
-                                "Failed to run proc-macro server from path {}, error: {:?}",
-                                path.display(),
-                                err
-                            );
-                            tracing::error!(error);
-                            error
-                        })
-                    })
-                    .collect()
-            };
+        tracing::info!(?args, "Using proc-macro server at {}", path.display(),);
+        let server = ProcMacroServer::spawn(path.clone(), args)
+            .map(|server| match self.config.proc_macro_cache() {
+                Some(config) => server.with_expansion_cache(config),
+                None => server,
+            })
+            .map_err(|err| {
+                let error = format!(
+                    "Failed to run proc-macro server from path {}, error: {:?}",
+                    path.display(),
+                    err
+                );
+                tracing::error!(error);
+                error
+            });
+        Some(server)
+    }
+
+    /// Spawns the proc-macro server of the `discovered_workspace`, unless it is already running.
+    pub(crate) fn spawn_discovered_proc_macro_server(&mut self) {
+        if self.discovered_proc_macro_client.is_some() {
+            return;
+        }
+        if let Some(ws) = self.discovered_workspace.clone() {
+            self.discovered_proc_macro_client = self.spawn_proc_macro_server(&ws);
+        }
+    }
+
+    /// Analyzes the opened Rust files which don't belong to any workspace as detached files.
+    pub(crate) fn update_detached_files(&mut self) {
+        if self.fetch_workspaces_queue.op_in_progress()
+            || self.fetch_workspaces_queue.op_requested()
+        {
+            // Every file would look detached before the workspaces are loaded.
+            return;
+        }
+
+        let roots = self
+            .workspaces
+            .iter()
+            .chain(self.discovered_workspace.as_deref())
+            .flat_map(|ws| ws.to_roots())
+            .flat_map(|root| root.include)
+            .collect::<Vec<_>>();
+        let open_files = self.mem_docs.iter().filter_map(|it| it.as_path()).collect::<Vec<_>>();
+        let n_scripts = self.cargo_scripts.len();
+        self.cargo_scripts.retain(|it| open_files.contains(&it.as_path()));
+        if self.cargo_scripts.len() != n_scripts {
+            self.fetch_workspaces_queue.request_op("cargo script closed".to_string());
+        }
+        let files = detached_files(&self.detached_files, &open_files, &roots);
+        if files == self.detached_files {
+            return;
+        }
+
+        tracing::info!("detached files changed to {:?}", files);
+        // Scripts are loaded like the linked projects, which requires a refetch. Until it is
+        // done, or if it fails, they are analyzed as plain detached files.
+        let new_scripts = files
+            .iter()
+            .filter(|it| !self.detached_files.contains(it) && !self.cargo_scripts.contains(it))
+            .filter(|it| self.is_open_cargo_script(it))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !new_scripts.is_empty() {
+            self.cargo_scripts.extend(new_scripts);
+            self.fetch_workspaces_queue.request_op("cargo script opened".to_string());
+        }
+        let undiscovered = files
+            .iter()
+            .filter(|it| !self.cargo_scripts.contains(it) && !self.discovered_files.contains(it))
+            .cloned()
+            .collect();
+        self.discover_projects(undiscovered);
+        self.detached_files = files.clone();
+        match &mut self.detached_workspace {
+            // Only the first load has to discover the sysroot.
+            Some(workspace) => {
+                workspace.set_detached_files(files);
+                self.recreate_crate_graph("detached files changed".to_string());
+            }
+            None if files.is_empty() => (),
+            None => self.task_pool.handle.spawn_with_sender(move |sender| {
+                let workspace = ProjectWorkspace::load_detached_files(files);
+                sender.send(Task::FetchDetachedFiles(workspace)).unwrap();
+            }),
         }
+    }
+
+    /// Runs the `discoverProjectCommand` for each of `files`.
+    fn discover_projects(&mut self, files: Vec<AbsPathBuf>) {
+        let command = match self.config.discover_project_command() {
+            Some(it) => it.to_vec(),
+            None => return,
+        };
+        for file in files {
+            self.discovered_files.push(file.clone());
+            let command = command.clone();
+            let root = self.config.root_path().clone();
+            let extra_env = self.config.extra_env().clone();
+            self.task_pool.handle.spawn_with_sender(move |sender| {
+                let project = ProjectJson::discover(&command, &file, &root, &extra_env);
+                sender.send(Task::DiscoverProject(file, project)).unwrap();
+            });
+        }
+    }
+
+    /// Discovers the projects of the open files again, after a build file changed. The current
+    /// `discovered_workspace` is kept until the new project is loaded.
+    pub(crate) fn rediscover_projects(&mut self) {
+        let files = mem::take(&mut self.discovered_files)
+            .into_iter()
+            .filter(|it| self.mem_docs.contains(&VfsPath::from(it.clone())))
+            .collect();
+        self.discovered_project = None;
+        self.discover_projects(files);
+    }
+
+    /// Checks whether the open file at `path` is a cargo script. Its contents come from the vfs, as
+    /// they might not have been saved.
+    fn is_open_cargo_script(&self, path: &AbsPath) -> bool {
+        let vfs = &self.vfs.read().0;
+        match vfs.file_id(&VfsPath::from(path.to_path_buf())) {
+            Some(file_id) => std::str::from_utf8(vfs.file_contents(file_id))
+                .map_or(false, ProjectManifest::has_embedded_manifest),
+            None => false,
+        }
+    }
+
+    pub(crate) fn recreate_crate_graph(&mut self, cause: String) {
+        let _p = profile::span("GlobalState::recreate_crate_graph");
+        tracing::info!(%cause, "will recreate crate graph");
+
+        let mut change = Change::new();
+
+        let files_config = self.config.files();
+        let workspaces = || {
+            self.workspaces
+                .iter()
+                .chain(self.discovered_workspace.as_deref())
+                .chain(self.detached_workspace.iter().filter(|_| !self.detached_files.is_empty()))
+        };
+        let project_folders = ProjectFolders::new(workspaces(), &files_config.exclude);
 
         let watch = match files_config.watcher {
             FilesWatcher::Client => vec![],
@@ -386,9 +528,19 @@ impl GlobalState {
                 res
             };
 
+            // The detached files don't have a proc-macro server.
+            let proc_macro_clients = (0..self.workspaces.len())
+                .map(|idx| self.proc_macro_clients.get(idx))
+                .chain(
+                    self.discovered_workspace
+                        .as_ref()
+                        .map(|_| self.discovered_proc_macro_client.as_ref()),
+                )
+                .chain(iter::repeat(None));
+
             let mut crate_graph = CrateGraph::default();
-            for (idx, ws) in self.workspaces.iter().enumerate() {
-                let proc_macro_client = match self.proc_macro_clients.get(idx) {
+            for (ws, proc_macro_client) in workspaces().zip(proc_macro_clients) {
+                let proc_macro_client = match proc_macro_client {
                     Some(res) => res.as_ref().map_err(|e| &**e),
                     None => Err("Proc macros are disabled"),
                 };
@@ -410,11 +562,13 @@ impl GlobalState {
         change.set_crate_graph(crate_graph);
 
         self.source_root_config = project_folders.source_root_config;
+        // Files which are already loaded, like open detached files, might have moved to another
+        // source root, so we can't wait for the vfs to report them.
+        change.set_roots(self.source_root_config.partition(&self.vfs.read().0));
 
         self.analysis_host.apply_change(change);
+        self.pull_diagnostics_invalidated = true;
         self.process_changes();
-        self.reload_flycheck();
-        tracing::info!("did switch workspaces");
     }
 
     fn fetch_workspace_error(&self) -> Result<(), String> {
@@ -471,6 +625,9 @@ impl GlobalState {
             .iter()
             .enumerate()
             .filter_map(|(id, w)| match w {
+                // FIXME: flycheck always checks the `Cargo.toml` of the workspace root, which
+                // a script doesn't have.
+                ProjectWorkspace::Cargo { cargo, .. } if cargo.cargo_script().is_some() => None,
                 ProjectWorkspace::Cargo { cargo, .. } => Some((id, cargo.workspace_root())),
                 ProjectWorkspace::Json { project, .. } => {
                     // Enable flychecks for json projects if a custom flycheck command was supplied
@@ -503,15 +660,15 @@ pub(crate) struct ProjectFolders {
 }
 
 impl ProjectFolders {
-    pub(crate) fn new(
-        workspaces: &[ProjectWorkspace],
+    pub(crate) fn new<'a>(
+        workspaces: impl IntoIterator<Item = &'a ProjectWorkspace>,
         global_excludes: &[AbsPathBuf],
     ) -> ProjectFolders {
         let mut res = ProjectFolders::default();
         let mut fsc = FileSetConfig::builder();
         let mut local_filesets = vec![];
 
-        for root in workspaces.iter().flat_map(|ws| ws.to_roots()) {
+        for root in workspaces.into_iter().flat_map(|ws| ws.to_roots()) {
             let file_set_roots: Vec<VfsPath> =
                 root.include.iter().cloned().map(VfsPath::from).collect();
 
@@ -683,6 +840,14 @@ pub(crate) fn load_proc_macro(
     }
 }
 
+/// The files of the build systems which generate `rust-project.json`s. A change to one of them
+/// might change the projects found by the `discoverProjectCommand`.
+const BUILD_FILES: &[&str] = &["BUILD", "BUILD.bazel", "BUCK", "TARGETS"];
+
+pub(crate) fn should_rediscover_for_change(path: &AbsPath) -> bool {
+    path.file_name().and_then(|it| it.to_str()).map_or(false, |it| BUILD_FILES.contains(&it))
+}
+
 pub(crate) fn should_refresh_for_change(path: &AbsPath, change_kind: ChangeKind) -> bool {
     const IMPLICIT_TARGET_FILES: &[&str] = &["build.rs", "src/main.rs", "src/lib.rs"];
     const IMPLICIT_TARGET_DIRS: &[&str] = &["src/bin", "examples", "tests", "benches"];
@@ -727,3 +892,56 @@ pub(crate) fn should_refresh_for_change(path: &AbsPath, change_kind: ChangeKind)
     }
     false
 }
+
+/// Returns the open Rust files that aren't within any of the `roots`. The files that are already
+/// detached keep their order, so that opening or closing a file doesn't reshuffle the others.
+fn detached_files(
+    current: &[AbsPathBuf],
+    open_files: &[&AbsPath],
+    roots: &[AbsPathBuf],
+) -> Vec<AbsPathBuf> {
+    let is_detached = |path: &AbsPath| {
+        path.extension().map_or(false, |it| it == "rs")
+            && !roots.iter().any(|root| path.starts_with(root))
+    };
+    let mut files = current
+        .iter()
+        .filter(|it| is_detached(it) && open_files.contains(&it.as_path()))
+        .cloned()
+        .collect::<Vec<_>>();
+    for &path in open_files {
+        if is_detached(path) && !files.iter().any(|it| it.as_path() == path) {
+            files.push(path.to_path_buf());
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(path: &str) -> AbsPathBuf {
+        AbsPathBuf::assert(std::env::temp_dir().join(path))
+    }
+
+    #[test]
+    fn detached_files_are_open_files_outside_of_roots() {
+        let roots = [path("ws")];
+        let (in_ws, script, readme) = (path("ws/src/lib.rs"), path("a.rs"), path("README.md"));
+        let open = [in_ws.as_path(), script.as_path(), readme.as_path()];
+        assert_eq!(detached_files(&[], &open, &roots), vec![script.clone()]);
+    }
+
+    #[test]
+    fn detached_files_drop_closed_files_and_keep_their_order() {
+        let (a, b, c) = (path("a.rs"), path("b.rs"), path("c.rs"));
+        let current = [b.clone(), a.clone()];
+        let open = [a.as_path(), b.as_path(), c.as_path()];
+        assert_eq!(detached_files(&current, &open, &[]), vec![b.clone(), a.clone(), c.clone()]);
+
+        let open = [a.as_path(), c.as_path()];
+        assert_eq!(detached_files(&current, &open, &[]), vec![a, c]);
+        assert_eq!(detached_files(&current, &[], &[]), Vec::<AbsPathBuf>::new());
+    }
+}
@@ -9,11 +9,12 @@ use ide::{
     Annotation, AnnotationKind, Assist, AssistKind, Cancellable, CompletionItem,
     CompletionItemKind, CompletionRelevance, Documentation, FileId, FileRange, FileSystemEdit,
     Fold, FoldKind, Highlight, HlMod, HlOperator, HlPunct, HlRange, HlTag, Indel, InlayHint,
-    InlayHintLabel, InlayKind, Markup, NavigationTarget, ReferenceCategory, RenameError, Runnable,
-    Severity, SignatureHelp, SourceChange, StructureNodeKind, SymbolKind, TextEdit, TextRange,
-    TextSize,
+    InlayHintLabel, InlayKind, InlineValue, InlineValueKind, Markup, NavigationTarget,
+    ReferenceCategory, RenameError, Runnable, Severity, SignatureHelp, SourceChange,
+    StructureNodeKind, SymbolKind, TextEdit, TextRange, TextSize,
 };
 use itertools::Itertools;
+use project_model::project_json::RunnableKind as ProjectJsonRunnableKind;
 use serde_json::to_value;
 use vfs::AbsPath;
 
@@ -22,7 +23,7 @@ use crate::{
     config::{CallInfoConfig, Config},
     global_state::GlobalStateSnapshot,
     line_index::{LineEndings, LineIndex, OffsetEncoding},
-    lsp_ext,
+    lsp_ext, lsp_types_ext,
     lsp_utils::invalid_params_error,
     semantic_tokens, Result,
 };
@@ -442,44 +443,64 @@ pub(crate) fn inlay_hint(
             // before annotated thing
             InlayKind::ParameterHint
             | InlayKind::ImplicitReborrowHint
-            | InlayKind::BindingModeHint => position(line_index, inlay_hint.range.start()),
+            | InlayKind::BindingModeHint
+            | InlayKind::AdjustmentHint
+            | InlayKind::OpeningParenthesis
+            | InlayKind::DropHint => position(line_index, inlay_hint.range.start()),
             // after annotated thing
             InlayKind::ClosureReturnTypeHint
             | InlayKind::TypeHint
             | InlayKind::ChainingHint
             | InlayKind::GenericParamListHint
             | InlayKind::LifetimeHint
-            | InlayKind::ClosingBraceHint => position(line_index, inlay_hint.range.end()),
+            | InlayKind::ClosingBraceHint
+            | InlayKind::ClosingParenthesis
+            | InlayKind::DiscriminantHint => position(line_index, inlay_hint.range.end()),
         },
         padding_left: Some(match inlay_hint.kind {
             InlayKind::TypeHint => !render_colons,
-            InlayKind::ChainingHint | InlayKind::ClosingBraceHint => true,
-            InlayKind::BindingModeHint
+            InlayKind::ChainingHint | InlayKind::ClosingBraceHint | InlayKind::DiscriminantHint => {
+                true
+            }
+            InlayKind::AdjustmentHint
+            | InlayKind::BindingModeHint
+            | InlayKind::ClosingParenthesis
             | InlayKind::ClosureReturnTypeHint
+            | InlayKind::DropHint
             | InlayKind::GenericParamListHint
             | InlayKind::ImplicitReborrowHint
             | InlayKind::LifetimeHint
+            | InlayKind::OpeningParenthesis
             | InlayKind::ParameterHint => false,
         }),
         padding_right: Some(match inlay_hint.kind {
-            InlayKind::ChainingHint
+            InlayKind::AdjustmentHint
+            | InlayKind::ChainingHint
+            | InlayKind::ClosingParenthesis
             | InlayKind::ClosureReturnTypeHint
+            | InlayKind::DiscriminantHint
             | InlayKind::GenericParamListHint
             | InlayKind::ImplicitReborrowHint
+            | InlayKind::OpeningParenthesis
             | InlayKind::TypeHint
             | InlayKind::ClosingBraceHint => false,
             InlayKind::BindingModeHint => inlay_hint.label.as_simple_str() != Some("&"),
-            InlayKind::ParameterHint | InlayKind::LifetimeHint => true,
+            InlayKind::DropHint | InlayKind::ParameterHint | InlayKind::LifetimeHint => true,
         }),
         kind: match inlay_hint.kind {
             InlayKind::ParameterHint => Some(lsp_types::InlayHintKind::PARAMETER),
             InlayKind::ClosureReturnTypeHint | InlayKind::TypeHint | InlayKind::ChainingHint => {
                 Some(lsp_types::InlayHintKind::TYPE)
             }
-            InlayKind::BindingModeHint
+            InlayKind::AdjustmentHint
+            | InlayKind::BindingModeHint
+            | InlayKind::ClosingParenthesis
+            | InlayKind::DiscriminantHint
+            | InlayKind::DropHint
             | InlayKind::GenericParamListHint
             | InlayKind::LifetimeHint
             | InlayKind::ImplicitReborrowHint
+            | InlayKind::OpeningParenthesis
             | InlayKind::ClosingBraceHint => None,
         },
         text_edits: None,
@@ -507,10 +528,13 @@ pub(crate) fn inlay_hint(
             }
             _ => None,
         })(),
-        tooltip: Some(match inlay_hint.tooltip {
-            Some(ide::InlayTooltip::String(s)) => lsp_types::InlayHintTooltip::String(s),
-            _ => lsp_types::InlayHintTooltip::String(inlay_hint.label.to_string()),
-        }),
+        tooltip: match inlay_hint.tooltip {
+            Some(ide::InlayTooltip::String(s)) => Some(lsp_types::InlayHintTooltip::String(s)),
+            // Computed lazily from the hover at the location in `data`, see
+            // `handle_inlay_hints_resolve`.
+            Some(ide::InlayTooltip::HoverRanged(..) | ide::InlayTooltip::HoverOffset(..)) => None,
+            None => Some(lsp_types::InlayHintTooltip::String(inlay_hint.label.to_string())),
+        },
         label: inlay_hint_label(snap, inlay_hint.label)?,
     })
 }
@@ -1065,6 +1089,50 @@ pub(crate) fn call_hierarchy_item(
     })
 }
 
+pub(crate) fn inline_value(
+    line_index: &LineIndex,
+    value: InlineValue,
+) -> lsp_types_ext::InlineValue {
+    let range = range(line_index, value.range);
+    match value.kind {
+        InlineValueKind::Variable(name) => {
+            lsp_types_ext::InlineValue::VariableLookup(lsp_types_ext::InlineValueVariableLookup {
+                range,
+                variable_name: Some(name),
+                case_sensitive_lookup: true,
+            })
+        }
+        InlineValueKind::Expression(expression) => {
+            lsp_types_ext::InlineValue::EvaluatableExpression(
+                lsp_types_ext::InlineValueEvaluatableExpression {
+                    range,
+                    expression: Some(expression),
+                },
+            )
+        }
+    }
+}
+
+pub(crate) fn type_hierarchy_item(
+    snap: &GlobalStateSnapshot,
+    target: NavigationTarget,
+) -> Result<lsp_types_ext::TypeHierarchyItem> {
+    let name = target.name.to_string();
+    let detail = target.description.clone();
+    let kind = target.kind.map(symbol_kind).unwrap_or(lsp_types::SymbolKind::STRUCT);
+    let (uri, range, selection_range) = location_info(snap, target)?;
+    Ok(lsp_types_ext::TypeHierarchyItem {
+        name,
+        kind,
+        tags: None,
+        detail,
+        uri,
+        range,
+        selection_range,
+        data: None,
+    })
+}
+
 pub(crate) fn code_action_kind(kind: AssistKind) -> lsp_types::CodeActionKind {
     match kind {
         AssistKind::None | AssistKind::Generate => lsp_types::CodeActionKind::EMPTY,
@@ -1116,6 +1184,11 @@ pub(crate) fn runnable(
 ) -> Result<lsp_ext::Runnable> {
     let config = snap.config.runnables();
     let spec = CargoTargetSpec::for_file(snap, runnable.nav.file_id)?;
+    if spec.is_none() && snap.config.shell_runnables() {
+        if let Some(res) = project_json_runnable(snap, &runnable)? {
+            return Ok(res);
+        }
+    }
     let workspace_root = spec.as_ref().map(|it| it.workspace_root.clone());
     let target = spec.as_ref().map(|s| s.target.clone());
     let (cargo_args, executable_args) =
@@ -1127,17 +1200,52 @@ pub(crate) fn runnable(
         label,
         location: Some(location),
         kind: lsp_ext::RunnableKind::Cargo,
-        args: lsp_ext::CargoRunnable {
+        args: lsp_ext::RunnableArgs::Cargo(lsp_ext::CargoRunnable {
             workspace_root: workspace_root.map(|it| it.into()),
             override_cargo: config.override_cargo,
             cargo_args,
             cargo_extra_args: config.cargo_extra_args,
             executable_args,
             expect_test: None,
-        },
+        }),
     })
 }
 
+/// Uses the commands of the `rust-project.json` crate of the runnable, if it specifies them.
+fn project_json_runnable(
+    snap: &GlobalStateSnapshot,
+    runnable: &Runnable,
+) -> Result<Option<lsp_ext::Runnable>> {
+    let krate = match snap.project_json_crate_for_file(runnable.nav.file_id)? {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let (kind, test_id) = match &runnable.kind {
+        ide::RunnableKind::Test { test_id, .. }
+        | ide::RunnableKind::Bench { test_id }
+        | ide::RunnableKind::DocTest { test_id } => {
+            (ProjectJsonRunnableKind::Test, test_id.to_string())
+        }
+        ide::RunnableKind::TestMod { path } => (ProjectJsonRunnableKind::Test, path.clone()),
+        ide::RunnableKind::Bin => (ProjectJsonRunnableKind::Run, String::new()),
+    };
+    let command = match krate.runnable(kind) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let target = krate.display_name().map(|it| it.to_string());
+    Ok(Some(lsp_ext::Runnable {
+        label: runnable.label(target),
+        location: Some(location_link(snap, None, runnable.nav.clone())?),
+        kind: lsp_ext::RunnableKind::Shell,
+        args: lsp_ext::RunnableArgs::Shell(lsp_ext::ShellRunnable {
+            program: command.program.clone(),
+            args: command.args.iter().map(|it| it.replace("{test_id}", &test_id)).collect(),
+            cwd: command.cwd.clone().into(),
+        }),
+    }))
+}
+
 pub(crate) fn code_lens(
     acc: &mut Vec<lsp_types::CodeLens>,
     snap: &GlobalStateSnapshot,
@@ -1158,6 +1266,8 @@ pub(crate) fn code_lens(
                 | ide::RunnableKind::Bin => true,
             };
             let r = runnable(snap, run)?;
+            // Only cargo runnables can be built for debugging.
+            let can_debug = can_debug && matches!(r.kind, lsp_ext::RunnableKind::Cargo);
 
             let lens_config = snap.config.lens();
             if lens_config.run && client_commands_config.run_single {
@@ -26,10 +26,16 @@ use lsp_types::{
     },
     CodeActionContext, CodeActionParams, CompletionParams, DidOpenTextDocumentParams,
     DocumentFormattingParams, FileRename, FormattingOptions, GotoDefinitionParams, HoverParams,
-    PartialResultParams, Position, Range, RenameFilesParams, TextDocumentItem,
+    NumberOrString, PartialResultParams, Position, Range, RenameFilesParams, TextDocumentItem,
     TextDocumentPositionParams, WorkDoneProgressParams,
 };
-use rust_analyzer::lsp_ext::{OnEnter, Runnables, RunnablesParams};
+use rust_analyzer::{
+    lsp_ext::{OnEnter, Runnables, RunnablesParams},
+    lsp_types_ext::{
+        DocumentDiagnosticParams, DocumentDiagnosticRequest, WorkspaceDiagnosticParams,
+        WorkspaceDiagnosticRequest,
+    },
+};
 use serde_json::json;
 use test_utils::skip_slow_tests;
 
@@ -589,6 +595,86 @@ fn main() {{}}
     );
 }
 
+#[test]
+fn pull_diagnostics_report_unchanged_results() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+mod bar;
+"#,
+    )
+    .with_pull_diagnostics()
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    let params = |previous_result_id: Option<String>| DocumentDiagnosticParams {
+        text_document: server.doc_id("src/lib.rs"),
+        identifier: None,
+        previous_result_id,
+        work_done_progress_params: WorkDoneProgressParams::default(),
+        partial_result_params: PartialResultParams::default(),
+    };
+
+    let full = server.send_request::<DocumentDiagnosticRequest>(params(None));
+    assert_eq!(full["kind"], "full");
+    assert_eq!(full["items"][0]["code"], "unresolved-module");
+    let result_id = full["resultId"].as_str().unwrap().to_string();
+
+    server.request::<DocumentDiagnosticRequest>(
+        params(Some(result_id.clone())),
+        json!({ "kind": "unchanged", "resultId": result_id }),
+    );
+}
+
+#[test]
+fn pull_workspace_diagnostics_as_partial_results() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+mod bar;
+"#,
+    )
+    .with_pull_diagnostics()
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    let token = NumberOrString::String("workspace-diagnostics".to_string());
+    server.request::<WorkspaceDiagnosticRequest>(
+        WorkspaceDiagnosticParams {
+            identifier: None,
+            previous_result_ids: Vec::new(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams { partial_result_token: Some(token) },
+        },
+        json!({ "items": [] }),
+    );
+
+    let partial_results = server.notifications("$/progress");
+    assert_eq!(partial_results.len(), 1);
+    assert_eq!(partial_results[0]["token"], "workspace-diagnostics");
+    let items = &partial_results[0]["value"]["items"];
+    assert!(items[0]["uri"].as_str().unwrap().ends_with("src/lib.rs"));
+    assert_eq!(items[0]["items"][0]["code"], "unresolved-module");
+}
+
 #[test]
 fn diagnostics_dont_block_typing() {
     if skip_slow_tests() {
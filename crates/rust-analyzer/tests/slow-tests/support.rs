@@ -10,7 +10,10 @@ use crossbeam_channel::{after, select, Receiver};
 use lsp_server::{Connection, Message, Notification, Request};
 use lsp_types::{notification::Exit, request::Shutdown, TextDocumentIdentifier, Url};
 use project_model::ProjectManifest;
-use rust_analyzer::{config::Config, lsp_ext, main_loop};
+use rust_analyzer::{
+    config::{ClientCapabilitiesExt, Config},
+    lsp_ext, main_loop,
+};
 use serde::Serialize;
 use serde_json::{json, to_string_pretty, Value};
 use test_utils::Fixture;
@@ -23,6 +26,7 @@ pub(crate) struct Project<'a> {
     tmp_dir: Option<TestDir>,
     roots: Vec<PathBuf>,
     config: serde_json::Value,
+    caps_ext: ClientCapabilitiesExt,
 }
 
 impl<'a> Project<'a> {
@@ -41,6 +45,7 @@ impl<'a> Project<'a> {
                     },
                 }
             }),
+            caps_ext: ClientCapabilitiesExt::default(),
         }
     }
 
@@ -54,6 +59,11 @@ impl<'a> Project<'a> {
         self
     }
 
+    pub(crate) fn with_pull_diagnostics(mut self) -> Project<'a> {
+        self.caps_ext.pull_diagnostics = true;
+        self
+    }
+
     pub(crate) fn with_config(mut self, config: serde_json::Value) -> Project<'a> {
         fn merge(dst: &mut serde_json::Value, src: serde_json::Value) {
             match (dst, src) {
@@ -138,6 +148,7 @@ impl<'a> Project<'a> {
                 ..Default::default()
             },
         );
+        config.set_caps_ext(self.caps_ext);
         config.discovered_projects = Some(discovered_projects);
         config.update(self.config).expect("invalid config");
 
@@ -242,6 +253,17 @@ impl Server {
         }
         panic!("no response for {:?}", r);
     }
+    /// Returns the params of the `method` notifications the server sent so far.
+    pub(crate) fn notifications(&self, method: &str) -> Vec<Value> {
+        self.messages
+            .borrow()
+            .iter()
+            .filter_map(|msg| match msg {
+                Message::Notification(n) if n.method == method => Some(n.params.clone()),
+                _ => None,
+            })
+            .collect()
+    }
     pub(crate) fn wait_until_workspace_is_loaded(self) -> Server {
         self.wait_for_message_cond(1, &|msg: &Message| match msg {
             Message::Notification(n) if n.method == "experimental/serverStatus" => {
@@ -261,6 +261,7 @@ class ExperimentalFeatures implements lc.StaticFeature {
         caps.codeActionGroup = true;
         caps.hoverActions = true;
         caps.serverStatusNotification = true;
+        caps.shellRunnables = true;
         caps.commands = {
             commands: [
                 "rust-analyzer.runSingle",
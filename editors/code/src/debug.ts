@@ -9,7 +9,7 @@ import { prepareEnv } from "./run";
 
 const debugOutput = vscode.window.createOutputChannel("Debug");
 type DebugConfigProvider = (
-    config: ra.Runnable,
+    config: ra.CargoRunnable,
     executable: string,
     env: Record<string, string>,
     sourceFileMap?: Record<string, string>
@@ -19,6 +19,10 @@ export async function makeDebugConfig(ctx: Ctx, runnable: ra.Runnable): Promise<
     const scope = ctx.activeRustEditor?.document.uri;
     if (!scope) return;
 
+    if (runnable.kind !== "cargo") {
+        await vscode.window.showErrorMessage(`'${runnable.label}' can't be debugged.`);
+        return;
+    }
     const debugConfig = await getDebugConfiguration(ctx, runnable);
     if (!debugConfig) return;
 
@@ -54,8 +58,10 @@ export async function startDebugSession(ctx: Ctx, runnable: ra.Runnable): Promis
         debugConfig = configurations[index];
         message = " (from launch.json)";
         debugOutput.clear();
-    } else {
+    } else if (runnable.kind === "cargo") {
         debugConfig = await getDebugConfiguration(ctx, runnable);
+    } else {
+        await vscode.window.showErrorMessage(`'${runnable.label}' can't be debugged.`);
     }
 
     if (!debugConfig) return false;
@@ -67,7 +73,7 @@ export async function startDebugSession(ctx: Ctx, runnable: ra.Runnable): Promis
 
 async function getDebugConfiguration(
     ctx: Ctx,
-    runnable: ra.Runnable
+    runnable: ra.CargoRunnable
 ): Promise<vscode.DebugConfiguration | undefined> {
     const editor = ctx.activeRustEditor;
     if (!editor) return;
@@ -156,7 +162,7 @@ async function getDebugConfiguration(
     return debugConfig;
 }
 
-async function getDebugExecutable(runnable: ra.Runnable): Promise<string> {
+async function getDebugExecutable(runnable: ra.CargoRunnable): Promise<string> {
     const cargo = new Cargo(runnable.args.workspaceRoot || ".", debugOutput);
     const executable = await cargo.executableFromArgs(runnable.args.cargoArgs);
 
@@ -165,7 +171,7 @@ async function getDebugExecutable(runnable: ra.Runnable): Promise<string> {
 }
 
 function getLldbDebugConfig(
-    runnable: ra.Runnable,
+    runnable: ra.CargoRunnable,
     executable: string,
     env: Record<string, string>,
     sourceFileMap?: Record<string, string>
@@ -184,7 +190,7 @@ function getLldbDebugConfig(
 }
 
 function getCppvsDebugConfig(
-    runnable: ra.Runnable,
+    runnable: ra.CargoRunnable,
     executable: string,
     env: Record<string, string>,
     sourceFileMap?: Record<string, string>
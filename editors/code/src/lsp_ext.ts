@@ -123,7 +123,8 @@ export interface RunnablesParams {
     position: lc.Position | null;
 }
 
-export interface Runnable {
+export type Runnable = CargoRunnable | ShellRunnable;
+export interface CargoRunnable {
     label: string;
     location?: lc.LocationLink;
     kind: "cargo";
@@ -136,6 +137,16 @@ export interface Runnable {
         overrideCargo?: string;
     };
 }
+export interface ShellRunnable {
+    label: string;
+    location?: lc.LocationLink;
+    kind: "shell";
+    args: {
+        program: string;
+        args: string[];
+        cwd: string;
+    };
+}
 export const runnables = new lc.RequestType<RunnablesParams, Runnable[], void>(
     "experimental/runnables"
 );
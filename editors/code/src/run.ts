@@ -38,7 +38,10 @@ export async function selectRunnable(
             continue;
         }
 
-        if (debuggeeOnly && (r.label.startsWith("doctest") || r.label.startsWith("cargo"))) {
+        if (
+            debuggeeOnly &&
+            (r.kind !== "cargo" || r.label.startsWith("doctest") || r.label.startsWith("cargo"))
+        ) {
             continue;
         }
         items.push(new RunnableQuickPick(r));
@@ -104,7 +107,7 @@ export function prepareEnv(
 ): Record<string, string> {
     const env: Record<string, string> = { RUST_BACKTRACE: "short" };
 
-    if (runnable.args.expectTest) {
+    if (runnable.kind === "cargo" && runnable.args.expectTest) {
         env["UPDATE_EXPECT"] = "1";
     }
 
@@ -126,11 +129,8 @@ export function prepareEnv(
 }
 
 export async function createTask(runnable: ra.Runnable, config: Config): Promise<vscode.Task> {
-    if (runnable.kind !== "cargo") {
-        // rust-analyzer supports only one kind, "cargo"
-        // do not use tasks.TASK_TYPE here, these are completely different meanings.
-
-        throw `Unexpected runnable kind: ${runnable.kind}`;
+    if (runnable.kind === "shell") {
+        return createShellTask(runnable, config);
     }
 
     const args = createArgs(runnable);
@@ -163,7 +163,32 @@ export async function createTask(runnable: ra.Runnable, config: Config): Promise
     return cargoTask;
 }
 
-export function createArgs(runnable: ra.Runnable): string[] {
+// Commands of other build systems, from `rust-project.json`, are run as they are.
+function createShellTask(runnable: ra.ShellRunnable, config: Config): vscode.Task {
+    // do not use tasks.TASK_TYPE here, these are not cargo tasks.
+    const definition: vscode.TaskDefinition = {
+        type: "shell",
+        command: runnable.args.program,
+        args: runnable.args.args,
+    };
+    const exec = new vscode.ProcessExecution(runnable.args.program, runnable.args.args, {
+        cwd: runnable.args.cwd,
+        env: prepareEnv(runnable, config.runnableEnv),
+    });
+    const task = new vscode.Task(
+        definition,
+        vscode.TaskScope.Workspace,
+        runnable.label,
+        tasks.TASK_SOURCE,
+        exec,
+        ["$rustc"]
+    );
+    task.presentationOptions.clear = true;
+    task.presentationOptions.focus = false;
+    return task;
+}
+
+export function createArgs(runnable: ra.CargoRunnable): string[] {
     const args = [...runnable.args.cargoArgs]; // should be a copy!
     if (runnable.args.cargoExtraArgs) {
         args.push(...runnable.args.cargoExtraArgs); // Append user-specified cargo options.